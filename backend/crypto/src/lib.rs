@@ -1,7 +1,15 @@
+pub mod oidc;
+
 pub mod password;
 
 pub mod sign;
 
+pub mod sigv4;
+
+pub mod token;
+
+pub mod totp;
+
 pub use password::{hash_password, verify_password};
 
 pub fn new_rng() -> rand::rngs::StdRng {
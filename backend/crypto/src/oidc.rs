@@ -0,0 +1,355 @@
+//! Building blocks of the OpenID Connect authorization code flow: PKCE,
+//! random state values and verification of RS256 signed ID tokens.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand_core::{CryptoRng, RngCore};
+use rsa::pkcs1v15::{Signature, SigningKey, VerifyingKey};
+use rsa::sha2::{Digest, Sha256};
+use rsa::signature::{RandomizedSigner, SignatureEncoding, Verifier};
+use rsa::{BigUint, PublicKeyParts, RsaPublicKey};
+
+pub use rsa::RsaPrivateKey;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("malformed token")]
+    Malformed,
+    #[error("unsupported signing algorithm {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("no key found for key id {0:?}")]
+    UnknownKey(Option<String>),
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("key error: {0}")]
+    KeyError(#[from] rsa::errors::Error),
+    #[error("invalid claim: {0}")]
+    InvalidClaim(&'static str),
+}
+
+/// Random value for `state`, `nonce` and similar single-use parameters.
+pub fn random_token<R>(rng: &mut R) -> String
+where
+    R: CryptoRng + RngCore,
+{
+    let mut bytes = [0; 32];
+    rng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// PKCE verifier kept by the client, and the S256 challenge sent along with
+/// the authorization request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl Pkce {
+    pub fn generate<R>(rng: &mut R) -> Self
+    where
+        R: CryptoRng + RngCore,
+    {
+        let verifier = random_token(rng);
+        let challenge = Self::challenge_for(&verifier);
+        Self {
+            verifier,
+            challenge,
+        }
+    }
+
+    pub fn challenge_for(verifier: &str) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+    }
+}
+
+/// Public key in JSON Web Key format, as served from a provider's `jwks_uri`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(default)]
+    pub kid: Option<String>,
+    #[serde(default)]
+    pub alg: Option<String>,
+    #[serde(default)]
+    pub n: Option<String>,
+    #[serde(default)]
+    pub e: Option<String>,
+}
+
+impl Jwk {
+    pub fn from_public_key(kid: &str, key: &RsaPublicKey) -> Self {
+        Self {
+            kty: "RSA".to_string(),
+            kid: Some(kid.to_string()),
+            alg: Some("RS256".to_string()),
+            n: Some(URL_SAFE_NO_PAD.encode(key.n().to_bytes_be())),
+            e: Some(URL_SAFE_NO_PAD.encode(key.e().to_bytes_be())),
+        }
+    }
+
+    fn public_key(&self) -> Result<RsaPublicKey, Error> {
+        let decode = |value: &Option<String>| {
+            value
+                .as_ref()
+                .and_then(|value| URL_SAFE_NO_PAD.decode(value).ok())
+                .map(|bytes| BigUint::from_bytes_be(&bytes))
+                .ok_or(Error::Malformed)
+        };
+        if self.kty != "RSA" {
+            return Err(Error::UnsupportedAlgorithm(self.kty.clone()));
+        }
+        Ok(RsaPublicKey::new(decode(&self.n)?, decode(&self.e)?)?)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    alg: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    typ: Option<String>,
+}
+
+/// `aud` may be a single string or a list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    pub fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Self::One(aud) => aud == client_id,
+            Self::Many(auds) => auds.iter().any(|aud| aud == client_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: Audience,
+    pub exp: i64,
+    pub iat: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Clock difference tolerated when checking `exp` and `iat`.
+pub const LEEWAY_SECS: i64 = 60;
+
+impl IdTokenClaims {
+    /// The email address, only if the provider vouches for it. Unverified
+    /// addresses can be typed in by anyone.
+    pub fn verified_email(&self) -> Option<&str> {
+        match self.email_verified {
+            Some(true) => self.email.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Checks the claims the OpenID Connect spec requires a client to check.
+    pub fn validate(
+        &self,
+        issuer: &str,
+        client_id: &str,
+        nonce: &str,
+        unix_time: i64,
+    ) -> Result<(), Error> {
+        if self.iss.trim_end_matches('/') != issuer.trim_end_matches('/') {
+            return Err(Error::InvalidClaim("iss"));
+        }
+        if !self.aud.contains(client_id) {
+            return Err(Error::InvalidClaim("aud"));
+        }
+        if self.exp + LEEWAY_SECS < unix_time {
+            return Err(Error::InvalidClaim("exp"));
+        }
+        if self.iat - LEEWAY_SECS > unix_time {
+            return Err(Error::InvalidClaim("iat"));
+        }
+        if self.nonce.as_deref() != Some(nonce) {
+            return Err(Error::InvalidClaim("nonce"));
+        }
+        Ok(())
+    }
+}
+
+/// Key id of the token, used to pick the key from the provider's key set.
+pub fn token_key_id(token: &str) -> Result<Option<String>, Error> {
+    let header = token.split('.').next().ok_or(Error::Malformed)?;
+    Ok(decode_part::<Header>(header)?.kid)
+}
+
+/// Verifies the signature of an RS256 JWT and returns its claims. The claims
+/// still have to be checked with [`IdTokenClaims::validate`].
+pub fn verify_id_token(token: &str, keys: &JwkSet) -> Result<IdTokenClaims, Error> {
+    let mut parts = token.split('.');
+    let (header, payload, signature) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(h), Some(p), Some(s)) if parts.next().is_none() => (h, p, s),
+        _ => return Err(Error::Malformed),
+    };
+
+    let parsed_header = decode_part::<Header>(header)?;
+    if parsed_header.alg != "RS256" {
+        return Err(Error::UnsupportedAlgorithm(parsed_header.alg));
+    }
+
+    let jwk = keys
+        .keys
+        .iter()
+        .filter(|key| key.kty == "RSA")
+        .find(|key| parsed_header.kid.is_none() || key.kid == parsed_header.kid)
+        .ok_or_else(|| Error::UnknownKey(parsed_header.kid.clone()))?;
+    let verifying_key = VerifyingKey::<Sha256>::new_with_prefix(jwk.public_key()?);
+
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| Error::Malformed)?;
+    let signature = Signature::try_from(signature.as_slice()).map_err(|_| Error::Malformed)?;
+    verifying_key
+        .verify(format!("{header}.{payload}").as_bytes(), &signature)
+        .map_err(|_| Error::InvalidSignature)?;
+
+    decode_part(payload)
+}
+
+/// Signs claims as an RS256 JWT. Used by the mock provider and in tests.
+pub fn sign_id_token<R>(
+    rng: &mut R,
+    key: &RsaPrivateKey,
+    kid: &str,
+    claims: &IdTokenClaims,
+) -> Result<String, Error>
+where
+    R: CryptoRng + RngCore,
+{
+    let header = Header {
+        alg: "RS256".to_string(),
+        kid: Some(kid.to_string()),
+        typ: Some("JWT".to_string()),
+    };
+    let signing_input = format!("{}.{}", encode_part(&header)?, encode_part(claims)?);
+
+    let signing_key = SigningKey::<Sha256>::new_with_prefix(key.clone());
+    let signature = signing_key.sign_with_rng(rng, signing_input.as_bytes());
+    Ok(format!(
+        "{signing_input}.{}",
+        URL_SAFE_NO_PAD.encode(signature.to_bytes())
+    ))
+}
+
+fn decode_part<T: for<'de> Deserialize<'de>>(part: &str) -> Result<T, Error> {
+    let bytes = URL_SAFE_NO_PAD.decode(part).map_err(|_| Error::Malformed)?;
+    serde_json::from_slice(&bytes).map_err(|_| Error::Malformed)
+}
+
+fn encode_part<T: Serialize>(value: &T) -> Result<String, Error> {
+    let bytes = serde_json::to_vec(value).map_err(|_| Error::Malformed)?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(now: i64) -> IdTokenClaims {
+        IdTokenClaims {
+            iss: "http://localhost:9000".to_string(),
+            sub: "alice".to_string(),
+            aud: Audience::One("uchat".to_string()),
+            exp: now + 300,
+            iat: now,
+            nonce: Some("nonce".to_string()),
+            email: Some("alice@example.com".to_string()),
+            email_verified: Some(true),
+            preferred_username: Some("alice".to_string()),
+            name: None,
+        }
+    }
+
+    #[test]
+    fn pkce_challenge_matches_rfc7636_example() {
+        assert_eq!(
+            Pkce::challenge_for("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn only_verified_emails_are_used() {
+        let mut claims = claims(0);
+        assert_eq!(claims.verified_email(), Some("alice@example.com"));
+        claims.email_verified = Some(false);
+        assert_eq!(claims.verified_email(), None);
+        claims.email_verified = None;
+        assert_eq!(claims.verified_email(), None);
+    }
+
+    #[test]
+    fn signed_tokens_verify_and_validate() {
+        let mut rng = crate::new_rng();
+        let key = RsaPrivateKey::new(&mut rng, 1024).unwrap();
+        let keys = JwkSet {
+            keys: vec![Jwk::from_public_key("k1", &key.to_public_key())],
+        };
+        let now = 1_700_000_000;
+
+        let token = sign_id_token(&mut rng, &key, "k1", &claims(now)).unwrap();
+        assert_eq!(token_key_id(&token).unwrap().as_deref(), Some("k1"));
+
+        let verified = verify_id_token(&token, &keys).unwrap();
+        assert_eq!(verified, claims(now));
+        verified
+            .validate("http://localhost:9000/", "uchat", "nonce", now)
+            .unwrap();
+        assert!(verified
+            .validate("http://localhost:9000", "other", "nonce", now)
+            .is_err());
+        assert!(verified
+            .validate("http://localhost:9000", "uchat", "replayed", now)
+            .is_err());
+        assert!(verified
+            .validate("http://localhost:9000", "uchat", "nonce", now + 3600)
+            .is_err());
+    }
+
+    #[test]
+    fn tampered_tokens_are_rejected() {
+        let mut rng = crate::new_rng();
+        let key = RsaPrivateKey::new(&mut rng, 1024).unwrap();
+        let keys = JwkSet {
+            keys: vec![Jwk::from_public_key("k1", &key.to_public_key())],
+        };
+        let token = sign_id_token(&mut rng, &key, "k1", &claims(0)).unwrap();
+
+        let mut forged = claims(0);
+        forged.sub = "mallory".to_string();
+        let forged_payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged).unwrap());
+        let parts = token.split('.').collect::<Vec<_>>();
+        let tampered = format!("{}.{}.{}", parts[0], forged_payload, parts[2]);
+
+        assert!(matches!(
+            verify_id_token(&tampered, &keys),
+            Err(Error::InvalidSignature)
+        ));
+    }
+}
@@ -1,6 +1,8 @@
+use std::time::{Duration, Instant};
+
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
+    Algorithm, Argon2, Params, Version,
 };
 use tracing::instrument;
 
@@ -12,7 +14,48 @@ pub enum Error {
     HashError(#[from] argon2::password_hash::Error),
     #[error("Invalid password")]
     InvalidPassword,
+    #[error("Invalid hash parameters: {0}")]
+    InvalidParams(argon2::Error),
+}
+
+/// Argon2id cost parameters used for new hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl HashParams {
+    /// Checks the parameters are accepted by Argon2 before they are needed to
+    /// hash a password.
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self, Error> {
+        let params = Self {
+            memory_kib,
+            iterations,
+            parallelism,
+        };
+        params.argon2()?;
+        Ok(params)
+    }
+
+    fn argon2(&self) -> Result<Argon2<'static>, Error> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+            .map_err(Error::InvalidParams)?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+impl Default for HashParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
 }
+
 fn new_salt() -> SaltString {
     SaltString::generate(&mut OsRng)
 }
@@ -34,6 +77,74 @@ pub fn hash_password<T: AsRef<str>>(password: T) -> Result<PasswordHashString, E
     hash_password_with_salt(password, salt.as_salt())
 }
 
+#[instrument(level = "trace", err, skip_all)]
+pub fn hash_password_with_params<T: AsRef<str>>(
+    password: T,
+    params: &HashParams,
+) -> Result<PasswordHashString, Error> {
+    let argon2 = params.argon2()?;
+    let password = password.as_ref().as_bytes();
+    let salt = new_salt();
+
+    Ok(argon2.hash_password(password, &salt)?.serialize())
+}
+
+/// Whether a stored hash was made with anything other than `params`, and
+/// should be replaced the next time the plain password is known.
+pub fn needs_rehash(hash: &PasswordHash, params: &HashParams) -> bool {
+    if hash.algorithm != Algorithm::Argon2id.ident() || hash.version != Some(Version::V0x13.into())
+    {
+        return true;
+    }
+    match Params::try_from(hash) {
+        Ok(stored) => {
+            stored.m_cost() != params.memory_kib
+                || stored.t_cost() != params.iterations
+                || stored.p_cost() != params.parallelism
+        }
+        Err(_) => true,
+    }
+}
+
+/// Time it takes to hash a password with `params` on this machine.
+pub fn time_hash(params: &HashParams) -> Result<Duration, Error> {
+    let start = Instant::now();
+    hash_password_with_params("benchmark password", params)?;
+    Ok(start.elapsed())
+}
+
+/// Finds the most expensive parameters which still hash within `target`.
+///
+/// Memory is raised first, up to `max_memory_kib`, since that is what makes
+/// Argon2 costly on dedicated cracking hardware. Iterations are added after.
+pub fn recommend_params(
+    target: Duration,
+    max_memory_kib: u32,
+    parallelism: u32,
+) -> Result<(HashParams, Duration), Error> {
+    let mut params = HashParams {
+        parallelism,
+        ..HashParams::default()
+    };
+    let mut elapsed = time_hash(&params)?;
+
+    loop {
+        let mut next = params;
+        if next.memory_kib.saturating_mul(2) <= max_memory_kib {
+            next.memory_kib *= 2;
+        } else {
+            next.iterations += 1;
+        }
+
+        let next_elapsed = time_hash(&next)?;
+        if next_elapsed > target || next.iterations > 10 {
+            return Ok((params, elapsed));
+        }
+        params = next;
+        elapsed = next_elapsed;
+    }
+}
+
 #[instrument(level = "debug", err, skip_all)]
 pub fn verify_password<T: AsRef<str>>(
     password: T,
@@ -83,6 +194,41 @@ mod tests {
         assert!(verify_password("wrong", &hashed_password).is_err());
     }
 
+    #[test]
+    fn rejects_invalid_params() {
+        assert!(HashParams::new(1024, 1, 1).is_ok());
+        assert!(matches!(
+            HashParams::new(1024, 0, 1),
+            Err(Error::InvalidParams(_))
+        ));
+        assert!(matches!(
+            HashParams::new(1, 1, 4),
+            Err(Error::InvalidParams(_))
+        ));
+    }
+
+    #[test]
+    fn detects_outdated_params() {
+        let cheap = HashParams {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let hashed_password = hash_password_with_params("password", &cheap).unwrap();
+        let hashed_password = deserialize_hash(hashed_password.as_str()).unwrap();
+
+        assert!(!needs_rehash(&hashed_password, &cheap));
+        assert!(needs_rehash(&hashed_password, &HashParams::default()));
+        verify_password("password", &hashed_password).unwrap();
+    }
+
+    #[test]
+    fn default_hashes_match_default_params() {
+        let hashed_password = hash_password("password").unwrap();
+        let hashed_password = deserialize_hash(hashed_password.as_str()).unwrap();
+        assert!(!needs_rehash(&hashed_password, &HashParams::default()));
+    }
+
     #[test]
     fn deserializes() {
         let password = "password";
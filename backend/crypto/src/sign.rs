@@ -1,7 +1,10 @@
+use std::collections::HashMap;
+use std::fmt;
+
 use rand_core::{CryptoRng, RngCore};
 use rsa::pss::{BlindedSigningKey, Signature, VerifyingKey};
 use rsa::sha2::Sha256;
-use rsa::signature::{Keypair, RandomizedSigner, Verifier};
+use rsa::signature::{Keypair, RandomizedSigner, SignatureEncoding, Verifier};
 use rsa::RsaPrivateKey;
 
 #[derive(Debug, thiserror::Error)]
@@ -17,6 +20,12 @@ pub enum Error {
 
     #[error("decoding error: {0}")]
     DecodingError(String),
+
+    #[error("unknown or retired key: {0}")]
+    UnknownKey(KeyId),
+
+    #[error("key {0} is active and cannot be retired")]
+    ActiveKey(KeyId),
 }
 
 pub fn new_private_key<R>(rng: &mut R) -> Result<RsaPrivateKey, Error>
@@ -102,3 +111,209 @@ pub fn decode_private_key<T: AsRef<[u8]>>(key: T) -> Result<RsaPrivateKey, Error
 pub fn signature_from_bytes<T: AsRef<[u8]>>(bytes: T) -> Result<Signature, Error> {
     Ok(Signature::try_from(bytes.as_ref())?)
 }
+
+//------------------------------------------------------------------------------
+/// Identifies a key within a [`Keyring`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyId(String);
+
+impl KeyId {
+    pub fn new<S: Into<String>>(id: S) -> Self {
+        Self(id.into())
+    }
+
+    pub fn generate<R: RngCore>(rng: &mut R) -> Self {
+        Self(format!("{:016x}", rng.next_u64()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl fmt::Display for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A signature along with the id of the key that produced it.
+///
+/// Encoded as `<key id>.<base64 signature>`. Signatures issued before key ids
+/// existed have no prefix and decode with `key_id` set to `None`.
+#[derive(Clone, Debug)]
+pub struct KeyedSignature {
+    pub key_id: Option<KeyId>,
+    pub signature: Signature,
+}
+
+impl KeyedSignature {
+    const SEPARATOR: char = '.';
+
+    pub fn encode(&self) -> String {
+        let signature = crate::encode_base64(self.signature.to_bytes());
+        match &self.key_id {
+            Some(key_id) => format!("{key_id}{}{signature}", Self::SEPARATOR),
+            None => signature,
+        }
+    }
+
+    pub fn decode<S: AsRef<str>>(encoded: S) -> Result<Self, Error> {
+        let encoded = encoded.as_ref();
+        let (key_id, signature) = match encoded.split_once(Self::SEPARATOR) {
+            Some((key_id, signature)) => (Some(KeyId::new(key_id)), signature),
+            None => (None, encoded),
+        };
+        let signature =
+            crate::decode_base64(signature).map_err(|e| Error::DecodingError(e.to_string()))?;
+
+        Ok(Self {
+            key_id,
+            signature: signature_from_bytes(signature)?,
+        })
+    }
+}
+
+/// Set of signing keys which are not retired.
+///
+/// New signatures are always made with the active key, while any key in the
+/// ring is accepted during verification. This allows rotating the active key
+/// without invalidating signatures made with the previous one.
+#[derive(Clone)]
+pub struct Keyring {
+    active: KeyId,
+    keys: HashMap<KeyId, Keys>,
+}
+
+impl Keyring {
+    pub fn new(active: KeyId, keys: Keys) -> Self {
+        let mut ring = HashMap::new();
+        ring.insert(active.clone(), keys);
+        Self { active, keys: ring }
+    }
+
+    pub fn generate<R>(rng: &mut R) -> Result<Self, Error>
+    where
+        R: CryptoRng + RngCore,
+    {
+        let (_, keys) = Keys::generate(rng)?;
+        Ok(Self::new(KeyId::generate(rng), keys))
+    }
+
+    pub fn active(&self) -> &KeyId {
+        &self.active
+    }
+
+    pub fn contains(&self, key_id: &KeyId) -> bool {
+        self.keys.contains_key(key_id)
+    }
+
+    pub fn key_ids(&self) -> impl Iterator<Item = &KeyId> {
+        self.keys.keys()
+    }
+
+    /// Adds a key which is accepted for verification but not used for signing.
+    pub fn insert(&mut self, key_id: KeyId, keys: Keys) {
+        self.keys.insert(key_id, keys);
+    }
+
+    pub fn activate(&mut self, key_id: &KeyId) -> Result<(), Error> {
+        if !self.contains(key_id) {
+            return Err(Error::UnknownKey(key_id.clone()));
+        }
+        self.active = key_id.clone();
+        Ok(())
+    }
+
+    pub fn retire(&mut self, key_id: &KeyId) -> Result<(), Error> {
+        if *key_id == self.active {
+            return Err(Error::ActiveKey(key_id.clone()));
+        }
+        self.keys
+            .remove(key_id)
+            .map(|_| ())
+            .ok_or_else(|| Error::UnknownKey(key_id.clone()))
+    }
+
+    pub fn sign<R>(&self, rng: &mut R, data: &[u8]) -> KeyedSignature
+    where
+        R: CryptoRng + RngCore,
+    {
+        let keys = &self.keys[&self.active];
+        KeyedSignature {
+            key_id: Some(self.active.clone()),
+            signature: keys.sign(rng, data),
+        }
+    }
+
+    pub fn verify(&self, data: &[u8], signature: &KeyedSignature) -> Result<(), Error> {
+        match &signature.key_id {
+            Some(key_id) => self
+                .keys
+                .get(key_id)
+                .ok_or_else(|| Error::UnknownKey(key_id.clone()))?
+                .verify(data, signature.signature.clone()),
+            None => {
+                let mut result = Err(Error::SignatureError(rsa::signature::Error::new()));
+                for keys in self.keys.values() {
+                    result = keys.verify(data, signature.signature.clone());
+                    if result.is_ok() {
+                        break;
+                    }
+                }
+                result
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyed_signature_roundtrips() {
+        let mut rng = crate::new_rng();
+        let keyring = Keyring::generate(&mut rng).unwrap();
+        let signature = keyring.sign(&mut rng, b"data");
+
+        let decoded = KeyedSignature::decode(signature.encode()).unwrap();
+        assert_eq!(decoded.key_id.as_ref(), Some(keyring.active()));
+        keyring.verify(b"data", &decoded).unwrap();
+    }
+
+    #[test]
+    fn rotated_keys_still_verify_until_retired() {
+        let mut rng = crate::new_rng();
+        let mut keyring = Keyring::generate(&mut rng).unwrap();
+        let old_key = keyring.active().clone();
+        let old_signature = keyring.sign(&mut rng, b"data");
+
+        let new_key = KeyId::generate(&mut rng);
+        keyring.insert(new_key.clone(), Keys::generate(&mut rng).unwrap().1);
+        keyring.activate(&new_key).unwrap();
+
+        assert_eq!(
+            keyring.sign(&mut rng, b"data").key_id,
+            Some(new_key.clone())
+        );
+        keyring.verify(b"data", &old_signature).unwrap();
+
+        assert!(keyring.retire(&new_key).is_err());
+        keyring.retire(&old_key).unwrap();
+        assert!(keyring.verify(b"data", &old_signature).is_err());
+    }
+
+    #[test]
+    fn signatures_without_key_id_are_checked_against_all_keys() {
+        let mut rng = crate::new_rng();
+        let keyring = Keyring::generate(&mut rng).unwrap();
+        let mut signature = keyring.sign(&mut rng, b"data");
+        signature.key_id = None;
+
+        let decoded = KeyedSignature::decode(signature.encode()).unwrap();
+        assert!(decoded.key_id.is_none());
+        keyring.verify(b"data", &decoded).unwrap();
+        assert!(keyring.verify(b"other", &decoded).is_err());
+    }
+}
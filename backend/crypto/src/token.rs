@@ -0,0 +1,54 @@
+//! Secrets for personal access tokens.
+//!
+//! Tokens are long random strings, so a plain SHA-256 is enough to store them
+//! and, unlike a password hash, allows looking a token up by its hash.
+
+use rand_core::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+
+/// Marks tokens of this service, which helps secret scanners find leaked ones.
+pub const TOKEN_PREFIX: &str = "uchat_";
+const TOKEN_BYTES: usize = 32;
+/// Characters of a token which are stored in plain text to tell tokens apart.
+pub const VISIBLE_CHARS: usize = TOKEN_PREFIX.len() + 6;
+
+pub fn generate_token<R>(rng: &mut R) -> String
+where
+    R: CryptoRng + RngCore,
+{
+    let mut secret = [0; TOKEN_BYTES];
+    rng.fill_bytes(&mut secret);
+    format!("{TOKEN_PREFIX}{}", to_hex(&secret))
+}
+
+pub fn hash_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+/// Beginning of the token, shown in token lists.
+pub fn visible_part(token: &str) -> &str {
+    token.get(..VISIBLE_CHARS).unwrap_or(token)
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_unique_and_hash_consistently() {
+        let mut rng = crate::new_rng();
+        let a = generate_token(&mut rng);
+        let b = generate_token(&mut rng);
+
+        assert_ne!(a, b);
+        assert!(a.starts_with(TOKEN_PREFIX));
+        assert_eq!(a.len(), TOKEN_PREFIX.len() + TOKEN_BYTES * 2);
+        assert_eq!(hash_token(&a), hash_token(&a));
+        assert_ne!(hash_token(&a), hash_token(&b));
+        assert_eq!(visible_part(&a).len(), VISIBLE_CHARS);
+    }
+}
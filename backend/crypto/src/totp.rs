@@ -0,0 +1,235 @@
+//! Time-based one-time passwords (RFC 6238) using HMAC-SHA1, which is what
+//! authenticator apps support out of the box.
+
+use hmac::{Hmac, Mac};
+use rand_core::{CryptoRng, RngCore};
+use sha1::Sha1;
+
+pub const DIGITS: u32 = 6;
+pub const STEP_SECS: i64 = 30;
+/// Number of steps before and after the current one that are still accepted.
+pub const SKEW_STEPS: i64 = 1;
+
+const SECRET_LEN: usize = 20;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+const RECOVERY_CODE_LEN: usize = 10;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("secret is not valid base32")]
+    InvalidSecret,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct TotpSecret(Vec<u8>);
+
+impl TotpSecret {
+    pub fn generate<R>(rng: &mut R) -> Self
+    where
+        R: CryptoRng + RngCore,
+    {
+        let mut secret = vec![0; SECRET_LEN];
+        rng.fill_bytes(&mut secret);
+        Self(secret)
+    }
+
+    pub fn from_bytes<T: Into<Vec<u8>>>(secret: T) -> Self {
+        Self(secret.into())
+    }
+
+    pub fn from_base32<S: AsRef<str>>(secret: S) -> Result<Self, Error> {
+        decode_base32(secret.as_ref()).map(Self)
+    }
+
+    pub fn to_base32(&self) -> String {
+        encode_base32(&self.0)
+    }
+
+    /// Code for the given time step.
+    pub fn code_at(&self, step: i64) -> String {
+        let mut mac =
+            Hmac::<Sha1>::new_from_slice(&self.0).expect("HMAC accepts keys of any length");
+        mac.update(&step.to_be_bytes());
+        let digest = mac.finalize().into_bytes();
+
+        let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            digest[offset] & 0x7f,
+            digest[offset + 1],
+            digest[offset + 2],
+            digest[offset + 3],
+        ]);
+        format!(
+            "{:0width$}",
+            binary % 10u32.pow(DIGITS),
+            width = DIGITS as usize
+        )
+    }
+
+    /// Checks `code` against the steps around `unix_time`, and returns the
+    /// matching step so callers can reject codes which were already used.
+    pub fn verify(&self, code: &str, unix_time: i64) -> Option<i64> {
+        let code = code.trim();
+        let current = time_step(unix_time);
+        (current - SKEW_STEPS..=current + SKEW_STEPS)
+            .find(|step| constant_time_eq(self.code_at(*step).as_bytes(), code.as_bytes()))
+    }
+
+    /// URI understood by authenticator apps, usually shown as a QR code.
+    pub fn otpauth_uri(&self, issuer: &str, account: &str) -> String {
+        let issuer = percent_encode(issuer);
+        let account = percent_encode(account);
+        format!(
+            "otpauth://totp/{issuer}:{account}?secret={}&issuer={issuer}&algorithm=SHA1&digits={DIGITS}&period={STEP_SECS}",
+            self.to_base32()
+        )
+    }
+}
+
+impl std::fmt::Debug for TotpSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TotpSecret(..)")
+    }
+}
+
+pub fn time_step(unix_time: i64) -> i64 {
+    unix_time.div_euclid(STEP_SECS)
+}
+
+/// Single-use codes handed to the user when enabling 2FA.
+pub fn generate_recovery_codes<R>(rng: &mut R, count: usize) -> Vec<String>
+where
+    R: CryptoRng + RngCore,
+{
+    (0..count)
+        .map(|_| {
+            let code: String = (0..RECOVERY_CODE_LEN)
+                .map(|_| {
+                    let idx = rng.next_u32() as usize % RECOVERY_CODE_ALPHABET.len();
+                    RECOVERY_CODE_ALPHABET[idx] as char
+                })
+                .collect();
+            let (head, tail) = code.split_at(RECOVERY_CODE_LEN / 2);
+            format!("{head}-{tail}")
+        })
+        .collect()
+}
+
+/// Normalizes user input of a recovery code before hashing or verifying it.
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn encode_base32(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() * 8).div_ceil(5));
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+fn decode_base32(data: &str) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(data.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in data.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a as char == c.to_ascii_uppercase())
+            .ok_or(Error::InvalidSecret)?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rfc_secret() -> TotpSecret {
+        TotpSecret::from_bytes(b"12345678901234567890".to_vec())
+    }
+
+    #[test]
+    fn matches_rfc6238_test_vectors() {
+        let secret = rfc_secret();
+        assert_eq!(secret.code_at(time_step(59)), "287082");
+        assert_eq!(secret.code_at(time_step(1111111109)), "081804");
+        assert_eq!(secret.code_at(time_step(2000000000)), "279037");
+    }
+
+    #[test]
+    fn accepts_codes_within_skew() {
+        let secret = rfc_secret();
+        let now = 1111111109;
+        let previous = secret.code_at(time_step(now) - 1);
+
+        assert_eq!(secret.verify(&previous, now), Some(time_step(now) - 1));
+        assert_eq!(secret.verify(&previous, now + 3 * STEP_SECS), None);
+        assert_eq!(secret.verify("000000x", now), None);
+    }
+
+    #[test]
+    fn base32_roundtrips() {
+        let secret = rfc_secret();
+        assert_eq!(
+            secret.to_base32(),
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string()
+        );
+        assert_eq!(
+            TotpSecret::from_base32(secret.to_base32().to_lowercase()).unwrap(),
+            secret
+        );
+        assert!(TotpSecret::from_base32("not base32!").is_err());
+    }
+
+    #[test]
+    fn recovery_codes_are_normalized() {
+        let mut rng = crate::new_rng();
+        let codes = generate_recovery_codes(&mut rng, 10);
+        assert_eq!(codes.len(), 10);
+
+        let code = &codes[0];
+        assert_eq!(code.len(), RECOVERY_CODE_LEN + 1);
+        assert_eq!(
+            normalize_recovery_code(&format!(" {} ", code.to_uppercase())),
+            code.replace('-', "")
+        );
+    }
+}
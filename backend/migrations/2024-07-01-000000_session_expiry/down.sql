@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.web DROP COLUMN IF EXISTS absolute_expires_at;
+ALTER TABLE public.web DROP COLUMN IF EXISTS last_seen_at;
@@ -0,0 +1,13 @@
+-- object: public.web.last_seen_at | type: COLUMN --
+ALTER TABLE public.web ADD COLUMN last_seen_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP;
+-- ddl-end --
+COMMENT ON COLUMN public.web.last_seen_at IS E'last authenticated request, updated at most once per touch interval';
+-- ddl-end --
+
+-- object: public.web.absolute_expires_at | type: COLUMN --
+ALTER TABLE public.web ADD COLUMN absolute_expires_at timestamptz;
+UPDATE public.web SET absolute_expires_at = expires_at;
+ALTER TABLE public.web ALTER COLUMN absolute_expires_at SET NOT NULL;
+-- ddl-end --
+COMMENT ON COLUMN public.web.absolute_expires_at IS E'hard limit after which the session can no longer be extended';
+-- ddl-end --
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS public.one_active_signing_key CASCADE;
+DROP TABLE IF EXISTS public.signing_keys CASCADE;
@@ -0,0 +1,24 @@
+-- object: public.signing_keys | type: TABLE --
+-- DROP TABLE IF EXISTS public.signing_keys CASCADE;
+CREATE TABLE public.signing_keys (
+  id text NOT NULL,
+  private_key text NOT NULL,
+  active boolean NOT NULL DEFAULT false,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  retired_at timestamptz,
+  CONSTRAINT signing_keys_pk PRIMARY KEY (id),
+  CONSTRAINT active_key_not_retired CHECK (NOT (active AND retired_at IS NOT NULL))
+);
+-- ddl-end --
+COMMENT ON TABLE public.signing_keys IS E'keys used to sign session ids';
+-- ddl-end --
+
+-- object: one_active_signing_key | type: INDEX --
+-- DROP INDEX IF EXISTS public.one_active_signing_key CASCADE;
+CREATE UNIQUE INDEX one_active_signing_key ON public.signing_keys
+USING btree
+(
+  active
+)
+WHERE (active);
+-- ddl-end --
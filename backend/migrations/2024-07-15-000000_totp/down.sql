@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.totp DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.recovery_codes DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.login_challenges DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+DROP TABLE IF EXISTS public.login_challenges CASCADE;
+DROP TABLE IF EXISTS public.recovery_codes CASCADE;
+DROP TABLE IF EXISTS public.totp CASCADE;
@@ -0,0 +1,63 @@
+-- object: public.totp | type: TABLE --
+-- DROP TABLE IF EXISTS public.totp CASCADE;
+CREATE TABLE public.totp (
+  user_id uuid NOT NULL,
+  secret text NOT NULL,
+  confirmed_at timestamptz,
+  last_used_step bigint,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT totp_pk PRIMARY KEY (user_id)
+);
+-- ddl-end --
+COMMENT ON COLUMN public.totp.confirmed_at IS E'2FA is only enforced once the first code was confirmed';
+-- ddl-end --
+COMMENT ON COLUMN public.totp.last_used_step IS E'time step of the last accepted code, used to reject replays';
+-- ddl-end --
+
+-- object: public.recovery_codes | type: TABLE --
+-- DROP TABLE IF EXISTS public.recovery_codes CASCADE;
+CREATE TABLE public.recovery_codes (
+  id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  code_hash text NOT NULL,
+  used_at timestamptz,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT recovery_codes_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+
+-- object: public.login_challenges | type: TABLE --
+-- DROP TABLE IF EXISTS public.login_challenges CASCADE;
+CREATE TABLE public.login_challenges (
+  id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  remember_me boolean NOT NULL,
+  attempts smallint NOT NULL DEFAULT 0,
+  expires_at timestamptz NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT login_challenges_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+COMMENT ON TABLE public.login_challenges IS E'logins waiting for the second factor';
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.totp DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.totp ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.recovery_codes DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.recovery_codes ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.login_challenges DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.login_challenges ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.login_failures DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.security_events DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+DROP INDEX IF EXISTS public.security_events_user_index CASCADE;
+DROP TABLE IF EXISTS public.security_events CASCADE;
+DROP TABLE IF EXISTS public.login_failures CASCADE;
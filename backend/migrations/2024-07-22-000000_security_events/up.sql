@@ -0,0 +1,49 @@
+-- object: public.login_failures | type: TABLE --
+-- DROP TABLE IF EXISTS public.login_failures CASCADE;
+CREATE TABLE public.login_failures (
+  user_id uuid NOT NULL,
+  failed_count smallint NOT NULL DEFAULT 0,
+  last_failed_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  locked_until timestamptz,
+  CONSTRAINT login_failures_pk PRIMARY KEY (user_id)
+);
+-- ddl-end --
+COMMENT ON TABLE public.login_failures IS E'consecutive failed logins, cleared on success';
+-- ddl-end --
+
+-- object: public.security_events | type: TABLE --
+-- DROP TABLE IF EXISTS public.security_events CASCADE;
+CREATE TABLE public.security_events (
+  id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  kind text NOT NULL,
+  ip_address text,
+  user_agent text,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT security_events_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+
+-- object: security_events_user_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.security_events_user_index CASCADE;
+CREATE INDEX security_events_user_index ON public.security_events
+USING btree
+(
+  user_id,
+  created_at
+);
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.login_failures DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.login_failures ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.security_events DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.security_events ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.api_tokens DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+DROP TABLE IF EXISTS public.api_tokens CASCADE;
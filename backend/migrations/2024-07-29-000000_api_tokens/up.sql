@@ -0,0 +1,26 @@
+-- object: public.api_tokens | type: TABLE --
+-- DROP TABLE IF EXISTS public.api_tokens CASCADE;
+CREATE TABLE public.api_tokens (
+  id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  name text NOT NULL,
+  token_prefix text NOT NULL,
+  token_hash text NOT NULL,
+  scopes text[] NOT NULL,
+  expires_at timestamptz,
+  last_used_at timestamptz,
+  revoked_at timestamptz,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT api_tokens_pk PRIMARY KEY (id),
+  CONSTRAINT api_tokens_hash_unique UNIQUE (token_hash)
+);
+-- ddl-end --
+COMMENT ON COLUMN public.api_tokens.token_hash IS E'sha256 of the token, the token itself is only shown once';
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.api_tokens DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.api_tokens ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.user_identities DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+DROP INDEX IF EXISTS public.user_identities_user_index CASCADE;
+DROP TABLE IF EXISTS public.user_identities CASCADE;
+DROP TABLE IF EXISTS public.oidc_signups CASCADE;
+ALTER TABLE public.oidc_logins DROP CONSTRAINT IF EXISTS link_user_id_fk CASCADE;
+DROP TABLE IF EXISTS public.oidc_logins CASCADE;
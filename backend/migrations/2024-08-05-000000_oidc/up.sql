@@ -0,0 +1,68 @@
+-- object: public.oidc_logins | type: TABLE --
+-- DROP TABLE IF EXISTS public.oidc_logins CASCADE;
+CREATE TABLE public.oidc_logins (
+  state text NOT NULL,
+  provider text NOT NULL,
+  nonce text NOT NULL,
+  pkce_verifier text NOT NULL,
+  link_user_id uuid,
+  remember_me boolean NOT NULL DEFAULT false,
+  expires_at timestamptz NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT oidc_logins_pk PRIMARY KEY (state)
+);
+-- ddl-end --
+COMMENT ON COLUMN public.oidc_logins.link_user_id IS E'set when a signed in user links an identity instead of logging in';
+-- ddl-end --
+
+-- object: link_user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.oidc_logins DROP CONSTRAINT IF EXISTS link_user_id_fk CASCADE;
+ALTER TABLE public.oidc_logins ADD CONSTRAINT link_user_id_fk FOREIGN KEY (link_user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: public.oidc_signups | type: TABLE --
+-- DROP TABLE IF EXISTS public.oidc_signups CASCADE;
+CREATE TABLE public.oidc_signups (
+  id uuid NOT NULL,
+  provider text NOT NULL,
+  subject text NOT NULL,
+  email text,
+  suggested_handle text,
+  expires_at timestamptz NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT oidc_signups_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+COMMENT ON TABLE public.oidc_signups IS E'verified external identities waiting for the user to pick a handle';
+-- ddl-end --
+
+-- object: public.user_identities | type: TABLE --
+-- DROP TABLE IF EXISTS public.user_identities CASCADE;
+CREATE TABLE public.user_identities (
+  provider text NOT NULL,
+  subject text NOT NULL,
+  user_id uuid NOT NULL,
+  email text,
+  last_login_at timestamptz,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT user_identities_pk PRIMARY KEY (provider,subject)
+);
+-- ddl-end --
+
+-- object: user_identities_user_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.user_identities_user_index CASCADE;
+CREATE INDEX user_identities_user_index ON public.user_identities
+USING btree
+(
+  user_id
+);
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.user_identities DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.user_identities ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
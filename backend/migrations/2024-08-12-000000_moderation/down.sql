@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.reports DROP CONSTRAINT IF EXISTS resolved_by_fk CASCADE;
+ALTER TABLE public.reports DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.reports DROP CONSTRAINT IF EXISTS reporter_id_fk CASCADE;
+DROP INDEX IF EXISTS public.moderation_actions_created_index CASCADE;
+DROP TABLE IF EXISTS public.moderation_actions CASCADE;
+DROP INDEX IF EXISTS public.reports_status_index CASCADE;
+DROP TABLE IF EXISTS public.reports CASCADE;
+ALTER TABLE public.posts DROP COLUMN IF EXISTS hidden_at;
+ALTER TABLE public.users DROP COLUMN IF EXISTS banned_at;
+ALTER TABLE public.users DROP COLUMN IF EXISTS suspended_until;
+ALTER TABLE public.users DROP CONSTRAINT IF EXISTS users_role_check;
+ALTER TABLE public.users DROP COLUMN IF EXISTS role;
@@ -0,0 +1,101 @@
+-- object: public.users.role | type: COLUMN --
+ALTER TABLE public.users ADD COLUMN role text NOT NULL DEFAULT 'user';
+ALTER TABLE public.users ADD CONSTRAINT users_role_check CHECK (role IN ('user', 'moderator', 'admin'));
+-- ddl-end --
+
+-- object: public.users.suspended_until | type: COLUMN --
+ALTER TABLE public.users ADD COLUMN suspended_until timestamptz;
+-- ddl-end --
+COMMENT ON COLUMN public.users.suspended_until IS E'the account can not be used before this time';
+-- ddl-end --
+
+-- object: public.users.banned_at | type: COLUMN --
+ALTER TABLE public.users ADD COLUMN banned_at timestamptz;
+-- ddl-end --
+COMMENT ON COLUMN public.users.banned_at IS E'banned accounts can not be used and their posts are left out of feeds';
+-- ddl-end --
+
+-- object: public.posts.hidden_at | type: COLUMN --
+ALTER TABLE public.posts ADD COLUMN hidden_at timestamptz;
+-- ddl-end --
+COMMENT ON COLUMN public.posts.hidden_at IS E'set when a moderator hides the post from feeds';
+-- ddl-end --
+
+-- object: public.reports | type: TABLE --
+-- DROP TABLE IF EXISTS public.reports CASCADE;
+CREATE TABLE public.reports (
+  id uuid NOT NULL,
+  reporter_id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  post_id uuid,
+  reason text NOT NULL,
+  details text,
+  status text NOT NULL DEFAULT 'open',
+  resolved_by uuid,
+  resolved_at timestamptz,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT reports_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+COMMENT ON COLUMN public.reports.user_id IS E'reported user, or the author of the reported post';
+-- ddl-end --
+COMMENT ON COLUMN public.reports.post_id IS E'no foreign key, reports are kept after the post is deleted';
+-- ddl-end --
+
+-- object: reports_status_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.reports_status_index CASCADE;
+CREATE INDEX reports_status_index ON public.reports
+USING btree
+(
+  status,
+  created_at
+);
+-- ddl-end --
+
+-- object: public.moderation_actions | type: TABLE --
+-- DROP TABLE IF EXISTS public.moderation_actions CASCADE;
+CREATE TABLE public.moderation_actions (
+  id uuid NOT NULL,
+  moderator_id uuid NOT NULL,
+  action text NOT NULL,
+  user_id uuid,
+  post_id uuid,
+  report_id uuid,
+  note text,
+  expires_at timestamptz,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT moderation_actions_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+COMMENT ON TABLE public.moderation_actions IS E'audit trail of moderator actions, without foreign keys so entries outlive their targets';
+-- ddl-end --
+
+-- object: moderation_actions_created_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.moderation_actions_created_index CASCADE;
+CREATE INDEX moderation_actions_created_index ON public.moderation_actions
+USING btree
+(
+  created_at
+);
+-- ddl-end --
+
+-- object: reporter_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.reports DROP CONSTRAINT IF EXISTS reporter_id_fk CASCADE;
+ALTER TABLE public.reports ADD CONSTRAINT reporter_id_fk FOREIGN KEY (reporter_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.reports DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.reports ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: resolved_by_fk | type: CONSTRAINT --
+-- ALTER TABLE public.reports DROP CONSTRAINT IF EXISTS resolved_by_fk CASCADE;
+ALTER TABLE public.reports ADD CONSTRAINT resolved_by_fk FOREIGN KEY (resolved_by)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE SET NULL ON UPDATE NO ACTION;
+-- ddl-end --
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.mute_filters DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+DROP INDEX IF EXISTS public.mute_filters_user_index CASCADE;
+DROP TABLE IF EXISTS public.mute_filters CASCADE;
@@ -0,0 +1,32 @@
+-- object: public.mute_filters | type: TABLE --
+-- DROP TABLE IF EXISTS public.mute_filters CASCADE;
+CREATE TABLE public.mute_filters (
+  id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  phrase text NOT NULL,
+  whole_word boolean NOT NULL DEFAULT true,
+  scopes text[] NOT NULL,
+  action text NOT NULL,
+  expires_at timestamptz,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT mute_filters_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+COMMENT ON COLUMN public.mute_filters.action IS E'hide leaves matching posts out, warn collapses them behind a warning';
+-- ddl-end --
+
+-- object: mute_filters_user_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.mute_filters_user_index CASCADE;
+CREATE INDEX mute_filters_user_index ON public.mute_filters
+USING btree
+(
+  user_id
+);
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.mute_filters DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.mute_filters ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.images DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+DROP INDEX IF EXISTS public.images_sha256_index CASCADE;
+DROP INDEX IF EXISTS public.images_user_index CASCADE;
+DROP TABLE IF EXISTS public.images CASCADE;
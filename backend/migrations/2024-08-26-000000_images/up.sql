@@ -0,0 +1,41 @@
+-- object: public.images | type: TABLE --
+-- DROP TABLE IF EXISTS public.images CASCADE;
+CREATE TABLE public.images (
+  id uuid NOT NULL,
+  user_id uuid,
+  mime text NOT NULL,
+  byte_size bigint NOT NULL,
+  width integer NOT NULL,
+  height integer NOT NULL,
+  sha256 text NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT images_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+COMMENT ON COLUMN public.images.user_id IS E'NULL for migrated uploads whose owner could not be found';
+-- ddl-end --
+
+-- object: images_user_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.images_user_index CASCADE;
+CREATE INDEX images_user_index ON public.images
+USING btree
+(
+  user_id
+);
+-- ddl-end --
+
+-- object: images_sha256_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.images_sha256_index CASCADE;
+CREATE INDEX images_sha256_index ON public.images
+USING btree
+(
+  sha256
+);
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.images DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.images ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE SET NULL ON UPDATE NO ACTION;
+-- ddl-end --
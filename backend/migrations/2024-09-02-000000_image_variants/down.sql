@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.image_variants DROP CONSTRAINT IF EXISTS image_id_fk CASCADE;
+DROP TABLE IF EXISTS public.image_variants CASCADE;
@@ -0,0 +1,20 @@
+-- object: public.image_variants | type: TABLE --
+-- DROP TABLE IF EXISTS public.image_variants CASCADE;
+CREATE TABLE public.image_variants (
+  image_id uuid NOT NULL,
+  width integer NOT NULL,
+  height integer NOT NULL,
+  mime text NOT NULL,
+  byte_size bigint NOT NULL,
+  CONSTRAINT image_variants_pk PRIMARY KEY (image_id,width)
+);
+-- ddl-end --
+COMMENT ON TABLE public.image_variants IS E'Resized copies of images, stored under the key <image_id>_w<width>';
+-- ddl-end --
+
+-- object: image_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.image_variants DROP CONSTRAINT IF EXISTS image_id_fk CASCADE;
+ALTER TABLE public.image_variants ADD CONSTRAINT image_id_fk FOREIGN KEY (image_id)
+REFERENCES public.images (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS public.images_used_at_index CASCADE;
+ALTER TABLE public.images DROP COLUMN IF EXISTS used_at CASCADE;
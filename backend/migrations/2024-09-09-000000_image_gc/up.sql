@@ -0,0 +1,15 @@
+-- object: public.images.used_at | type: COLUMN --
+-- ALTER TABLE public.images DROP COLUMN IF EXISTS used_at CASCADE;
+ALTER TABLE public.images ADD COLUMN used_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP;
+-- ddl-end --
+COMMENT ON COLUMN public.images.used_at IS E'Last upload of these bytes. Unreferenced images are only removed once this is older than the grace period';
+-- ddl-end --
+
+-- object: images_used_at_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.images_used_at_index CASCADE;
+CREATE INDEX images_used_at_index ON public.images
+USING btree
+(
+  used_at
+);
+-- ddl-end --
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.videos DROP CONSTRAINT IF EXISTS poster_image_id_fk CASCADE;
+ALTER TABLE public.videos DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+DROP INDEX IF EXISTS public.videos_sha256_index CASCADE;
+DROP INDEX IF EXISTS public.videos_user_index CASCADE;
+DROP TABLE IF EXISTS public.videos CASCADE;
@@ -0,0 +1,57 @@
+-- object: public.videos | type: TABLE --
+-- DROP TABLE IF EXISTS public.videos CASCADE;
+CREATE TABLE public.videos (
+  id uuid NOT NULL,
+  user_id uuid,
+  poster_image_id uuid NOT NULL,
+  mime text NOT NULL,
+  codec text NOT NULL,
+  byte_size bigint NOT NULL,
+  width integer NOT NULL,
+  height integer NOT NULL,
+  duration_ms integer NOT NULL,
+  looping boolean NOT NULL DEFAULT false,
+  sha256 text NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  used_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT videos_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+COMMENT ON TABLE public.videos IS E'Uploaded videos and GIFs, stored under the key <id>_video';
+-- ddl-end --
+COMMENT ON COLUMN public.videos.poster_image_id IS E'Frame shown before the video plays';
+-- ddl-end --
+COMMENT ON COLUMN public.videos.looping IS E'Plays muted and on repeat, like the GIF it was converted from';
+-- ddl-end --
+
+-- object: videos_user_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.videos_user_index CASCADE;
+CREATE INDEX videos_user_index ON public.videos
+USING btree
+(
+  user_id
+);
+-- ddl-end --
+
+-- object: videos_sha256_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.videos_sha256_index CASCADE;
+CREATE INDEX videos_sha256_index ON public.videos
+USING btree
+(
+  sha256
+);
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.videos DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.videos ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE SET NULL ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: poster_image_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.videos DROP CONSTRAINT IF EXISTS poster_image_id_fk CASCADE;
+ALTER TABLE public.videos ADD CONSTRAINT poster_image_id_fk FOREIGN KEY (poster_image_id)
+REFERENCES public.images (id) MATCH SIMPLE
+ON DELETE RESTRICT ON UPDATE NO ACTION;
+-- ddl-end --
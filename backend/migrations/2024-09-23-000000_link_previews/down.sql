@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.link_previews DROP CONSTRAINT IF EXISTS image_id_fk CASCADE;
+DROP TABLE IF EXISTS public.link_previews CASCADE;
@@ -0,0 +1,27 @@
+-- object: public.link_previews | type: TABLE --
+-- DROP TABLE IF EXISTS public.link_previews CASCADE;
+CREATE TABLE public.link_previews (
+  url text NOT NULL,
+  title text,
+  description text,
+  site_name text,
+  image_id uuid,
+  failed boolean NOT NULL DEFAULT false,
+  fetched_at timestamptz,
+  claimed_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT link_previews_pk PRIMARY KEY (url)
+);
+-- ddl-end --
+COMMENT ON TABLE public.link_previews IS E'Previews of linked pages, fetched once per URL for all posts sharing it';
+-- ddl-end --
+COMMENT ON COLUMN public.link_previews.fetched_at IS E'NULL while the first fetch is running';
+-- ddl-end --
+COMMENT ON COLUMN public.link_previews.claimed_at IS E'Start of the last fetch, which keeps others from fetching the same page';
+-- ddl-end --
+
+-- object: image_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.link_previews DROP CONSTRAINT IF EXISTS image_id_fk CASCADE;
+ALTER TABLE public.link_previews ADD CONSTRAINT image_id_fk FOREIGN KEY (image_id)
+REFERENCES public.images (id) MATCH SIMPLE
+ON DELETE SET NULL ON UPDATE NO ACTION;
+-- ddl-end --
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.drafts DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+DROP INDEX IF EXISTS public.drafts_user_index CASCADE;
+DROP TABLE IF EXISTS public.drafts CASCADE;
@@ -0,0 +1,33 @@
+-- object: public.drafts | type: TABLE --
+-- DROP TABLE IF EXISTS public.drafts CASCADE;
+CREATE TABLE public.drafts (
+  id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  content jsonb NOT NULL,
+  options jsonb NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT drafts_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+COMMENT ON TABLE public.drafts IS E'Posts being written, kept until they are published or deleted';
+-- ddl-end --
+COMMENT ON COLUMN public.drafts.options IS E'NewPostOptions the post is published with';
+-- ddl-end --
+
+-- object: drafts_user_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.drafts_user_index CASCADE;
+CREATE INDEX drafts_user_index ON public.drafts
+USING btree
+(
+  user_id,
+  updated_at
+);
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.drafts DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.drafts ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
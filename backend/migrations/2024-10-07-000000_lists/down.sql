@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.list_members DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.list_members DROP CONSTRAINT IF EXISTS list_id_fk CASCADE;
+DROP INDEX IF EXISTS public.list_members_user_index CASCADE;
+DROP TABLE IF EXISTS public.list_members CASCADE;
+ALTER TABLE public.lists DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+DROP INDEX IF EXISTS public.lists_user_index CASCADE;
+DROP TABLE IF EXISTS public.lists CASCADE;
@@ -0,0 +1,65 @@
+-- object: public.lists | type: TABLE --
+-- DROP TABLE IF EXISTS public.lists CASCADE;
+CREATE TABLE public.lists (
+  id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  name text NOT NULL,
+  description text,
+  private boolean NOT NULL DEFAULT false,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT lists_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+COMMENT ON TABLE public.lists IS E'Named sets of users, each with a timeline of their posts';
+-- ddl-end --
+COMMENT ON COLUMN public.lists.private IS E'Only the owner sees private lists';
+-- ddl-end --
+
+-- object: lists_user_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.lists_user_index CASCADE;
+CREATE INDEX lists_user_index ON public.lists
+USING btree
+(
+  user_id
+);
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.lists DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.lists ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: public.list_members | type: TABLE --
+-- DROP TABLE IF EXISTS public.list_members CASCADE;
+CREATE TABLE public.list_members (
+  list_id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT list_members_pk PRIMARY KEY (list_id,user_id)
+);
+-- ddl-end --
+
+-- object: list_members_user_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.list_members_user_index CASCADE;
+CREATE INDEX list_members_user_index ON public.list_members
+USING btree
+(
+  user_id
+);
+-- ddl-end --
+
+-- object: list_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.list_members DROP CONSTRAINT IF EXISTS list_id_fk CASCADE;
+ALTER TABLE public.list_members ADD CONSTRAINT list_id_fk FOREIGN KEY (list_id)
+REFERENCES public.lists (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.list_members DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.list_members ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS public.bookmarks_user_created_index CASCADE;
+ALTER TABLE public.bookmarks DROP CONSTRAINT IF EXISTS folder_id_fk CASCADE;
+ALTER TABLE public.bookmarks DROP COLUMN IF EXISTS note;
+ALTER TABLE public.bookmarks DROP COLUMN IF EXISTS folder_id;
+ALTER TABLE public.bookmark_folders DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+DROP INDEX IF EXISTS public.bookmark_folders_user_index CASCADE;
+DROP TABLE IF EXISTS public.bookmark_folders CASCADE;
@@ -0,0 +1,57 @@
+-- object: public.bookmark_folders | type: TABLE --
+-- DROP TABLE IF EXISTS public.bookmark_folders CASCADE;
+CREATE TABLE public.bookmark_folders (
+  id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  name text NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT bookmark_folders_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+COMMENT ON TABLE public.bookmark_folders IS E'Named collections users sort their bookmarks into';
+-- ddl-end --
+
+-- object: bookmark_folders_user_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.bookmark_folders_user_index CASCADE;
+CREATE INDEX bookmark_folders_user_index ON public.bookmark_folders
+USING btree
+(
+  user_id
+);
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.bookmark_folders DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.bookmark_folders ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: public.bookmarks.folder_id | type: COLUMN --
+ALTER TABLE public.bookmarks ADD COLUMN folder_id uuid;
+-- ddl-end --
+COMMENT ON COLUMN public.bookmarks.folder_id IS E'bookmarks without a folder are unsorted';
+-- ddl-end --
+
+-- object: public.bookmarks.note | type: COLUMN --
+ALTER TABLE public.bookmarks ADD COLUMN note text;
+-- ddl-end --
+COMMENT ON COLUMN public.bookmarks.note IS E'only seen by the user who bookmarked the post';
+-- ddl-end --
+
+-- object: folder_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.bookmarks DROP CONSTRAINT IF EXISTS folder_id_fk CASCADE;
+ALTER TABLE public.bookmarks ADD CONSTRAINT folder_id_fk FOREIGN KEY (folder_id)
+REFERENCES public.bookmark_folders (id) MATCH SIMPLE
+ON DELETE SET NULL ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: bookmarks_user_created_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.bookmarks_user_created_index CASCADE;
+CREATE INDEX bookmarks_user_created_index ON public.bookmarks
+USING btree
+(
+  user_id,
+  created_at DESC
+);
+-- ddl-end --
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.profile_visits DROP CONSTRAINT IF EXISTS visitor_id_fk CASCADE;
+ALTER TABLE public.profile_visits DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+DROP INDEX IF EXISTS public.profile_visits_user_window_index CASCADE;
+DROP TABLE IF EXISTS public.profile_visits CASCADE;
+ALTER TABLE public.post_impressions DROP CONSTRAINT IF EXISTS viewer_id_fk CASCADE;
+ALTER TABLE public.post_impressions DROP CONSTRAINT IF EXISTS post_id_fk CASCADE;
+DROP INDEX IF EXISTS public.post_impressions_post_window_index CASCADE;
+DROP TABLE IF EXISTS public.post_impressions CASCADE;
@@ -0,0 +1,73 @@
+-- object: public.post_impressions | type: TABLE --
+-- DROP TABLE IF EXISTS public.post_impressions CASCADE;
+CREATE TABLE public.post_impressions (
+  post_id uuid NOT NULL,
+  viewer_id uuid NOT NULL,
+  window_start timestamptz NOT NULL,
+  CONSTRAINT post_impressions_pk PRIMARY KEY (post_id,viewer_id,window_start)
+);
+-- ddl-end --
+COMMENT ON TABLE public.post_impressions IS E'Posts served to viewers, counted once per viewer and window';
+-- ddl-end --
+COMMENT ON COLUMN public.post_impressions.window_start IS E'start of the hour the post was served in';
+-- ddl-end --
+
+-- object: post_impressions_post_window_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.post_impressions_post_window_index CASCADE;
+CREATE INDEX post_impressions_post_window_index ON public.post_impressions
+USING btree
+(
+  post_id,
+  window_start
+);
+-- ddl-end --
+
+-- object: post_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.post_impressions DROP CONSTRAINT IF EXISTS post_id_fk CASCADE;
+ALTER TABLE public.post_impressions ADD CONSTRAINT post_id_fk FOREIGN KEY (post_id)
+REFERENCES public.posts (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: viewer_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.post_impressions DROP CONSTRAINT IF EXISTS viewer_id_fk CASCADE;
+ALTER TABLE public.post_impressions ADD CONSTRAINT viewer_id_fk FOREIGN KEY (viewer_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: public.profile_visits | type: TABLE --
+-- DROP TABLE IF EXISTS public.profile_visits CASCADE;
+CREATE TABLE public.profile_visits (
+  user_id uuid NOT NULL,
+  visitor_id uuid NOT NULL,
+  window_start timestamptz NOT NULL,
+  CONSTRAINT profile_visits_pk PRIMARY KEY (user_id,visitor_id,window_start)
+);
+-- ddl-end --
+COMMENT ON TABLE public.profile_visits IS E'Profile views, counted once per visitor and window';
+-- ddl-end --
+
+-- object: profile_visits_user_window_index | type: INDEX --
+-- DROP INDEX IF EXISTS public.profile_visits_user_window_index CASCADE;
+CREATE INDEX profile_visits_user_window_index ON public.profile_visits
+USING btree
+(
+  user_id,
+  window_start
+);
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.profile_visits DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.profile_visits ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: visitor_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.profile_visits DROP CONSTRAINT IF EXISTS visitor_id_fk CASCADE;
+ALTER TABLE public.profile_visits ADD CONSTRAINT visitor_id_fk FOREIGN KEY (visitor_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
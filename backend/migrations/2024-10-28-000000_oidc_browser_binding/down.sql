@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.oidc_logins DROP COLUMN IF EXISTS browser_key_hash;
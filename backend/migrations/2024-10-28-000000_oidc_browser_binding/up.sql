@@ -0,0 +1,8 @@
+-- Pending logins were not bound to a browser and cannot be finished anymore
+DELETE FROM public.oidc_logins;
+
+-- object: public.oidc_logins.browser_key_hash | type: COLUMN --
+ALTER TABLE public.oidc_logins ADD COLUMN browser_key_hash text NOT NULL;
+-- ddl-end --
+COMMENT ON COLUMN public.oidc_logins.browser_key_hash IS E'hash of the cookie given to the browser which started the login';
+-- ddl-end --
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.oidc_signups DROP COLUMN IF EXISTS remember_me;
+ALTER TABLE public.oidc_signups DROP COLUMN IF EXISTS browser_key_hash;
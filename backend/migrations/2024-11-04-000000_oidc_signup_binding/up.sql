@@ -0,0 +1,12 @@
+-- Pending signups were not bound to a browser and cannot be completed anymore
+DELETE FROM public.oidc_signups;
+
+-- object: public.oidc_signups.browser_key_hash | type: COLUMN --
+ALTER TABLE public.oidc_signups ADD COLUMN browser_key_hash text NOT NULL;
+-- ddl-end --
+COMMENT ON COLUMN public.oidc_signups.browser_key_hash IS E'hash of the cookie given to the browser which started the login';
+-- ddl-end --
+
+-- object: public.oidc_signups.remember_me | type: COLUMN --
+ALTER TABLE public.oidc_signups ADD COLUMN remember_me boolean NOT NULL DEFAULT false;
+-- ddl-end --
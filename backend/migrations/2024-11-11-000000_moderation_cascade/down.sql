@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.moderation_actions DROP COLUMN IF EXISTS deleted_post_ids;
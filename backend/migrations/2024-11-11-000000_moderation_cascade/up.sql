@@ -0,0 +1,5 @@
+-- object: public.moderation_actions.deleted_post_ids | type: COLUMN --
+ALTER TABLE public.moderation_actions ADD COLUMN deleted_post_ids uuid[] NOT NULL DEFAULT '{}';
+-- ddl-end --
+COMMENT ON COLUMN public.moderation_actions.deleted_post_ids IS E'replies deleted along with the post';
+-- ddl-end --
@@ -0,0 +1,219 @@
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uchat_domain::{PostId, UserId};
+
+use crate::schema::{boosts, poll_votes, post_impressions, posts, profile_visits, reactions};
+use crate::DieselError;
+
+/// Impressions and profile visits are counted once per viewer in each window.
+pub fn window_start(at: DateTime<Utc>) -> DateTime<Utc> {
+    at.duration_trunc(Duration::hours(1)).unwrap_or(at)
+}
+
+/// Counts the posts as seen by `viewer_id`, unless they were already seen in
+/// the window of `at`.
+pub async fn record_impressions(
+    conn: &mut AsyncPgConnection,
+    viewer_id: UserId,
+    post_ids: &[PostId],
+    at: DateTime<Utc>,
+) -> Result<(), DieselError> {
+    if post_ids.is_empty() {
+        return Ok(());
+    }
+    let window_start = window_start(at);
+    let rows = post_ids
+        .iter()
+        .map(|post_id| {
+            (
+                post_impressions::post_id.eq(*post_id),
+                post_impressions::viewer_id.eq(viewer_id),
+                post_impressions::window_start.eq(window_start),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    diesel::insert_into(post_impressions::table)
+        .values(rows)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .await
+        .map(|_| ())
+}
+
+/// Counts a visit of `visitor_id` on the profile of `user_id`, unless they
+/// already visited in the window of `at`.
+pub async fn record_profile_visit(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    visitor_id: UserId,
+    at: DateTime<Utc>,
+) -> Result<(), DieselError> {
+    diesel::insert_into(profile_visits::table)
+        .values((
+            profile_visits::user_id.eq(user_id),
+            profile_visits::visitor_id.eq(visitor_id),
+            profile_visits::window_start.eq(window_start(at)),
+        ))
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .await
+        .map(|_| ())
+}
+
+/// Impressions of a post per window since `since`, oldest first.
+pub async fn impressions(
+    conn: &mut AsyncPgConnection,
+    post_id: PostId,
+    since: DateTime<Utc>,
+) -> Result<Vec<(DateTime<Utc>, i64)>, DieselError> {
+    post_impressions::table
+        .filter(post_impressions::post_id.eq(post_id))
+        .filter(post_impressions::window_start.ge(since))
+        .group_by(post_impressions::window_start)
+        .select((post_impressions::window_start, count_star()))
+        .order(post_impressions::window_start.asc())
+        .load(conn)
+        .await
+}
+
+/// Visits of a profile per window since `since`, oldest first.
+pub async fn profile_visits(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    since: DateTime<Utc>,
+) -> Result<Vec<(DateTime<Utc>, i64)>, DieselError> {
+    profile_visits::table
+        .filter(profile_visits::user_id.eq(user_id))
+        .filter(profile_visits::window_start.ge(since))
+        .group_by(profile_visits::window_start)
+        .select((profile_visits::window_start, count_star()))
+        .order(profile_visits::window_start.asc())
+        .load(conn)
+        .await
+}
+
+/// Posts of `author` seen most since `since`, with their impressions.
+pub async fn most_seen_posts(
+    conn: &mut AsyncPgConnection,
+    author: UserId,
+    since: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<(PostId, i64)>, DieselError> {
+    post_impressions::table
+        .inner_join(posts::table)
+        .filter(posts::user_id.eq(author))
+        .filter(post_impressions::window_start.ge(since))
+        .group_by(post_impressions::post_id)
+        .select((post_impressions::post_id, count_star()))
+        .order(count_star().desc())
+        .limit(limit)
+        .load(conn)
+        .await
+}
+
+/// When the reactions still standing on a post were made.
+pub async fn reaction_times(
+    conn: &mut AsyncPgConnection,
+    post_id: PostId,
+    since: DateTime<Utc>,
+) -> Result<Vec<DateTime<Utc>>, DieselError> {
+    reactions::table
+        .filter(reactions::post_id.eq(post_id))
+        .filter(reactions::like_status.ne(0))
+        .filter(reactions::created_at.ge(since))
+        .select(reactions::created_at)
+        .load(conn)
+        .await
+}
+
+pub async fn boost_times(
+    conn: &mut AsyncPgConnection,
+    post_id: PostId,
+    since: DateTime<Utc>,
+) -> Result<Vec<DateTime<Utc>>, DieselError> {
+    boosts::table
+        .filter(boosts::post_id.eq(post_id))
+        .filter(boosts::boosted_at.ge(since))
+        .select(boosts::boosted_at)
+        .load(conn)
+        .await
+}
+
+pub async fn reply_times(
+    conn: &mut AsyncPgConnection,
+    post_id: PostId,
+    since: DateTime<Utc>,
+) -> Result<Vec<DateTime<Utc>>, DieselError> {
+    posts::table
+        .filter(posts::reply_to.eq(post_id))
+        .filter(posts::hidden_at.is_null())
+        .filter(posts::time_posted.ge(since))
+        .filter(posts::time_posted.le(Utc::now()))
+        .select(posts::time_posted)
+        .load(conn)
+        .await
+}
+
+pub async fn vote_times(
+    conn: &mut AsyncPgConnection,
+    post_id: PostId,
+    since: DateTime<Utc>,
+) -> Result<Vec<DateTime<Utc>>, DieselError> {
+    poll_votes::table
+        .filter(poll_votes::post_id.eq(post_id))
+        .filter(poll_votes::created_at.ge(since))
+        .select(poll_votes::created_at)
+        .load(conn)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::tests::util::new_chat;
+    use crate::post::Post;
+    use crate::test_db::{self, Result};
+    use crate::user::tests::util::new_user;
+    use uchat_endpoint::post::types::NewPostOptions;
+
+    #[tokio::test]
+    async fn impressions_are_counted_once_per_viewer_and_window() -> Result<()> {
+        let mut conn = test_db::new_connection().await;
+        let author = new_user(&mut conn, "author").await;
+        let viewer = new_user(&mut conn, "viewer").await;
+        let other = new_user(&mut conn, "other").await;
+        let post = Post::new(author.id, new_chat("hello"), NewPostOptions::default())?;
+        let post_id = crate::post::new(&mut conn, post).await?;
+
+        let now = Utc::now();
+        let since = window_start(now) - Duration::hours(1);
+        record_impressions(&mut conn, viewer.id, &[post_id], now).await?;
+        record_impressions(&mut conn, viewer.id, &[post_id], now).await?;
+        record_impressions(&mut conn, other.id, &[post_id], now).await?;
+        record_impressions(&mut conn, viewer.id, &[post_id], now - Duration::hours(1)).await?;
+
+        assert_eq!(
+            impressions(&mut conn, post_id, since).await?,
+            vec![
+                (window_start(now - Duration::hours(1)), 1),
+                (window_start(now), 2)
+            ]
+        );
+        assert_eq!(
+            most_seen_posts(&mut conn, author.id, since, 10).await?,
+            vec![(post_id, 3)]
+        );
+
+        record_profile_visit(&mut conn, author.id, viewer.id, now).await?;
+        record_profile_visit(&mut conn, author.id, viewer.id, now).await?;
+        assert_eq!(
+            profile_visits(&mut conn, author.id, since).await?,
+            vec![(window_start(now), 1)]
+        );
+
+        Ok(())
+    }
+}
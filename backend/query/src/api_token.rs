@@ -0,0 +1,116 @@
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uchat_domain::{ApiTokenId, UserId};
+
+use crate::schema::api_tokens;
+use crate::DieselError;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = api_tokens)]
+pub struct ApiToken {
+    pub id: ApiTokenId,
+    pub user_id: UserId,
+    pub name: String,
+    pub token_prefix: String,
+    pub token_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiToken {
+    pub fn is_usable(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at.map(|at| at > now).unwrap_or(true)
+    }
+}
+
+pub async fn new(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    name: &str,
+    token_prefix: &str,
+    token_hash: &str,
+    scopes: Vec<String>,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<ApiToken, DieselError> {
+    let token = ApiToken {
+        id: ApiTokenId::new(),
+        user_id,
+        name: name.to_string(),
+        token_prefix: token_prefix.to_string(),
+        token_hash: token_hash.to_string(),
+        scopes,
+        expires_at,
+        last_used_at: None,
+        revoked_at: None,
+        created_at: Utc::now(),
+    };
+
+    diesel::insert_into(api_tokens::table)
+        .values(&token)
+        .get_result(conn)
+        .await
+}
+
+pub async fn find_by_hash(
+    conn: &mut AsyncPgConnection,
+    token_hash: &str,
+) -> Result<Option<ApiToken>, DieselError> {
+    api_tokens::table
+        .filter(api_tokens::token_hash.eq(token_hash))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+/// Tokens of a user which were not revoked, newest first.
+pub async fn list(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+) -> Result<Vec<ApiToken>, DieselError> {
+    api_tokens::table
+        .filter(api_tokens::user_id.eq(user_id))
+        .filter(api_tokens::revoked_at.is_null())
+        .order(api_tokens::created_at.desc())
+        .get_results(conn)
+        .await
+}
+
+/// Revokes a token of the user. Returns `false` if there was nothing to revoke.
+pub async fn revoke(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    token_id: ApiTokenId,
+) -> Result<bool, DieselError> {
+    let updated = diesel::update(api_tokens::table)
+        .filter(api_tokens::id.eq(token_id))
+        .filter(api_tokens::user_id.eq(user_id))
+        .filter(api_tokens::revoked_at.is_null())
+        .set(api_tokens::revoked_at.eq(Utc::now()))
+        .execute(conn)
+        .await?;
+    Ok(updated == 1)
+}
+
+/// Records that the token was used, at most once per `interval`.
+pub async fn touch(
+    conn: &mut AsyncPgConnection,
+    token_id: ApiTokenId,
+    now: DateTime<Utc>,
+    interval: Duration,
+) -> Result<(), DieselError> {
+    diesel::update(api_tokens::table)
+        .filter(api_tokens::id.eq(token_id))
+        .filter(
+            api_tokens::last_used_at
+                .is_null()
+                .or(api_tokens::last_used_at.lt(now - interval)),
+        )
+        .set(api_tokens::last_used_at.eq(now))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
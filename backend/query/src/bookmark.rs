@@ -0,0 +1,203 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uchat_domain::{BookmarkFolderId, PostId, UserId};
+
+use crate::schema::{bookmark_folders, bookmarks};
+use crate::DieselError;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable)]
+#[diesel(table_name = bookmarks)]
+pub struct Bookmark {
+    pub user_id: UserId,
+    pub post_id: PostId,
+    pub created_at: DateTime<Utc>,
+    pub folder_id: Option<BookmarkFolderId>,
+    pub note: Option<String>,
+}
+
+/// Named collection of bookmarks of `user_id`.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = bookmark_folders)]
+pub struct Folder {
+    pub id: BookmarkFolderId,
+    pub user_id: UserId,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn new_folder(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    name: &str,
+) -> Result<Folder, DieselError> {
+    let folder = Folder {
+        id: BookmarkFolderId::new(),
+        user_id,
+        name: name.to_string(),
+        created_at: Utc::now(),
+    };
+
+    diesel::insert_into(bookmark_folders::table)
+        .values(&folder)
+        .get_result(conn)
+        .await
+}
+
+/// Renames a folder of the user. Returns `None` if the user has no such folder.
+pub async fn rename_folder(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    folder_id: BookmarkFolderId,
+    name: &str,
+) -> Result<Option<Folder>, DieselError> {
+    diesel::update(bookmark_folders::table)
+        .filter(bookmark_folders::id.eq(folder_id))
+        .filter(bookmark_folders::user_id.eq(user_id))
+        .set(bookmark_folders::name.eq(name))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+/// Deletes a folder of the user, leaving its bookmarks unsorted. Returns
+/// `false` if there was nothing to delete.
+pub async fn delete_folder(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    folder_id: BookmarkFolderId,
+) -> Result<bool, DieselError> {
+    let deleted = diesel::delete(bookmark_folders::table)
+        .filter(bookmark_folders::id.eq(folder_id))
+        .filter(bookmark_folders::user_id.eq(user_id))
+        .execute(conn)
+        .await?;
+    Ok(deleted == 1)
+}
+
+pub async fn get_folder(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    folder_id: BookmarkFolderId,
+) -> Result<Option<Folder>, DieselError> {
+    bookmark_folders::table
+        .filter(bookmark_folders::id.eq(folder_id))
+        .filter(bookmark_folders::user_id.eq(user_id))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+/// Folders of the user by name.
+pub async fn folders(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+) -> Result<Vec<Folder>, DieselError> {
+    bookmark_folders::table
+        .filter(bookmark_folders::user_id.eq(user_id))
+        .order((
+            bookmark_folders::name.asc(),
+            bookmark_folders::created_at.asc(),
+        ))
+        .get_results(conn)
+        .await
+}
+
+pub async fn folder_count(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+) -> Result<i64, DieselError> {
+    bookmark_folders::table
+        .filter(bookmark_folders::user_id.eq(user_id))
+        .count()
+        .get_result(conn)
+        .await
+}
+
+pub async fn count_in_folder(
+    conn: &mut AsyncPgConnection,
+    folder_id: BookmarkFolderId,
+) -> Result<i64, DieselError> {
+    bookmarks::table
+        .filter(bookmarks::folder_id.eq(folder_id))
+        .count()
+        .get_result(conn)
+        .await
+}
+
+/// Moves a bookmark into a folder, or out of all folders with `None`.
+/// Returns `false` if the post isn't bookmarked.
+pub async fn move_to(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    post_id: PostId,
+    folder_id: Option<BookmarkFolderId>,
+) -> Result<bool, DieselError> {
+    let updated = diesel::update(bookmarks::table)
+        .filter(bookmarks::user_id.eq(user_id))
+        .filter(bookmarks::post_id.eq(post_id))
+        .set(bookmarks::folder_id.eq(folder_id))
+        .execute(conn)
+        .await?;
+    Ok(updated == 1)
+}
+
+/// Sets or clears the note on a bookmark. Returns `false` if the post isn't
+/// bookmarked.
+pub async fn set_note(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    post_id: PostId,
+    note: Option<&str>,
+) -> Result<bool, DieselError> {
+    let updated = diesel::update(bookmarks::table)
+        .filter(bookmarks::user_id.eq(user_id))
+        .filter(bookmarks::post_id.eq(post_id))
+        .set(bookmarks::note.eq(note))
+        .execute(conn)
+        .await?;
+    Ok(updated == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::tests::util::new_chat;
+    use crate::post::Post;
+    use crate::test_db::{self, Result};
+    use crate::user::tests::util::new_user;
+    use uchat_endpoint::post::types::NewPostOptions;
+
+    #[tokio::test]
+    async fn bookmarks_are_sorted_into_folders_newest_first() -> Result<()> {
+        let mut conn = test_db::new_connection().await;
+        let user = new_user(&mut conn, "reader").await;
+        let author = new_user(&mut conn, "author").await;
+
+        let post = |msg| Post::new(author.id, new_chat(msg), NewPostOptions::default());
+        let first = crate::post::new(&mut conn, post("first")?).await?;
+        let second = crate::post::new(&mut conn, post("second")?).await?;
+        crate::post::bookmark(&mut conn, user.id, first).await?;
+        crate::post::bookmark(&mut conn, user.id, second).await?;
+
+        let folder = new_folder(&mut conn, user.id, "recipes").await?;
+        assert!(move_to(&mut conn, user.id, first, Some(folder.id)).await?);
+        assert!(set_note(&mut conn, user.id, first, Some("try this")).await?);
+        assert_eq!(count_in_folder(&mut conn, folder.id).await?, 1);
+
+        let all = crate::post::get_bookmarked_posts(&mut conn, user.id, None).await?;
+        let ids = all.iter().map(|(post, _)| post.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![second, first]);
+
+        let sorted = crate::post::get_bookmarked_posts(&mut conn, user.id, Some(folder.id)).await?;
+        assert_eq!(sorted.len(), 1);
+        assert_eq!(sorted[0].0.id, first);
+        assert_eq!(sorted[0].1.note.as_deref(), Some("try this"));
+
+        assert!(delete_folder(&mut conn, user.id, folder.id).await?);
+        let all = crate::post::get_bookmarked_posts(&mut conn, user.id, None).await?;
+        assert!(all.iter().all(|(_, bookmark)| bookmark.folder_id.is_none()));
+
+        Ok(())
+    }
+}
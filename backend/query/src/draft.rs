@@ -0,0 +1,206 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use uchat_domain::{DraftId, ImageId, PostId, UserId};
+use uchat_endpoint::post::types::Content;
+
+use crate::post::Post;
+use crate::schema::drafts;
+use crate::DieselError;
+
+/// Post being written. Content and options are stored as sent, so drafts
+/// can be of any kind of post.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = drafts)]
+pub struct Draft {
+    pub id: DraftId,
+    pub user_id: UserId,
+    pub content: serde_json::Value,
+    pub options: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+pub async fn new(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    content: serde_json::Value,
+    options: serde_json::Value,
+) -> Result<Draft, DieselError> {
+    let now = Utc::now();
+    let draft = Draft {
+        id: DraftId::new(),
+        user_id,
+        content,
+        options,
+        created_at: now,
+        updated_at: now,
+    };
+
+    diesel::insert_into(drafts::table)
+        .values(&draft)
+        .get_result(conn)
+        .await
+}
+
+/// Replaces a draft of the user. Returns `None` if the user has no such draft.
+pub async fn update(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    draft_id: DraftId,
+    content: serde_json::Value,
+    options: serde_json::Value,
+) -> Result<Option<Draft>, DieselError> {
+    diesel::update(drafts::table)
+        .filter(drafts::id.eq(draft_id))
+        .filter(drafts::user_id.eq(user_id))
+        .set((
+            drafts::content.eq(content),
+            drafts::options.eq(options),
+            drafts::updated_at.eq(Utc::now()),
+        ))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+/// Every draft of a user, most recently saved first.
+pub async fn list(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+) -> Result<Vec<Draft>, DieselError> {
+    drafts::table
+        .filter(drafts::user_id.eq(user_id))
+        .order(drafts::updated_at.desc())
+        .get_results(conn)
+        .await
+}
+
+pub async fn get(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    draft_id: DraftId,
+) -> Result<Option<Draft>, DieselError> {
+    drafts::table
+        .filter(drafts::id.eq(draft_id))
+        .filter(drafts::user_id.eq(user_id))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+pub async fn count(conn: &mut AsyncPgConnection, user_id: UserId) -> Result<i64, DieselError> {
+    drafts::table
+        .filter(drafts::user_id.eq(user_id))
+        .count()
+        .get_result(conn)
+        .await
+}
+
+/// Deletes a draft of the user. Returns `false` if there was nothing to delete.
+pub async fn delete(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    draft_id: DraftId,
+) -> Result<bool, DieselError> {
+    let deleted = diesel::delete(drafts::table)
+        .filter(drafts::id.eq(draft_id))
+        .filter(drafts::user_id.eq(user_id))
+        .execute(conn)
+        .await?;
+    Ok(deleted == 1)
+}
+
+/// Creates the post and deletes the draft it was written in, both or
+/// neither, so a draft is posted once. Returns `None` if the user has no
+/// such draft.
+pub async fn publish(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    draft_id: DraftId,
+    post: Post,
+) -> Result<Option<PostId>, DieselError> {
+    conn.transaction::<Option<PostId>, DieselError, _>(|conn| {
+        async move {
+            if !delete(conn, user_id, draft_id).await? {
+                return Ok(None);
+            }
+            crate::post::new(conn, post).await.map(Some)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Ids of the images in drafts, which have to be kept until the drafts are
+/// published or deleted.
+pub async fn image_ids(conn: &mut AsyncPgConnection) -> Result<Vec<ImageId>, DieselError> {
+    let contents = drafts::table
+        .filter(drafts::content.has_any_key(vec!["Image", "Gallery"]))
+        .select(drafts::content)
+        .load::<serde_json::Value>(conn)
+        .await?;
+    Ok(contents
+        .into_iter()
+        .filter_map(|content| serde_json::from_value::<Content>(content).ok())
+        .flat_map(|content| content.image_ids())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::tests::util::new_chat;
+    use crate::test_db::{self, Result};
+    use crate::user::tests::util::new_user;
+    use uchat_endpoint::post::types::NewPostOptions;
+
+    #[tokio::test]
+    async fn drafts_are_private_and_published_once() -> Result<()> {
+        let mut conn = test_db::new_connection().await;
+        let author = new_user(&mut conn, "author").await;
+        let other = new_user(&mut conn, "other").await;
+
+        let content = serde_json::to_value(new_chat("first try")).unwrap();
+        let options = serde_json::to_value(NewPostOptions::default()).unwrap();
+        let draft = new(&mut conn, author.id, content, options.clone()).await?;
+
+        let content = serde_json::to_value(new_chat("second try")).unwrap();
+        assert!(update(
+            &mut conn,
+            other.id,
+            draft.id,
+            content.clone(),
+            options.clone()
+        )
+        .await?
+        .is_none());
+        let updated = update(&mut conn, author.id, draft.id, content.clone(), options)
+            .await?
+            .expect("author can update the draft");
+        assert_eq!(updated.content, content);
+        assert!(list(&mut conn, other.id).await?.is_empty());
+        assert_eq!(list(&mut conn, author.id).await?, vec![updated]);
+
+        let post = |user_id| {
+            Post::new(user_id, new_chat("second try"), NewPostOptions::default()).unwrap()
+        };
+        assert_eq!(
+            publish(&mut conn, other.id, draft.id, post(other.id)).await?,
+            None
+        );
+        let post_id = publish(&mut conn, author.id, draft.id, post(author.id))
+            .await?
+            .expect("author can publish the draft");
+        crate::post::get(&mut conn, post_id).await?;
+        assert_eq!(
+            publish(&mut conn, author.id, draft.id, post(author.id)).await?,
+            None
+        );
+        assert!(get(&mut conn, author.id, draft.id).await?.is_none());
+
+        Ok(())
+    }
+}
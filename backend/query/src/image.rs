@@ -0,0 +1,271 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncPgConnection, RunQueryDsl};
+use uchat_domain::{ImageId, UserId};
+use uchat_endpoint::post::types::Content;
+
+use crate::schema::{image_variants, images, posts, users};
+use crate::DieselError;
+
+/// Metadata of an uploaded image. The bytes live in the media store, under
+/// the id as key.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = images)]
+pub struct Image {
+    pub id: ImageId,
+    pub user_id: Option<UserId>,
+    pub mime: String,
+    pub byte_size: i64,
+    pub width: i32,
+    pub height: i32,
+    pub sha256: String,
+    pub created_at: DateTime<Utc>,
+    pub used_at: DateTime<Utc>,
+}
+
+/// Resized copy of an image.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = image_variants)]
+pub struct ImageVariant {
+    pub image_id: ImageId,
+    pub width: i32,
+    pub height: i32,
+    pub mime: String,
+    pub byte_size: i64,
+}
+
+pub async fn new(
+    conn: &mut AsyncPgConnection,
+    image: &Image,
+    variants: &[ImageVariant],
+) -> Result<(), DieselError> {
+    use diesel_async::AsyncConnection;
+
+    conn.transaction::<(), DieselError, _>(|conn| {
+        async move {
+            diesel::insert_into(images::table)
+                .values(image)
+                .execute(conn)
+                .await?;
+            diesel::insert_into(image_variants::table)
+                .values(variants)
+                .execute(conn)
+                .await?;
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+pub async fn get(
+    conn: &mut AsyncPgConnection,
+    image_id: ImageId,
+) -> Result<Option<Image>, DieselError> {
+    images::table.find(image_id).first(conn).await.optional()
+}
+
+/// Image with the same content, to store identical uploads once.
+pub async fn find_by_sha256(
+    conn: &mut AsyncPgConnection,
+    sha256: &str,
+) -> Result<Option<Image>, DieselError> {
+    images::table
+        .filter(images::sha256.eq(sha256))
+        .order(images::created_at.asc())
+        .first(conn)
+        .await
+        .optional()
+}
+
+/// Records another upload of the image, which keeps it from being removed
+/// before the upload is put to use.
+pub async fn mark_used(conn: &mut AsyncPgConnection, image_id: ImageId) -> Result<(), DieselError> {
+    diesel::update(images::table.find(image_id))
+        .set(images::used_at.eq(Utc::now()))
+        .execute(conn)
+        .await
+        .map(|_| ())
+}
+
+/// Images last uploaded before `cutoff`, oldest first.
+pub async fn used_before(
+    conn: &mut AsyncPgConnection,
+    cutoff: DateTime<Utc>,
+) -> Result<Vec<Image>, DieselError> {
+    images::table
+        .filter(images::used_at.lt(cutoff))
+        .order(images::used_at.asc())
+        .load(conn)
+        .await
+}
+
+/// Ids of all images which profiles, posts, drafts, videos or link previews
+/// refer to.
+pub async fn referenced(conn: &mut AsyncPgConnection) -> Result<HashSet<ImageId>, DieselError> {
+    let mut ids = users::table
+        .filter(users::profile_image.is_not_null())
+        .select(users::profile_image)
+        .load::<Option<String>>(conn)
+        .await?
+        .into_iter()
+        .flatten()
+        .filter_map(|image_id| image_id.parse().ok())
+        .collect::<HashSet<ImageId>>();
+
+    let contents = posts::table
+        .filter(posts::content.has_any_key(vec!["Image", "Gallery"]))
+        .select(posts::content)
+        .load::<serde_json::Value>(conn)
+        .await?;
+    for content in contents {
+        if let Ok(content) = serde_json::from_value::<Content>(content) {
+            ids.extend(content.image_ids());
+        }
+    }
+    ids.extend(crate::draft::image_ids(conn).await?);
+    ids.extend(crate::video::poster_ids(conn).await?);
+    ids.extend(crate::link_preview::image_ids(conn).await?);
+    Ok(ids)
+}
+
+/// Removes the image and its variants, unless it was uploaded again since
+/// `cutoff`. Returns whether it was removed.
+pub async fn delete_unused(
+    conn: &mut AsyncPgConnection,
+    image_id: ImageId,
+    cutoff: DateTime<Utc>,
+) -> Result<bool, DieselError> {
+    diesel::delete(
+        images::table
+            .find(image_id)
+            .filter(images::used_at.lt(cutoff)),
+    )
+    .execute(conn)
+    .await
+    .map(|deleted| deleted > 0)
+}
+
+/// Variants of an image, narrowest first.
+pub async fn variants(
+    conn: &mut AsyncPgConnection,
+    image_id: ImageId,
+) -> Result<Vec<ImageVariant>, DieselError> {
+    image_variants::table
+        .filter(image_variants::image_id.eq(image_id))
+        .order(image_variants::width.asc())
+        .load(conn)
+        .await
+}
+
+pub async fn get_variant(
+    conn: &mut AsyncPgConnection,
+    image_id: ImageId,
+    width: i32,
+) -> Result<Option<ImageVariant>, DieselError> {
+    image_variants::table
+        .find((image_id, width))
+        .first(conn)
+        .await
+        .optional()
+}
+
+/// Finds who uploaded an image stored before owners were recorded, by
+/// looking for the profile or post using it.
+pub async fn find_legacy_owner(
+    conn: &mut AsyncPgConnection,
+    image_id: ImageId,
+) -> Result<Option<UserId>, DieselError> {
+    let profile_owner = users::table
+        .filter(users::profile_image.eq(image_id.to_string()))
+        .select(users::id)
+        .first(conn)
+        .await
+        .optional()?;
+    if profile_owner.is_some() {
+        return Ok(profile_owner);
+    }
+
+    let content = serde_json::json!({ "Image": { "kind": { "Id": image_id } } });
+    posts::table
+        .filter(posts::content.contains(content))
+        .select(posts::user_id)
+        .first(conn)
+        .await
+        .optional()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use uchat_endpoint::post::types::{
+        Gallery, GalleryImage, Image as ImageContent, ImageKind, NewPostOptions,
+    };
+
+    use super::*;
+    use crate::post::Post;
+    use crate::test_db::{self, Result};
+    use crate::user::tests::util as test_user;
+
+    fn new_image(user_id: UserId) -> Image {
+        Image {
+            id: ImageId::new(),
+            user_id: Some(user_id),
+            mime: "image/png".to_string(),
+            byte_size: 1,
+            width: 1,
+            height: 1,
+            sha256: "hash".to_string(),
+            created_at: Utc::now(),
+            used_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn removes_only_unused_images() -> Result<()> {
+        let mut conn = test_db::new_connection().await;
+        let user = test_user::new_user(&mut conn, "user1").await;
+        let (posted, in_gallery, unused) =
+            (new_image(user.id), new_image(user.id), new_image(user.id));
+        new(&mut conn, &posted, &[]).await?;
+        new(&mut conn, &in_gallery, &[]).await?;
+        new(&mut conn, &unused, &[]).await?;
+
+        let content = Content::Image(ImageContent {
+            kind: ImageKind::Id(posted.id),
+            caption: None,
+            variants: vec![],
+        });
+        let post = Post::new(user.id, content, NewPostOptions::default())
+            .expect("Failed to create new post structure");
+        crate::post::new(&mut conn, post).await?;
+        let content = Content::Gallery(Gallery {
+            images: vec![GalleryImage {
+                kind: ImageKind::Id(in_gallery.id),
+                alt_text: None,
+                sensitive: false,
+                variants: vec![],
+            }],
+            caption: None,
+        });
+        let post = Post::new(user.id, content, NewPostOptions::default())
+            .expect("Failed to create new post structure");
+        crate::post::new(&mut conn, post).await?;
+
+        let ids = referenced(&mut conn).await?;
+        assert!(ids.contains(&posted.id));
+        assert!(ids.contains(&in_gallery.id));
+        assert!(!ids.contains(&unused.id));
+
+        // Used after the cutoff
+        let cutoff = Utc::now() - Duration::hours(1);
+        assert!(!delete_unused(&mut conn, unused.id, cutoff).await?);
+        let cutoff = Utc::now() + Duration::hours(1);
+        assert!(delete_unused(&mut conn, unused.id, cutoff).await?);
+        assert!(get(&mut conn, unused.id).await?.is_none());
+
+        Ok(())
+    }
+}
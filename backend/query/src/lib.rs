@@ -16,5 +16,19 @@ pub use uchat_domain::id::*;
 pub mod util;
 pub use util::*;
 
+pub mod analytics;
+pub mod api_token;
+pub mod bookmark;
+pub mod draft;
+pub mod image;
+pub mod link_preview;
+pub mod list;
+pub mod moderation;
+pub mod mute;
+pub mod oidc;
 pub mod post;
+pub mod security;
 pub mod session;
+pub mod signing_key;
+pub mod totp;
+pub mod video;
@@ -0,0 +1,142 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uchat_domain::ImageId;
+
+use crate::schema::link_previews;
+use crate::DieselError;
+
+/// Preview of a linked page, shared by all posts linking to it.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = link_previews)]
+pub struct LinkPreview {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub site_name: Option<String>,
+    pub image_id: Option<ImageId>,
+    /// The last fetch failed, what is left is from an earlier one.
+    pub failed: bool,
+    pub fetched_at: Option<DateTime<Utc>>,
+    pub claimed_at: DateTime<Utc>,
+}
+
+/// What a fetch found on a page.
+#[derive(Debug, Clone, Default, PartialEq, AsChangeset)]
+#[diesel(table_name = link_previews, treat_none_as_null = true)]
+pub struct PageDetails {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub site_name: Option<String>,
+    pub image_id: Option<ImageId>,
+}
+
+pub async fn get(
+    conn: &mut AsyncPgConnection,
+    url: &str,
+) -> Result<Option<LinkPreview>, DieselError> {
+    link_previews::table.find(url).first(conn).await.optional()
+}
+
+/// Claims fetching the page at `url`, unless that was last done after
+/// `stale_before`. Returns whether the caller should fetch it.
+pub async fn claim(
+    conn: &mut AsyncPgConnection,
+    url: &str,
+    stale_before: DateTime<Utc>,
+) -> Result<bool, DieselError> {
+    // `QueryDsl::filter` only covers tables, not the upsert's WHERE clause
+    use diesel::query_dsl::methods::FilterDsl;
+
+    let now = Utc::now();
+    diesel::insert_into(link_previews::table)
+        .values((
+            link_previews::url.eq(url),
+            link_previews::claimed_at.eq(now),
+        ))
+        .on_conflict(link_previews::url)
+        .do_update()
+        .set(link_previews::claimed_at.eq(now))
+        .filter(link_previews::claimed_at.lt(stale_before))
+        .execute(conn)
+        .await
+        .map(|claimed| claimed > 0)
+}
+
+/// Records the outcome of a fetch. A failed fetch keeps what an earlier one
+/// found.
+pub async fn save(
+    conn: &mut AsyncPgConnection,
+    url: &str,
+    details: Option<&PageDetails>,
+) -> Result<(), DieselError> {
+    let target = link_previews::table.find(url);
+    let now = Utc::now();
+    match details {
+        Some(details) => {
+            diesel::update(target)
+                .set((
+                    details,
+                    link_previews::failed.eq(false),
+                    link_previews::fetched_at.eq(now),
+                ))
+                .execute(conn)
+                .await?
+        }
+        None => {
+            diesel::update(target)
+                .set((
+                    link_previews::failed.eq(true),
+                    link_previews::fetched_at.eq(now),
+                ))
+                .execute(conn)
+                .await?
+        }
+    };
+    Ok(())
+}
+
+/// Images shown in previews, which must not be removed as unused.
+pub async fn image_ids(conn: &mut AsyncPgConnection) -> Result<Vec<ImageId>, DieselError> {
+    link_previews::table
+        .filter(link_previews::image_id.is_not_null())
+        .select(link_previews::image_id.assume_not_null())
+        .load(conn)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::{self, Result};
+    use chrono::Duration;
+
+    const URL: &str = "https://example.com/article";
+
+    #[tokio::test]
+    async fn fetches_are_claimed_once_until_stale() -> Result<()> {
+        let mut conn = test_db::new_connection().await;
+
+        let stale_before = Utc::now() - Duration::hours(24);
+        assert!(claim(&mut conn, URL, stale_before).await?);
+        assert!(!claim(&mut conn, URL, stale_before).await?);
+        let pending = get(&mut conn, URL).await?.expect("claim adds the row");
+        assert_eq!(pending.fetched_at, None);
+
+        let details = PageDetails {
+            title: Some("Article".to_string()),
+            ..PageDetails::default()
+        };
+        save(&mut conn, URL, Some(&details)).await?;
+        // A failed refresh keeps the earlier details
+        assert!(claim(&mut conn, URL, Utc::now() + Duration::seconds(1)).await?);
+        save(&mut conn, URL, None).await?;
+
+        let preview = get(&mut conn, URL).await?.expect("preview was saved");
+        assert_eq!(preview.title.as_deref(), Some("Article"));
+        assert!(preview.failed);
+        assert!(preview.fetched_at.is_some());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uchat_domain::{ListId, UserId};
+
+use crate::schema::{list_members, lists};
+use crate::DieselError;
+
+/// Named set of users curated by `user_id`.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = lists)]
+pub struct List {
+    pub id: ListId,
+    pub user_id: UserId,
+    pub name: String,
+    pub description: Option<String>,
+    pub private: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn new(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    name: &str,
+    description: Option<&str>,
+    private: bool,
+) -> Result<List, DieselError> {
+    let list = List {
+        id: ListId::new(),
+        user_id,
+        name: name.to_string(),
+        description: description.map(str::to_string),
+        private,
+        created_at: Utc::now(),
+    };
+
+    diesel::insert_into(lists::table)
+        .values(&list)
+        .get_result(conn)
+        .await
+}
+
+/// Renames a list of the user. Returns `None` if the user has no such list.
+pub async fn update(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    list_id: ListId,
+    name: &str,
+    description: Option<&str>,
+    private: bool,
+) -> Result<Option<List>, DieselError> {
+    diesel::update(lists::table)
+        .filter(lists::id.eq(list_id))
+        .filter(lists::user_id.eq(user_id))
+        .set((
+            lists::name.eq(name),
+            lists::description.eq(description),
+            lists::private.eq(private),
+        ))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+/// Deletes a list of the user. Returns `false` if there was nothing to delete.
+pub async fn delete(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    list_id: ListId,
+) -> Result<bool, DieselError> {
+    let deleted = diesel::delete(lists::table)
+        .filter(lists::id.eq(list_id))
+        .filter(lists::user_id.eq(user_id))
+        .execute(conn)
+        .await?;
+    Ok(deleted == 1)
+}
+
+pub async fn get(
+    conn: &mut AsyncPgConnection,
+    list_id: ListId,
+) -> Result<Option<List>, DieselError> {
+    lists::table
+        .filter(lists::id.eq(list_id))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+/// Lists of a user by name, leaving out private ones unless `with_private`.
+pub async fn owned_by(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    with_private: bool,
+) -> Result<Vec<List>, DieselError> {
+    let mut query = lists::table
+        .filter(lists::user_id.eq(user_id))
+        .order((lists::name.asc(), lists::created_at.asc()))
+        .into_boxed();
+    if !with_private {
+        query = query.filter(lists::private.eq(false));
+    }
+    query.get_results(conn).await
+}
+
+pub async fn count(conn: &mut AsyncPgConnection, user_id: UserId) -> Result<i64, DieselError> {
+    lists::table
+        .filter(lists::user_id.eq(user_id))
+        .count()
+        .get_result(conn)
+        .await
+}
+
+pub async fn add_member(
+    conn: &mut AsyncPgConnection,
+    list_id: ListId,
+    user_id: UserId,
+) -> Result<(), DieselError> {
+    diesel::insert_into(list_members::table)
+        .values((
+            list_members::list_id.eq(list_id),
+            list_members::user_id.eq(user_id),
+        ))
+        .on_conflict((list_members::list_id, list_members::user_id))
+        .do_nothing()
+        .execute(conn)
+        .await
+        .map(|_| ())
+}
+
+pub async fn remove_member(
+    conn: &mut AsyncPgConnection,
+    list_id: ListId,
+    user_id: UserId,
+) -> Result<(), DieselError> {
+    diesel::delete(list_members::table)
+        .filter(list_members::list_id.eq(list_id))
+        .filter(list_members::user_id.eq(user_id))
+        .execute(conn)
+        .await
+        .map(|_| ())
+}
+
+/// Members of a list, in the order they were added.
+pub async fn member_ids(
+    conn: &mut AsyncPgConnection,
+    list_id: ListId,
+) -> Result<Vec<UserId>, DieselError> {
+    list_members::table
+        .filter(list_members::list_id.eq(list_id))
+        .order(list_members::created_at.asc())
+        .select(list_members::user_id)
+        .get_results(conn)
+        .await
+}
+
+pub async fn member_count(
+    conn: &mut AsyncPgConnection,
+    list_id: ListId,
+) -> Result<i64, DieselError> {
+    list_members::table
+        .filter(list_members::list_id.eq(list_id))
+        .count()
+        .get_result(conn)
+        .await
+}
+
+/// Lists of `owner` which `member` is on.
+pub async fn containing(
+    conn: &mut AsyncPgConnection,
+    owner: UserId,
+    member: UserId,
+) -> Result<Vec<ListId>, DieselError> {
+    list_members::table
+        .inner_join(lists::table)
+        .filter(lists::user_id.eq(owner))
+        .filter(list_members::user_id.eq(member))
+        .select(lists::id)
+        .get_results(conn)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::tests::util::new_chat;
+    use crate::post::Post;
+    use crate::test_db::{self, Result};
+    use crate::user::tests::util::new_user;
+    use uchat_endpoint::post::types::NewPostOptions;
+
+    #[tokio::test]
+    async fn list_timeline_shows_posts_and_boosts_of_members() -> Result<()> {
+        let mut conn = test_db::new_connection().await;
+        let owner = new_user(&mut conn, "owner").await;
+        let member = new_user(&mut conn, "member").await;
+        let other = new_user(&mut conn, "other").await;
+
+        let list = new(&mut conn, owner.id, "friends", None, true).await?;
+        add_member(&mut conn, list.id, member.id).await?;
+        add_member(&mut conn, list.id, member.id).await?;
+        assert_eq!(member_count(&mut conn, list.id).await?, 1);
+        assert_eq!(
+            containing(&mut conn, owner.id, member.id).await?,
+            vec![list.id]
+        );
+
+        let post = |user_id, msg| Post::new(user_id, new_chat(msg), NewPostOptions::default());
+        let by_member = crate::post::new(&mut conn, post(member.id, "member")?).await?;
+        let by_other = crate::post::new(&mut conn, post(other.id, "other")?).await?;
+        let boosted = crate::post::new(&mut conn, post(other.id, "boosted")?).await?;
+        crate::post::boost(&mut conn, member.id, boosted, Utc::now()).await?;
+
+        let mut ids = crate::post::get_list_posts(&mut conn, list.id)
+            .await?
+            .into_iter()
+            .map(|post| post.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        let mut expected = vec![by_member, boosted];
+        expected.sort();
+        assert_eq!(ids, expected);
+        assert!(!ids.contains(&by_other));
+
+        remove_member(&mut conn, list.id, member.id).await?;
+        assert!(crate::post::get_list_posts(&mut conn, list.id)
+            .await?
+            .is_empty());
+
+        assert!(owned_by(&mut conn, owner.id, false).await?.is_empty());
+        assert_eq!(owned_by(&mut conn, owner.id, true).await?, vec![list]);
+
+        Ok(())
+    }
+}
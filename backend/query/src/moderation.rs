@@ -0,0 +1,324 @@
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use uchat_domain::{ModerationActionId, PostId, ReportId, UserId};
+use uchat_endpoint::moderation::types::{ModerationAction, ReportReason, ReportStatus};
+
+use crate::schema::{moderation_actions, posts, reports, users};
+use crate::DieselError;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = reports)]
+pub struct Report {
+    pub id: ReportId,
+    pub reporter_id: UserId,
+    pub user_id: UserId,
+    pub post_id: Option<PostId>,
+    pub reason: String,
+    pub details: Option<String>,
+    pub status: String,
+    pub resolved_by: Option<UserId>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = moderation_actions)]
+pub struct ModerationEntry {
+    pub id: ModerationActionId,
+    pub moderator_id: UserId,
+    pub action: String,
+    pub user_id: Option<UserId>,
+    pub post_id: Option<PostId>,
+    pub report_id: Option<ReportId>,
+    pub note: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// Replies which went with a deleted post, `posts.reply_to` cascades.
+    pub deleted_post_ids: Vec<PostId>,
+}
+
+/// Files a report about `user_id`, or about their post `post_id`.
+///
+/// A reporter has at most one open report per target, reporting the same
+/// thing again returns the existing report.
+pub async fn new_report(
+    conn: &mut AsyncPgConnection,
+    reporter_id: UserId,
+    user_id: UserId,
+    post_id: Option<PostId>,
+    reason: ReportReason,
+    details: Option<String>,
+) -> Result<Report, DieselError> {
+    let mut existing = reports::table
+        .filter(reports::reporter_id.eq(reporter_id))
+        .filter(reports::user_id.eq(user_id))
+        .filter(reports::status.eq(ReportStatus::Open.as_str()))
+        .into_boxed();
+    existing = match post_id {
+        Some(post_id) => existing.filter(reports::post_id.eq(post_id)),
+        None => existing.filter(reports::post_id.is_null()),
+    };
+    if let Some(report) = existing.first(conn).await.optional()? {
+        return Ok(report);
+    }
+
+    let report = Report {
+        id: ReportId::new(),
+        reporter_id,
+        user_id,
+        post_id,
+        reason: reason.as_str().to_string(),
+        details,
+        status: ReportStatus::Open.as_str().to_string(),
+        resolved_by: None,
+        resolved_at: None,
+        created_at: Utc::now(),
+    };
+    diesel::insert_into(reports::table)
+        .values(&report)
+        .get_result(conn)
+        .await
+}
+
+pub async fn get_report(
+    conn: &mut AsyncPgConnection,
+    report_id: ReportId,
+) -> Result<Option<Report>, DieselError> {
+    reports::table
+        .filter(reports::id.eq(report_id))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+/// Open reports, oldest first.
+pub async fn open_reports(
+    conn: &mut AsyncPgConnection,
+    limit: i64,
+) -> Result<Vec<Report>, DieselError> {
+    reports::table
+        .filter(reports::status.eq(ReportStatus::Open.as_str()))
+        .order(reports::created_at.asc())
+        .limit(limit)
+        .get_results(conn)
+        .await
+}
+
+/// Applies `action` and records it in the moderation log, in one transaction.
+///
+/// `user_id` is the user the action is about, which for post actions is the
+/// author. Every open report about the target is resolved, `report_id` is
+/// dismissed instead when the action is [`ModerationAction::DismissReport`].
+/// Deleting a post deletes the replies below it as well, their ids are kept in
+/// the entry and reports about them are resolved too.
+pub async fn take_action(
+    conn: &mut AsyncPgConnection,
+    moderator_id: UserId,
+    action: ModerationAction,
+    user_id: Option<UserId>,
+    report_id: Option<ReportId>,
+    note: Option<String>,
+) -> Result<ModerationEntry, DieselError> {
+    let now = Utc::now();
+    let mut entry = ModerationEntry {
+        id: ModerationActionId::new(),
+        moderator_id,
+        action: action.kind().as_str().to_string(),
+        user_id,
+        post_id: None,
+        report_id,
+        note,
+        expires_at: None,
+        created_at: now,
+        deleted_post_ids: vec![],
+    };
+
+    conn.transaction::<ModerationEntry, DieselError, _>(|conn| {
+        async move {
+            let open = reports::status.eq(ReportStatus::Open.as_str());
+            let resolved = (
+                reports::status.eq(ReportStatus::Resolved.as_str()),
+                reports::resolved_by.eq(moderator_id),
+                reports::resolved_at.eq(now),
+            );
+
+            match action {
+                ModerationAction::HidePost(post_id) | ModerationAction::DeletePost(post_id) => {
+                    entry.post_id = Some(post_id);
+                    if let ModerationAction::HidePost(_) = action {
+                        diesel::update(posts::table)
+                            .filter(posts::id.eq(post_id))
+                            .set(posts::hidden_at.eq(now))
+                            .execute(conn)
+                            .await?;
+                    } else {
+                        entry.deleted_post_ids = replies_below(conn, post_id).await?;
+                        diesel::delete(posts::table)
+                            .filter(posts::id.eq(post_id))
+                            .execute(conn)
+                            .await?;
+                    }
+                    diesel::update(reports::table)
+                        .filter(open)
+                        .filter(
+                            reports::post_id
+                                .eq(post_id)
+                                .or(reports::post_id.eq_any(&entry.deleted_post_ids)),
+                        )
+                        .set(resolved)
+                        .execute(conn)
+                        .await?;
+                }
+                ModerationAction::SuspendUser { user_id, days } => {
+                    let until = now + Duration::days(days.into());
+                    entry.expires_at = Some(until);
+                    diesel::update(users::table)
+                        .filter(users::id.eq(user_id))
+                        .set(users::suspended_until.eq(until))
+                        .execute(conn)
+                        .await?;
+                    diesel::update(reports::table)
+                        .filter(open)
+                        .filter(reports::user_id.eq(user_id))
+                        .filter(reports::post_id.is_null())
+                        .set(resolved)
+                        .execute(conn)
+                        .await?;
+                }
+                ModerationAction::BanUser(user_id) => {
+                    diesel::update(users::table)
+                        .filter(users::id.eq(user_id))
+                        .set(users::banned_at.eq(now))
+                        .execute(conn)
+                        .await?;
+                    // Their posts are gone from the feeds, so reports about
+                    // them are settled as well
+                    diesel::update(reports::table)
+                        .filter(open)
+                        .filter(reports::user_id.eq(user_id))
+                        .set(resolved)
+                        .execute(conn)
+                        .await?;
+                }
+                ModerationAction::DismissReport => {}
+            }
+
+            if let Some(report_id) = report_id {
+                let status = match action {
+                    ModerationAction::DismissReport => ReportStatus::Dismissed,
+                    _ => ReportStatus::Resolved,
+                };
+                diesel::update(reports::table)
+                    .filter(reports::id.eq(report_id))
+                    .filter(open)
+                    .set((
+                        reports::status.eq(status.as_str()),
+                        reports::resolved_by.eq(moderator_id),
+                        reports::resolved_at.eq(now),
+                    ))
+                    .execute(conn)
+                    .await?;
+            }
+
+            diesel::insert_into(moderation_actions::table)
+                .values(&entry)
+                .get_result(conn)
+                .await
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Every reply below `post_id`, at any depth.
+async fn replies_below(
+    conn: &mut AsyncPgConnection,
+    post_id: PostId,
+) -> Result<Vec<PostId>, DieselError> {
+    let mut replies = vec![];
+    let mut parents = vec![post_id];
+    while !parents.is_empty() {
+        parents = posts::table
+            .filter(posts::reply_to.eq_any(&parents))
+            .select(posts::id)
+            .get_results(conn)
+            .await?;
+        replies.extend_from_slice(&parents);
+    }
+    Ok(replies)
+}
+
+/// Moderation log, newest first.
+pub async fn log(
+    conn: &mut AsyncPgConnection,
+    older_than: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<ModerationEntry>, DieselError> {
+    let mut query = moderation_actions::table
+        .order(moderation_actions::created_at.desc())
+        .limit(limit)
+        .into_boxed();
+    if let Some(older_than) = older_than {
+        query = query.filter(moderation_actions::created_at.lt(older_than));
+    }
+    query.get_results(conn).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::post::{self as post_query, Post};
+    use crate::test_db::{self, Result};
+    use crate::user::tests::util as test_user;
+    use uchat_endpoint::post::types::NewPostOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn deleting_a_post_logs_the_replies_it_takes_along() -> Result<()> {
+        let mut conn = test_db::new_connection().await;
+        let author = test_user::new_user(&mut conn, "author").await;
+        let moderator = test_user::new_user(&mut conn, "moderator").await;
+        let new_post = |msg| {
+            let content = post_query::tests::util::new_chat(msg);
+            Post::new(author.id, content, NewPostOptions::default()).unwrap()
+        };
+        let thread = post_query::new_thread(
+            &mut conn,
+            vec![new_post("one"), new_post("two"), new_post("three")],
+        )
+        .await?;
+        let report = new_report(
+            &mut conn,
+            moderator.id,
+            author.id,
+            Some(thread[2]),
+            ReportReason::Spam,
+            None,
+        )
+        .await?;
+
+        let entry = take_action(
+            &mut conn,
+            moderator.id,
+            ModerationAction::DeletePost(thread[0]),
+            Some(author.id),
+            None,
+            None,
+        )
+        .await?;
+
+        assert_eq!(entry.post_id, Some(thread[0]));
+        let mut deleted = entry.deleted_post_ids;
+        deleted.sort();
+        let mut replies = thread[1..].to_vec();
+        replies.sort();
+        assert_eq!(deleted, replies);
+        assert!(post_query::get(&mut conn, thread[2]).await.is_err());
+        let report = get_report(&mut conn, report.id).await?.unwrap();
+        assert_eq!(report.status, ReportStatus::Resolved.as_str());
+
+        Ok(())
+    }
+}
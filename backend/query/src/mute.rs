@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uchat_domain::{MuteFilterId, UserId};
+
+use crate::schema::mute_filters;
+use crate::DieselError;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = mute_filters)]
+pub struct MuteFilter {
+    pub id: MuteFilterId,
+    pub user_id: UserId,
+    pub phrase: String,
+    pub whole_word: bool,
+    pub scopes: Vec<String>,
+    pub action: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn new(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    phrase: &str,
+    whole_word: bool,
+    scopes: Vec<String>,
+    action: &str,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<MuteFilter, DieselError> {
+    let filter = MuteFilter {
+        id: MuteFilterId::new(),
+        user_id,
+        phrase: phrase.to_string(),
+        whole_word,
+        scopes,
+        action: action.to_string(),
+        expires_at,
+        created_at: Utc::now(),
+    };
+
+    diesel::insert_into(mute_filters::table)
+        .values(&filter)
+        .get_result(conn)
+        .await
+}
+
+/// Every filter of a user, newest first.
+pub async fn list(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+) -> Result<Vec<MuteFilter>, DieselError> {
+    mute_filters::table
+        .filter(mute_filters::user_id.eq(user_id))
+        .order(mute_filters::created_at.desc())
+        .get_results(conn)
+        .await
+}
+
+/// Filters of a user which have not expired at `now`.
+pub async fn active(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    now: DateTime<Utc>,
+) -> Result<Vec<MuteFilter>, DieselError> {
+    mute_filters::table
+        .filter(mute_filters::user_id.eq(user_id))
+        .filter(
+            mute_filters::expires_at
+                .is_null()
+                .or(mute_filters::expires_at.gt(now)),
+        )
+        .get_results(conn)
+        .await
+}
+
+pub async fn count(conn: &mut AsyncPgConnection, user_id: UserId) -> Result<i64, DieselError> {
+    mute_filters::table
+        .filter(mute_filters::user_id.eq(user_id))
+        .count()
+        .get_result(conn)
+        .await
+}
+
+/// Deletes a filter of the user. Returns `false` if there was nothing to delete.
+pub async fn delete(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    filter_id: MuteFilterId,
+) -> Result<bool, DieselError> {
+    let deleted = diesel::delete(mute_filters::table)
+        .filter(mute_filters::id.eq(filter_id))
+        .filter(mute_filters::user_id.eq(user_id))
+        .execute(conn)
+        .await?;
+    Ok(deleted == 1)
+}
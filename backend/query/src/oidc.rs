@@ -0,0 +1,217 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use uchat_domain::{OidcSignupId, UserId};
+
+use crate::schema::{oidc_logins, oidc_signups, user_identities, users};
+use crate::DieselError;
+
+/// An authorization request which was sent to a provider and is waiting for
+/// the user to come back with a code.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = oidc_logins)]
+pub struct OidcLogin {
+    pub state: String,
+    pub provider: String,
+    pub nonce: String,
+    pub pkce_verifier: String,
+    pub link_user_id: Option<UserId>,
+    pub remember_me: bool,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub browser_key_hash: String,
+}
+
+/// A verified identity without an account, waiting for a handle.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = oidc_signups)]
+pub struct OidcSignup {
+    pub id: OidcSignupId,
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub suggested_handle: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub browser_key_hash: String,
+    pub remember_me: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = user_identities)]
+pub struct UserIdentity {
+    pub provider: String,
+    pub subject: String,
+    pub user_id: UserId,
+    pub email: Option<String>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn new_login(conn: &mut AsyncPgConnection, login: &OidcLogin) -> Result<(), DieselError> {
+    diesel::insert_into(oidc_logins::table)
+        .values(login)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Removes and returns a pending login, so each `state` can only be used once.
+/// Only the browser which started the login can finish it.
+pub async fn take_login(
+    conn: &mut AsyncPgConnection,
+    state: &str,
+    browser_key_hash: &str,
+) -> Result<Option<OidcLogin>, DieselError> {
+    diesel::delete(oidc_logins::table)
+        .filter(oidc_logins::state.eq(state))
+        .filter(oidc_logins::browser_key_hash.eq(browser_key_hash))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+pub async fn find_identity(
+    conn: &mut AsyncPgConnection,
+    provider: &str,
+    subject: &str,
+) -> Result<Option<UserIdentity>, DieselError> {
+    user_identities::table
+        .filter(user_identities::provider.eq(provider))
+        .filter(user_identities::subject.eq(subject))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+pub async fn identities(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+) -> Result<Vec<UserIdentity>, DieselError> {
+    user_identities::table
+        .filter(user_identities::user_id.eq(user_id))
+        .order(user_identities::created_at.asc())
+        .get_results(conn)
+        .await
+}
+
+pub async fn link_identity(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    provider: &str,
+    subject: &str,
+    email: Option<&str>,
+) -> Result<UserIdentity, DieselError> {
+    let identity = UserIdentity {
+        provider: provider.to_string(),
+        subject: subject.to_string(),
+        user_id,
+        email: email.map(ToString::to_string),
+        last_login_at: None,
+        created_at: Utc::now(),
+    };
+
+    diesel::insert_into(user_identities::table)
+        .values(&identity)
+        .get_result(conn)
+        .await
+}
+
+/// Removes a linked identity. Returns `false` if there was nothing to remove.
+pub async fn unlink_identity(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    provider: &str,
+) -> Result<bool, DieselError> {
+    let deleted = diesel::delete(user_identities::table)
+        .filter(user_identities::user_id.eq(user_id))
+        .filter(user_identities::provider.eq(provider))
+        .execute(conn)
+        .await?;
+    Ok(deleted > 0)
+}
+
+pub async fn touch_identity(
+    conn: &mut AsyncPgConnection,
+    provider: &str,
+    subject: &str,
+    email: Option<&str>,
+) -> Result<(), DieselError> {
+    diesel::update(user_identities::table)
+        .filter(user_identities::provider.eq(provider))
+        .filter(user_identities::subject.eq(subject))
+        .set((
+            user_identities::last_login_at.eq(Utc::now()),
+            user_identities::email.eq(email),
+        ))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+//------------------------------------------------------------------------------
+pub async fn new_signup(
+    conn: &mut AsyncPgConnection,
+    signup: &OidcSignup,
+) -> Result<(), DieselError> {
+    diesel::insert_into(oidc_signups::table)
+        .values(signup)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Pending signup, if it was started by the browser holding the key.
+pub async fn get_signup(
+    conn: &mut AsyncPgConnection,
+    signup_id: OidcSignupId,
+    browser_key_hash: &str,
+) -> Result<Option<OidcSignup>, DieselError> {
+    oidc_signups::table
+        .filter(oidc_signups::id.eq(signup_id))
+        .filter(oidc_signups::browser_key_hash.eq(browser_key_hash))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+/// Creates the account for a pending signup and links the identity to it.
+/// Fails without changes if the handle is already taken.
+pub async fn complete_signup(
+    conn: &mut AsyncPgConnection,
+    signup: &OidcSignup,
+    password_hash: &str,
+    handle: &str,
+) -> Result<UserId, DieselError> {
+    let user_id = UserId::new();
+    conn.transaction::<(), DieselError, _>(|conn| {
+        async move {
+            diesel::delete(oidc_signups::table)
+                .filter(oidc_signups::id.eq(signup.id))
+                .execute(conn)
+                .await?;
+            diesel::insert_into(users::table)
+                .values((
+                    users::id.eq(user_id),
+                    users::password_hash.eq(password_hash),
+                    users::handle.eq(handle),
+                    users::email.eq(signup.email.as_deref()),
+                ))
+                .execute(conn)
+                .await?;
+            link_identity(
+                conn,
+                user_id,
+                &signup.provider,
+                &signup.subject,
+                signup.email.as_deref(),
+            )
+            .await?;
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await?;
+    Ok(user_id)
+}
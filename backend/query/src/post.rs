@@ -1,3 +1,4 @@
+use crate::bookmark::Bookmark;
 use crate::schema::*;
 use crate::DieselError;
 use chrono::DateTime;
@@ -7,7 +8,7 @@ use diesel_async::scoped_futures::ScopedFutureExt;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use serde::{Deserialize, Serialize};
 use uchat_domain::PollChoiceId;
-use uchat_domain::{PostId, UserId};
+use uchat_domain::{BookmarkFolderId, ListId, PostId, UserId};
 use uchat_endpoint::post::types::VoteCast;
 use uchat_endpoint::post::types::{self, Content as EndpointContent};
 use uuid::Uuid;
@@ -26,6 +27,7 @@ pub struct Post {
     pub direct_message_to: Option<UserId>,
     pub reply_to: Option<PostId>,
     pub created_at: DateTime<Utc>,
+    pub hidden_at: Option<DateTime<Utc>>,
 }
 
 impl Post {
@@ -42,6 +44,7 @@ impl Post {
             direct_message_to: options.direct_message_to,
             reply_to: options.reply_to,
             created_at: Utc::now(),
+            hidden_at: None,
         })
     }
 }
@@ -81,6 +84,36 @@ pub async fn new(conn: &mut AsyncPgConnection, post: Post) -> Result<PostId, Die
     .await
 }
 
+/// Creates a thread: every post replies to the one before it, and is posted
+/// a millisecond later so feeds keep them in order. Either all posts are
+/// created or none.
+pub async fn new_thread(
+    conn: &mut AsyncPgConnection,
+    mut posts: Vec<Post>,
+) -> Result<Vec<PostId>, DieselError> {
+    use diesel_async::AsyncConnection;
+
+    let mut previous = None;
+    for post in posts.iter_mut() {
+        if let Some((post_id, time_posted)) = previous {
+            post.reply_to = Some(post_id);
+            post.time_posted = time_posted + chrono::Duration::milliseconds(1);
+        }
+        previous = Some((post.id, post.time_posted));
+    }
+    conn.transaction::<Vec<PostId>, DieselError, _>(|conn| {
+        async move {
+            let mut post_ids = vec![];
+            for post in posts {
+                post_ids.push(new(conn, post).await?);
+            }
+            Ok(post_ids)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
 pub async fn get(conn: &mut AsyncPgConnection, post_id: PostId) -> Result<Post, DieselError> {
     posts::table
         .filter(posts::columns::id.eq(post_id.as_uuid()))
@@ -88,11 +121,23 @@ pub async fn get(conn: &mut AsyncPgConnection, post_id: PostId) -> Result<Post,
         .await
 }
 
+/// Users whose posts are left out of every feed.
+fn banned_users() -> diesel::dsl::Select<
+    diesel::dsl::Filter<users::table, diesel::dsl::IsNotNull<users::banned_at>>,
+    users::id,
+> {
+    users::table
+        .filter(users::banned_at.is_not_null())
+        .select(users::id)
+}
+
 pub async fn get_trending(conn: &mut AsyncPgConnection) -> Result<Vec<Post>, DieselError> {
     use crate::schema::posts::dsl::*;
     posts
         .filter(time_posted.lt(Utc::now()))
         .filter(direct_message_to.is_null())
+        .filter(hidden_at.is_null())
+        .filter(user_id.ne_all(banned_users()))
         .order(time_posted.desc())
         .limit(30)
         .load(conn)
@@ -111,6 +156,7 @@ pub async fn get_public_posts(
             .filter(user_id.eq(uid))
             .filter(time_posted.lt(Utc::now()))
             .filter(direct_message_to.is_null())
+            .filter(hidden_at.is_null())
             .order(time_posted.desc())
             .limit(30)
             .load(conn)
@@ -130,7 +176,7 @@ pub async fn bookmark(
     {
         use crate::schema::bookmarks::dsl::*;
         diesel::insert_into(bookmarks)
-            .values((user_id.eq(uid), post_id.eq(pid)))
+            .values((user_id.eq(uid), post_id.eq(pid), created_at.eq(Utc::now())))
             .on_conflict((user_id, post_id))
             .do_nothing()
             .execute(conn)
@@ -441,31 +487,34 @@ pub async fn get_poll_results(
     }
 }
 
-pub async fn get_home_posts(
+/// Posts and boosts of the `authors`, newest first.
+async fn get_timeline(
     conn: &mut AsyncPgConnection,
-    user_id: UserId,
+    authors: Vec<UserId>,
 ) -> Result<Vec<Post>, DieselError> {
-    let uid = user_id;
     let on_schedule = posts::time_posted.lt(Utc::now());
     let public_only = posts::direct_message_to.is_null();
+    let not_hidden = posts::hidden_at.is_null();
     let order = posts::time_posted.desc();
     let limit = 30;
 
-    followers::table
-        .filter(followers::user_id.eq(uid))
-        .inner_join(posts::table.on(followers::follows.eq(posts::user_id)))
+    posts::table
+        .filter(posts::user_id.eq_any(authors.clone()))
         .filter(on_schedule)
         .filter(public_only)
+        .filter(not_hidden)
+        .filter(posts::user_id.ne_all(banned_users()))
         .select(Post::as_select())
         .order(order)
         .limit(limit)
         .union(
-            followers::table
-                .filter(followers::user_id.eq(uid))
-                .inner_join(boosts::table.on(boosts::user_id.eq(followers::follows)))
+            boosts::table
+                .filter(boosts::user_id.eq_any(authors))
                 .inner_join(posts::table.on(posts::id.eq(boosts::post_id)))
                 .filter(on_schedule)
                 .filter(public_only)
+                .filter(not_hidden)
+                .filter(posts::user_id.ne_all(banned_users()))
                 .select(Post::as_select())
                 .order(order)
                 .limit(limit),
@@ -474,6 +523,27 @@ pub async fn get_home_posts(
         .await
 }
 
+pub async fn get_home_posts(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+) -> Result<Vec<Post>, DieselError> {
+    let follows = followers::table
+        .filter(followers::user_id.eq(user_id))
+        .select(followers::follows)
+        .load::<UserId>(conn)
+        .await?;
+    get_timeline(conn, follows).await
+}
+
+/// Timeline of the members of a list, built like the home timeline.
+pub async fn get_list_posts(
+    conn: &mut AsyncPgConnection,
+    list_id: ListId,
+) -> Result<Vec<Post>, DieselError> {
+    let members = crate::list::member_ids(conn, list_id).await?;
+    get_timeline(conn, members).await
+}
+
 pub async fn get_liked_posts(
     conn: &mut AsyncPgConnection,
     user_id: UserId,
@@ -483,24 +553,35 @@ pub async fn get_liked_posts(
         .filter(reactions::user_id.eq(user_id))
         .filter(reactions::like_status.eq(1))
         .filter(posts::direct_message_to.is_null())
+        .filter(posts::hidden_at.is_null())
+        .filter(posts::user_id.ne_all(banned_users()))
         .select(Post::as_select())
         .limit(30)
         .get_results(conn)
         .await
 }
 
+/// Bookmarked posts, most recently bookmarked first, from all folders or
+/// only from `folder_id`.
 pub async fn get_bookmarked_posts(
     conn: &mut AsyncPgConnection,
     user_id: UserId,
-) -> Result<Vec<Post>, DieselError> {
-    bookmarks::table
+    folder_id: Option<BookmarkFolderId>,
+) -> Result<Vec<(Post, Bookmark)>, DieselError> {
+    let mut query = bookmarks::table
         .inner_join(posts::table)
         .filter(bookmarks::user_id.eq(user_id))
         .filter(posts::direct_message_to.is_null())
-        .select(Post::as_select())
+        .filter(posts::hidden_at.is_null())
+        .filter(posts::user_id.ne_all(banned_users()))
+        .select((Post::as_select(), Bookmark::as_select()))
+        .order(bookmarks::created_at.desc())
         .limit(30)
-        .get_results(conn)
-        .await
+        .into_boxed();
+    if let Some(folder_id) = folder_id {
+        query = query.filter(bookmarks::folder_id.eq(folder_id));
+    }
+    query.get_results(conn).await
 }
 
 #[cfg(test)]
@@ -541,4 +622,39 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn threads_are_chained_and_atomic() -> Result<()> {
+        let mut conn = test_db::new_connection().await;
+        let user = test_user::new_user(&mut conn, "user1").await;
+        let new_post =
+            |msg| Post::new(user.id, test_post::new_chat(msg), NewPostOptions::default()).unwrap();
+
+        let posts = vec![
+            new_post("one 1/3"),
+            new_post("two 2/3"),
+            new_post("three 3/3"),
+        ];
+        let post_ids = super::new_thread(&mut conn, posts).await?;
+        assert_eq!(post_ids.len(), 3);
+        let first = super::get(&mut conn, post_ids[0]).await?;
+        let second = super::get(&mut conn, post_ids[1]).await?;
+        let third = super::get(&mut conn, post_ids[2]).await?;
+        assert_eq!(first.reply_to, None);
+        assert_eq!(second.reply_to, Some(first.id));
+        assert_eq!(third.reply_to, Some(second.id));
+        assert!(first.time_posted < second.time_posted);
+
+        // The second post can't be created, so neither is the first
+        let first = new_post("one 1/2");
+        let mut second = new_post("two 2/2");
+        second.id = post_ids[0];
+        let first_id = first.id;
+        assert!(super::new_thread(&mut conn, vec![first, second])
+            .await
+            .is_err());
+        assert!(super::get(&mut conn, first_id).await.is_err());
+
+        Ok(())
+    }
 }
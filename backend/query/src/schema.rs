@@ -1,10 +1,36 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    api_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Text,
+        token_prefix -> Text,
+        token_hash -> Text,
+        scopes -> Array<Text>,
+        expires_at -> Nullable<Timestamptz>,
+        last_used_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    bookmark_folders (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     bookmarks (user_id, post_id) {
         user_id -> Uuid,
         post_id -> Uuid,
         created_at -> Timestamptz,
+        folder_id -> Nullable<Uuid>,
+        note -> Nullable<Text>,
     }
 }
 
@@ -16,6 +42,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    drafts (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        content -> Jsonb,
+        options -> Jsonb,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     followers (user_id, follows) {
         user_id -> Uuid,
@@ -24,6 +61,127 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    image_variants (image_id, width) {
+        image_id -> Uuid,
+        width -> Int4,
+        height -> Int4,
+        mime -> Text,
+        byte_size -> Int8,
+    }
+}
+
+diesel::table! {
+    images (id) {
+        id -> Uuid,
+        user_id -> Nullable<Uuid>,
+        mime -> Text,
+        byte_size -> Int8,
+        width -> Int4,
+        height -> Int4,
+        sha256 -> Text,
+        created_at -> Timestamptz,
+        used_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    link_previews (url) {
+        url -> Text,
+        title -> Nullable<Text>,
+        description -> Nullable<Text>,
+        site_name -> Nullable<Text>,
+        image_id -> Nullable<Uuid>,
+        failed -> Bool,
+        fetched_at -> Nullable<Timestamptz>,
+        claimed_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    list_members (list_id, user_id) {
+        list_id -> Uuid,
+        user_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    lists (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Text,
+        description -> Nullable<Text>,
+        private -> Bool,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    login_failures (user_id) {
+        user_id -> Uuid,
+        failed_count -> Int2,
+        last_failed_at -> Timestamptz,
+        locked_until -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    moderation_actions (id) {
+        id -> Uuid,
+        moderator_id -> Uuid,
+        action -> Text,
+        user_id -> Nullable<Uuid>,
+        post_id -> Nullable<Uuid>,
+        report_id -> Nullable<Uuid>,
+        note -> Nullable<Text>,
+        expires_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        deleted_post_ids -> Array<Uuid>,
+    }
+}
+
+diesel::table! {
+    mute_filters (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        phrase -> Text,
+        whole_word -> Bool,
+        scopes -> Array<Text>,
+        action -> Text,
+        expires_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    oidc_logins (state) {
+        state -> Text,
+        provider -> Text,
+        nonce -> Text,
+        pkce_verifier -> Text,
+        link_user_id -> Nullable<Uuid>,
+        remember_me -> Bool,
+        expires_at -> Timestamptz,
+        created_at -> Timestamptz,
+        browser_key_hash -> Text,
+    }
+}
+
+diesel::table! {
+    oidc_signups (id) {
+        id -> Uuid,
+        provider -> Text,
+        subject -> Text,
+        email -> Nullable<Text>,
+        suggested_handle -> Nullable<Text>,
+        expires_at -> Timestamptz,
+        created_at -> Timestamptz,
+        browser_key_hash -> Text,
+        remember_me -> Bool,
+    }
+}
+
 diesel::table! {
     poll_choices (id) {
         id -> Uuid,
@@ -41,6 +199,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    login_challenges (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        remember_me -> Bool,
+        attempts -> Int2,
+        expires_at -> Timestamptz,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     posts (id) {
         id -> Uuid,
@@ -50,6 +219,23 @@ diesel::table! {
         direct_message_to -> Nullable<Uuid>,
         reply_to -> Nullable<Uuid>,
         created_at -> Timestamptz,
+        hidden_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    post_impressions (post_id, viewer_id, window_start) {
+        post_id -> Uuid,
+        viewer_id -> Uuid,
+        window_start -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    profile_visits (user_id, visitor_id, window_start) {
+        user_id -> Uuid,
+        visitor_id -> Uuid,
+        window_start -> Timestamptz,
     }
 }
 
@@ -63,6 +249,73 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    recovery_codes (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        code_hash -> Text,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    reports (id) {
+        id -> Uuid,
+        reporter_id -> Uuid,
+        user_id -> Uuid,
+        post_id -> Nullable<Uuid>,
+        reason -> Text,
+        details -> Nullable<Text>,
+        status -> Text,
+        resolved_by -> Nullable<Uuid>,
+        resolved_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    security_events (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        kind -> Text,
+        ip_address -> Nullable<Text>,
+        user_agent -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    signing_keys (id) {
+        id -> Text,
+        private_key -> Text,
+        active -> Bool,
+        created_at -> Timestamptz,
+        retired_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    totp (user_id) {
+        user_id -> Uuid,
+        secret -> Text,
+        confirmed_at -> Nullable<Timestamptz>,
+        last_used_step -> Nullable<Int8>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    user_identities (provider, subject) {
+        provider -> Text,
+        subject -> Text,
+        user_id -> Uuid,
+        email -> Nullable<Text>,
+        last_login_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Uuid,
@@ -73,6 +326,27 @@ diesel::table! {
         handle -> Text,
         created_at -> Timestamptz,
         profile_image -> Nullable<Text>,
+        role -> Text,
+        suspended_until -> Nullable<Timestamptz>,
+        banned_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    videos (id) {
+        id -> Uuid,
+        user_id -> Nullable<Uuid>,
+        poster_image_id -> Uuid,
+        mime -> Text,
+        codec -> Text,
+        byte_size -> Int8,
+        width -> Int4,
+        height -> Int4,
+        duration_ms -> Int4,
+        looping -> Bool,
+        sha256 -> Text,
+        created_at -> Timestamptz,
+        used_at -> Timestamptz,
     }
 }
 
@@ -83,29 +357,76 @@ diesel::table! {
         expires_at -> Timestamptz,
         created_at -> Timestamptz,
         fingerprint -> Jsonb,
+        last_seen_at -> Timestamptz,
+        absolute_expires_at -> Timestamptz,
     }
 }
 
+diesel::joinable!(api_tokens -> users (user_id));
+diesel::joinable!(bookmark_folders -> users (user_id));
+diesel::joinable!(bookmarks -> bookmark_folders (folder_id));
 diesel::joinable!(bookmarks -> posts (post_id));
 diesel::joinable!(bookmarks -> users (user_id));
 diesel::joinable!(boosts -> posts (post_id));
 diesel::joinable!(boosts -> users (user_id));
+diesel::joinable!(drafts -> users (user_id));
+diesel::joinable!(image_variants -> images (image_id));
+diesel::joinable!(images -> users (user_id));
+diesel::joinable!(link_previews -> images (image_id));
+diesel::joinable!(list_members -> lists (list_id));
+diesel::joinable!(list_members -> users (user_id));
+diesel::joinable!(lists -> users (user_id));
+diesel::joinable!(login_challenges -> users (user_id));
+diesel::joinable!(login_failures -> users (user_id));
+diesel::joinable!(mute_filters -> users (user_id));
+diesel::joinable!(oidc_logins -> users (link_user_id));
 diesel::joinable!(poll_choices -> posts (post_id));
 diesel::joinable!(poll_votes -> poll_choices (choice_id));
 diesel::joinable!(poll_votes -> posts (post_id));
 diesel::joinable!(poll_votes -> users (user_id));
+diesel::joinable!(post_impressions -> posts (post_id));
+diesel::joinable!(post_impressions -> users (viewer_id));
 diesel::joinable!(reactions -> posts (post_id));
 diesel::joinable!(reactions -> users (user_id));
+diesel::joinable!(recovery_codes -> users (user_id));
+diesel::joinable!(security_events -> users (user_id));
+diesel::joinable!(totp -> users (user_id));
+diesel::joinable!(user_identities -> users (user_id));
+diesel::joinable!(videos -> images (poster_image_id));
+diesel::joinable!(videos -> users (user_id));
 diesel::joinable!(web -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    api_tokens,
+    bookmark_folders,
     bookmarks,
     boosts,
+    drafts,
     followers,
+    image_variants,
+    images,
+    link_previews,
+    list_members,
+    lists,
+    login_challenges,
+    login_failures,
+    moderation_actions,
+    mute_filters,
+    oidc_logins,
+    oidc_signups,
     poll_choices,
     poll_votes,
+    post_impressions,
     posts,
+    profile_visits,
     reactions,
+    recovery_codes,
+    reports,
+    security_events,
+    signing_keys,
+    totp,
+    user_identities,
     users,
+    videos,
     web,
 );
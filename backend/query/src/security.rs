@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uchat_domain::UserId;
+use uchat_endpoint::user::types::SecurityEventKind;
+use uuid::Uuid;
+
+use crate::schema::{login_failures, security_events};
+use crate::DieselError;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = login_failures)]
+pub struct LoginFailures {
+    pub user_id: UserId,
+    pub failed_count: i16,
+    pub last_failed_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl LoginFailures {
+    pub fn is_locked(&self, now: DateTime<Utc>) -> bool {
+        self.locked_until.map(|until| until > now).unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = security_events)]
+pub struct SecurityEvent {
+    pub id: Uuid,
+    pub user_id: UserId,
+    pub kind: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn login_failures(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+) -> Result<Option<LoginFailures>, DieselError> {
+    login_failures::table
+        .filter(login_failures::user_id.eq(user_id))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+/// Counts a failed login and returns the updated counter.
+pub async fn add_login_failure(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    now: DateTime<Utc>,
+) -> Result<LoginFailures, DieselError> {
+    let first = LoginFailures {
+        user_id,
+        failed_count: 1,
+        last_failed_at: now,
+        locked_until: None,
+    };
+
+    diesel::insert_into(login_failures::table)
+        .values(&first)
+        .on_conflict(login_failures::user_id)
+        .do_update()
+        .set((
+            login_failures::failed_count.eq(login_failures::failed_count + 1),
+            login_failures::last_failed_at.eq(now),
+        ))
+        .get_result(conn)
+        .await
+}
+
+/// Blocks further login attempts for the user until `until`.
+pub async fn lock_login(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    until: DateTime<Utc>,
+) -> Result<(), DieselError> {
+    diesel::update(login_failures::table)
+        .filter(login_failures::user_id.eq(user_id))
+        .set(login_failures::locked_until.eq(until))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn clear_login_failures(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+) -> Result<(), DieselError> {
+    diesel::delete(login_failures::table)
+        .filter(login_failures::user_id.eq(user_id))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+//------------------------------------------------------------------------------
+pub async fn new_event(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    kind: SecurityEventKind,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+) -> Result<SecurityEvent, DieselError> {
+    let event = SecurityEvent {
+        id: Uuid::new_v4(),
+        user_id,
+        kind: kind.as_str().to_string(),
+        ip_address,
+        user_agent,
+        created_at: Utc::now(),
+    };
+
+    diesel::insert_into(security_events::table)
+        .values(&event)
+        .get_result(conn)
+        .await
+}
+
+/// Most recent events of a user, newest first.
+pub async fn events(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    older_than: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<SecurityEvent>, DieselError> {
+    let mut query = security_events::table
+        .filter(security_events::user_id.eq(user_id))
+        .order(security_events::created_at.desc())
+        .limit(limit)
+        .into_boxed();
+    if let Some(older_than) = older_than {
+        query = query.filter(security_events::created_at.lt(older_than));
+    }
+    query.get_results(conn).await
+}
@@ -25,27 +25,56 @@ pub struct Session {
     pub expires_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
     pub fingerprint: FingerPrint,
+    pub last_seen_at: DateTime<Utc>,
+    pub absolute_expires_at: DateTime<Utc>,
 }
 
+/// Creates a session which expires after `idle` without activity, and
+/// unconditionally once `absolute` has passed.
 pub async fn new(
     conn: &mut AsyncPgConnection,
     user_id: UserId,
-    duration: Duration,
+    idle: Duration,
+    absolute: Duration,
     fingerprint: FingerPrint,
 ) -> Result<Session, DieselError> {
+    let now = Utc::now();
     let new_session = Session {
         id: SessionId::new(),
         user_id,
-        expires_at: Utc::now() + duration,
-        created_at: Utc::now(),
+        expires_at: now + idle.min(absolute),
+        created_at: now,
         fingerprint,
+        last_seen_at: now,
+        absolute_expires_at: now + absolute,
     };
 
     diesel::insert_into(web::table)
         .values(&new_session)
         .on_conflict((web::user_id, web::fingerprint))
         .do_update()
-        .set(web::expires_at.eq(new_session.expires_at))
+        .set((
+            web::expires_at.eq(new_session.expires_at),
+            web::last_seen_at.eq(new_session.last_seen_at),
+            web::absolute_expires_at.eq(new_session.absolute_expires_at),
+        ))
+        .get_result::<Session>(conn)
+        .await
+}
+
+/// Records activity on a session and moves its expiration to `expires_at`.
+pub async fn touch(
+    conn: &mut AsyncPgConnection,
+    session_id: SessionId,
+    last_seen_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+) -> Result<Session, DieselError> {
+    diesel::update(web::table)
+        .filter(web::id.eq(session_id))
+        .set((
+            web::last_seen_at.eq(last_seen_at),
+            web::expires_at.eq(expires_at),
+        ))
         .get_result::<Session>(conn)
         .await
 }
@@ -76,3 +105,14 @@ pub async fn find(
         .get_result(conn)
         .await
 }
+
+pub async fn delete(
+    conn: &mut AsyncPgConnection,
+    session_id: SessionId,
+) -> Result<(), DieselError> {
+    diesel::delete(web::table)
+        .filter(web::id.eq(session_id))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use crate::schema::signing_keys;
+use crate::DieselError;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = signing_keys)]
+pub struct SigningKey {
+    pub id: String,
+    pub private_key: String,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub retired_at: Option<DateTime<Utc>>,
+}
+
+/// Stores a new key. The key is not used for signing until activated.
+pub async fn new<I, K>(
+    conn: &mut AsyncPgConnection,
+    id: I,
+    private_key: K,
+) -> Result<SigningKey, DieselError>
+where
+    I: Into<String>,
+    K: Into<String>,
+{
+    let key = SigningKey {
+        id: id.into(),
+        private_key: private_key.into(),
+        active: false,
+        created_at: Utc::now(),
+        retired_at: None,
+    };
+
+    diesel::insert_into(signing_keys::table)
+        .values(&key)
+        .get_result(conn)
+        .await
+}
+
+pub async fn all(conn: &mut AsyncPgConnection) -> Result<Vec<SigningKey>, DieselError> {
+    signing_keys::table
+        .order(signing_keys::created_at.asc())
+        .get_results(conn)
+        .await
+}
+
+/// Keys which are still accepted when verifying signatures.
+pub async fn usable(conn: &mut AsyncPgConnection) -> Result<Vec<SigningKey>, DieselError> {
+    signing_keys::table
+        .filter(signing_keys::retired_at.is_null())
+        .order(signing_keys::created_at.asc())
+        .get_results(conn)
+        .await
+}
+
+/// Makes `id` the only active key. Retired keys cannot be activated.
+pub async fn activate(conn: &mut AsyncPgConnection, id: &str) -> Result<SigningKey, DieselError> {
+    use diesel_async::AsyncConnection;
+
+    let id = id.to_string();
+    conn.transaction::<SigningKey, DieselError, _>(|conn| {
+        async move {
+            diesel::update(signing_keys::table)
+                .filter(signing_keys::active.eq(true))
+                .set(signing_keys::active.eq(false))
+                .execute(conn)
+                .await?;
+
+            diesel::update(signing_keys::table)
+                .filter(signing_keys::id.eq(id))
+                .filter(signing_keys::retired_at.is_null())
+                .set(signing_keys::active.eq(true))
+                .get_result(conn)
+                .await
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Retires `id` so signatures made with it are no longer accepted.
+/// The active key cannot be retired.
+pub async fn retire(conn: &mut AsyncPgConnection, id: &str) -> Result<SigningKey, DieselError> {
+    diesel::update(signing_keys::table)
+        .filter(signing_keys::id.eq(id))
+        .filter(signing_keys::active.eq(false))
+        .filter(signing_keys::retired_at.is_null())
+        .set(signing_keys::retired_at.eq(Utc::now()))
+        .get_result(conn)
+        .await
+}
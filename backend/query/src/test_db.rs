@@ -52,7 +52,7 @@ pub async fn new_connection() -> AsyncPgConnection {
     });
 
     // test transactions are never committed
-    let _ = conn.begin_test_transaction();
+    conn.begin_test_transaction().await.unwrap();
     conn
 }
 
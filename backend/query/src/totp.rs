@@ -0,0 +1,256 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uchat_domain::{LoginChallengeId, UserId};
+use uuid::Uuid;
+
+use crate::schema::{login_challenges, recovery_codes, totp};
+use crate::DieselError;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = totp)]
+pub struct Totp {
+    pub user_id: UserId,
+    pub secret: String,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Totp {
+    pub fn is_enabled(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = recovery_codes)]
+pub struct RecoveryCode {
+    pub id: Uuid,
+    pub user_id: UserId,
+    pub code_hash: String,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = login_challenges)]
+pub struct LoginChallenge {
+    pub id: LoginChallengeId,
+    pub user_id: UserId,
+    pub remember_me: bool,
+    pub attempts: i16,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn get(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+) -> Result<Option<Totp>, DieselError> {
+    totp::table
+        .filter(totp::user_id.eq(user_id))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+/// Whether the user has to provide a second factor when logging in.
+pub async fn is_enabled(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+) -> Result<bool, DieselError> {
+    Ok(get(conn, user_id)
+        .await?
+        .map(|totp| totp.is_enabled())
+        .unwrap_or(false))
+}
+
+/// Stores a pending secret for the user, replacing any previous pending one.
+/// Confirmed secrets are left untouched.
+pub async fn enroll(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    secret: &str,
+) -> Result<Totp, DieselError> {
+    // `QueryDsl::filter` only covers tables, not the upsert's WHERE clause
+    use diesel::query_dsl::methods::FilterDsl;
+
+    let pending = Totp {
+        user_id,
+        secret: secret.to_string(),
+        confirmed_at: None,
+        last_used_step: None,
+        created_at: Utc::now(),
+    };
+
+    diesel::insert_into(totp::table)
+        .values(&pending)
+        .on_conflict(totp::user_id)
+        .do_update()
+        .set((
+            totp::secret.eq(&pending.secret),
+            totp::created_at.eq(pending.created_at),
+        ))
+        .filter(totp::confirmed_at.is_null())
+        .get_result(conn)
+        .await
+}
+
+/// Enables 2FA and replaces the recovery codes of the user.
+pub async fn confirm(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    step: i64,
+    recovery_code_hashes: Vec<String>,
+) -> Result<(), DieselError> {
+    use diesel_async::AsyncConnection;
+
+    conn.transaction::<(), DieselError, _>(|conn| {
+        async move {
+            let now = Utc::now();
+            diesel::update(totp::table)
+                .filter(totp::user_id.eq(user_id))
+                .set((totp::confirmed_at.eq(now), totp::last_used_step.eq(step)))
+                .execute(conn)
+                .await?;
+
+            diesel::delete(recovery_codes::table)
+                .filter(recovery_codes::user_id.eq(user_id))
+                .execute(conn)
+                .await?;
+
+            let codes = recovery_code_hashes
+                .into_iter()
+                .map(|code_hash| RecoveryCode {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    code_hash,
+                    used_at: None,
+                    created_at: now,
+                })
+                .collect::<Vec<_>>();
+            diesel::insert_into(recovery_codes::table)
+                .values(&codes)
+                .execute(conn)
+                .await?;
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Records `step` as used. Returns `false` if a code from this or a later
+/// step was already accepted, which means the code is being replayed.
+pub async fn use_step(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    step: i64,
+) -> Result<bool, DieselError> {
+    let updated = diesel::update(totp::table)
+        .filter(totp::user_id.eq(user_id))
+        .filter(
+            totp::last_used_step
+                .is_null()
+                .or(totp::last_used_step.lt(step)),
+        )
+        .set(totp::last_used_step.eq(step))
+        .execute(conn)
+        .await?;
+    Ok(updated == 1)
+}
+
+/// Removes the secret and all recovery codes of the user.
+pub async fn disable(conn: &mut AsyncPgConnection, user_id: UserId) -> Result<(), DieselError> {
+    use diesel_async::AsyncConnection;
+
+    conn.transaction::<(), DieselError, _>(|conn| {
+        async move {
+            diesel::delete(recovery_codes::table)
+                .filter(recovery_codes::user_id.eq(user_id))
+                .execute(conn)
+                .await?;
+            diesel::delete(totp::table)
+                .filter(totp::user_id.eq(user_id))
+                .execute(conn)
+                .await?;
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+pub async fn unused_recovery_codes(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+) -> Result<Vec<RecoveryCode>, DieselError> {
+    recovery_codes::table
+        .filter(recovery_codes::user_id.eq(user_id))
+        .filter(recovery_codes::used_at.is_null())
+        .get_results(conn)
+        .await
+}
+
+/// Marks a recovery code as used. Returns `false` if it was already used.
+pub async fn use_recovery_code(
+    conn: &mut AsyncPgConnection,
+    id: Uuid,
+) -> Result<bool, DieselError> {
+    let updated = diesel::update(recovery_codes::table)
+        .filter(recovery_codes::id.eq(id))
+        .filter(recovery_codes::used_at.is_null())
+        .set(recovery_codes::used_at.eq(Utc::now()))
+        .execute(conn)
+        .await?;
+    Ok(updated == 1)
+}
+
+//------------------------------------------------------------------------------
+pub async fn new_challenge(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    remember_me: bool,
+    expires_at: DateTime<Utc>,
+) -> Result<LoginChallenge, DieselError> {
+    let challenge = LoginChallenge {
+        id: LoginChallengeId::new(),
+        user_id,
+        remember_me,
+        attempts: 0,
+        expires_at,
+        created_at: Utc::now(),
+    };
+
+    diesel::insert_into(login_challenges::table)
+        .values(&challenge)
+        .get_result(conn)
+        .await
+}
+
+/// Fetches a challenge and counts the attempt to answer it.
+pub async fn attempt_challenge(
+    conn: &mut AsyncPgConnection,
+    challenge_id: LoginChallengeId,
+) -> Result<Option<LoginChallenge>, DieselError> {
+    diesel::update(login_challenges::table)
+        .filter(login_challenges::id.eq(challenge_id))
+        .set(login_challenges::attempts.eq(login_challenges::attempts + 1))
+        .get_result(conn)
+        .await
+        .optional()
+}
+
+pub async fn delete_challenge(
+    conn: &mut AsyncPgConnection,
+    challenge_id: LoginChallengeId,
+) -> Result<(), DieselError> {
+    diesel::delete(login_challenges::table)
+        .filter(login_challenges::id.eq(challenge_id))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
@@ -20,7 +20,7 @@ pub async fn new<T: AsRef<str>>(
     let user_id = UserId::new();
 
     // Insert new user into the users table
-    let _ = diesel::insert_into(users::table)
+    diesel::insert_into(users::table)
         .values((
             columns::id.eq(user_id),                  // Set the user ID
             columns::password_hash.eq(hash.as_str()), // Set the password hash
@@ -31,7 +31,7 @@ pub async fn new<T: AsRef<str>>(
         .map_err(|e| {
             tracing::log::error!("Failed to insert new user: {}", e);
             QueryError::from(e)
-        });
+        })?;
 
     // Return the new user ID if successful
     Ok(user_id)
@@ -58,6 +58,9 @@ pub struct User {
     pub handle: String,
     pub created_at: DateTime<Utc>,
     pub profile_image: Option<String>,
+    pub role: String,
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub banned_at: Option<DateTime<Utc>>,
 }
 
 pub async fn get(conn: &mut AsyncPgConnection, user_id: UserId) -> Result<User, QueryError> {
@@ -68,6 +71,26 @@ pub async fn get(conn: &mut AsyncPgConnection, user_id: UserId) -> Result<User,
         .map_err(QueryError::from)
 }
 
+/// What the user may do, read on every authenticated request.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::users)]
+pub struct Standing {
+    pub role: String,
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub banned_at: Option<DateTime<Utc>>,
+}
+
+pub async fn standing(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+) -> Result<Standing, DieselError> {
+    users::table
+        .filter(columns::id.eq(user_id))
+        .select(Standing::as_select())
+        .get_result(conn)
+        .await
+}
+
 pub async fn find(conn: &mut AsyncPgConnection, username: &Username) -> Result<User, DieselError> {
     users::table
         .filter(columns::handle.eq(username.as_ref()))
@@ -75,6 +98,16 @@ pub async fn find(conn: &mut AsyncPgConnection, username: &Username) -> Result<U
         .await
 }
 
+pub async fn handle_exists(
+    conn: &mut AsyncPgConnection,
+    handle: &str,
+) -> Result<bool, DieselError> {
+    use diesel::dsl::exists;
+    diesel::select(exists(users::table.filter(columns::handle.eq(handle))))
+        .get_result(conn)
+        .await
+}
+
 #[derive(Debug)]
 pub struct UpdateProfileParams {
     pub id: UserId,
@@ -117,6 +150,22 @@ pub async fn update_profile(
         .map(|_| ())
 }
 
+/// Replaces the password hash, e.g. after upgrading its parameters.
+pub async fn set_password_hash(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    hash: &PasswordHashString,
+) -> Result<(), DieselError> {
+    use crate::schema::users;
+
+    diesel::update(users::table)
+        .filter(users::id.eq(user_id))
+        .set(users::password_hash.eq(hash.as_str()))
+        .execute(conn)
+        .await
+        .map(|_| ())
+}
+
 //------------------------------------------------------------------------------
 pub async fn follow(
     conn: &mut AsyncPgConnection,
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uchat_domain::{ImageId, UserId, VideoId};
+
+use crate::schema::videos;
+use crate::DieselError;
+
+/// Metadata of an uploaded video. The bytes live in the media store, the
+/// poster frame is an image of its own.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = videos)]
+pub struct Video {
+    pub id: VideoId,
+    pub user_id: Option<UserId>,
+    pub poster_image_id: ImageId,
+    pub mime: String,
+    pub codec: String,
+    pub byte_size: i64,
+    pub width: i32,
+    pub height: i32,
+    pub duration_ms: i32,
+    pub looping: bool,
+    pub sha256: String,
+    pub created_at: DateTime<Utc>,
+    pub used_at: DateTime<Utc>,
+}
+
+pub async fn new(conn: &mut AsyncPgConnection, video: &Video) -> Result<(), DieselError> {
+    diesel::insert_into(videos::table)
+        .values(video)
+        .execute(conn)
+        .await
+        .map(|_| ())
+}
+
+pub async fn get(
+    conn: &mut AsyncPgConnection,
+    video_id: VideoId,
+) -> Result<Option<Video>, DieselError> {
+    videos::table.find(video_id).first(conn).await.optional()
+}
+
+/// Video with the same content, to store identical uploads once.
+pub async fn find_by_sha256(
+    conn: &mut AsyncPgConnection,
+    sha256: &str,
+) -> Result<Option<Video>, DieselError> {
+    videos::table
+        .filter(videos::sha256.eq(sha256))
+        .order(videos::created_at.asc())
+        .first(conn)
+        .await
+        .optional()
+}
+
+/// Records another upload of the video.
+pub async fn mark_used(conn: &mut AsyncPgConnection, video_id: VideoId) -> Result<(), DieselError> {
+    diesel::update(videos::table.find(video_id))
+        .set(videos::used_at.eq(Utc::now()))
+        .execute(conn)
+        .await
+        .map(|_| ())
+}
+
+/// Poster frames of all videos, which images must not be removed as unused.
+pub async fn poster_ids(conn: &mut AsyncPgConnection) -> Result<Vec<ImageId>, DieselError> {
+    videos::table
+        .select(videos::poster_image_id)
+        .load(conn)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{self, Image};
+    use crate::test_db::{self, Result};
+    use crate::user::tests::util as test_user;
+
+    #[tokio::test]
+    async fn new_and_get() -> Result<()> {
+        let mut conn = test_db::new_connection().await;
+        let user = test_user::new_user(&mut conn, "user1").await;
+        let now = Utc::now();
+        let poster = Image {
+            id: ImageId::new(),
+            user_id: Some(user.id),
+            mime: "image/png".to_string(),
+            byte_size: 1,
+            width: 16,
+            height: 9,
+            sha256: "poster".to_string(),
+            created_at: now,
+            used_at: now,
+        };
+        image::new(&mut conn, &poster, &[]).await?;
+        let video = Video {
+            id: VideoId::new(),
+            user_id: Some(user.id),
+            poster_image_id: poster.id,
+            mime: "video/mp4".to_string(),
+            codec: "h264".to_string(),
+            byte_size: 1024,
+            width: 16,
+            height: 9,
+            duration_ms: 1500,
+            looping: false,
+            sha256: "video".to_string(),
+            created_at: now,
+            used_at: now,
+        };
+        new(&mut conn, &video).await?;
+
+        let stored = get(&mut conn, video.id).await?.expect("video was stored");
+        assert_eq!(stored.poster_image_id, poster.id);
+        assert_eq!(stored.duration_ms, 1500);
+        let found = find_by_sha256(&mut conn, "video").await?;
+        assert_eq!(found.map(|video| video.id), Some(video.id));
+        // The poster counts as used
+        assert!(image::referenced(&mut conn).await?.contains(&poster.id));
+
+        Ok(())
+    }
+}
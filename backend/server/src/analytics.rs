@@ -0,0 +1,128 @@
+//! Impressions and profile visits, recorded in the background while feeds
+//! and profiles are served, and the time buckets authors see them in.
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use uchat_domain::{PostId, UserId};
+use uchat_endpoint::{analytics::types::AnalyticsPeriod, post::types::PublicPost};
+
+use crate::AppState;
+
+/// Counts the posts as seen by `viewer`. Their own posts aren't counted.
+pub fn record_impressions(state: &AppState, viewer: UserId, posts: &[PublicPost]) {
+    let post_ids = posts
+        .iter()
+        .filter(|post| post.by_user.id != viewer)
+        .map(|post| post.id)
+        .collect::<Vec<PostId>>();
+    if post_ids.is_empty() {
+        return;
+    }
+    let state = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = save_impressions(&state, viewer, &post_ids).await {
+            tracing::error!(error = %e, "Failed to record impressions");
+        }
+    });
+}
+
+async fn save_impressions(
+    state: &AppState,
+    viewer: UserId,
+    post_ids: &[PostId],
+) -> anyhow::Result<()> {
+    let mut conn = state.connect().await?;
+    uchat_query::analytics::record_impressions(&mut conn, viewer, post_ids, Utc::now()).await?;
+    Ok(())
+}
+
+/// Counts a visit of `visitor` on the profile of `user_id`, unless it's
+/// their own.
+pub fn record_profile_visit(state: &AppState, user_id: UserId, visitor: UserId) {
+    if user_id == visitor {
+        return;
+    }
+    let state = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = save_profile_visit(&state, user_id, visitor).await {
+            tracing::error!(error = %e, "Failed to record profile visit");
+        }
+    });
+}
+
+async fn save_profile_visit(
+    state: &AppState,
+    user_id: UserId,
+    visitor: UserId,
+) -> anyhow::Result<()> {
+    let mut conn = state.connect().await?;
+    uchat_query::analytics::record_profile_visit(&mut conn, user_id, visitor, Utc::now()).await?;
+    Ok(())
+}
+
+/// Consecutive buckets of a period, the last one holding `now`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Buckets {
+    first: DateTime<Utc>,
+    hours: i64,
+    count: i64,
+}
+
+impl Buckets {
+    pub fn new(period: AnalyticsPeriod, now: DateTime<Utc>) -> Self {
+        let hours = period.bucket_hours();
+        let count = period.bucket_count();
+        let last = now.duration_trunc(Duration::hours(hours)).unwrap_or(now);
+        Self {
+            first: last - Duration::hours(hours * (count - 1)),
+            hours,
+            count,
+        }
+    }
+
+    /// Start of the first bucket.
+    pub fn since(&self) -> DateTime<Utc> {
+        self.first
+    }
+
+    pub fn starts(&self) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        (0..self.count).map(|i| self.first + Duration::hours(self.hours * i))
+    }
+
+    /// Bucket holding `at`, if any does.
+    pub fn index(&self, at: DateTime<Utc>) -> Option<usize> {
+        if at < self.first {
+            return None;
+        }
+        let index = (at - self.first).num_hours() / self.hours;
+        (index < self.count).then_some(index as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn buckets_end_with_the_current_one() {
+        let now = Utc.with_ymd_and_hms(2024, 10, 21, 15, 30, 0).unwrap();
+
+        let day = Buckets::new(AnalyticsPeriod::Day, now);
+        assert_eq!(
+            day.since(),
+            Utc.with_ymd_and_hms(2024, 10, 20, 16, 0, 0).unwrap()
+        );
+        assert_eq!(day.starts().count(), 24);
+        assert_eq!(day.index(now), Some(23));
+        assert_eq!(day.index(day.since()), Some(0));
+        assert_eq!(day.index(day.since() - Duration::seconds(1)), None);
+
+        let week = Buckets::new(AnalyticsPeriod::Week, now);
+        assert_eq!(
+            week.since(),
+            Utc.with_ymd_and_hms(2024, 10, 15, 0, 0, 0).unwrap()
+        );
+        assert_eq!(week.index(now), Some(6));
+        assert_eq!(week.index(now + Duration::days(1)), None);
+    }
+}
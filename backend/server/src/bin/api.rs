@@ -1,13 +1,15 @@
 use anyhow::{Context, Result};
 use clap::{command, Parser, Subcommand};
 use dotenvy::dotenv;
-use std::net::SocketAddr;
+use std::{net::SocketAddr, time::Duration};
 use tracing::{debug, info};
-use uchat_crypto::new_rng;
+use uchat_crypto::{new_rng, sign::KeyId};
 use uchat_query::AsyncConnectionPool;
 use uchat_server::{
-    cli::{gen_keys, load_keys},
+    cli::{gen_keys, load_keyring},
+    config::Config,
     logging::{setup, Verbosity},
+    rate_limit::RateLimiter,
     router::new_router,
     AppState,
 };
@@ -31,6 +33,9 @@ struct Cli {
     #[clap(flatten)]
     verbosity: Verbosity,
 
+    #[clap(flatten)]
+    config: Config,
+
     #[command(subcommand)]
     command: Option<Command>,
 }
@@ -39,6 +44,195 @@ struct Cli {
 enum Command {
     /// generate a session signing key
     GenKey,
+
+    /// manage the session signing keys stored in the database
+    Key {
+        #[command(subcommand)]
+        command: KeyCommand,
+    },
+
+    /// measure password hashing on this host and recommend Argon2 parameters
+    Benchmark {
+        /// longest acceptable time to hash a single password, in milliseconds
+        #[clap(long, default_value_t = 500)]
+        target_ms: u64,
+
+        /// most memory a single hash may use, in MiB
+        #[clap(long, default_value_t = 256)]
+        max_memory_mib: u32,
+
+        /// number of lanes hashed in parallel
+        #[clap(long, default_value_t = 1)]
+        parallelism: u32,
+    },
+
+    /// convert images stored as data URLs by older versions to binary files
+    MigrateImages,
+
+    /// remove images which no profile or post uses anymore
+    GcImages {
+        /// keep images uploaded within this many hours
+        #[clap(long, default_value_t = 24)]
+        grace_hours: u32,
+
+        /// only list the images which would be removed
+        #[clap(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum KeyCommand {
+    /// generate and store a new signing key
+    Add {
+        /// immediately use the new key for signing
+        #[clap(long)]
+        activate: bool,
+    },
+    /// sign new sessions with the given key
+    Activate { id: String },
+    /// stop accepting sessions signed with the given key
+    Retire { id: String },
+    /// list stored keys
+    List,
+}
+
+async fn run_key_command(database_url: &str, command: KeyCommand) -> Result<()> {
+    use uchat_query::signing_key;
+
+    let mut conn = uchat_query::connect(database_url)
+        .await
+        .with_context(|| "Check database url")?;
+
+    match command {
+        KeyCommand::Add { activate } => {
+            let mut rng = new_rng();
+            info!(target: "uchat_server", "Generating private key...");
+            let (key, _) = gen_keys(&mut rng)?;
+            let key_id = KeyId::generate(&mut rng);
+            signing_key::new(&mut conn, key_id.as_str(), key.as_str()).await?;
+            info!(target: "uchat_server", key_id = %key_id, "Signing key has been stored");
+            if activate {
+                signing_key::activate(&mut conn, key_id.as_str()).await?;
+                info!(target: "uchat_server", key_id = %key_id, "Signing key is now active");
+            }
+        }
+        KeyCommand::Activate { id } => {
+            signing_key::activate(&mut conn, &id)
+                .await
+                .with_context(|| format!("key {id} does not exist or is retired"))?;
+            info!(target: "uchat_server", key_id = %id, "Signing key is now active");
+        }
+        KeyCommand::Retire { id } => {
+            signing_key::retire(&mut conn, &id).await.with_context(|| {
+                format!("key {id} does not exist, is active, or is already retired")
+            })?;
+            info!(target: "uchat_server", key_id = %id, "Signing key has been retired");
+        }
+        KeyCommand::List => {
+            for key in signing_key::all(&mut conn).await? {
+                let status = match (key.active, key.retired_at) {
+                    (true, _) => "active".to_string(),
+                    (false, Some(retired_at)) => format!("retired {retired_at}"),
+                    (false, None) => "inactive".to_string(),
+                };
+                println!("{}\t{}\t{}", key.id, key.created_at, status);
+            }
+        }
+    }
+    info!(target: "uchat_server", "Restart running servers to pick up key changes");
+    Ok(())
+}
+
+fn run_benchmark(target_ms: u64, max_memory_mib: u32, parallelism: u32) -> Result<()> {
+    use uchat_crypto::password::{recommend_params, time_hash};
+
+    let current = Config::default().password_hash.params()?;
+    let elapsed = time_hash(&current)?;
+    println!("defaults: {current:?} take {} ms", elapsed.as_millis());
+
+    info!(target: "uchat_server", target_ms, max_memory_mib, "Searching for parameters...");
+    let (params, elapsed) = recommend_params(
+        Duration::from_millis(target_ms),
+        max_memory_mib.saturating_mul(1024),
+        parallelism,
+    )?;
+    println!("recommended: {params:?} take {} ms", elapsed.as_millis());
+    println!("API_ARGON2_MEMORY_KIB={}", params.memory_kib);
+    println!("API_ARGON2_ITERATIONS={}", params.iterations);
+    println!("API_ARGON2_PARALLELISM={}", params.parallelism);
+    Ok(())
+}
+
+async fn run_migrate_images(database_url: &str, config: &Config) -> Result<()> {
+    use uchat_query::ImageId;
+    use uchat_server::upload::{migrate_legacy, Migration};
+
+    let media = config.media.media()?;
+    let legacy_dir = &config.media.media_dir;
+    let webp_variants = config.image.image_webp_variants;
+    let mut conn = uchat_query::connect(database_url)
+        .await
+        .with_context(|| "Check database url")?;
+
+    let (mut converted, mut stored, mut skipped) = (0, 0, 0);
+    let mut entries = tokio::fs::read_dir(legacy_dir)
+        .await
+        .with_context(|| format!("failed to read {}", legacy_dir.display()))?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let Some(image_id) = name.to_str().and_then(|name| name.parse::<ImageId>().ok()) else {
+            continue;
+        };
+        match migrate_legacy(&mut conn, &media, legacy_dir, image_id, webp_variants)
+            .await
+            .with_context(|| format!("failed to migrate image {image_id:?}"))?
+        {
+            Migration::Converted => converted += 1,
+            Migration::AlreadyStored => stored += 1,
+            Migration::Skipped(reason) => {
+                skipped += 1;
+                println!("skipped {image_id}: {reason}");
+            }
+        }
+    }
+    info!(target: "uchat_server", converted, stored, skipped, "Image migration finished");
+    Ok(())
+}
+
+async fn run_gc_images(
+    database_url: &str,
+    config: &Config,
+    grace_hours: u32,
+    dry_run: bool,
+) -> Result<()> {
+    use uchat_server::upload::gc::Sweep;
+
+    let media = config.media.media()?;
+    let mut conn = uchat_query::connect(database_url)
+        .await
+        .with_context(|| "Check database url")?;
+
+    let sweep = Sweep::find(&mut conn, chrono::Duration::hours(grace_hours.into())).await?;
+    let (count, byte_size) = (sweep.orphans.len(), sweep.byte_size());
+    if dry_run {
+        for orphan in &sweep.orphans {
+            let owner = orphan
+                .owner
+                .map(|owner| owner.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            println!(
+                "{}\t{}\t{}\t{}",
+                orphan.image_id, owner, orphan.byte_size, orphan.used_at
+            );
+        }
+        info!(target: "uchat_server", count, byte_size, "Images which would be removed");
+        return Ok(());
+    }
+
+    let removed = sweep.run(&mut conn, &media).await?;
+    info!(target: "uchat_server", found = count, removed, byte_size, "Image garbage collection finished");
+    Ok(())
 }
 
 async fn run() -> Result<()> {
@@ -64,11 +258,24 @@ async fn run() -> Result<()> {
                 info!(target: "uchat_server", "Set API_PRIVATE_KEY environment variable with the content of the key to use it");
                 return Ok(());
             }
+            Command::Key { command } => return run_key_command(&args.database_url, command).await,
+            Command::Benchmark {
+                target_ms,
+                max_memory_mib,
+                parallelism,
+            } => return run_benchmark(target_ms, max_memory_mib, parallelism),
+            Command::MigrateImages => {
+                return run_migrate_images(&args.database_url, &args.config).await
+            }
+            Command::GcImages {
+                grace_hours,
+                dry_run,
+            } => {
+                return run_gc_images(&args.database_url, &args.config, grace_hours, dry_run).await
+            }
         }
     }
 
-    debug!(target: "uchat_server", "loading signing keys");
-    let signing_keys = load_keys()?;
     info!(target: "uchat_server", database_url = %args.database_url, "connecting to postgres database");
     // Create new connection pool
     let db_pool = AsyncConnectionPool::new(&args.database_url)
@@ -77,10 +284,36 @@ async fn run() -> Result<()> {
         .with_context(|| "Ensure databasae access rights")
         .with_context(|| "Make sure database exists")?;
 
+    debug!(target: "uchat_server", "checking password hash parameters");
+    let hash_params = args.config.password_hash.params()?;
+
+    debug!(target: "uchat_server", "loading password policy");
+    let password_checker = args.config.password_policy.checker()?;
+
+    debug!(target: "uchat_server", "setting up media store");
+    let media = args.config.media.media()?;
+    let ffmpeg = args.config.video.ffmpeg();
+
+    debug!(target: "uchat_server", "discovering OIDC providers");
+    let oidc = args.config.oidc.providers().await?;
+
+    debug!(target: "uchat_server", "loading signing keys");
+    let signing_keys = {
+        let mut conn = db_pool.get().await?;
+        load_keyring(&mut conn).await?
+    };
+
     let state = AppState {
         db_pool,
         signing_keys,
         rng: new_rng(),
+        config: args.config,
+        rate_limiter: RateLimiter::in_memory(),
+        hash_params,
+        password_checker,
+        oidc,
+        media,
+        ffmpeg,
     };
 
     info!(target: "uchat_server", bind_addr = %args.bind, "Backend server is up and running at ");
@@ -89,7 +322,11 @@ async fn run() -> Result<()> {
         .await
         .with_context(|| "Check bind address")
         .with_context(|| "Check if another service using this port")?;
-    axum::serve(listener, router.into_make_service()).await?;
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
@@ -0,0 +1,50 @@
+//! Local OpenID Connect provider for trying out external sign in.
+//!
+//! Add it to the providers file given in `API_OIDC_PROVIDERS`:
+//!
+//! ```json
+//! [{ "id": "mock", "name": "Mock", "issuer": "http://127.0.0.1:9000", "client_id": "uchat" }]
+//! ```
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::net::SocketAddr;
+use tracing::info;
+use uchat_crypto::{new_rng, oidc::RsaPrivateKey};
+use uchat_server::{
+    logging::{setup, Verbosity},
+    oidc::mock::MockProvider,
+};
+use url::Url;
+
+#[derive(Debug, Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[clap(short, long, default_value = "127.0.0.1:9000", env = "MOCK_OIDC_BIND")]
+    bind: SocketAddr,
+
+    /// client id the provider accepts
+    #[clap(long, default_value = "uchat", env = "MOCK_OIDC_CLIENT_ID")]
+    client_id: String,
+
+    #[clap(flatten)]
+    verbosity: Verbosity,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Cli::parse();
+    setup(args.verbosity);
+
+    info!(target: "uchat_server", "Generating signing key...");
+    let key = RsaPrivateKey::new(&mut new_rng(), 2048)?;
+    let issuer = Url::parse(&format!("http://{}", args.bind))?;
+    let provider = MockProvider::new(issuer.clone(), &args.client_id, key);
+
+    let listener = tokio::net::TcpListener::bind(&args.bind)
+        .await
+        .with_context(|| "Check bind address")?;
+    info!(target: "uchat_server", issuer = %issuer, "Mock OIDC provider is up and running");
+    axum::serve(listener, provider.router()).await?;
+    Ok(())
+}
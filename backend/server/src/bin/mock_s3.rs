@@ -0,0 +1,59 @@
+//! Local S3-compatible object store for trying out the S3 media store.
+//!
+//! Point the API at it with the matching credentials:
+//!
+//! ```sh
+//! API_MEDIA_STORE=s3 API_S3_ENDPOINT=http://127.0.0.1:9100 API_S3_BUCKET=uchat \
+//! API_S3_ACCESS_KEY=uchat API_S3_SECRET_KEY=uchat-secret
+//! ```
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::net::SocketAddr;
+use tracing::info;
+use uchat_crypto::sigv4::Credentials;
+use uchat_server::{
+    config::MediaConfig,
+    logging::{setup, Verbosity},
+    media::mock::MockS3,
+};
+
+#[derive(Debug, Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[clap(short, long, default_value = "127.0.0.1:9100", env = "MOCK_S3_BIND")]
+    bind: SocketAddr,
+
+    /// access key requests must be signed with
+    #[clap(long, default_value = "uchat", env = "MOCK_S3_ACCESS_KEY")]
+    access_key: String,
+
+    /// secret key requests must be signed with
+    #[clap(long, default_value = "uchat-secret", env = "MOCK_S3_SECRET_KEY")]
+    secret_key: String,
+
+    #[clap(long, default_value = MediaConfig::REGION, env = "MOCK_S3_REGION")]
+    region: String,
+
+    #[clap(flatten)]
+    verbosity: Verbosity,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Cli::parse();
+    setup(args.verbosity);
+
+    let store = MockS3::new(Credentials {
+        access_key: args.access_key,
+        secret_key: args.secret_key,
+        region: args.region,
+    });
+
+    let listener = tokio::net::TcpListener::bind(&args.bind)
+        .await
+        .with_context(|| "Check bind address")?;
+    info!(target: "uchat_server", bind_addr = %args.bind, "Mock S3 store is up and running");
+    axum::serve(listener, store.router()).await?;
+    Ok(())
+}
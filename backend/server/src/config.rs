@@ -0,0 +1,806 @@
+use std::{path::PathBuf, sync::Arc, time::Duration as StdDuration};
+
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use clap::{builder::RangedU64ValueParser, Args, ValueEnum};
+use uchat_crypto::{password::HashParams, sigv4::Credentials};
+use uchat_domain::{password_policy::PasswordPolicy, Headline, Message};
+use uchat_endpoint::post::{endpoint::ServerLimitsOk, types::Gallery};
+use url::Url;
+
+use crate::{
+    link_preview::Fetcher,
+    media::{LocalStore, Media, MediaLinks, S3Store},
+    oidc::{OidcProviders, ProviderSettings},
+    password_policy::{Blocklist, PasswordChecker},
+    upload::{
+        video::{Ffmpeg, VideoLimits},
+        ImageLimits,
+    },
+};
+
+#[derive(Debug, Clone, Default, Args)]
+pub struct Config {
+    #[clap(flatten)]
+    pub session: SessionConfig,
+
+    #[clap(flatten)]
+    pub totp: TotpConfig,
+
+    #[clap(flatten)]
+    pub rate_limit: RateLimitConfig,
+
+    #[clap(flatten)]
+    pub lockout: LockoutConfig,
+
+    #[clap(flatten)]
+    pub password_hash: PasswordHashConfig,
+
+    #[clap(flatten)]
+    pub password_policy: PasswordPolicyConfig,
+
+    #[clap(flatten)]
+    pub oidc: OidcConfig,
+
+    #[clap(flatten)]
+    pub image: ImageConfig,
+
+    #[clap(flatten)]
+    pub video: VideoConfig,
+
+    #[clap(flatten)]
+    pub media: MediaConfig,
+
+    #[clap(flatten)]
+    pub link_preview: LinkPreviewConfig,
+
+    #[clap(flatten)]
+    pub post_limits: PostLimitsConfig,
+}
+
+impl Config {
+    /// Smallest request body limit there ever was.
+    pub const MIN_BODY_BYTES: usize = 8 * 1024 * 1024;
+
+    /// Largest accepted request body, big enough for a full gallery or the
+    /// largest video sent as data URLs.
+    pub fn body_limit(&self) -> usize {
+        let largest = self
+            .image
+            .image_max_bytes
+            .saturating_mul(Gallery::MAX_IMAGES)
+            .max(self.video.video_max_bytes);
+        // Base64 takes 4 characters for every 3 bytes, the rest of the
+        // request fits in the minimum
+        (largest / 3)
+            .saturating_mul(4)
+            .saturating_add(Self::MIN_BODY_BYTES)
+    }
+}
+
+//------------------------------------------------------------------------------
+#[derive(Debug, Clone, Args)]
+pub struct SessionConfig {
+    /// session lifetime in hours when "remember me" is selected
+    #[clap(long, default_value_t = SessionConfig::REMEMBER_HOURS, env = "API_SESSION_REMEMBER_HOURS")]
+    pub session_remember_hours: i64,
+
+    /// session lifetime in hours when "remember me" is not selected
+    #[clap(long, default_value_t = SessionConfig::SHORT_HOURS, env = "API_SESSION_SHORT_HOURS")]
+    pub session_short_hours: i64,
+
+    /// hours without activity after which a session expires
+    #[clap(long, default_value_t = SessionConfig::IDLE_HOURS, env = "API_SESSION_IDLE_HOURS")]
+    pub session_idle_hours: i64,
+
+    /// minimum seconds between two activity updates of the same session
+    #[clap(long, default_value_t = SessionConfig::TOUCH_SECS, env = "API_SESSION_TOUCH_SECS")]
+    pub session_touch_secs: i64,
+}
+
+impl SessionConfig {
+    pub const REMEMBER_HOURS: i64 = 24 * 30;
+    pub const SHORT_HOURS: i64 = 12;
+    pub const IDLE_HOURS: i64 = 24 * 7;
+    pub const TOUCH_SECS: i64 = 5 * 60;
+
+    pub fn absolute_timeout(&self, remember_me: bool) -> Duration {
+        if remember_me {
+            Duration::hours(self.session_remember_hours)
+        } else {
+            Duration::hours(self.session_short_hours)
+        }
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::hours(self.session_idle_hours)
+    }
+
+    pub fn touch_interval(&self) -> Duration {
+        Duration::seconds(self.session_touch_secs)
+    }
+
+    /// Whether a session last seen at `last_seen_at` should be extended now.
+    pub fn needs_touch(&self, last_seen_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        now - last_seen_at >= self.touch_interval()
+    }
+
+    /// New expiration for a session active at `now`, capped by its absolute limit.
+    pub fn extended_expiry(
+        &self,
+        now: DateTime<Utc>,
+        absolute_expires_at: DateTime<Utc>,
+    ) -> DateTime<Utc> {
+        (now + self.idle_timeout()).min(absolute_expires_at)
+    }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            session_remember_hours: Self::REMEMBER_HOURS,
+            session_short_hours: Self::SHORT_HOURS,
+            session_idle_hours: Self::IDLE_HOURS,
+            session_touch_secs: Self::TOUCH_SECS,
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+#[derive(Debug, Clone, Args)]
+pub struct TotpConfig {
+    /// issuer shown in authenticator apps
+    #[clap(long, default_value = TotpConfig::ISSUER, env = "API_TOTP_ISSUER")]
+    pub totp_issuer: String,
+
+    /// seconds a login waits for the second factor before it must be restarted
+    #[clap(long, default_value_t = TotpConfig::CHALLENGE_SECS, env = "API_TOTP_CHALLENGE_SECS")]
+    pub totp_challenge_secs: i64,
+
+    /// wrong codes accepted for a single login before it must be restarted
+    #[clap(long, default_value_t = TotpConfig::MAX_ATTEMPTS, env = "API_TOTP_MAX_ATTEMPTS")]
+    pub totp_max_attempts: i16,
+
+    /// number of recovery codes handed out when enabling 2FA
+    #[clap(long, default_value_t = TotpConfig::RECOVERY_CODES, env = "API_TOTP_RECOVERY_CODES")]
+    pub totp_recovery_codes: usize,
+}
+
+impl TotpConfig {
+    pub const ISSUER: &'static str = "uchat";
+    pub const CHALLENGE_SECS: i64 = 5 * 60;
+    pub const MAX_ATTEMPTS: i16 = 5;
+    pub const RECOVERY_CODES: usize = 10;
+
+    pub fn challenge_timeout(&self) -> Duration {
+        Duration::seconds(self.totp_challenge_secs)
+    }
+}
+
+impl Default for TotpConfig {
+    fn default() -> Self {
+        Self {
+            totp_issuer: Self::ISSUER.to_string(),
+            totp_challenge_secs: Self::CHALLENGE_SECS,
+            totp_max_attempts: Self::MAX_ATTEMPTS,
+            totp_recovery_codes: Self::RECOVERY_CODES,
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+#[derive(Debug, Clone, Default, Args)]
+pub struct RateLimitConfig {
+    /// turn off request rate limiting
+    #[clap(long, env = "API_RATE_LIMIT_DISABLED")]
+    pub rate_limit_disabled: bool,
+
+    /// use the X-Forwarded-For header as client address (only behind a trusted proxy)
+    #[clap(long, env = "API_RATE_LIMIT_TRUST_FORWARDED")]
+    pub rate_limit_trust_forwarded: bool,
+}
+
+//------------------------------------------------------------------------------
+#[derive(Debug, Clone, Args)]
+pub struct LockoutConfig {
+    /// failed logins in a row which are answered without any delay
+    #[clap(long, default_value_t = LockoutConfig::FREE_ATTEMPTS, env = "API_LOCKOUT_FREE_ATTEMPTS")]
+    pub lockout_free_attempts: i16,
+
+    /// failed logins in a row after which the account is locked
+    #[clap(long, default_value_t = LockoutConfig::THRESHOLD, env = "API_LOCKOUT_THRESHOLD")]
+    pub lockout_threshold: i16,
+
+    /// minutes an account stays locked
+    #[clap(long, default_value_t = LockoutConfig::LOCK_MINUTES, env = "API_LOCKOUT_MINUTES")]
+    pub lockout_minutes: i64,
+}
+
+impl LockoutConfig {
+    pub const FREE_ATTEMPTS: i16 = 3;
+    pub const THRESHOLD: i16 = 10;
+    pub const LOCK_MINUTES: i64 = 15;
+    /// Upper bound of the progressive delay before the account gets locked.
+    pub const MAX_DELAY_SECS: i64 = 60;
+
+    /// How long logins are blocked after `failures` failed attempts in a row.
+    ///
+    /// The delay doubles with every failure past the free attempts, and turns
+    /// into a lockout once the threshold is reached.
+    pub fn delay_after(&self, failures: i16) -> Option<Duration> {
+        if self.is_lockout(failures) {
+            return Some(Duration::minutes(self.lockout_minutes));
+        }
+        let extra = failures - self.lockout_free_attempts;
+        if extra <= 0 {
+            return None;
+        }
+        let secs = 1i64 << (extra - 1).min(16);
+        Some(Duration::seconds(secs.min(Self::MAX_DELAY_SECS)))
+    }
+
+    pub fn is_lockout(&self, failures: i16) -> bool {
+        failures >= self.lockout_threshold
+    }
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            lockout_free_attempts: Self::FREE_ATTEMPTS,
+            lockout_threshold: Self::THRESHOLD,
+            lockout_minutes: Self::LOCK_MINUTES,
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+/// Argon2id cost of new password hashes. Run `api benchmark` for values
+/// suited to the host; existing hashes are upgraded when users log in.
+#[derive(Debug, Clone, Args)]
+pub struct PasswordHashConfig {
+    /// memory used to hash a password, in KiB
+    #[clap(long, default_value_t = HashParams::default().memory_kib, env = "API_ARGON2_MEMORY_KIB")]
+    pub argon2_memory_kib: u32,
+
+    /// number of passes over the memory
+    #[clap(long, default_value_t = HashParams::default().iterations, env = "API_ARGON2_ITERATIONS")]
+    pub argon2_iterations: u32,
+
+    /// number of lanes hashed in parallel
+    #[clap(long, default_value_t = HashParams::default().parallelism, env = "API_ARGON2_PARALLELISM")]
+    pub argon2_parallelism: u32,
+}
+
+impl PasswordHashConfig {
+    /// Builds the hash parameters, failing if Argon2 would reject them.
+    pub fn params(&self) -> anyhow::Result<HashParams> {
+        HashParams::new(
+            self.argon2_memory_kib,
+            self.argon2_iterations,
+            self.argon2_parallelism,
+        )
+        .with_context(|| "Check the API_ARGON2_* settings")
+    }
+}
+
+impl Default for PasswordHashConfig {
+    fn default() -> Self {
+        let params = HashParams::default();
+        Self {
+            argon2_memory_kib: params.memory_kib,
+            argon2_iterations: params.iterations,
+            argon2_parallelism: params.parallelism,
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+#[derive(Debug, Clone, Args)]
+pub struct PasswordPolicyConfig {
+    /// minimum estimated strength of new passwords, in bits
+    #[clap(long, default_value_t = PasswordPolicy::MIN_ENTROPY_BITS, env = "API_PASSWORD_MIN_ENTROPY_BITS")]
+    pub password_min_entropy_bits: u32,
+
+    /// file with one common or breached password per line, which are rejected
+    #[clap(long, env = "API_PASSWORD_BLOCKLIST")]
+    pub password_blocklist: Option<PathBuf>,
+}
+
+impl PasswordPolicyConfig {
+    /// Builds the checker, loading the blocklist file if one is configured.
+    pub fn checker(&self) -> anyhow::Result<PasswordChecker> {
+        let blocklist = match &self.password_blocklist {
+            Some(path) => Some(Arc::new(Blocklist::load(path)?)),
+            None => None,
+        };
+        Ok(PasswordChecker {
+            policy: PasswordPolicy {
+                min_entropy_bits: self.password_min_entropy_bits,
+                ..PasswordPolicy::default()
+            },
+            blocklist,
+        })
+    }
+}
+
+impl Default for PasswordPolicyConfig {
+    fn default() -> Self {
+        Self {
+            password_min_entropy_bits: PasswordPolicy::MIN_ENTROPY_BITS,
+            password_blocklist: None,
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+#[derive(Debug, Clone, Args)]
+pub struct OidcConfig {
+    /// JSON file listing the OpenID Connect providers users can sign in with
+    #[clap(long, env = "API_OIDC_PROVIDERS")]
+    pub oidc_providers: Option<PathBuf>,
+
+    /// frontend page providers redirect back to after sign in
+    #[clap(long, default_value = OidcConfig::REDIRECT_URL, env = "API_OIDC_REDIRECT_URL")]
+    pub oidc_redirect_url: Url,
+
+    /// minutes a user has to finish signing in at the provider, or to pick a handle
+    #[clap(long, default_value_t = OidcConfig::LOGIN_MINUTES, env = "API_OIDC_LOGIN_MINUTES")]
+    pub oidc_login_minutes: i64,
+}
+
+impl OidcConfig {
+    pub const REDIRECT_URL: &'static str = "http://localhost:8080/account/oidc/callback";
+    pub const LOGIN_MINUTES: i64 = 10;
+
+    pub fn login_timeout(&self) -> Duration {
+        Duration::minutes(self.oidc_login_minutes)
+    }
+
+    /// Discovers the configured providers. Without a providers file, OIDC
+    /// sign in is disabled.
+    pub async fn providers(&self) -> anyhow::Result<OidcProviders> {
+        match &self.oidc_providers {
+            Some(path) => OidcProviders::discover(ProviderSettings::load(path)?).await,
+            None => Ok(OidcProviders::default()),
+        }
+    }
+}
+
+impl Default for OidcConfig {
+    fn default() -> Self {
+        Self {
+            oidc_providers: None,
+            oidc_redirect_url: Url::parse(Self::REDIRECT_URL).expect("valid default redirect URL"),
+            oidc_login_minutes: Self::LOGIN_MINUTES,
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+#[derive(Debug, Clone, Args)]
+pub struct ImageConfig {
+    /// largest accepted image upload, in bytes
+    #[clap(long, default_value_t = ImageConfig::MAX_BYTES, env = "API_IMAGE_MAX_BYTES")]
+    pub image_max_bytes: usize,
+
+    /// largest accepted image width or height, in pixels
+    #[clap(long, default_value_t = ImageConfig::MAX_DIMENSION, env = "API_IMAGE_MAX_DIMENSION")]
+    pub image_max_dimension: u32,
+
+    /// render resized variants as lossless WebP instead of JPEG and PNG
+    #[clap(long, env = "API_IMAGE_WEBP_VARIANTS")]
+    pub image_webp_variants: bool,
+}
+
+impl ImageConfig {
+    pub const MAX_BYTES: usize = 5 * 1024 * 1024;
+    pub const MAX_DIMENSION: u32 = 8192;
+
+    pub fn limits(&self) -> ImageLimits {
+        ImageLimits {
+            max_bytes: self.image_max_bytes,
+            max_dimension: self.image_max_dimension,
+        }
+    }
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            image_max_bytes: Self::MAX_BYTES,
+            image_max_dimension: Self::MAX_DIMENSION,
+            image_webp_variants: false,
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+#[derive(Debug, Clone, Args)]
+pub struct VideoConfig {
+    /// largest accepted video or GIF upload, in bytes
+    #[clap(long, default_value_t = VideoConfig::MAX_BYTES, env = "API_VIDEO_MAX_BYTES")]
+    pub video_max_bytes: usize,
+
+    /// longest accepted video, in seconds
+    #[clap(long, default_value_t = VideoConfig::MAX_SECONDS, env = "API_VIDEO_MAX_SECONDS")]
+    pub video_max_seconds: u32,
+
+    /// largest accepted video width or height, in pixels
+    #[clap(long, default_value_t = VideoConfig::MAX_DIMENSION, env = "API_VIDEO_MAX_DIMENSION")]
+    pub video_max_dimension: u32,
+
+    /// store GIFs posted as videos as looping MP4 instead
+    #[clap(long, env = "API_VIDEO_CONVERT_GIFS")]
+    pub video_convert_gifs: bool,
+
+    /// ffmpeg executable used to process videos
+    #[clap(long, default_value = "ffmpeg", env = "API_FFMPEG")]
+    pub ffmpeg: PathBuf,
+
+    /// ffprobe executable used to inspect videos
+    #[clap(long, default_value = "ffprobe", env = "API_FFPROBE")]
+    pub ffprobe: PathBuf,
+
+    /// seconds a single ffmpeg or ffprobe run may take
+    #[clap(long, default_value_t = VideoConfig::TIMEOUT_SECS, env = "API_VIDEO_TIMEOUT_SECS")]
+    pub video_timeout_secs: u64,
+
+    /// uploads processed at once, others wait for their turn
+    #[clap(long, default_value_t = VideoConfig::MAX_JOBS, env = "API_VIDEO_MAX_JOBS")]
+    pub video_max_jobs: usize,
+}
+
+impl VideoConfig {
+    pub const MAX_BYTES: usize = 32 * 1024 * 1024;
+    pub const MAX_SECONDS: u32 = 140;
+    pub const MAX_DIMENSION: u32 = 3840;
+    pub const TIMEOUT_SECS: u64 = 60;
+    pub const MAX_JOBS: usize = 2;
+
+    pub fn limits(&self) -> VideoLimits {
+        VideoLimits {
+            max_bytes: self.video_max_bytes,
+            max_seconds: self.video_max_seconds,
+            max_dimension: self.video_max_dimension,
+        }
+    }
+
+    /// Sets up running ffmpeg, clones share the limit on uploads processed at
+    /// once.
+    pub fn ffmpeg(&self) -> Ffmpeg {
+        Ffmpeg::new(
+            self.ffmpeg.clone(),
+            self.ffprobe.clone(),
+            StdDuration::from_secs(self.video_timeout_secs),
+            self.video_max_jobs,
+        )
+    }
+}
+
+impl Default for VideoConfig {
+    fn default() -> Self {
+        Self {
+            video_max_bytes: Self::MAX_BYTES,
+            video_max_seconds: Self::MAX_SECONDS,
+            video_max_dimension: Self::MAX_DIMENSION,
+            video_convert_gifs: false,
+            ffmpeg: PathBuf::from("ffmpeg"),
+            ffprobe: PathBuf::from("ffprobe"),
+            video_timeout_secs: Self::TIMEOUT_SECS,
+            video_max_jobs: Self::MAX_JOBS,
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum MediaBackend {
+    /// files in a local directory, for a single API instance
+    #[default]
+    Local,
+    /// an S3-compatible bucket, shared by all API instances
+    S3,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct MediaConfig {
+    /// where uploaded media is stored
+    #[clap(long, value_enum, default_value_t = MediaBackend::Local, env = "API_MEDIA_STORE")]
+    pub media_store: MediaBackend,
+
+    /// directory of the local media store
+    #[clap(long, default_value = MediaConfig::DIR, env = "API_MEDIA_DIR")]
+    pub media_dir: PathBuf,
+
+    /// public base URL of the stored media (such as a CDN), linked to instead of the API
+    #[clap(long, env = "API_MEDIA_PUBLIC_URL")]
+    pub media_public_url: Option<Url>,
+
+    /// link to pre-signed URLs valid for this many seconds instead of the API (S3 only)
+    #[clap(long, env = "API_MEDIA_PRESIGN_SECS")]
+    pub media_presign_secs: Option<u64>,
+
+    /// endpoint of the S3-compatible service
+    #[clap(long, env = "API_S3_ENDPOINT")]
+    pub s3_endpoint: Option<Url>,
+
+    /// bucket to store media in
+    #[clap(long, env = "API_S3_BUCKET")]
+    pub s3_bucket: Option<String>,
+
+    /// region the bucket is in
+    #[clap(long, default_value = MediaConfig::REGION, env = "API_S3_REGION")]
+    pub s3_region: String,
+
+    #[clap(long, env = "API_S3_ACCESS_KEY")]
+    pub s3_access_key: Option<String>,
+
+    #[clap(long, env = "API_S3_SECRET_KEY")]
+    pub s3_secret_key: Option<String>,
+
+    /// seconds connecting to the S3-compatible service may take
+    #[clap(long, default_value_t = MediaConfig::S3_CONNECT_TIMEOUT_SECS, env = "API_S3_CONNECT_TIMEOUT_SECS")]
+    pub s3_connect_timeout_secs: u64,
+
+    /// seconds a request to the S3-compatible service may take, reading the object included
+    #[clap(long, default_value_t = MediaConfig::S3_TIMEOUT_SECS, env = "API_S3_TIMEOUT_SECS")]
+    pub s3_timeout_secs: u64,
+}
+
+impl MediaConfig {
+    pub const DIR: &'static str = "usercontent";
+    pub const REGION: &'static str = "us-east-1";
+    pub const S3_CONNECT_TIMEOUT_SECS: u64 = 5;
+    pub const S3_TIMEOUT_SECS: u64 = 60;
+
+    /// Sets up the configured store.
+    pub fn media(&self) -> anyhow::Result<Media> {
+        let links = match (&self.media_public_url, self.media_presign_secs) {
+            (Some(url), _) => {
+                // Without the slash, joining an id would replace the last segment
+                let mut url = url.clone();
+                if !url.path().ends_with('/') {
+                    url.set_path(&format!("{}/", url.path()));
+                }
+                MediaLinks::Public(url)
+            }
+            (None, Some(secs)) => MediaLinks::Presigned(StdDuration::from_secs(secs)),
+            (None, None) => MediaLinks::Api,
+        };
+
+        Ok(match self.media_store {
+            MediaBackend::Local => Media::new(LocalStore::new(&self.media_dir), links),
+            MediaBackend::S3 => {
+                let endpoint = self
+                    .s3_endpoint
+                    .clone()
+                    .with_context(|| "API_S3_ENDPOINT is required for the S3 media store")?;
+                let bucket = self
+                    .s3_bucket
+                    .as_deref()
+                    .with_context(|| "API_S3_BUCKET is required for the S3 media store")?;
+                let (Some(access_key), Some(secret_key)) =
+                    (self.s3_access_key.clone(), self.s3_secret_key.clone())
+                else {
+                    anyhow::bail!("API_S3_ACCESS_KEY and API_S3_SECRET_KEY are required for the S3 media store");
+                };
+                let credentials = Credentials {
+                    access_key,
+                    secret_key,
+                    region: self.s3_region.clone(),
+                };
+                let store = S3Store::new(
+                    endpoint,
+                    bucket,
+                    credentials,
+                    StdDuration::from_secs(self.s3_connect_timeout_secs),
+                    StdDuration::from_secs(self.s3_timeout_secs),
+                )?;
+                Media::new(store, links)
+            }
+        })
+    }
+}
+
+impl Default for MediaConfig {
+    fn default() -> Self {
+        Self {
+            media_store: MediaBackend::default(),
+            media_dir: PathBuf::from(Self::DIR),
+            media_public_url: None,
+            media_presign_secs: None,
+            s3_endpoint: None,
+            s3_bucket: None,
+            s3_region: Self::REGION.to_string(),
+            s3_access_key: None,
+            s3_secret_key: None,
+            s3_connect_timeout_secs: Self::S3_CONNECT_TIMEOUT_SECS,
+            s3_timeout_secs: Self::S3_TIMEOUT_SECS,
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+#[derive(Debug, Clone, Args)]
+pub struct LinkPreviewConfig {
+    /// don't fetch previews of linked pages
+    #[clap(long, env = "API_LINK_PREVIEW_DISABLED")]
+    pub link_preview_disabled: bool,
+
+    /// seconds fetching a linked page or its image may take
+    #[clap(long, default_value_t = LinkPreviewConfig::TIMEOUT_SECS, env = "API_LINK_PREVIEW_TIMEOUT_SECS")]
+    pub link_preview_timeout_secs: u64,
+
+    /// bytes of a linked page read to find its title and description
+    #[clap(long, default_value_t = LinkPreviewConfig::MAX_PAGE_BYTES, env = "API_LINK_PREVIEW_MAX_PAGE_BYTES")]
+    pub link_preview_max_page_bytes: usize,
+
+    /// hours until a preview is fetched again when the page is linked to
+    #[clap(long, default_value_t = LinkPreviewConfig::REFRESH_HOURS, env = "API_LINK_PREVIEW_REFRESH_HOURS")]
+    pub link_preview_refresh_hours: i64,
+}
+
+impl LinkPreviewConfig {
+    pub const TIMEOUT_SECS: u64 = 5;
+    pub const MAX_PAGE_BYTES: usize = 512 * 1024;
+    pub const REFRESH_HOURS: i64 = 24;
+
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::hours(self.link_preview_refresh_hours)
+    }
+
+    pub fn fetcher(&self) -> Fetcher {
+        Fetcher {
+            timeout: StdDuration::from_secs(self.link_preview_timeout_secs),
+            max_page_bytes: self.link_preview_max_page_bytes,
+            allow_private: false,
+        }
+    }
+}
+
+impl Default for LinkPreviewConfig {
+    fn default() -> Self {
+        Self {
+            link_preview_disabled: false,
+            link_preview_timeout_secs: Self::TIMEOUT_SECS,
+            link_preview_max_page_bytes: Self::MAX_PAGE_BYTES,
+            link_preview_refresh_hours: Self::REFRESH_HOURS,
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+#[derive(Debug, Clone, Args)]
+pub struct PostLimitsConfig {
+    /// most characters readers see in a message, markup aside
+    #[clap(
+        long,
+        default_value_t = PostLimitsConfig::MESSAGE_MAX_CHARS,
+        env = "API_MESSAGE_MAX_CHARS",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..=Message::MAX_CHARS as u64)
+    )]
+    pub message_max_chars: usize,
+
+    /// most characters in a headline
+    #[clap(
+        long,
+        default_value_t = PostLimitsConfig::HEADLINE_MAX_CHARS,
+        env = "API_HEADLINE_MAX_CHARS",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..=Headline::MAX_CHARS as u64)
+    )]
+    pub headline_max_chars: usize,
+
+    /// most posts created at once as a thread
+    #[clap(
+        long,
+        default_value_t = PostLimitsConfig::THREAD_MAX_POSTS,
+        env = "API_THREAD_MAX_POSTS",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..=100)
+    )]
+    pub thread_max_posts: usize,
+}
+
+impl PostLimitsConfig {
+    pub const MESSAGE_MAX_CHARS: usize = 100;
+    pub const HEADLINE_MAX_CHARS: usize = 30;
+    pub const THREAD_MAX_POSTS: usize = 10;
+
+    pub fn limits(&self) -> ServerLimitsOk {
+        ServerLimitsOk {
+            message_max_chars: self.message_max_chars,
+            headline_max_chars: self.headline_max_chars,
+            thread_max_posts: self.thread_max_posts,
+        }
+    }
+}
+
+impl Default for PostLimitsConfig {
+    fn default() -> Self {
+        Self {
+            message_max_chars: Self::MESSAGE_MAX_CHARS,
+            headline_max_chars: Self::HEADLINE_MAX_CHARS,
+            thread_max_posts: Self::THREAD_MAX_POSTS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_hash_params_are_rejected() {
+        assert!(PasswordHashConfig::default().params().is_ok());
+        let config = PasswordHashConfig {
+            argon2_iterations: 0,
+            ..PasswordHashConfig::default()
+        };
+        assert!(config.params().is_err());
+    }
+
+    #[test]
+    fn extension_is_capped_by_absolute_expiry() {
+        let config = SessionConfig::default();
+        let now = Utc::now();
+
+        let far = now + Duration::days(365);
+        assert_eq!(
+            config.extended_expiry(now, far),
+            now + config.idle_timeout()
+        );
+
+        let near = now + Duration::hours(1);
+        assert_eq!(config.extended_expiry(now, near), near);
+    }
+
+    #[test]
+    fn touches_are_throttled() {
+        let config = SessionConfig::default();
+        let now = Utc::now();
+
+        assert!(!config.needs_touch(now - Duration::seconds(10), now));
+        assert!(config.needs_touch(now - config.touch_interval(), now));
+    }
+
+    #[test]
+    fn default_post_limits_are_what_clients_assume() {
+        assert_eq!(
+            PostLimitsConfig::default().limits(),
+            ServerLimitsOk::default()
+        );
+    }
+
+    #[test]
+    fn login_delay_grows_until_lockout() {
+        let config = LockoutConfig::default();
+
+        assert_eq!(config.delay_after(1), None);
+        assert_eq!(config.delay_after(3), None);
+        assert_eq!(config.delay_after(4), Some(Duration::seconds(1)));
+        assert_eq!(config.delay_after(6), Some(Duration::seconds(4)));
+        assert_eq!(config.delay_after(9), Some(Duration::seconds(32)));
+        assert!(!config.is_lockout(9));
+
+        assert!(config.is_lockout(10));
+        assert_eq!(config.delay_after(12), Some(Duration::minutes(15)));
+    }
+
+    #[test]
+    fn login_delay_is_capped() {
+        let config = LockoutConfig {
+            lockout_threshold: 100,
+            ..LockoutConfig::default()
+        };
+
+        assert_eq!(config.delay_after(50), Some(Duration::seconds(60)));
+    }
+
+    #[test]
+    fn body_fits_the_largest_upload() {
+        let config = Config::default();
+        let gallery = ImageConfig::MAX_BYTES * Gallery::MAX_IMAGES;
+        assert!(config.body_limit() > (gallery / 3 * 4).max(VideoConfig::MAX_BYTES / 3 * 4));
+        assert!(config.body_limit() >= Config::MIN_BODY_BYTES);
+    }
+}
@@ -3,7 +3,8 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use uchat_endpoint::RequestFailed;
+use uchat_domain::password_policy::PasswordProblem;
+use uchat_endpoint::{user::endpoint::PasswordRejected, RequestFailed};
 
 pub type ApiResult<T> = Result<T, ApiError>;
 pub struct ApiError {
@@ -17,6 +18,8 @@ pub enum ServerError {
     Login((StatusCode, String)),
     #[error("Registration failed")]
     Registration((StatusCode, String)),
+    #[error("Password rejected")]
+    WeakPassword(Vec<PasswordProblem>),
 }
 
 impl ServerError {
@@ -30,12 +33,51 @@ impl ServerError {
     pub fn account_exists() -> Self {
         Self::Login((StatusCode::CONFLICT, "Account already exists".to_string()))
     }
+
+    pub fn weak_password(problems: &[PasswordProblem]) -> Self {
+        Self::WeakPassword(problems.to_vec())
+    }
+
+    pub fn invalid_code() -> Self {
+        Self::Login((StatusCode::BAD_REQUEST, "Invalid code".to_string()))
+    }
+
+    pub fn login_locked(retry_in_secs: i64) -> Self {
+        Self::Login((
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("Too many failed attempts, try again in {retry_in_secs} seconds"),
+        ))
+    }
+
+    pub fn account_restricted(msg: String) -> Self {
+        Self::Login((StatusCode::FORBIDDEN, msg))
+    }
+
+    pub fn challenge_expired() -> Self {
+        Self::Login((
+            StatusCode::UNAUTHORIZED,
+            "Login expired, please sign in again".to_string(),
+        ))
+    }
 }
 
 pub fn error_response<T: Into<String>>(code: StatusCode, msg: T) -> Response {
     (code, Json(RequestFailed { msg: msg.into() })).into_response()
 }
 
+fn weak_password_response(problems: &[PasswordProblem]) -> Response {
+    let msg = problems
+        .iter()
+        .map(|problem| problem.to_string())
+        .collect::<Vec<_>>()
+        .join(". ");
+    let body = PasswordRejected {
+        msg,
+        problems: problems.to_vec(),
+    };
+    (StatusCode::BAD_REQUEST, Json(body)).into_response()
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         if let Some(code) = self.code {
@@ -46,6 +88,7 @@ impl IntoResponse for ApiError {
             return match server_err {
                 ServerError::Login((code, msg)) => error_response(*code, msg),
                 ServerError::Registration((code, msg)) => error_response(*code, msg),
+                ServerError::WeakPassword(problems) => weak_password_response(problems),
             };
         }
 
@@ -1,16 +1,26 @@
 use std::str::FromStr;
 
-use crate::AppState;
+use crate::{
+    handler::moderation::restriction,
+    rate_limit::client_ip,
+    session::{sign_session, RefreshedSession, SessionRefresh},
+    AppState,
+};
 use axum::{
     async_trait,
     extract::FromRequestParts,
-    http::{header::COOKIE, request::Parts, StatusCode},
+    http::{
+        header::{AUTHORIZATION, COOKIE, USER_AGENT},
+        request::Parts,
+        StatusCode,
+    },
     Extension, Json, RequestPartsExt,
 };
-use chrono::Utc;
+use chrono::{Duration, Utc};
 use diesel_async::{pooled_connection::deadpool::Object, AsyncPgConnection};
-use uchat_endpoint::RequestFailed;
-use uchat_query::{SessionId, UserId};
+use uchat_crypto::{sign::KeyedSignature, token::hash_token};
+use uchat_endpoint::{moderation::types::Role, user::types::TokenScope, RequestFailed};
+use uchat_query::{ApiTokenId, SessionId, UserId};
 
 pub struct DbConnection(pub Object<AsyncPgConnection>);
 
@@ -36,10 +46,165 @@ where
     }
 }
 
+/// Where a request comes from, as recorded in the security events.
+#[derive(Clone, Debug, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    const MAX_USER_AGENT_LEN: usize = 256;
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Sync + Send,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let trust_forwarded = parts
+            .extensions
+            .get::<AppState>()
+            .map(|state| state.config.rate_limit.rate_limit_trust_forwarded)
+            .unwrap_or(false);
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|agent| agent.chars().take(Self::MAX_USER_AGENT_LEN).collect());
+
+        Ok(Self {
+            ip_address: client_ip(parts, trust_forwarded).map(|ip| ip.to_string()),
+            user_agent,
+        })
+    }
+}
+
+/// Set of [`TokenScope`]s granted to a personal access token.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScopeSet(u8);
+
+impl ScopeSet {
+    fn bit(scope: TokenScope) -> u8 {
+        match scope {
+            TokenScope::Read => 1,
+            TokenScope::Write => 1 << 1,
+            TokenScope::Dm => 1 << 2,
+        }
+    }
+
+    pub fn contains(&self, scope: TokenScope) -> bool {
+        self.0 & Self::bit(scope) != 0
+    }
+}
+
+impl FromIterator<TokenScope> for ScopeSet {
+    fn from_iter<I: IntoIterator<Item = TokenScope>>(iter: I) -> Self {
+        Self(
+            iter.into_iter()
+                .fold(0, |set, scope| set | Self::bit(scope)),
+        )
+    }
+}
+
+/// How the user of a request authenticated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Credential {
+    Session(SessionId),
+    Token { id: ApiTokenId, scopes: ScopeSet },
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct UserSession {
     pub user_id: UserId,
-    pub session_id: SessionId,
+    pub credential: Credential,
+    pub role: Role,
+}
+
+impl UserSession {
+    /// Minimum time between two updates of a token's last use.
+    const TOKEN_TOUCH_SECS: i64 = 60;
+
+    pub fn session_id(&self) -> Option<SessionId> {
+        match self.credential {
+            Credential::Session(id) => Some(id),
+            Credential::Token { .. } => None,
+        }
+    }
+
+    /// Whether the request may use an endpoint requiring `scope`.
+    ///
+    /// Sessions may use everything, tokens only endpoints which declare a
+    /// scope that was granted to the token.
+    pub fn allows(&self, scope: Option<TokenScope>) -> bool {
+        match (self.credential, scope) {
+            (Credential::Session(_), _) => true,
+            (Credential::Token { scopes, .. }, Some(scope)) => scopes.contains(scope),
+            (Credential::Token { .. }, None) => false,
+        }
+    }
+
+    async fn from_token(conn: &mut AsyncPgConnection, token: &str) -> Option<Self> {
+        let token = uchat_query::api_token::find_by_hash(conn, &hash_token(token))
+            .await
+            .map_err(|err| tracing::debug!("Failed to retrieve token: {:?}", err))
+            .ok()??;
+
+        let now = Utc::now();
+        if !token.is_usable(now) {
+            tracing::debug!("Token is revoked or expired");
+            return None;
+        }
+
+        let touch_interval = Duration::seconds(Self::TOKEN_TOUCH_SECS);
+        if let Err(err) = uchat_query::api_token::touch(conn, token.id, now, touch_interval).await {
+            tracing::debug!("Failed to record token use: {:?}", err);
+        }
+
+        let scopes = token
+            .scopes
+            .iter()
+            .filter_map(|scope| scope.parse::<TokenScope>().ok())
+            .collect();
+        Some(Self {
+            user_id: token.user_id,
+            credential: Credential::Token {
+                id: token.id,
+                scopes,
+            },
+            role: Role::User,
+        })
+    }
+
+    /// Loads the role of the user, refusing banned and suspended accounts.
+    async fn check_standing(
+        mut self,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, (StatusCode, Json<RequestFailed>)> {
+        let standing = uchat_query::user::standing(conn, self.user_id)
+            .await
+            .map_err(|err| {
+                tracing::debug!("Failed to retrieve account standing: {:?}", err);
+                (
+                    StatusCode::UNAUTHORIZED,
+                    Json(RequestFailed {
+                        msg: "Unauthorized".into(),
+                    }),
+                )
+            })?;
+
+        if let Some(msg) = restriction(standing.suspended_until, standing.banned_at, Utc::now()) {
+            tracing::debug!(user_id = ?self.user_id, "Account is restricted");
+            return Err((StatusCode::FORBIDDEN, Json(RequestFailed { msg })));
+        }
+
+        self.role = standing.role.parse().unwrap_or_default();
+        Ok(self)
+    }
 }
 
 #[async_trait]
@@ -52,6 +217,11 @@ where
     async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
         tracing::debug!("Starting extraction of UserSession");
 
+        // Already extracted by an outer layer, such as the rate limiter
+        if let Some(session) = parts.extensions.get::<UserSession>() {
+            return Ok(*session);
+        }
+
         // Debug log the extracted cookies
         let unauthorized = || {
             (
@@ -77,6 +247,20 @@ where
                 unauthorized()
             })?;
 
+        // Scripts authenticate with a personal access token instead of cookies
+        let bearer = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "));
+        if let Some(token) = bearer {
+            return Self::from_token(&mut conn, token.trim())
+                .await
+                .ok_or_else(unauthorized)?
+                .check_standing(&mut conn)
+                .await;
+        }
+
         // Extract Cookies
         let cookies = parts
             .headers
@@ -103,10 +287,8 @@ where
                     unauthorized()
                 })?;
 
-                let session_signature = uchat_crypto::decode_base64(session_signature)
-                    .ok()
-                    .and_then(|sig| uchat_crypto::sign::signature_from_bytes(sig).ok())
-                    .ok_or_else(|| {
+                let session_signature =
+                    KeyedSignature::decode(session_signature).map_err(|_| {
                         tracing::debug!("Failed to decode SESSION_SIGNATURE");
                         unauthorized()
                     })?;
@@ -114,13 +296,13 @@ where
                 tracing::debug!("Verifying session signature...");
                 state
                     .signing_keys
-                    .verify(session_id.as_uuid().as_bytes(), session_signature)
-                    .map_err(|_| {
-                        tracing::debug!("Session signature verification failed");
+                    .verify(session_id.as_uuid().as_bytes(), &session_signature)
+                    .map_err(|err| {
+                        tracing::debug!("Session signature verification failed: {:?}", err);
                         unauthorized()
                     })?;
 
-                let session = uchat_query::session::get(&mut conn, session_id)
+                let mut session = uchat_query::session::get(&mut conn, session_id)
                     .await
                     .map_err(|err| {
                         tracing::debug!("Failed to retrieve session: {:?}", err);
@@ -131,20 +313,44 @@ where
                         unauthorized()
                     })?;
 
-                if session.expires_at < Utc::now() {
+                let now = Utc::now();
+                if session.expires_at < now {
                     tracing::debug!("Session has expired");
                     return Err(unauthorized());
                 }
 
+                let policy = &state.config.session;
+                if policy.needs_touch(session.last_seen_at, now) {
+                    let expires_at = policy.extended_expiry(now, session.absolute_expires_at);
+                    session = uchat_query::session::touch(&mut conn, session.id, now, expires_at)
+                        .await
+                        .map_err(|err| {
+                            tracing::debug!("Failed to extend session: {:?}", err);
+                            unauthorized()
+                        })?;
+
+                    if let Some(refresh) = parts.extensions.get::<SessionRefresh>() {
+                        tracing::debug!("Session extended, refreshing cookies");
+                        refresh.set(RefreshedSession {
+                            session_id: session.id,
+                            signature: sign_session(&state, session.id),
+                            expires_at: session.expires_at,
+                        });
+                    }
+                }
+
                 tracing::info!(
                     user_id = session.user_id.into_inner().to_string(),
                     "User logged in."
                 );
 
-                Ok(Self {
+                Self {
                     user_id: session.user_id,
-                    session_id: session.id,
-                })
+                    credential: Credential::Session(session.id),
+                    role: Role::User,
+                }
+                .check_standing(&mut conn)
+                .await
             }
             None => {
                 tracing::debug!("Failed to extract cookies from headers");
@@ -153,3 +359,31 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_only_allow_granted_scopes() {
+        let token = UserSession {
+            user_id: UserId::new(),
+            credential: Credential::Token {
+                id: ApiTokenId::new(),
+                scopes: [TokenScope::Read].into_iter().collect(),
+            },
+            role: Role::User,
+        };
+        assert!(token.allows(Some(TokenScope::Read)));
+        assert!(!token.allows(Some(TokenScope::Write)));
+        assert!(!token.allows(None));
+
+        let session = UserSession {
+            user_id: UserId::new(),
+            credential: Credential::Session(SessionId::new()),
+            role: Role::User,
+        };
+        assert!(session.allows(None));
+        assert!(session.allows(Some(TokenScope::Dm)));
+    }
+}
@@ -1,28 +1,49 @@
 use crate::{
     error::{ApiError, ApiResult},
-    extractor::{DbConnection, UserSession},
-    AppState,
+    extractor::{ClientInfo, DbConnection, UserSession},
+    media::{
+        http::{self, ByteRange},
+        Media,
+    },
+    upload, AppState,
 };
 use axum::{
     async_trait,
     body::Body,
     extract::{Path, State},
-    http::{header::CONTENT_TYPE, StatusCode},
+    http::{
+        header::{
+            ACCEPT_RANGES, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+            LAST_MODIFIED, X_CONTENT_TYPE_OPTIONS,
+        },
+        response, HeaderMap, Method, StatusCode,
+    },
     response::{IntoResponse, Response},
     Json,
 };
-use base64::{engine::general_purpose, Engine as _};
 use core::fmt::Debug;
+use diesel_async::AsyncPgConnection;
 use serde::Deserialize;
-use std::path::PathBuf;
-use tokio::fs;
-use uchat_query::ImageId;
-use uuid::Uuid;
+use uchat_endpoint::{
+    post::types::{ImageVariant, VideoDetails},
+    user::types::TokenScope,
+    RequestFailed,
+};
+use uchat_query::{ImageId, UserId, VideoId};
 
+pub mod analytics;
+pub mod api_token;
+pub mod bookmark;
+pub mod draft;
+pub mod list;
+pub mod moderation;
+pub mod mute;
+pub mod oidc;
 pub mod post;
+pub mod security;
+pub mod totp;
 pub mod user;
 
-const USER_CONTEND_DIR: &str = "usercontent";
 #[async_trait]
 pub trait PublicApiRequest {
     type Response: IntoResponse;
@@ -30,103 +51,257 @@ pub trait PublicApiRequest {
         self,
         conn: DbConnection,
         state: AppState,
+        client: ClientInfo,
     ) -> ApiResult<Self::Response>;
 }
 
 pub async fn with_public_handler<'a, Req>(
     conn: DbConnection,
+    client: ClientInfo,
     State(state): State<AppState>,
     Json(payload): Json<Req>,
 ) -> ApiResult<Req::Response>
 where
     Req: PublicApiRequest + Deserialize<'a>,
 {
-    payload.process_request(conn, state).await
+    payload.process_request(conn, state, client).await
 }
 
 #[async_trait]
 pub trait AuthorizedApiRequest {
     type Response: IntoResponse;
+    /// Scope a personal access token needs for this request. Requests without
+    /// a scope can only be made from a logged in session.
+    const SCOPE: Option<TokenScope> = None;
+
     async fn process_request(
         self,
         conn: DbConnection,
         session: UserSession,
         state: AppState,
+        client: ClientInfo,
     ) -> ApiResult<Self::Response>;
 }
 
 pub async fn with_handler<'a, Req>(
     conn: DbConnection,
     session: UserSession,
+    client: ClientInfo,
     State(state): State<AppState>,
     Json(payload): Json<Req>,
 ) -> ApiResult<Req::Response>
 where
     Req: AuthorizedApiRequest + Deserialize<'a> + Debug,
 {
-    payload.process_request(conn, session, state).await
+    if !session.allows(Req::SCOPE) {
+        return Err(ApiError {
+            code: Some(StatusCode::FORBIDDEN),
+            error: anyhow::anyhow!(RequestFailed {
+                msg: "Token is missing the required scope".to_string()
+            }),
+        });
+    }
+
+    payload.process_request(conn, session, state, client).await
 }
 
-pub async fn save_image<T: AsRef<[u8]>>(id: ImageId, data: T) -> Result<(), ApiError> {
-    let mut path = PathBuf::from(USER_CONTEND_DIR);
-    fs::create_dir_all(&path).await?;
-    path.push(id.to_string());
-    fs::write(&path, data).await?;
+/// Decodes, checks and stores an image uploaded as a data URL.
+pub async fn save_image(
+    conn: &mut AsyncPgConnection,
+    state: &AppState,
+    owner: UserId,
+    data_url: &str,
+) -> Result<ImageId, ApiError> {
+    let limits = state.config.image.limits();
+    let webp_variants = state.config.image.image_webp_variants;
+    let bytes = upload::decode_data_url(data_url, limits.max_bytes)
+        .map_err(|e| ApiError::new(e.status_code(), e))?;
+    let image = tokio::task::spawn_blocking(move || upload::check(bytes, &limits))
+        .await?
+        .map_err(|e| ApiError::new(e.status_code(), e))?;
+    tracing::info!(
+        mime = image.format.mime(),
+        byte_size = image.bytes.len(),
+        "Storing uploaded image"
+    );
+    Ok(upload::store(conn, &state.media, owner, image, webp_variants).await?)
+}
 
-    Ok(())
+/// Decodes, checks and stores a video or GIF uploaded as a data URL.
+pub async fn save_video(
+    conn: &mut AsyncPgConnection,
+    state: &AppState,
+    owner: UserId,
+    data_url: &str,
+) -> Result<VideoId, ApiError> {
+    let config = &state.config.video;
+    let limits = config.limits();
+    let bytes = upload::decode_data_url(data_url, limits.max_bytes)
+        .map_err(|e| ApiError::new(e.status_code(), e))?;
+    let video = upload::video::check(&state.ffmpeg, bytes, &limits, config.video_convert_gifs)
+        .await
+        .map_err(|e| ApiError::new(e.status_code(), e))?;
+    tracing::info!(
+        mime = video.format.mime(),
+        byte_size = video.bytes.len(),
+        duration_ms = video.probe.duration_ms,
+        "Storing uploaded video"
+    );
+    Ok(upload::video::store(
+        conn,
+        &state.media,
+        owner,
+        video,
+        state.config.image.limits(),
+        state.config.image.image_webp_variants,
+    )
+    .await?)
 }
 
-#[tracing::instrument(name = "Getting image from server", skip_all)]
-pub async fn load_image(Path(img_id): Path<Uuid>) -> Result<Response<Body>, ApiError> {
-    let mut path = PathBuf::from(USER_CONTEND_DIR);
-    path.push(img_id.to_string());
-    tracing::info!("Reading image...");
-    // Attempt to read the image file
-    let raw = fs::read_to_string(path).await.map_err(|e| {
-        ApiError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            anyhow::Error::msg(format!("Failed to read image file: {}", e)),
-        )
-    })?;
+/// How a stored video plays, `None` if it is gone.
+pub async fn video_details(
+    conn: &mut AsyncPgConnection,
+    media: &Media,
+    video_id: VideoId,
+) -> ApiResult<Option<VideoDetails>> {
+    let Some(video) = uchat_query::video::get(conn, video_id).await? else {
+        return Ok(None);
+    };
+    Ok(Some(VideoDetails {
+        mime: video.mime,
+        width: video.width as u32,
+        height: video.height as u32,
+        duration_ms: video.duration_ms as u32,
+        looping: video.looping,
+        poster: media.image_url(&video.poster_image_id.to_string()).await?,
+    }))
+}
 
-    // Split the data into header and image data
-    let (header, image_data) = raw.split_once(',').ok_or_else(|| {
-        ApiError::new(
-            StatusCode::BAD_REQUEST,
-            anyhow::Error::msg("Invalid image format"),
-        )
-    })?;
-
-    // Extract the MIME type from the header
-    let mime = header
-        .split_once("data:")
-        .and_then(|(_, mime)| mime.split_once(";base64"))
-        .map(|(mime, _)| mime)
-        .ok_or_else(|| {
-            ApiError::new(
-                StatusCode::BAD_REQUEST,
-                anyhow::Error::msg("Invalid MIME type format"),
-            )
-        })?;
-
-    // Decode the base64 image data
-    tracing::info!("Decoding base64 image data");
-    let image_data = general_purpose::STANDARD.decode(image_data).map_err(|e| {
-        ApiError::new(
-            StatusCode::BAD_REQUEST,
-            anyhow::Error::msg(format!("Failed to decode base64 image data: {}", e)),
-        )
-    })?;
+/// URLs of the variants of an image and of the image itself, narrowest
+/// first.
+pub async fn image_variants(
+    conn: &mut AsyncPgConnection,
+    media: &Media,
+    image_id: ImageId,
+) -> ApiResult<Vec<ImageVariant>> {
+    let Some(image) = uchat_query::image::get(conn, image_id).await? else {
+        return Ok(vec![]);
+    };
+    let mut variants = vec![];
+    for variant in uchat_query::image::variants(conn, image_id).await? {
+        let width = variant.width as u32;
+        variants.push(ImageVariant {
+            width,
+            url: media
+                .image_url(&upload::variant::key(image_id, width))
+                .await?,
+        });
+    }
+    variants.push(ImageVariant {
+        width: image.width as u32,
+        url: media.image_url(&image_id.to_string()).await?,
+    });
+    Ok(variants)
+}
+
+/// Serves an image, one of its variants for keys ending in `_w<width>`, or
+/// a video for keys ending in `_video`. Responses may be cached for good, as
+/// nothing is ever stored under the same key twice.
+#[tracing::instrument(name = "Getting image from server", skip_all, fields(key = %key))]
+pub async fn load_image(
+    DbConnection(mut conn): DbConnection,
+    State(state): State<AppState>,
+    method: Method,
+    headers: HeaderMap,
+    Path(key): Path<String>,
+) -> Result<Response<Body>, ApiError> {
+    let not_found = || ApiError::new(StatusCode::NOT_FOUND, anyhow::Error::msg("Image not found"));
 
-    // Build and return the HTTP response
-    Response::builder()
-        .status(StatusCode::OK)
+    let (mime, byte_size, etag, created_at) = match upload::video::parse_key(&key) {
+        Some(video_id) => {
+            let video = uchat_query::video::get(&mut conn, video_id)
+                .await?
+                .ok_or_else(not_found)?;
+            let etag = http::etag(&video.sha256);
+            (video.mime, video.byte_size, etag, video.created_at)
+        }
+        None => {
+            let (image_id, width) = upload::variant::parse_key(&key).ok_or_else(not_found)?;
+            let image = uchat_query::image::get(&mut conn, image_id)
+                .await?
+                .ok_or_else(not_found)?;
+            // Variants are rendered from the image, so its hash identifies them too
+            match width {
+                None => (
+                    image.mime,
+                    image.byte_size,
+                    http::etag(&image.sha256),
+                    image.created_at,
+                ),
+                Some(width) => {
+                    let width = i32::try_from(width).map_err(|_| not_found())?;
+                    let variant = uchat_query::image::get_variant(&mut conn, image_id, width)
+                        .await?
+                        .ok_or_else(not_found)?;
+                    let etag = http::etag(&format!("{}-w{width}", image.sha256));
+                    (variant.mime, variant.byte_size, etag, image.created_at)
+                }
+            }
+        }
+    };
+    let len = byte_size as u64;
+
+    let response = Response::builder()
+        .header(ETAG, &etag)
+        .header(LAST_MODIFIED, http::http_date(created_at))
+        .header(CACHE_CONTROL, http::CACHE_CONTROL);
+    if http::not_modified(&headers, &etag, created_at) {
+        return build_response(response.status(StatusCode::NOT_MODIFIED), Body::empty());
+    }
+    let response = response
         .header(CONTENT_TYPE, mime)
-        .body(Body::from(image_data))
-        .map_err(|e| {
-            ApiError::new(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                anyhow::Error::msg(format!("Failed to build response: {}", e)),
-            )
-        })
+        .header(X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .header(ACCEPT_RANGES, "bytes");
+
+    let (response, range) = match http::byte_range(&headers, &etag, len) {
+        ByteRange::Full => (
+            response.status(StatusCode::OK).header(CONTENT_LENGTH, len),
+            None,
+        ),
+        ByteRange::Partial(range) => (
+            response
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(CONTENT_RANGE, http::content_range(&range, len))
+                .header(CONTENT_LENGTH, range.end - range.start),
+            Some(range),
+        ),
+        ByteRange::Unsatisfiable => {
+            let response = response
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(CONTENT_RANGE, format!("bytes */{len}"));
+            return build_response(response, Body::empty());
+        }
+    };
+    if method == Method::HEAD {
+        return build_response(response, Body::empty());
+    }
+
+    let body = match range {
+        Some(range) => state.media.get_range(&key, range).await?,
+        None => state.media.get(&key).await?,
+    };
+    let Some(body) = body else {
+        tracing::error!("Image has metadata but is missing from the media store");
+        return Err(not_found());
+    };
+    build_response(response, body)
+}
+
+fn build_response(response: response::Builder, body: Body) -> Result<Response<Body>, ApiError> {
+    response.body(body).map_err(|e| {
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::Error::msg(format!("Failed to build response: {}", e)),
+        )
+    })
 }
@@ -0,0 +1,155 @@
+use anyhow::anyhow;
+use axum::{async_trait, http::StatusCode, Json};
+use chrono::Utc;
+use uchat_endpoint::{
+    analytics::{
+        endpoint::{PostAnalytics, PostAnalyticsOk, ProfileAnalytics, ProfileAnalyticsOk},
+        types::{PostActivity, ProfileVisits, SeenPost},
+    },
+    user::types::TokenScope,
+    RequestFailed,
+};
+use uchat_query::DieselError;
+
+use crate::{
+    analytics::Buckets,
+    error::{ApiError, ApiResult},
+    extractor::{ClientInfo, DbConnection, UserSession},
+    AppState,
+};
+
+use super::AuthorizedApiRequest;
+
+/// Posts listed as seen most on the profile analytics.
+const MOST_SEEN_POSTS: i64 = 5;
+
+fn post_not_found() -> ApiError {
+    ApiError {
+        code: Some(StatusCode::NOT_FOUND),
+        error: anyhow!(RequestFailed {
+            msg: "Post not found".to_string()
+        }),
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for PostAnalytics {
+    type Response = (StatusCode, Json<PostAnalyticsOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
+
+    #[tracing::instrument(
+        name = "Post analytics",
+        skip_all,
+        fields(user_id = ?session.user_id, post_id = ?self.post_id, period = ?self.period)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let post = match uchat_query::post::get(&mut conn, self.post_id).await {
+            Ok(post) if post.user_id == session.user_id => post,
+            Ok(_) | Err(DieselError::NotFound) => return Err(post_not_found()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let buckets = Buckets::new(self.period, Utc::now());
+        let since = buckets.since();
+        let mut activity = buckets
+            .starts()
+            .map(|start| PostActivity {
+                start,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        for (window, impressions) in
+            uchat_query::analytics::impressions(&mut conn, post.id, since).await?
+        {
+            if let Some(i) = buckets.index(window) {
+                activity[i].impressions += impressions;
+            }
+        }
+        for at in uchat_query::analytics::reaction_times(&mut conn, post.id, since).await? {
+            if let Some(i) = buckets.index(at) {
+                activity[i].reactions += 1;
+            }
+        }
+        for at in uchat_query::analytics::boost_times(&mut conn, post.id, since).await? {
+            if let Some(i) = buckets.index(at) {
+                activity[i].boosts += 1;
+            }
+        }
+        for at in uchat_query::analytics::reply_times(&mut conn, post.id, since).await? {
+            if let Some(i) = buckets.index(at) {
+                activity[i].replies += 1;
+            }
+        }
+        for at in uchat_query::analytics::vote_times(&mut conn, post.id, since).await? {
+            if let Some(i) = buckets.index(at) {
+                activity[i].votes += 1;
+            }
+        }
+
+        let post = super::post::to_public(&mut conn, &state.media, post, Some(&session)).await?;
+        Ok((StatusCode::OK, Json(PostAnalyticsOk { post, activity })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for ProfileAnalytics {
+    type Response = (StatusCode, Json<ProfileAnalyticsOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
+
+    #[tracing::instrument(
+        name = "Profile analytics",
+        skip_all,
+        fields(user_id = ?session.user_id, period = ?self.period)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let buckets = Buckets::new(self.period, Utc::now());
+        let since = buckets.since();
+        let mut visits = buckets
+            .starts()
+            .map(|start| ProfileVisits { start, visits: 0 })
+            .collect::<Vec<_>>();
+        for (window, count) in
+            uchat_query::analytics::profile_visits(&mut conn, session.user_id, since).await?
+        {
+            if let Some(i) = buckets.index(window) {
+                visits[i].visits += count;
+            }
+        }
+
+        let mut most_seen = vec![];
+        for (post_id, impressions) in uchat_query::analytics::most_seen_posts(
+            &mut conn,
+            session.user_id,
+            since,
+            MOST_SEEN_POSTS,
+        )
+        .await?
+        {
+            let post = uchat_query::post::get(&mut conn, post_id).await?;
+            match super::post::to_public(&mut conn, &state.media, post, Some(&session)).await {
+                Ok(post) => most_seen.push(SeenPost { post, impressions }),
+                Err(e) => {
+                    tracing::error!(error = %e.error, post_id = ?post_id, "Post contains invalid data");
+                }
+            }
+        }
+
+        Ok((
+            StatusCode::OK,
+            Json(ProfileAnalyticsOk { visits, most_seen }),
+        ))
+    }
+}
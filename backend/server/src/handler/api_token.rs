@@ -0,0 +1,182 @@
+use anyhow::anyhow;
+use axum::{async_trait, http::StatusCode, Json};
+use chrono::{Duration, Utc};
+use uchat_crypto::token::{generate_token, hash_token, visible_part};
+use uchat_endpoint::{
+    user::{
+        endpoint::{
+            CreateApiToken, CreateApiTokenOk, ListApiTokens, ListApiTokensOk, RevokeApiToken,
+            RevokeApiTokenOk,
+        },
+        types::{ApiToken, SecurityEventKind, TokenScope},
+    },
+    RequestFailed,
+};
+
+use crate::{
+    error::{ApiError, ApiResult},
+    extractor::{ClientInfo, DbConnection, UserSession},
+    AppState,
+};
+
+use super::{security::record_event, AuthorizedApiRequest};
+
+const MAX_NAME_CHARS: usize = 60;
+const MAX_EXPIRY_DAYS: i64 = 366;
+
+fn to_public(token: uchat_query::api_token::ApiToken) -> ApiToken {
+    ApiToken {
+        id: token.id,
+        name: token.name,
+        prefix: token.token_prefix,
+        scopes: token
+            .scopes
+            .iter()
+            .filter_map(|scope| scope.parse::<TokenScope>().ok())
+            .collect(),
+        expires_at: token.expires_at,
+        last_used_at: token.last_used_at,
+        created_at: token.created_at,
+    }
+}
+
+fn bad_request<T: Into<String>>(msg: T) -> ApiError {
+    ApiError {
+        code: Some(StatusCode::BAD_REQUEST),
+        error: anyhow!(RequestFailed { msg: msg.into() }),
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for CreateApiToken {
+    type Response = (StatusCode, Json<CreateApiTokenOk>);
+    #[tracing::instrument(
+        name = "Create API token",
+        skip_all,
+        fields(user_id = ?session.user_id, scopes = ?self.scopes)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let name = self.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+            return Err(bad_request(format!(
+                "Token name must have 1 to {MAX_NAME_CHARS} characters"
+            )));
+        }
+        if self.scopes.is_empty() {
+            return Err(bad_request("Select at least one scope"));
+        }
+        let expires_at = match self.expires_in_days {
+            Some(days) if !(1..=MAX_EXPIRY_DAYS).contains(&days) => {
+                return Err(bad_request(format!(
+                    "Tokens expire after 1 to {MAX_EXPIRY_DAYS} days"
+                )));
+            }
+            Some(days) => Some(Utc::now() + Duration::days(days)),
+            None => None,
+        };
+
+        let mut scopes = self
+            .scopes
+            .iter()
+            .map(|scope| scope.as_str().to_string())
+            .collect::<Vec<_>>();
+        scopes.sort();
+        scopes.dedup();
+
+        let mut rng = uchat_crypto::new_rng();
+        let secret = generate_token(&mut rng);
+        let token = uchat_query::api_token::new(
+            &mut conn,
+            session.user_id,
+            name,
+            visible_part(&secret),
+            &hash_token(&secret),
+            scopes,
+            expires_at,
+        )
+        .await?;
+        record_event(
+            &mut conn,
+            &client,
+            session.user_id,
+            SecurityEventKind::TokenCreated,
+        )
+        .await;
+
+        tracing::info!(token_id = ?token.id, "API token created");
+        Ok((
+            StatusCode::CREATED,
+            Json(CreateApiTokenOk {
+                token: to_public(token),
+                secret,
+            }),
+        ))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for ListApiTokens {
+    type Response = (StatusCode, Json<ListApiTokensOk>);
+    #[tracing::instrument(
+        name = "List API tokens",
+        skip_all,
+        fields(user_id = ?session.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let tokens = uchat_query::api_token::list(&mut conn, session.user_id)
+            .await?
+            .into_iter()
+            .map(to_public)
+            .collect();
+
+        Ok((StatusCode::OK, Json(ListApiTokensOk { tokens })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for RevokeApiToken {
+    type Response = (StatusCode, Json<RevokeApiTokenOk>);
+    #[tracing::instrument(
+        name = "Revoke API token",
+        skip_all,
+        fields(user_id = ?session.user_id, token_id = ?self.id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        if !uchat_query::api_token::revoke(&mut conn, session.user_id, self.id).await? {
+            return Err(ApiError {
+                code: Some(StatusCode::NOT_FOUND),
+                error: anyhow!(RequestFailed {
+                    msg: "Token not found".to_string()
+                }),
+            });
+        }
+        record_event(
+            &mut conn,
+            &client,
+            session.user_id,
+            SecurityEventKind::TokenRevoked,
+        )
+        .await;
+
+        tracing::info!("API token revoked");
+        Ok((StatusCode::OK, Json(RevokeApiTokenOk)))
+    }
+}
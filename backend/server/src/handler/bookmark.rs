@@ -0,0 +1,255 @@
+use anyhow::anyhow;
+use axum::{async_trait, http::StatusCode, Json};
+use diesel_async::AsyncPgConnection;
+use uchat_endpoint::{
+    post::{
+        endpoint::{
+            CreateBookmarkFolder, CreateBookmarkFolderOk, DeleteBookmarkFolder,
+            DeleteBookmarkFolderOk, GetBookmarkFolders, GetBookmarkFoldersOk, MoveBookmark,
+            MoveBookmarkOk, RenameBookmarkFolder, RenameBookmarkFolderOk, SetBookmarkNote,
+            SetBookmarkNoteOk,
+        },
+        types::{BookmarkDetails, BookmarkFolder},
+    },
+    user::types::TokenScope,
+    RequestFailed,
+};
+
+use crate::{
+    error::{ApiError, ApiResult},
+    extractor::{ClientInfo, DbConnection, UserSession},
+    AppState,
+};
+
+use super::AuthorizedApiRequest;
+
+const MAX_FOLDERS: i64 = 50;
+
+fn bad_request<T: Into<String>>(msg: T) -> ApiError {
+    ApiError {
+        code: Some(StatusCode::BAD_REQUEST),
+        error: anyhow!(RequestFailed { msg: msg.into() }),
+    }
+}
+
+fn not_found(what: &str) -> ApiError {
+    ApiError {
+        code: Some(StatusCode::NOT_FOUND),
+        error: anyhow!(RequestFailed {
+            msg: format!("{what} not found")
+        }),
+    }
+}
+
+fn check_name(name: &str) -> ApiResult<&str> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > BookmarkFolder::MAX_NAME_CHARS {
+        return Err(bad_request(format!(
+            "Folder names must have 1 to {} characters",
+            BookmarkFolder::MAX_NAME_CHARS
+        )));
+    }
+    Ok(name)
+}
+
+async fn to_public(
+    conn: &mut AsyncPgConnection,
+    folder: uchat_query::bookmark::Folder,
+) -> ApiResult<BookmarkFolder> {
+    Ok(BookmarkFolder {
+        bookmark_count: uchat_query::bookmark::count_in_folder(conn, folder.id).await?,
+        id: folder.id,
+        name: folder.name,
+        created_at: folder.created_at,
+    })
+}
+
+pub fn to_details(bookmark: uchat_query::bookmark::Bookmark) -> BookmarkDetails {
+    BookmarkDetails {
+        post_id: bookmark.post_id,
+        folder_id: bookmark.folder_id,
+        note: bookmark.note,
+        bookmarked_at: bookmark.created_at,
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for GetBookmarkFolders {
+    type Response = (StatusCode, Json<GetBookmarkFoldersOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
+
+    #[tracing::instrument(
+        name = "Get bookmark folders",
+        skip_all,
+        fields(user_id = ?session.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let mut folders = vec![];
+        for folder in uchat_query::bookmark::folders(&mut conn, session.user_id).await? {
+            folders.push(to_public(&mut conn, folder).await?);
+        }
+
+        Ok((StatusCode::OK, Json(GetBookmarkFoldersOk { folders })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for CreateBookmarkFolder {
+    type Response = (StatusCode, Json<CreateBookmarkFolderOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Create bookmark folder",
+        skip_all,
+        fields(user_id = ?session.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let name = check_name(&self.name)?;
+        if uchat_query::bookmark::folder_count(&mut conn, session.user_id).await? >= MAX_FOLDERS {
+            return Err(bad_request(format!(
+                "You can keep at most {MAX_FOLDERS} bookmark folders"
+            )));
+        }
+
+        let folder = uchat_query::bookmark::new_folder(&mut conn, session.user_id, name).await?;
+        tracing::info!(folder_id = ?folder.id, "Bookmark folder created");
+        let folder = to_public(&mut conn, folder).await?;
+        Ok((StatusCode::CREATED, Json(CreateBookmarkFolderOk { folder })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for RenameBookmarkFolder {
+    type Response = (StatusCode, Json<RenameBookmarkFolderOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Rename bookmark folder",
+        skip_all,
+        fields(user_id = ?session.user_id, folder_id = ?self.folder_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let name = check_name(&self.name)?;
+        let folder =
+            uchat_query::bookmark::rename_folder(&mut conn, session.user_id, self.folder_id, name)
+                .await?
+                .ok_or_else(|| not_found("Folder"))?;
+
+        let folder = to_public(&mut conn, folder).await?;
+        Ok((StatusCode::OK, Json(RenameBookmarkFolderOk { folder })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for DeleteBookmarkFolder {
+    type Response = (StatusCode, Json<DeleteBookmarkFolderOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Delete bookmark folder",
+        skip_all,
+        fields(user_id = ?session.user_id, folder_id = ?self.folder_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        if !uchat_query::bookmark::delete_folder(&mut conn, session.user_id, self.folder_id).await?
+        {
+            return Err(not_found("Folder"));
+        }
+
+        tracing::info!("Bookmark folder deleted");
+        Ok((StatusCode::OK, Json(DeleteBookmarkFolderOk)))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for MoveBookmark {
+    type Response = (StatusCode, Json<MoveBookmarkOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Move bookmark",
+        skip_all,
+        fields(user_id = ?session.user_id, post_id = ?self.post_id, folder_id = ?self.folder_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        if let Some(folder_id) = self.folder_id {
+            uchat_query::bookmark::get_folder(&mut conn, session.user_id, folder_id)
+                .await?
+                .ok_or_else(|| not_found("Folder"))?;
+        }
+        if !uchat_query::bookmark::move_to(&mut conn, session.user_id, self.post_id, self.folder_id)
+            .await?
+        {
+            return Err(not_found("Bookmark"));
+        }
+
+        Ok((StatusCode::OK, Json(MoveBookmarkOk)))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for SetBookmarkNote {
+    type Response = (StatusCode, Json<SetBookmarkNoteOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Set bookmark note",
+        skip_all,
+        fields(user_id = ?session.user_id, post_id = ?self.post_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let note = Some(self.note.trim()).filter(|note| !note.is_empty());
+        if note.is_some_and(|note| note.chars().count() > BookmarkDetails::MAX_NOTE_CHARS) {
+            return Err(bad_request(format!(
+                "Notes can be at most {} characters long",
+                BookmarkDetails::MAX_NOTE_CHARS
+            )));
+        }
+        if !uchat_query::bookmark::set_note(&mut conn, session.user_id, self.post_id, note).await? {
+            return Err(not_found("Bookmark"));
+        }
+
+        Ok((
+            StatusCode::OK,
+            Json(SetBookmarkNoteOk {
+                note: note.map(str::to_string),
+            }),
+        ))
+    }
+}
@@ -0,0 +1,215 @@
+use anyhow::anyhow;
+use axum::{async_trait, http::StatusCode, Json};
+use chrono::Utc;
+use uchat_endpoint::{
+    post::{
+        endpoint::{
+            DeleteDraft, DeleteDraftOk, ListDrafts, ListDraftsOk, PublishDraft, PublishDraftOk,
+            SaveDraft, SaveDraftOk,
+        },
+        types::{Content, Draft, NewPostOptions},
+    },
+    user::types::TokenScope,
+    RequestFailed,
+};
+use uchat_query::post::Post;
+
+use crate::{
+    error::{ApiError, ApiResult},
+    extractor::{ClientInfo, DbConnection, UserSession},
+    link_preview,
+    media::Media,
+    AppState,
+};
+
+use super::{
+    post::{check_content, save_uploads},
+    AuthorizedApiRequest,
+};
+
+const MAX_DRAFTS: i64 = 100;
+
+fn not_found() -> ApiError {
+    ApiError {
+        code: Some(StatusCode::NOT_FOUND),
+        error: anyhow!(RequestFailed {
+            msg: "Draft not found".to_string()
+        }),
+    }
+}
+
+async fn to_public(media: &Media, draft: uchat_query::draft::Draft) -> ApiResult<Option<Draft>> {
+    let (Ok(content), Ok(options)) = (
+        serde_json::from_value::<Content>(draft.content),
+        serde_json::from_value::<NewPostOptions>(draft.options),
+    ) else {
+        return Ok(None);
+    };
+    let mut image_urls = vec![];
+    for image_id in content.image_ids() {
+        image_urls.push((image_id, media.image_url(&image_id.to_string()).await?));
+    }
+    Ok(Some(Draft {
+        id: draft.id,
+        content,
+        options,
+        image_urls,
+        updated_at: draft.updated_at,
+    }))
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for SaveDraft {
+    type Response = (StatusCode, Json<SaveDraftOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Save draft",
+        skip_all,
+        fields(user_id = ?session.user_id, draft_id = ?self.draft_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let mut content = self.content;
+        // Drafts may run over the post limits while being written, but
+        // uploads are stored right away so they aren't sent again
+        save_uploads(&mut conn, &state, session.user_id, &mut content).await?;
+        let content = serde_json::to_value(content)?;
+        let options = serde_json::to_value(self.options)?;
+
+        let draft = match self.draft_id {
+            Some(draft_id) => {
+                uchat_query::draft::update(&mut conn, session.user_id, draft_id, content, options)
+                    .await?
+                    .ok_or_else(not_found)?
+            }
+            None => {
+                if uchat_query::draft::count(&mut conn, session.user_id).await? >= MAX_DRAFTS {
+                    return Err(ApiError::new(
+                        StatusCode::BAD_REQUEST,
+                        RequestFailed {
+                            msg: format!("You can keep at most {MAX_DRAFTS} drafts"),
+                        },
+                    ));
+                }
+                uchat_query::draft::new(&mut conn, session.user_id, content, options).await?
+            }
+        };
+
+        let draft = to_public(&state.media, draft)
+            .await?
+            .ok_or_else(|| ApiError {
+                code: Some(StatusCode::INTERNAL_SERVER_ERROR),
+                error: anyhow!(RequestFailed {
+                    msg: "Invalid draft data".to_string()
+                }),
+            })?;
+        Ok((StatusCode::OK, Json(SaveDraftOk { draft })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for ListDrafts {
+    type Response = (StatusCode, Json<ListDraftsOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
+
+    #[tracing::instrument(name = "List drafts", skip_all, fields(user_id = ?session.user_id))]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let mut drafts = vec![];
+        for draft in uchat_query::draft::list(&mut conn, session.user_id).await? {
+            if let Some(draft) = to_public(&state.media, draft).await? {
+                drafts.push(draft);
+            }
+        }
+
+        Ok((StatusCode::OK, Json(ListDraftsOk { drafts })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for DeleteDraft {
+    type Response = (StatusCode, Json<DeleteDraftOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Delete draft",
+        skip_all,
+        fields(user_id = ?session.user_id, draft_id = ?self.draft_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        if !uchat_query::draft::delete(&mut conn, session.user_id, self.draft_id).await? {
+            return Err(not_found());
+        }
+
+        Ok((StatusCode::OK, Json(DeleteDraftOk)))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for PublishDraft {
+    type Response = (StatusCode, Json<PublishDraftOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Publish draft",
+        skip_all,
+        fields(user_id = ?session.user_id, draft_id = ?self.draft_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let draft = uchat_query::draft::get(&mut conn, session.user_id, self.draft_id)
+            .await?
+            .ok_or_else(not_found)?;
+        let (Ok(content), Ok(mut options)) = (
+            serde_json::from_value::<Content>(draft.content),
+            serde_json::from_value::<NewPostOptions>(draft.options),
+        ) else {
+            return Err(ApiError {
+                code: Some(StatusCode::INTERNAL_SERVER_ERROR),
+                error: anyhow!(RequestFailed {
+                    msg: "Invalid draft data".to_string()
+                }),
+            });
+        };
+
+        let mut content = check_content(content, &state.config.post_limits)?;
+        save_uploads(&mut conn, &state, session.user_id, &mut content).await?;
+        options.time_posted = options.time_posted.max(Utc::now());
+        let linked_url = match &content {
+            Content::Link(link) => Some(link.url.clone()),
+            _ => None,
+        };
+        let post = Post::new(session.user_id, content, options)?;
+        let post_id = uchat_query::draft::publish(&mut conn, session.user_id, draft.id, post)
+            .await?
+            .ok_or_else(not_found)?;
+        tracing::info!(post_id = ?post_id, "Draft published");
+        if let Some(url) = linked_url {
+            link_preview::refresh(&state, url, session.user_id);
+        }
+
+        Ok((StatusCode::OK, Json(PublishDraftOk { post_id })))
+    }
+}
@@ -0,0 +1,342 @@
+use anyhow::anyhow;
+use axum::{async_trait, http::StatusCode, Json};
+use diesel_async::AsyncPgConnection;
+use uchat_endpoint::{
+    user::{
+        endpoint::{
+            AddListMember, AddListMemberOk, CreateList, CreateListOk, DeleteList, DeleteListOk,
+            GetList, GetListMemberships, GetListMembershipsOk, GetListOk, GetLists, GetListsOk,
+            RemoveListMember, RemoveListMemberOk, UpdateList, UpdateListOk,
+        },
+        types::{TokenScope, UserList},
+    },
+    RequestFailed,
+};
+use uchat_query::{ListId, QueryError, UserId};
+
+use crate::{
+    error::{ApiError, ApiResult},
+    extractor::{ClientInfo, DbConnection, UserSession},
+    AppState,
+};
+
+use super::AuthorizedApiRequest;
+
+const MAX_LISTS: i64 = 100;
+const MAX_MEMBERS: i64 = 500;
+
+fn bad_request<T: Into<String>>(msg: T) -> ApiError {
+    ApiError {
+        code: Some(StatusCode::BAD_REQUEST),
+        error: anyhow!(RequestFailed { msg: msg.into() }),
+    }
+}
+
+fn not_found() -> ApiError {
+    ApiError {
+        code: Some(StatusCode::NOT_FOUND),
+        error: anyhow!(RequestFailed {
+            msg: "List not found".to_string()
+        }),
+    }
+}
+
+/// Trimmed name and description, with an empty description left out.
+fn check_list<'a>(
+    name: &'a str,
+    description: Option<&'a str>,
+) -> ApiResult<(&'a str, Option<&'a str>)> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > UserList::MAX_NAME_CHARS {
+        return Err(bad_request(format!(
+            "List names must have 1 to {} characters",
+            UserList::MAX_NAME_CHARS
+        )));
+    }
+    let description = description
+        .map(str::trim)
+        .filter(|description| !description.is_empty());
+    if description
+        .is_some_and(|description| description.chars().count() > UserList::MAX_DESCRIPTION_CHARS)
+    {
+        return Err(bad_request(format!(
+            "List descriptions can be at most {} characters long",
+            UserList::MAX_DESCRIPTION_CHARS
+        )));
+    }
+    Ok((name, description))
+}
+
+async fn to_public(
+    conn: &mut AsyncPgConnection,
+    list: uchat_query::list::List,
+) -> ApiResult<UserList> {
+    Ok(UserList {
+        member_count: uchat_query::list::member_count(conn, list.id).await?,
+        id: list.id,
+        owner: list.user_id,
+        name: list.name,
+        description: list.description,
+        private: list.private,
+        created_at: list.created_at,
+    })
+}
+
+/// The list, if the user may see it: their own, or a public one.
+pub async fn visible_list(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    list_id: ListId,
+) -> ApiResult<uchat_query::list::List> {
+    match uchat_query::list::get(conn, list_id).await? {
+        Some(list) if list.user_id == user_id || !list.private => Ok(list),
+        _ => Err(not_found()),
+    }
+}
+
+/// The list, if the user owns it.
+async fn own_list(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    list_id: ListId,
+) -> ApiResult<uchat_query::list::List> {
+    match uchat_query::list::get(conn, list_id).await? {
+        Some(list) if list.user_id == user_id => Ok(list),
+        _ => Err(not_found()),
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for CreateList {
+    type Response = (StatusCode, Json<CreateListOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(name = "Create list", skip_all, fields(user_id = ?session.user_id))]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let (name, description) = check_list(&self.name, self.description.as_deref())?;
+        if uchat_query::list::count(&mut conn, session.user_id).await? >= MAX_LISTS {
+            return Err(bad_request(format!(
+                "You can keep at most {MAX_LISTS} lists"
+            )));
+        }
+
+        let list =
+            uchat_query::list::new(&mut conn, session.user_id, name, description, self.private)
+                .await?;
+
+        tracing::info!(list_id = ?list.id, "List created");
+        let list = to_public(&mut conn, list).await?;
+        Ok((StatusCode::CREATED, Json(CreateListOk { list })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for UpdateList {
+    type Response = (StatusCode, Json<UpdateListOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Update list",
+        skip_all,
+        fields(user_id = ?session.user_id, list_id = ?self.list_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let (name, description) = check_list(&self.name, self.description.as_deref())?;
+        let list = uchat_query::list::update(
+            &mut conn,
+            session.user_id,
+            self.list_id,
+            name,
+            description,
+            self.private,
+        )
+        .await?
+        .ok_or_else(not_found)?;
+
+        let list = to_public(&mut conn, list).await?;
+        Ok((StatusCode::OK, Json(UpdateListOk { list })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for DeleteList {
+    type Response = (StatusCode, Json<DeleteListOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Delete list",
+        skip_all,
+        fields(user_id = ?session.user_id, list_id = ?self.list_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        if !uchat_query::list::delete(&mut conn, session.user_id, self.list_id).await? {
+            return Err(not_found());
+        }
+
+        tracing::info!("List deleted");
+        Ok((StatusCode::OK, Json(DeleteListOk)))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for GetLists {
+    type Response = (StatusCode, Json<GetListsOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
+
+    #[tracing::instrument(
+        name = "Get lists",
+        skip_all,
+        fields(user_id = ?session.user_id, owner = ?self.owner)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let owner = self.owner.unwrap_or(session.user_id);
+        let with_private = owner == session.user_id;
+        let mut lists = vec![];
+        for list in uchat_query::list::owned_by(&mut conn, owner, with_private).await? {
+            lists.push(to_public(&mut conn, list).await?);
+        }
+
+        Ok((StatusCode::OK, Json(GetListsOk { lists })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for GetList {
+    type Response = (StatusCode, Json<GetListOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
+
+    #[tracing::instrument(
+        name = "Get list",
+        skip_all,
+        fields(user_id = ?session.user_id, list_id = ?self.list_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let list = visible_list(&mut conn, session.user_id, self.list_id).await?;
+        let mut members = vec![];
+        for user_id in uchat_query::list::member_ids(&mut conn, list.id).await? {
+            let user = uchat_query::user::get(&mut conn, user_id).await?;
+            members
+                .push(super::user::to_public(&mut conn, &state.media, Some(&session), user).await?);
+        }
+        let list = to_public(&mut conn, list).await?;
+
+        Ok((StatusCode::OK, Json(GetListOk { list, members })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for AddListMember {
+    type Response = (StatusCode, Json<AddListMemberOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Add list member",
+        skip_all,
+        fields(user_id = ?session.user_id, list_id = ?self.list_id, member = ?self.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let list = own_list(&mut conn, session.user_id, self.list_id).await?;
+        match uchat_query::user::get(&mut conn, self.user_id).await {
+            Ok(_) => {}
+            Err(QueryError::NotFound) => {
+                return Err(ApiError {
+                    code: Some(StatusCode::NOT_FOUND),
+                    error: anyhow!(RequestFailed {
+                        msg: "User not found".to_string()
+                    }),
+                })
+            }
+            Err(e) => return Err(e.into()),
+        }
+        if uchat_query::list::member_count(&mut conn, list.id).await? >= MAX_MEMBERS {
+            return Err(bad_request(format!(
+                "A list holds at most {MAX_MEMBERS} users"
+            )));
+        }
+
+        uchat_query::list::add_member(&mut conn, list.id, self.user_id).await?;
+        Ok((StatusCode::OK, Json(AddListMemberOk)))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for RemoveListMember {
+    type Response = (StatusCode, Json<RemoveListMemberOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Remove list member",
+        skip_all,
+        fields(user_id = ?session.user_id, list_id = ?self.list_id, member = ?self.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let list = own_list(&mut conn, session.user_id, self.list_id).await?;
+        uchat_query::list::remove_member(&mut conn, list.id, self.user_id).await?;
+        Ok((StatusCode::OK, Json(RemoveListMemberOk)))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for GetListMemberships {
+    type Response = (StatusCode, Json<GetListMembershipsOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
+
+    #[tracing::instrument(
+        name = "Get list memberships",
+        skip_all,
+        fields(user_id = ?session.user_id, member = ?self.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let list_ids =
+            uchat_query::list::containing(&mut conn, session.user_id, self.user_id).await?;
+        Ok((StatusCode::OK, Json(GetListMembershipsOk { list_ids })))
+    }
+}
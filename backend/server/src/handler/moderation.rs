@@ -0,0 +1,504 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use axum::{async_trait, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use diesel_async::AsyncPgConnection;
+use uchat_endpoint::{
+    moderation::{
+        endpoint::{
+            GetModerationLog, GetModerationLogOk, GetModerationQueue, GetModerationQueueOk,
+            ReportPost, ReportPostOk, ReportUser, ReportUserOk, TakeModerationAction,
+            TakeModerationActionOk,
+        },
+        types::{
+            ModerationAction, ModerationActionKind, ModerationLogEntry, QueuedReport, ReportReason,
+            ReportStatus, Role,
+        },
+    },
+    user::types::TokenScope,
+    RequestFailed,
+};
+use uchat_query::{moderation::ModerationEntry, DieselError, QueryError, UserId};
+
+use crate::{
+    error::{ApiError, ApiResult},
+    extractor::{ClientInfo, DbConnection, UserSession},
+    AppState,
+};
+
+use super::AuthorizedApiRequest;
+
+const MAX_DETAILS_CHARS: usize = 500;
+const MAX_SUSPENSION_DAYS: u16 = 365;
+const QUEUE_SIZE: i64 = 50;
+const LOG_ENTRIES_PER_PAGE: i64 = 50;
+
+/// Why the account may not be used right now, if it may not.
+pub fn restriction(
+    suspended_until: Option<DateTime<Utc>>,
+    banned_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    if banned_at.is_some() {
+        return Some("This account has been banned".to_string());
+    }
+    match suspended_until {
+        Some(until) if until > now => Some(format!(
+            "This account is suspended until {}",
+            until.format("%Y-%m-%d %H:%M UTC")
+        )),
+        _ => None,
+    }
+}
+
+fn request_failed<T: Into<String>>(code: StatusCode, msg: T) -> ApiError {
+    ApiError {
+        code: Some(code),
+        error: anyhow!(RequestFailed { msg: msg.into() }),
+    }
+}
+
+fn require_moderator(session: &UserSession) -> ApiResult<()> {
+    if session.role.is_moderator() {
+        Ok(())
+    } else {
+        Err(request_failed(
+            StatusCode::FORBIDDEN,
+            "Only moderators can do this",
+        ))
+    }
+}
+
+/// Trimmed free text, `None` when empty.
+fn optional_text(text: Option<String>) -> ApiResult<Option<String>> {
+    let text = text
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty());
+    match text {
+        Some(text) if text.chars().count() > MAX_DETAILS_CHARS => Err(request_failed(
+            StatusCode::BAD_REQUEST,
+            format!("Keep it under {MAX_DETAILS_CHARS} characters"),
+        )),
+        text => Ok(text),
+    }
+}
+
+/// Looks up handles, remembering them as a page usually names the same
+/// users many times. Users who were deleted since have no handle.
+#[derive(Default)]
+struct Handles(HashMap<UserId, Option<String>>);
+
+impl Handles {
+    async fn get(
+        &mut self,
+        conn: &mut AsyncPgConnection,
+        user_id: UserId,
+    ) -> ApiResult<Option<String>> {
+        if let Some(handle) = self.0.get(&user_id) {
+            return Ok(handle.clone());
+        }
+        let handle = match uchat_query::user::get(conn, user_id).await {
+            Ok(user) => Some(user.handle),
+            Err(QueryError::NotFound) => None,
+            Err(e) => return Err(e.into()),
+        };
+        self.0.insert(user_id, handle.clone());
+        Ok(handle)
+    }
+}
+
+async fn to_log_entry(
+    conn: &mut AsyncPgConnection,
+    handles: &mut Handles,
+    entry: ModerationEntry,
+) -> ApiResult<Option<ModerationLogEntry>> {
+    let action = match entry.action.parse::<ModerationActionKind>() {
+        Ok(action) => action,
+        Err(e) => {
+            tracing::error!(entry_id = ?entry.id, "Invalid moderation log entry: {}", e);
+            return Ok(None);
+        }
+    };
+    let moderator = handles
+        .get(conn, entry.moderator_id)
+        .await?
+        .unwrap_or_else(|| "deleted user".to_string());
+    let handle = match entry.user_id {
+        Some(user_id) => handles.get(conn, user_id).await?,
+        None => None,
+    };
+
+    Ok(Some(ModerationLogEntry {
+        id: entry.id,
+        moderator,
+        action,
+        user_id: entry.user_id,
+        handle,
+        post_id: entry.post_id,
+        report_id: entry.report_id,
+        note: entry.note,
+        expires_at: entry.expires_at,
+        created_at: entry.created_at,
+        deleted_post_ids: entry.deleted_post_ids,
+    }))
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for ReportPost {
+    type Response = (StatusCode, Json<ReportPostOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Report post",
+        skip_all,
+        fields(post_id = ?self.post_id, reason = ?self.reason)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let post = match uchat_query::post::get(&mut conn, self.post_id).await {
+            Ok(post) => post,
+            Err(DieselError::NotFound) => {
+                return Err(request_failed(StatusCode::NOT_FOUND, "Post not found"))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if post.user_id == session.user_id {
+            return Err(request_failed(
+                StatusCode::BAD_REQUEST,
+                "You can not report your own post",
+            ));
+        }
+        let details = optional_text(self.details)?;
+
+        let report = uchat_query::moderation::new_report(
+            &mut conn,
+            session.user_id,
+            post.user_id,
+            Some(post.id),
+            self.reason,
+            details,
+        )
+        .await?;
+
+        tracing::info!(report_id = ?report.id, "Post reported");
+        Ok((
+            StatusCode::OK,
+            Json(ReportPostOk {
+                report_id: report.id,
+            }),
+        ))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for ReportUser {
+    type Response = (StatusCode, Json<ReportUserOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(
+        name = "Report user",
+        skip_all,
+        fields(user_id = ?self.user_id, reason = ?self.reason)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        if self.user_id == session.user_id {
+            return Err(request_failed(
+                StatusCode::BAD_REQUEST,
+                "You can not report yourself",
+            ));
+        }
+        match uchat_query::user::standing(&mut conn, self.user_id).await {
+            Ok(_) => {}
+            Err(DieselError::NotFound) => {
+                return Err(request_failed(StatusCode::NOT_FOUND, "User not found"))
+            }
+            Err(e) => return Err(e.into()),
+        }
+        let details = optional_text(self.details)?;
+
+        let report = uchat_query::moderation::new_report(
+            &mut conn,
+            session.user_id,
+            self.user_id,
+            None,
+            self.reason,
+            details,
+        )
+        .await?;
+
+        tracing::info!(report_id = ?report.id, "User reported");
+        Ok((
+            StatusCode::OK,
+            Json(ReportUserOk {
+                report_id: report.id,
+            }),
+        ))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for GetModerationQueue {
+    type Response = (StatusCode, Json<GetModerationQueueOk>);
+
+    #[tracing::instrument(
+        name = "Get moderation queue",
+        skip_all,
+        fields(user_id = ?session.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        require_moderator(&session)?;
+
+        let mut handles = Handles::default();
+        let mut reports = vec![];
+        for report in uchat_query::moderation::open_reports(&mut conn, QUEUE_SIZE).await? {
+            let reason = match report.reason.parse::<ReportReason>() {
+                Ok(reason) => reason,
+                Err(e) => {
+                    tracing::error!(report_id = ?report.id, "Invalid report: {}", e);
+                    continue;
+                }
+            };
+            // Reports are removed along with the reporter or the reported
+            // user, so both still exist
+            let reporter = handles
+                .get(&mut conn, report.reporter_id)
+                .await?
+                .unwrap_or_default();
+            let handle = handles
+                .get(&mut conn, report.user_id)
+                .await?
+                .unwrap_or_default();
+            let post = match report.post_id {
+                Some(post_id) => match uchat_query::post::get(&mut conn, post_id).await {
+                    Ok(post) => Some(
+                        super::post::to_public(&mut conn, &state.media, post, Some(&session))
+                            .await?,
+                    ),
+                    Err(DieselError::NotFound) => None,
+                    Err(e) => return Err(e.into()),
+                },
+                None => None,
+            };
+
+            reports.push(QueuedReport {
+                id: report.id,
+                reporter,
+                user_id: report.user_id,
+                handle,
+                post,
+                reason,
+                details: report.details,
+                created_at: report.created_at,
+            });
+        }
+
+        Ok((StatusCode::OK, Json(GetModerationQueueOk { reports })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for TakeModerationAction {
+    type Response = (StatusCode, Json<TakeModerationActionOk>);
+
+    #[tracing::instrument(
+        name = "Take moderation action",
+        skip_all,
+        fields(user_id = ?session.user_id, action = ?self.action, report_id = ?self.report_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        require_moderator(&session)?;
+        let note = optional_text(self.note)?;
+
+        let report = match self.report_id {
+            Some(report_id) => {
+                let report = uchat_query::moderation::get_report(&mut conn, report_id)
+                    .await?
+                    .ok_or_else(|| request_failed(StatusCode::NOT_FOUND, "Report not found"))?;
+                if report.status != ReportStatus::Open.as_str() {
+                    return Err(request_failed(
+                        StatusCode::CONFLICT,
+                        "The report was already handled",
+                    ));
+                }
+                Some(report)
+            }
+            None => None,
+        };
+
+        let target = match self.action {
+            ModerationAction::HidePost(post_id) | ModerationAction::DeletePost(post_id) => {
+                match uchat_query::post::get(&mut conn, post_id).await {
+                    Ok(post) => Some(post.user_id),
+                    Err(DieselError::NotFound) => {
+                        return Err(request_failed(StatusCode::NOT_FOUND, "Post not found"))
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            ModerationAction::SuspendUser { user_id, days } => {
+                if !(1..=MAX_SUSPENSION_DAYS).contains(&days) {
+                    return Err(request_failed(
+                        StatusCode::BAD_REQUEST,
+                        format!("Suspensions last 1 to {MAX_SUSPENSION_DAYS} days"),
+                    ));
+                }
+                Some(user_id)
+            }
+            ModerationAction::BanUser(user_id) => Some(user_id),
+            ModerationAction::DismissReport => match &report {
+                Some(report) => Some(report.user_id),
+                None => {
+                    return Err(request_failed(
+                        StatusCode::BAD_REQUEST,
+                        "Select the report to dismiss",
+                    ))
+                }
+            },
+        };
+
+        // The report is settled by this action, so it has to be about its target
+        if let Some(report) = &report {
+            let about_target = match self.action {
+                ModerationAction::HidePost(post_id) | ModerationAction::DeletePost(post_id) => {
+                    report.post_id == Some(post_id)
+                }
+                ModerationAction::SuspendUser { user_id, .. }
+                | ModerationAction::BanUser(user_id) => report.user_id == user_id,
+                ModerationAction::DismissReport => true,
+            };
+            if !about_target {
+                return Err(request_failed(
+                    StatusCode::BAD_REQUEST,
+                    "The report is about something else",
+                ));
+            }
+        }
+
+        let sanctions = !matches!(self.action, ModerationAction::DismissReport);
+        if let Some(user_id) = target.filter(|_| sanctions) {
+            if user_id == session.user_id {
+                return Err(request_failed(
+                    StatusCode::BAD_REQUEST,
+                    "You can not moderate yourself",
+                ));
+            }
+            let standing = match uchat_query::user::standing(&mut conn, user_id).await {
+                Ok(standing) => standing,
+                Err(DieselError::NotFound) => {
+                    return Err(request_failed(StatusCode::NOT_FOUND, "User not found"))
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let role = standing.role.parse::<Role>().unwrap_or_default();
+            if role >= session.role {
+                return Err(request_failed(
+                    StatusCode::FORBIDDEN,
+                    format!("Only a higher role can act against a {}", role.as_str()),
+                ));
+            }
+        }
+
+        let entry = uchat_query::moderation::take_action(
+            &mut conn,
+            session.user_id,
+            self.action,
+            target,
+            self.report_id,
+            note,
+        )
+        .await?;
+        tracing::info!(entry_id = ?entry.id, "Moderation action taken");
+
+        let entry = to_log_entry(&mut conn, &mut Handles::default(), entry)
+            .await?
+            .ok_or_else(|| anyhow!("moderation action was logged with an unknown kind"))?;
+        Ok((StatusCode::OK, Json(TakeModerationActionOk { entry })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for GetModerationLog {
+    type Response = (StatusCode, Json<GetModerationLogOk>);
+
+    #[tracing::instrument(
+        name = "Get moderation log",
+        skip_all,
+        fields(user_id = ?session.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        require_moderator(&session)?;
+
+        let mut handles = Handles::default();
+        let mut entries = vec![];
+        for entry in
+            uchat_query::moderation::log(&mut conn, self.older_than, LOG_ENTRIES_PER_PAGE).await?
+        {
+            if let Some(entry) = to_log_entry(&mut conn, &mut handles, entry).await? {
+                entries.push(entry);
+            }
+        }
+
+        Ok((StatusCode::OK, Json(GetModerationLogOk { entries })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn bans_and_running_suspensions_restrict_the_account() {
+        let now = Utc::now();
+        assert_eq!(restriction(None, None, now), None);
+        assert_eq!(restriction(Some(now - Duration::hours(1)), None, now), None);
+        assert!(restriction(Some(now + Duration::hours(1)), None, now)
+            .unwrap()
+            .starts_with("This account is suspended until"));
+        assert_eq!(
+            restriction(None, Some(now - Duration::days(30)), now).as_deref(),
+            Some("This account has been banned")
+        );
+    }
+
+    #[test]
+    fn blank_text_is_dropped() {
+        assert_eq!(optional_text(Some("  ".to_string())).ok(), Some(None));
+        assert_eq!(
+            optional_text(Some(" spam ".to_string())).ok(),
+            Some(Some("spam".to_string()))
+        );
+        assert!(optional_text(Some("x".repeat(MAX_DETAILS_CHARS + 1))).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::anyhow;
+use axum::{async_trait, http::StatusCode, Json};
+use chrono::{Duration, Utc};
+use uchat_endpoint::{
+    user::{
+        endpoint::{
+            CreateMuteFilter, CreateMuteFilterOk, DeleteMuteFilter, DeleteMuteFilterOk,
+            ListMuteFilters, ListMuteFiltersOk,
+        },
+        types::{MuteAction, MuteFilter, MuteScope},
+    },
+    RequestFailed,
+};
+
+use crate::{
+    error::{ApiError, ApiResult},
+    extractor::{ClientInfo, DbConnection, UserSession},
+    AppState,
+};
+
+use super::AuthorizedApiRequest;
+
+const MAX_PHRASE_CHARS: usize = 100;
+const MAX_EXPIRY_DAYS: i64 = 366;
+const MAX_FILTERS: i64 = 200;
+
+fn to_public(filter: uchat_query::mute::MuteFilter) -> Option<MuteFilter> {
+    Some(MuteFilter {
+        id: filter.id,
+        phrase: filter.phrase,
+        whole_word: filter.whole_word,
+        scopes: filter
+            .scopes
+            .iter()
+            .filter_map(|scope| scope.parse::<MuteScope>().ok())
+            .collect(),
+        action: filter.action.parse::<MuteAction>().ok()?,
+        expires_at: filter.expires_at,
+        created_at: filter.created_at,
+    })
+}
+
+fn bad_request<T: Into<String>>(msg: T) -> ApiError {
+    ApiError {
+        code: Some(StatusCode::BAD_REQUEST),
+        error: anyhow!(RequestFailed { msg: msg.into() }),
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for CreateMuteFilter {
+    type Response = (StatusCode, Json<CreateMuteFilterOk>);
+    #[tracing::instrument(
+        name = "Create mute filter",
+        skip_all,
+        fields(user_id = ?session.user_id, scopes = ?self.scopes, action = ?self.action)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let phrase = self.phrase.trim();
+        if phrase.is_empty() || phrase.chars().count() > MAX_PHRASE_CHARS {
+            return Err(bad_request(format!(
+                "Muted phrases must have 1 to {MAX_PHRASE_CHARS} characters"
+            )));
+        }
+        if self.scopes.is_empty() {
+            return Err(bad_request("Select at least one place to mute the phrase"));
+        }
+        let expires_at = match self.expires_in_days {
+            Some(days) if !(1..=MAX_EXPIRY_DAYS).contains(&days) => {
+                return Err(bad_request(format!(
+                    "Mutes expire after 1 to {MAX_EXPIRY_DAYS} days"
+                )));
+            }
+            Some(days) => Some(Utc::now() + Duration::days(days)),
+            None => None,
+        };
+        if uchat_query::mute::count(&mut conn, session.user_id).await? >= MAX_FILTERS {
+            return Err(bad_request(format!(
+                "You can mute at most {MAX_FILTERS} phrases"
+            )));
+        }
+
+        let mut scopes = self
+            .scopes
+            .iter()
+            .map(|scope| scope.as_str().to_string())
+            .collect::<Vec<_>>();
+        scopes.sort();
+        scopes.dedup();
+
+        let filter = uchat_query::mute::new(
+            &mut conn,
+            session.user_id,
+            phrase,
+            self.whole_word,
+            scopes,
+            self.action.as_str(),
+            expires_at,
+        )
+        .await?;
+
+        tracing::info!(filter_id = ?filter.id, "Mute filter created");
+        let filter = to_public(filter).expect("stored the action just parsed");
+        Ok((StatusCode::CREATED, Json(CreateMuteFilterOk { filter })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for ListMuteFilters {
+    type Response = (StatusCode, Json<ListMuteFiltersOk>);
+    #[tracing::instrument(
+        name = "List mute filters",
+        skip_all,
+        fields(user_id = ?session.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let filters = uchat_query::mute::list(&mut conn, session.user_id)
+            .await?
+            .into_iter()
+            .filter_map(to_public)
+            .collect();
+
+        Ok((StatusCode::OK, Json(ListMuteFiltersOk { filters })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for DeleteMuteFilter {
+    type Response = (StatusCode, Json<DeleteMuteFilterOk>);
+    #[tracing::instrument(
+        name = "Delete mute filter",
+        skip_all,
+        fields(user_id = ?session.user_id, filter_id = ?self.filter_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        if !uchat_query::mute::delete(&mut conn, session.user_id, self.filter_id).await? {
+            return Err(ApiError {
+                code: Some(StatusCode::NOT_FOUND),
+                error: anyhow!(RequestFailed {
+                    msg: "Mute filter not found".to_string()
+                }),
+            });
+        }
+
+        tracing::info!("Mute filter deleted");
+        Ok((StatusCode::OK, Json(DeleteMuteFilterOk)))
+    }
+}
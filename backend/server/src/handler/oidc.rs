@@ -0,0 +1,510 @@
+use anyhow::anyhow;
+use axum::{
+    async_trait,
+    extract::State,
+    http::{
+        header::{COOKIE, SET_COOKIE},
+        HeaderMap, HeaderName, StatusCode,
+    },
+    Json,
+};
+use chrono::Utc;
+use diesel_async::AsyncPgConnection;
+use tracing::info;
+use uchat_crypto::{
+    oidc::{random_token, IdTokenClaims, Pkce},
+    token::hash_token,
+};
+use uchat_endpoint::{
+    user::{
+        endpoint::{
+            CompleteOidcSignup, FinishOidcLogin, ListIdentities, ListIdentitiesOk,
+            ListOidcProviders, ListOidcProvidersOk, LoginOk, OidcOutcome, StartOidcLink,
+            StartOidcLogin, StartOidcLoginOk, TwoFactorChallenge, UnlinkIdentity, UnlinkIdentityOk,
+        },
+        types::{LinkedIdentity, OidcProvider, SecurityEventKind},
+    },
+    RequestFailed,
+};
+use uchat_query::{
+    oidc::{OidcLogin, OidcSignup, UserIdentity},
+    OidcSignupId, UserId,
+};
+
+use crate::{
+    error::{ApiError, ApiResult, ServerError},
+    extractor::{ClientInfo, DbConnection, UserSession},
+    oidc::{suggested_handle, Provider},
+    AppState,
+};
+
+use super::{
+    security::{login_succeeded, record_event},
+    user::logged_in,
+    AuthorizedApiRequest, PublicApiRequest,
+};
+
+/// Stored instead of a password hash for accounts created through a
+/// provider. It never parses as a hash, so password logins always fail until
+/// the user sets a password.
+const NO_PASSWORD: &str = "!";
+
+fn request_failed<T: Into<String>>(code: StatusCode, msg: T) -> ApiError {
+    ApiError {
+        code: Some(code),
+        error: anyhow!(RequestFailed { msg: msg.into() }),
+    }
+}
+
+fn unknown_provider() -> ApiError {
+    request_failed(StatusCode::NOT_FOUND, "Unknown sign in provider")
+}
+
+fn to_public_provider(state: &AppState, id: &str) -> OidcProvider {
+    OidcProvider {
+        id: id.to_string(),
+        name: state
+            .oidc
+            .get(id)
+            .map(|provider| provider.settings.name.clone())
+            .unwrap_or_else(|| id.to_string()),
+    }
+}
+
+fn to_public(state: &AppState, identity: UserIdentity) -> LinkedIdentity {
+    LinkedIdentity {
+        provider: to_public_provider(state, &identity.provider),
+        email: identity.email,
+        last_login_at: identity.last_login_at,
+        created_at: identity.created_at,
+    }
+}
+
+/// Response setting the browser key cookie.
+type WithCookie<T> = (StatusCode, [(HeaderName, String); 1], Json<T>);
+
+/// Cookie holding the browser key of a pending login. It is only ever read by
+/// the server. `Lax`, since the browser comes back from the provider through
+/// a cross-site redirect.
+fn browser_key_cookie(key: &str, max_age_secs: i64) -> String {
+    format!(
+        "{}={key}; Max-Age={max_age_secs}; HttpOnly; SameSite=Lax; Path=/",
+        uchat_cookie::OIDC_BROWSER_KEY
+    )
+}
+
+/// Browser key sent back by the browser which started the login.
+fn browser_key(headers: &HeaderMap) -> ApiResult<&str> {
+    headers
+        .get(COOKIE)
+        .and_then(|cookies| cookies.to_str().ok())
+        .and_then(|cookies| uchat_cookie::get_from_str(cookies, uchat_cookie::OIDC_BROWSER_KEY))
+        .ok_or_else(|| ServerError::challenge_expired().into())
+}
+
+/// Stores the state, nonce and PKCE verifier of a new authorization request
+/// and returns the URL to send the browser to, along with the cookie binding
+/// the login to this browser.
+async fn start(
+    conn: &mut AsyncPgConnection,
+    state: &AppState,
+    provider: &Provider,
+    link_user_id: Option<UserId>,
+    remember_me: bool,
+) -> ApiResult<(String, StartOidcLoginOk)> {
+    let mut rng = uchat_crypto::new_rng();
+    let pkce = Pkce::generate(&mut rng);
+    let browser_key = random_token(&mut rng);
+    let now = Utc::now();
+    let timeout = state.config.oidc.login_timeout();
+    let login = OidcLogin {
+        state: random_token(&mut rng),
+        provider: provider.settings.id.clone(),
+        nonce: random_token(&mut rng),
+        pkce_verifier: pkce.verifier,
+        link_user_id,
+        remember_me,
+        expires_at: now + timeout,
+        created_at: now,
+        browser_key_hash: hash_token(&browser_key),
+    };
+    uchat_query::oidc::new_login(conn, &login).await?;
+
+    let cookie = browser_key_cookie(&browser_key, timeout.num_seconds());
+    let started = StartOidcLoginOk {
+        authorization_url: provider.authorization_url(
+            &state.config.oidc.oidc_redirect_url,
+            &login.state,
+            &login.nonce,
+            &pkce.challenge,
+        ),
+    };
+    Ok((cookie, started))
+}
+
+#[async_trait]
+impl PublicApiRequest for ListOidcProviders {
+    type Response = (StatusCode, Json<ListOidcProvidersOk>);
+
+    #[tracing::instrument(name = "Listing OIDC providers", skip_all)]
+    async fn process_request(
+        self,
+        _conn: DbConnection,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let providers = state
+            .oidc
+            .iter()
+            .map(|provider| OidcProvider {
+                id: provider.settings.id.clone(),
+                name: provider.settings.name.clone(),
+            })
+            .collect();
+        Ok((StatusCode::OK, Json(ListOidcProvidersOk { providers })))
+    }
+}
+
+#[async_trait]
+impl PublicApiRequest for StartOidcLogin {
+    type Response = WithCookie<StartOidcLoginOk>;
+
+    #[tracing::instrument(
+        name = "Starting OIDC login",
+        skip_all,
+        fields(provider = %self.provider)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let provider = state
+            .oidc
+            .get(&self.provider)
+            .ok_or_else(unknown_provider)?;
+        let (cookie, started) = start(&mut conn, &state, provider, None, self.remember_me).await?;
+        Ok((StatusCode::OK, [(SET_COOKIE, cookie)], Json(started)))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for StartOidcLink {
+    type Response = WithCookie<StartOidcLoginOk>;
+
+    #[tracing::instrument(
+        name = "Starting OIDC link",
+        skip_all,
+        fields(user_id = ?session.user_id, provider = %self.provider)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let provider = state
+            .oidc
+            .get(&self.provider)
+            .ok_or_else(unknown_provider)?;
+        let (cookie, started) =
+            start(&mut conn, &state, provider, Some(session.user_id), false).await?;
+        Ok((StatusCode::OK, [(SET_COOKIE, cookie)], Json(started)))
+    }
+}
+
+/// Finishes a login or link started by [`StartOidcLogin`] or [`StartOidcLink`].
+///
+/// Not a [`PublicApiRequest`], as it needs the browser key cookie and, for
+/// links, the session of the user who started the link.
+#[tracing::instrument(name = "Finishing OIDC login", skip_all)]
+pub async fn finish_login(
+    DbConnection(mut conn): DbConnection,
+    session: Option<UserSession>,
+    client: ClientInfo,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<FinishOidcLogin>,
+) -> ApiResult<WithCookie<OidcOutcome>> {
+    let browser_key = browser_key(&headers)?;
+    let login = uchat_query::oidc::take_login(&mut conn, &payload.state, &hash_token(browser_key))
+        .await?
+        .filter(|login| login.expires_at > Utc::now())
+        .ok_or_else(ServerError::challenge_expired)?;
+
+    // A link must be finished by the same user, from a session and not with
+    // a token, or a stolen callback URL would attach an identity to them
+    if let Some(user_id) = login.link_user_id {
+        match session {
+            Some(session) if session.user_id == user_id && session.allows(None) => (),
+            _ => {
+                return Err(request_failed(
+                    StatusCode::FORBIDDEN,
+                    "Sign in as the user who started linking",
+                ))
+            }
+        }
+    }
+
+    let provider = state
+        .oidc
+        .get(&login.provider)
+        .ok_or_else(unknown_provider)?;
+
+    let claims = state
+        .oidc
+        .exchange_code(
+            provider,
+            &state.config.oidc.oidc_redirect_url,
+            &payload.code,
+            &login.pkce_verifier,
+            &login.nonce,
+        )
+        .await
+        .map_err(|e| {
+            tracing::warn!(provider = %login.provider, "OIDC code exchange failed: {}", e);
+            request_failed(
+                StatusCode::UNAUTHORIZED,
+                format!("Sign in with {} failed", provider.settings.name),
+            )
+        })?;
+
+    let outcome = match login.link_user_id {
+        Some(user_id) => {
+            link(
+                &mut conn,
+                &state,
+                &client,
+                user_id,
+                &login.provider,
+                &claims,
+            )
+            .await?
+        }
+        None => sign_in(&mut conn, &state, &client, &login, &claims).await?,
+    };
+    // The key is needed once more to complete a signup
+    let cookie = match outcome {
+        OidcOutcome::ChooseHandle { .. } => {
+            let timeout = state.config.oidc.login_timeout();
+            browser_key_cookie(browser_key, timeout.num_seconds())
+        }
+        _ => browser_key_cookie("", 0),
+    };
+    Ok((StatusCode::OK, [(SET_COOKIE, cookie)], Json(outcome)))
+}
+
+async fn link(
+    conn: &mut AsyncPgConnection,
+    state: &AppState,
+    client: &ClientInfo,
+    user_id: UserId,
+    provider: &str,
+    claims: &IdTokenClaims,
+) -> ApiResult<OidcOutcome> {
+    let identity = match uchat_query::oidc::find_identity(conn, provider, &claims.sub).await? {
+        Some(identity) if identity.user_id == user_id => identity,
+        Some(_) => {
+            return Err(request_failed(
+                StatusCode::CONFLICT,
+                "This identity is already linked to another account",
+            ))
+        }
+        None => {
+            let identity = uchat_query::oidc::link_identity(
+                conn,
+                user_id,
+                provider,
+                &claims.sub,
+                claims.verified_email(),
+            )
+            .await?;
+            info!(user_id = ?user_id, provider = %provider, "Identity linked.");
+            record_event(conn, client, user_id, SecurityEventKind::IdentityLinked).await;
+            identity
+        }
+    };
+    Ok(OidcOutcome::Linked(to_public(state, identity)))
+}
+
+async fn sign_in(
+    conn: &mut AsyncPgConnection,
+    state: &AppState,
+    client: &ClientInfo,
+    login: &OidcLogin,
+    claims: &IdTokenClaims,
+) -> ApiResult<OidcOutcome> {
+    let identity = uchat_query::oidc::find_identity(conn, &login.provider, &claims.sub).await?;
+
+    // Identities are never matched to existing accounts by email, since that
+    // would let anyone who controls the address at a provider take them over.
+    let Some(identity) = identity else {
+        let suggested_handle = match suggested_handle(claims) {
+            Some(handle) if !uchat_query::user::handle_exists(conn, &handle).await? => Some(handle),
+            _ => None,
+        };
+        let signup = OidcSignup {
+            id: OidcSignupId::new(),
+            provider: login.provider.clone(),
+            subject: claims.sub.clone(),
+            email: claims.verified_email().map(str::to_string),
+            suggested_handle: suggested_handle.clone(),
+            expires_at: Utc::now() + state.config.oidc.login_timeout(),
+            created_at: Utc::now(),
+            browser_key_hash: login.browser_key_hash.clone(),
+            remember_me: login.remember_me,
+        };
+        uchat_query::oidc::new_signup(conn, &signup).await?;
+        return Ok(OidcOutcome::ChooseHandle {
+            signup_id: signup.id,
+            suggested_handle,
+        });
+    };
+
+    uchat_query::oidc::touch_identity(conn, &login.provider, &claims.sub, claims.verified_email())
+        .await?;
+
+    if uchat_query::totp::is_enabled(conn, identity.user_id).await? {
+        let expires_at = Utc::now() + state.config.totp.challenge_timeout();
+        let challenge =
+            uchat_query::totp::new_challenge(conn, identity.user_id, login.remember_me, expires_at)
+                .await?;
+        info!(user_id = ?identity.user_id, "Provider accepted, waiting for second factor.");
+        return Ok(OidcOutcome::TwoFactorRequired(TwoFactorChallenge {
+            challenge_id: challenge.id,
+            expires_at: challenge.expires_at,
+        }));
+    }
+
+    let user = uchat_query::user::get(conn, identity.user_id).await?;
+    info!(user_id = ?user.id, provider = %login.provider, "Login successfully.");
+    login_succeeded(conn, client, user.id).await?;
+
+    let login = logged_in(conn, state, user, login.remember_me).await?;
+    Ok(OidcOutcome::LoggedIn(login))
+}
+
+/// Creates the account for an identity which [`finish_login`] didn't know.
+///
+/// Only the browser which started the login can complete it, so nobody can
+/// be signed into an account prepared with someone else's identity.
+#[tracing::instrument(
+    name = "Completing OIDC signup",
+    skip_all,
+    fields(username = %payload.username)
+)]
+pub async fn complete_signup(
+    DbConnection(mut conn): DbConnection,
+    client: ClientInfo,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CompleteOidcSignup>,
+) -> ApiResult<WithCookie<LoginOk>> {
+    let browser_key = browser_key(&headers)?;
+    let signup =
+        uchat_query::oidc::get_signup(&mut conn, payload.signup_id, &hash_token(browser_key))
+            .await?
+            .filter(|signup| signup.expires_at > Utc::now())
+            .ok_or_else(ServerError::challenge_expired)?;
+
+    let user_id = uchat_query::oidc::complete_signup(
+        &mut conn,
+        &signup,
+        NO_PASSWORD,
+        payload.username.as_ref(),
+    )
+    .await
+    .map_err(|_| ServerError::account_exists())?;
+    info!(
+        username = %payload.username.as_ref(),
+        provider = %signup.provider,
+        "New user created successfully."
+    );
+    record_event(
+        &mut conn,
+        &client,
+        user_id,
+        SecurityEventKind::IdentityLinked,
+    )
+    .await;
+    login_succeeded(&mut conn, &client, user_id).await?;
+
+    let user = uchat_query::user::get(&mut conn, user_id).await?;
+    let login = logged_in(&mut conn, &state, user, signup.remember_me).await?;
+    let cookie = browser_key_cookie("", 0);
+    Ok((StatusCode::CREATED, [(SET_COOKIE, cookie)], Json(login)))
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for ListIdentities {
+    type Response = (StatusCode, Json<ListIdentitiesOk>);
+
+    #[tracing::instrument(
+        name = "Listing linked identities",
+        skip_all,
+        fields(user_id = ?session.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let identities = uchat_query::oidc::identities(&mut conn, session.user_id)
+            .await?
+            .into_iter()
+            .map(|identity| to_public(&state, identity))
+            .collect();
+        Ok((StatusCode::OK, Json(ListIdentitiesOk { identities })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for UnlinkIdentity {
+    type Response = (StatusCode, Json<UnlinkIdentityOk>);
+
+    #[tracing::instrument(
+        name = "Unlinking identity",
+        skip_all,
+        fields(user_id = ?session.user_id, provider = %self.provider)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let identities = uchat_query::oidc::identities(&mut conn, session.user_id).await?;
+        if !identities
+            .iter()
+            .any(|identity| identity.provider == self.provider)
+        {
+            return Err(request_failed(
+                StatusCode::NOT_FOUND,
+                "No identity from this provider is linked",
+            ));
+        }
+
+        let user = uchat_query::user::get(&mut conn, session.user_id).await?;
+        if identities.len() == 1 && user.password_hash == NO_PASSWORD {
+            return Err(request_failed(
+                StatusCode::CONFLICT,
+                "Set a password before removing your last sign in method",
+            ));
+        }
+
+        uchat_query::oidc::unlink_identity(&mut conn, session.user_id, &self.provider).await?;
+        record_event(
+            &mut conn,
+            &client,
+            session.user_id,
+            SecurityEventKind::IdentityUnlinked,
+        )
+        .await;
+        Ok((StatusCode::OK, Json(UnlinkIdentityOk)))
+    }
+}
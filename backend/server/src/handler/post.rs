@@ -1,33 +1,171 @@
-use crate::handler::save_image;
+use crate::handler::{save_image, save_video};
 use anyhow::anyhow;
 use axum::{async_trait, http::StatusCode, Json};
 use chrono::Utc;
 use diesel_async::AsyncPgConnection;
-use uchat_domain::{ImageId, Username};
+use uchat_domain::{Headline, Message, UserId, Username};
 use uchat_endpoint::{
-    app_url::construct_image_url,
     post::{endpoint::*, types::*},
+    user::types::{MuteScope, TokenScope},
     RequestFailed,
 };
 use uchat_query::post::{did_vote, Post, Reaction};
+use url::Url;
 
 use crate::{
+    analytics,
+    config::PostLimitsConfig,
     error::{ApiError, ApiResult},
-    extractor::{DbConnection, UserSession},
+    extractor::{ClientInfo, DbConnection, UserSession},
+    link_preview,
+    media::Media,
+    mute::MuteRules,
     AppState,
 };
 
-use super::AuthorizedApiRequest;
+use super::{AuthorizedApiRequest, PublicApiRequest};
+
+/// Checks a chat against the configured limits. Messages are limited by
+/// the characters readers see, so markup like link URLs doesn't count.
+fn check_chat(
+    headline: Option<&Headline>,
+    message: &Message,
+    limits: &PostLimitsConfig,
+) -> ApiResult<()> {
+    let visible_chars = uchat_markup::parse(message.as_ref()).visible_chars();
+    if visible_chars > limits.message_max_chars {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            RequestFailed {
+                msg: format!(
+                    "Messages can be at most {} characters long",
+                    limits.message_max_chars
+                ),
+            },
+        ));
+    }
+    if headline
+        .is_some_and(|headline| headline.as_ref().chars().count() > limits.headline_max_chars)
+    {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            RequestFailed {
+                msg: format!(
+                    "Headlines can be at most {} characters long",
+                    limits.headline_max_chars
+                ),
+            },
+        ));
+    }
+    Ok(())
+}
+
+/// Preview of a linked page, once it was fetched.
+async fn load_link_preview(
+    conn: &mut AsyncPgConnection,
+    media: &Media,
+    url: &Url,
+) -> ApiResult<Option<LinkPreview>> {
+    let Some(preview) = uchat_query::link_preview::get(conn, url.as_str()).await? else {
+        return Ok(None);
+    };
+    // Not fetched yet, or nothing worth showing was found
+    if preview.title.is_none() && preview.description.is_none() && preview.image_id.is_none() {
+        return Ok(None);
+    }
+    let image = match preview.image_id {
+        Some(image_id) => Some(media.image_url(&image_id.to_string()).await?),
+        None => None,
+    };
+    Ok(Some(LinkPreview {
+        title: preview.title,
+        description: preview.description,
+        site_name: preview.site_name,
+        image,
+    }))
+}
+
+/// Checks content before it's posted. Chats linking somewhere become links,
+/// whose previews only come from the server.
+pub fn check_content(mut content: Content, limits: &PostLimitsConfig) -> ApiResult<Content> {
+    if let Content::Chat(Chat { headline, message })
+    | Content::Link(Link {
+        headline, message, ..
+    }) = &content
+    {
+        check_chat(headline.as_ref(), message, limits)?;
+    }
+    if let Content::Chat(ref chat) = content {
+        if let Some(link) = Link::from_chat(chat) {
+            content = Content::Link(link);
+        }
+    }
+    if let Content::Link(ref mut link) = content {
+        if !matches!(link.url.scheme(), "http" | "https") {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                RequestFailed {
+                    msg: "Only http and https links can be shared".to_string(),
+                },
+            ));
+        }
+        link.preview = None;
+    }
+    Ok(content)
+}
+
+/// Stores images and videos uploaded as data URLs, leaving their ids in the
+/// content.
+pub async fn save_uploads(
+    conn: &mut AsyncPgConnection,
+    state: &AppState,
+    user_id: UserId,
+    content: &mut Content,
+) -> ApiResult<()> {
+    match content {
+        Content::Image(img) => {
+            if let ImageKind::DataUrl(data) = &img.kind {
+                let id = save_image(conn, state, user_id, data).await?;
+                img.kind = ImageKind::Id(id);
+            }
+        }
+        Content::Gallery(gallery) => {
+            if gallery.images.is_empty() || gallery.images.len() > Gallery::MAX_IMAGES {
+                return Err(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    RequestFailed {
+                        msg: format!("A gallery holds 1 to {} images", Gallery::MAX_IMAGES),
+                    },
+                ));
+            }
+            for img in gallery.images.iter_mut() {
+                if let ImageKind::DataUrl(data) = &img.kind {
+                    let id = save_image(conn, state, user_id, data).await?;
+                    img.kind = ImageKind::Id(id);
+                }
+            }
+        }
+        Content::Video(video) => {
+            if let VideoKind::DataUrl(data) = &video.kind {
+                let id = save_video(conn, state, user_id, data).await?;
+                video.kind = VideoKind::Id(id);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
 
 #[tracing::instrument(
     name = "Make the post public",
-    skip(conn, session),
+    skip(conn, media, session),
     fields(
         user_id = ?post.user_id,
     )
 )]
 pub async fn to_public(
     conn: &mut AsyncPgConnection,
+    media: &Media,
     post: Post,
     session: Option<&UserSession>,
 ) -> ApiResult<PublicPost> {
@@ -35,8 +173,28 @@ pub async fn to_public(
         match content {
             Content::Image(ref mut img) => {
                 if let ImageKind::Id(id) = img.kind {
-                    let url = construct_image_url(&id.to_string()).await.unwrap();
+                    let url = media.image_url(&id.to_string()).await?;
                     img.kind = ImageKind::Url(url);
+                    img.variants = super::image_variants(conn, media, id).await?;
+                }
+            }
+            Content::Gallery(ref mut gallery) => {
+                for img in gallery.images.iter_mut() {
+                    if let ImageKind::Id(id) = img.kind {
+                        let url = media.image_url(&id.to_string()).await?;
+                        img.kind = ImageKind::Url(url);
+                        img.variants = super::image_variants(conn, media, id).await?;
+                    }
+                }
+            }
+            Content::Link(ref mut link) => {
+                link.preview = load_link_preview(conn, media, &link.url).await?;
+            }
+            Content::Video(ref mut video) => {
+                if let VideoKind::Id(id) = video.kind {
+                    let url = media.image_url(&crate::upload::video::key(id)).await?;
+                    video.kind = VideoKind::Url(url);
+                    video.details = super::video_details(conn, media, id).await?;
                 }
             }
             Content::Poll(ref mut poll) => {
@@ -63,7 +221,7 @@ pub async fn to_public(
             id: post.id,
             by_user: {
                 let profile = uchat_query::user::get(conn, post.user_id).await?;
-                super::user::to_public(conn, session, profile).await?
+                super::user::to_public(conn, media, session, profile).await?
             },
             content,
             time_posted: post.time_posted,
@@ -117,6 +275,7 @@ pub async fn to_public(
             likes: aggregate_reactions.likes,
             dislikes: aggregate_reactions.dislikes,
             boosts: aggregate_reactions.boosts,
+            muted_by: None,
         })
     } else {
         Err(ApiError {
@@ -131,33 +290,98 @@ pub async fn to_public(
 #[async_trait]
 impl AuthorizedApiRequest for NewPost {
     type Response = (StatusCode, Json<NewPostOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
 
     #[tracing::instrument(name = "Creating a new post", skip_all)]
     async fn process_request(
         self,
         DbConnection(mut conn): DbConnection,
         session: UserSession,
-        _state: AppState,
+        state: AppState,
+        _client: ClientInfo,
     ) -> ApiResult<Self::Response> {
-        let mut content = self.content;
-        if let Content::Image(ref mut img) = content {
-            if let ImageKind::DataUrl(data) = &img.kind {
-                let id = ImageId::new();
-                save_image(id, data).await?;
-                img.kind = ImageKind::Id(id);
-            }
-        }
+        let mut content = check_content(self.content, &state.config.post_limits)?;
+        save_uploads(&mut conn, &state, session.user_id, &mut content).await?;
+        let linked_url = match &content {
+            Content::Link(link) => Some(link.url.clone()),
+            _ => None,
+        };
         let post = Post::new(session.user_id, content, self.options)?;
         let post_id = uchat_query::post::new(&mut conn, post).await?;
         tracing::info!(post_id = ?post_id, "New post created successfully");
+        if let Some(url) = linked_url {
+            link_preview::refresh(&state, url, session.user_id);
+        }
 
         Ok((StatusCode::OK, Json(NewPostOk { post_id })))
     }
 }
 
+#[async_trait]
+impl AuthorizedApiRequest for NewThread {
+    type Response = (StatusCode, Json<NewThreadOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
+
+    #[tracing::instrument(name = "Creating a new thread", skip_all)]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let limits = &state.config.post_limits;
+        if self.chats.is_empty() || self.chats.len() > limits.thread_max_posts {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                RequestFailed {
+                    msg: format!("A thread holds 1 to {} posts", limits.thread_max_posts),
+                },
+            ));
+        }
+
+        let mut posts = vec![];
+        let mut linked_urls = vec![];
+        for chat in self.chats {
+            check_chat(chat.headline.as_ref(), &chat.message, limits)?;
+            let content = match Link::from_chat(&chat) {
+                Some(link) => {
+                    linked_urls.push(link.url.clone());
+                    Content::Link(link)
+                }
+                None => Content::Chat(chat),
+            };
+            posts.push(Post::new(session.user_id, content, self.options.clone())?);
+        }
+        let post_ids = uchat_query::post::new_thread(&mut conn, posts).await?;
+        tracing::info!(posts = post_ids.len(), "New thread created successfully");
+        for url in linked_urls {
+            link_preview::refresh(&state, url, session.user_id);
+        }
+
+        Ok((StatusCode::OK, Json(NewThreadOk { post_ids })))
+    }
+}
+
+#[async_trait]
+impl PublicApiRequest for ServerLimits {
+    type Response = (StatusCode, Json<ServerLimitsOk>);
+
+    #[tracing::instrument(name = "Getting the server limits", skip_all)]
+    async fn process_request(
+        self,
+        _conn: DbConnection,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        Ok((StatusCode::OK, Json(state.config.post_limits.limits())))
+    }
+}
+
 #[async_trait]
 impl AuthorizedApiRequest for TrendingPost {
     type Response = (StatusCode, Json<TrendingPostOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
 
     #[tracing::instrument(
         name = "Getting trending posts",
@@ -168,18 +392,22 @@ impl AuthorizedApiRequest for TrendingPost {
         self,
         DbConnection(mut conn): DbConnection,
         session: UserSession,
-        _state: AppState,
+        state: AppState,
+        _client: ClientInfo,
     ) -> ApiResult<Self::Response> {
         let mut posts = vec![];
         for post in uchat_query::post::get_trending(&mut conn).await? {
             let post_id = post.id;
-            match to_public(&mut conn, post, Some(&session)).await {
+            match to_public(&mut conn, &state.media, post, Some(&session)).await {
                 Ok(post) => posts.push(post),
                 Err(e) => {
                     tracing::error!(error = %e.error, post_id = ?post_id, "Post contains invalid data");
                 }
             }
         }
+        let mutes = uchat_query::mute::active(&mut conn, session.user_id, Utc::now()).await?;
+        let posts = MuteRules::from_filters(mutes).apply(posts, MuteScope::Trending);
+        analytics::record_impressions(&state, session.user_id, &posts);
         Ok((StatusCode::OK, Json(TrendingPostOk { posts })))
     }
 }
@@ -187,6 +415,7 @@ impl AuthorizedApiRequest for TrendingPost {
 #[async_trait]
 impl AuthorizedApiRequest for Bookmark {
     type Response = (StatusCode, Json<BookmarkOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
 
     #[tracing::instrument(
         name = "Add or remove a bookmark",
@@ -201,6 +430,7 @@ impl AuthorizedApiRequest for Bookmark {
         DbConnection(mut conn): DbConnection,
         session: UserSession,
         _state: AppState,
+        _client: ClientInfo,
     ) -> ApiResult<Self::Response> {
         match self.action {
             BookmarkAction::Add => {
@@ -226,6 +456,7 @@ impl AuthorizedApiRequest for Bookmark {
 #[async_trait]
 impl AuthorizedApiRequest for Boost {
     type Response = (StatusCode, Json<BoostOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
 
     #[tracing::instrument(
         name = "Add or remove a boost",
@@ -240,6 +471,7 @@ impl AuthorizedApiRequest for Boost {
         DbConnection(mut conn): DbConnection,
         session: UserSession,
         _state: AppState,
+        _client: ClientInfo,
     ) -> ApiResult<Self::Response> {
         match self.action {
             BoostAction::Add => {
@@ -265,6 +497,7 @@ impl AuthorizedApiRequest for Boost {
 #[async_trait]
 impl AuthorizedApiRequest for React {
     type Response = (StatusCode, Json<ReactOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
 
     #[tracing::instrument(
         name = "Update Like status",
@@ -279,6 +512,7 @@ impl AuthorizedApiRequest for React {
         DbConnection(mut conn): DbConnection,
         session: UserSession,
         _state: AppState,
+        _client: ClientInfo,
     ) -> ApiResult<Self::Response> {
         let reaction = Reaction {
             post_id: self.post_id,
@@ -313,6 +547,7 @@ impl AuthorizedApiRequest for React {
 #[async_trait]
 impl AuthorizedApiRequest for Vote {
     type Response = (StatusCode, Json<VoteOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
 
     #[tracing::instrument(
         name = "Cast a new vote",
@@ -327,6 +562,7 @@ impl AuthorizedApiRequest for Vote {
         DbConnection(mut conn): DbConnection,
         session: UserSession,
         _state: AppState,
+        _client: ClientInfo,
     ) -> ApiResult<Self::Response> {
         let cast =
             uchat_query::post::vote(&mut conn, session.user_id, self.post_id, self.choice_id)
@@ -340,43 +576,82 @@ impl AuthorizedApiRequest for Vote {
 #[async_trait]
 impl AuthorizedApiRequest for HomePost {
     type Response = (StatusCode, Json<HomePostOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
 
     #[tracing::instrument(name = "Getting home posts", skip_all)]
     async fn process_request(
         self,
         DbConnection(mut conn): DbConnection,
         session: UserSession,
-        _state: AppState,
+        state: AppState,
+        _client: ClientInfo,
     ) -> ApiResult<Self::Response> {
         let mut posts = vec![];
         for post in uchat_query::post::get_home_posts(&mut conn, session.user_id).await? {
             let post_id = post.id;
-            match to_public(&mut conn, post, Some(&session)).await {
+            match to_public(&mut conn, &state.media, post, Some(&session)).await {
                 Ok(post) => posts.push(post),
                 Err(e) => {
                     tracing::error!(error = %e.error, post_id = ?post_id, "Post contains invalid data");
                 }
             }
         }
+        let mutes = uchat_query::mute::active(&mut conn, session.user_id, Utc::now()).await?;
+        let posts = MuteRules::from_filters(mutes).apply(posts, MuteScope::Home);
+        analytics::record_impressions(&state, session.user_id, &posts);
         Ok((StatusCode::OK, Json(HomePostOk { posts })))
     }
 }
 
+#[async_trait]
+impl AuthorizedApiRequest for ListTimeline {
+    type Response = (StatusCode, Json<ListTimelineOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
+
+    #[tracing::instrument(name = "Getting list posts", skip_all, fields(list_id = ?self.list_id))]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let list = super::list::visible_list(&mut conn, session.user_id, self.list_id).await?;
+        let mut posts = vec![];
+        for post in uchat_query::post::get_list_posts(&mut conn, list.id).await? {
+            let post_id = post.id;
+            match to_public(&mut conn, &state.media, post, Some(&session)).await {
+                Ok(post) => posts.push(post),
+                Err(e) => {
+                    tracing::error!(error = %e.error, post_id = ?post_id, "Post contains invalid data");
+                }
+            }
+        }
+        // List timelines are muted like the home timeline
+        let mutes = uchat_query::mute::active(&mut conn, session.user_id, Utc::now()).await?;
+        let posts = MuteRules::from_filters(mutes).apply(posts, MuteScope::Home);
+        analytics::record_impressions(&state, session.user_id, &posts);
+        Ok((StatusCode::OK, Json(ListTimelineOk { posts })))
+    }
+}
+
 #[async_trait]
 impl AuthorizedApiRequest for LikedPost {
     type Response = (StatusCode, Json<LikedPostOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
 
     #[tracing::instrument(name = "Getting liked posts", skip_all)]
     async fn process_request(
         self,
         DbConnection(mut conn): DbConnection,
         session: UserSession,
-        _state: AppState,
+        state: AppState,
+        _client: ClientInfo,
     ) -> ApiResult<Self::Response> {
         let mut posts = vec![];
         for post in uchat_query::post::get_liked_posts(&mut conn, session.user_id).await? {
             let post_id = post.id;
-            match to_public(&mut conn, post, Some(&session)).await {
+            match to_public(&mut conn, &state.media, post, Some(&session)).await {
                 Ok(post) => posts.push(post),
                 Err(e) => {
                     tracing::error!(error = %e.error, post_id = ?post_id, "Post contains invalid data");
@@ -390,24 +665,33 @@ impl AuthorizedApiRequest for LikedPost {
 #[async_trait]
 impl AuthorizedApiRequest for BookmarkedPost {
     type Response = (StatusCode, Json<BookmarkedPostOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
 
     #[tracing::instrument(name = "Getting bookmarked posts", skip_all)]
     async fn process_request(
         self,
         DbConnection(mut conn): DbConnection,
         session: UserSession,
-        _state: AppState,
+        state: AppState,
+        _client: ClientInfo,
     ) -> ApiResult<Self::Response> {
         let mut posts = vec![];
-        for post in uchat_query::post::get_bookmarked_posts(&mut conn, session.user_id).await? {
+        let mut bookmarks = vec![];
+        for (post, bookmark) in
+            uchat_query::post::get_bookmarked_posts(&mut conn, session.user_id, self.folder_id)
+                .await?
+        {
             let post_id = post.id;
-            match to_public(&mut conn, post, Some(&session)).await {
-                Ok(post) => posts.push(post),
+            match to_public(&mut conn, &state.media, post, Some(&session)).await {
+                Ok(post) => {
+                    posts.push(post);
+                    bookmarks.push(super::bookmark::to_details(bookmark));
+                }
                 Err(e) => {
                     tracing::error!(error = %e.error, post_id = ?post_id, "Post contains invalid data");
                 }
             }
         }
-        Ok((StatusCode::OK, Json(BookmarkedPostOk { posts })))
+        Ok((StatusCode::OK, Json(BookmarkedPostOk { posts, bookmarks })))
     }
 }
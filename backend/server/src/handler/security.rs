@@ -0,0 +1,169 @@
+use axum::{async_trait, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use diesel_async::AsyncPgConnection;
+use uchat_endpoint::user::{
+    endpoint::{GetSecurityEvents, GetSecurityEventsOk, Logout, LogoutOk},
+    types::{SecurityEvent, SecurityEventKind},
+};
+use uchat_query::UserId;
+
+use crate::{
+    error::{ApiResult, ServerError},
+    extractor::{ClientInfo, DbConnection, UserSession},
+    AppState,
+};
+
+use super::AuthorizedApiRequest;
+
+const EVENTS_PER_PAGE: i64 = 50;
+
+/// Adds an entry to the security log of a user.
+///
+/// Failing to write the log must not fail the request which triggered it.
+pub async fn record_event(
+    conn: &mut AsyncPgConnection,
+    client: &ClientInfo,
+    user_id: UserId,
+    kind: SecurityEventKind,
+) {
+    let client = client.clone();
+    if let Err(e) =
+        uchat_query::security::new_event(conn, user_id, kind, client.ip_address, client.user_agent)
+            .await
+    {
+        tracing::error!(user_id = ?user_id, kind = ?kind, "Failed to record security event: {:?}", e);
+    }
+}
+
+/// Whether the account is locked or waiting out a delay.
+pub async fn is_login_locked(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    now: DateTime<Utc>,
+) -> ApiResult<bool> {
+    let failures = uchat_query::security::login_failures(conn, user_id).await?;
+    Ok(failures.is_some_and(|failures| failures.is_locked(now)))
+}
+
+/// Rejects the login while the account is locked or waiting out a delay.
+///
+/// Only for callers which already proved the password, since the answer
+/// tells locked accounts apart from unknown ones.
+pub async fn check_login_allowed(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    now: DateTime<Utc>,
+) -> ApiResult<()> {
+    match uchat_query::security::login_failures(conn, user_id).await? {
+        Some(failures) if failures.is_locked(now) => {
+            let locked_until = failures.locked_until.unwrap_or(now);
+            let retry_in = (locked_until - now).num_seconds().max(1);
+            tracing::info!(user_id = ?user_id, "Login attempt while locked");
+            Err(ServerError::login_locked(retry_in).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Counts a failed login, and delays or locks further attempts once the
+/// configured number of free attempts is used up.
+pub async fn login_failed(
+    conn: &mut AsyncPgConnection,
+    state: &AppState,
+    client: &ClientInfo,
+    user_id: UserId,
+) -> ApiResult<()> {
+    let now = Utc::now();
+    let failures = uchat_query::security::add_login_failure(conn, user_id, now).await?;
+    record_event(conn, client, user_id, SecurityEventKind::LoginFailed).await;
+
+    let policy = &state.config.lockout;
+    if let Some(delay) = policy.delay_after(failures.failed_count) {
+        uchat_query::security::lock_login(conn, user_id, now + delay).await?;
+        if policy.is_lockout(failures.failed_count) {
+            tracing::warn!(user_id = ?user_id, failures = failures.failed_count, "Account locked");
+            record_event(conn, client, user_id, SecurityEventKind::AccountLocked).await;
+        }
+    }
+    Ok(())
+}
+
+pub async fn login_succeeded(
+    conn: &mut AsyncPgConnection,
+    client: &ClientInfo,
+    user_id: UserId,
+) -> ApiResult<()> {
+    uchat_query::security::clear_login_failures(conn, user_id).await?;
+    record_event(conn, client, user_id, SecurityEventKind::LoginSucceeded).await;
+    Ok(())
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for Logout {
+    type Response = (StatusCode, Json<LogoutOk>);
+    #[tracing::instrument(
+        name = "Logging out",
+        skip_all,
+        fields(user_id = ?session.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        if let Some(session_id) = session.session_id() {
+            uchat_query::session::delete(&mut conn, session_id).await?;
+        }
+        record_event(
+            &mut conn,
+            &client,
+            session.user_id,
+            SecurityEventKind::SessionRevoked,
+        )
+        .await;
+
+        tracing::info!("Session revoked");
+        Ok((StatusCode::OK, Json(LogoutOk)))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for GetSecurityEvents {
+    type Response = (StatusCode, Json<GetSecurityEventsOk>);
+    #[tracing::instrument(
+        name = "Get security events",
+        skip_all,
+        fields(user_id = ?session.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let mut events = vec![];
+        for event in uchat_query::security::events(
+            &mut conn,
+            session.user_id,
+            self.older_than,
+            EVENTS_PER_PAGE,
+        )
+        .await?
+        {
+            match event.kind.parse::<SecurityEventKind>() {
+                Ok(kind) => events.push(SecurityEvent {
+                    kind,
+                    ip_address: event.ip_address,
+                    user_agent: event.user_agent,
+                    created_at: event.created_at,
+                }),
+                Err(e) => tracing::error!(event_id = ?event.id, "Invalid security event: {e}"),
+            }
+        }
+
+        Ok((StatusCode::OK, Json(GetSecurityEventsOk { events })))
+    }
+}
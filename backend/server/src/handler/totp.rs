@@ -0,0 +1,228 @@
+use anyhow::anyhow;
+use axum::{async_trait, http::StatusCode, Json};
+use chrono::Utc;
+use diesel_async::AsyncPgConnection;
+use uchat_crypto::{
+    password::{deserialize_hash, hash_password_with_params},
+    totp::{generate_recovery_codes, normalize_recovery_code, TotpSecret},
+    verify_password,
+};
+use uchat_endpoint::{
+    user::{endpoint::*, types::SecurityEventKind},
+    RequestFailed,
+};
+use uchat_query::UserId;
+
+use crate::{
+    error::{ApiError, ApiResult, ServerError},
+    extractor::{ClientInfo, DbConnection, UserSession},
+    AppState,
+};
+
+use super::{security::record_event, AuthorizedApiRequest};
+
+/// Checks a TOTP code or an unused recovery code for a user with 2FA enabled.
+///
+/// Accepted codes are consumed, so the same code cannot be used twice.
+pub async fn verify_second_factor(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    code: &str,
+) -> ApiResult<bool> {
+    let totp = match uchat_query::totp::get(conn, user_id).await? {
+        Some(totp) if totp.is_enabled() => totp,
+        _ => return Ok(false),
+    };
+
+    let secret = TotpSecret::from_base32(&totp.secret)?;
+    if let Some(step) = secret.verify(code, Utc::now().timestamp()) {
+        return Ok(uchat_query::totp::use_step(conn, user_id, step).await?);
+    }
+
+    let code = normalize_recovery_code(code);
+    for recovery_code in uchat_query::totp::unused_recovery_codes(conn, user_id).await? {
+        let hash = deserialize_hash(&recovery_code.code_hash)?;
+        if verify_password(&code, &hash).is_ok() {
+            tracing::info!(user_id = ?user_id, "Recovery code used");
+            return Ok(uchat_query::totp::use_recovery_code(conn, recovery_code.id).await?);
+        }
+    }
+
+    Ok(false)
+}
+
+fn already_enabled() -> ApiError {
+    ApiError {
+        code: Some(StatusCode::CONFLICT),
+        error: anyhow!(RequestFailed {
+            msg: "Two-factor authentication is already enabled".to_string()
+        }),
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for GetTwoFactorStatus {
+    type Response = (StatusCode, Json<GetTwoFactorStatusOk>);
+    #[tracing::instrument(
+        name = "Get two-factor status",
+        skip_all,
+        fields(user_id = ?session.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let enabled = uchat_query::totp::is_enabled(&mut conn, session.user_id).await?;
+        let recovery_codes_left = if enabled {
+            uchat_query::totp::unused_recovery_codes(&mut conn, session.user_id)
+                .await?
+                .len()
+        } else {
+            0
+        };
+
+        Ok((
+            StatusCode::OK,
+            Json(GetTwoFactorStatusOk {
+                enabled,
+                recovery_codes_left,
+            }),
+        ))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for EnrollTotp {
+    type Response = (StatusCode, Json<EnrollTotpOk>);
+    #[tracing::instrument(
+        name = "Enroll TOTP",
+        skip_all,
+        fields(user_id = ?session.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        if uchat_query::totp::is_enabled(&mut conn, session.user_id).await? {
+            return Err(already_enabled());
+        }
+
+        let user = uchat_query::user::get(&mut conn, session.user_id).await?;
+        let mut rng = uchat_crypto::new_rng();
+        let secret = TotpSecret::generate(&mut rng);
+        uchat_query::totp::enroll(&mut conn, session.user_id, &secret.to_base32()).await?;
+
+        tracing::info!("TOTP secret generated, waiting for confirmation");
+        Ok((
+            StatusCode::OK,
+            Json(EnrollTotpOk {
+                secret: secret.to_base32(),
+                otpauth_uri: secret.otpauth_uri(&state.config.totp.totp_issuer, &user.handle),
+            }),
+        ))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for ConfirmTotp {
+    type Response = (StatusCode, Json<ConfirmTotpOk>);
+    #[tracing::instrument(
+        name = "Confirm TOTP",
+        skip_all,
+        fields(user_id = ?session.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        state: AppState,
+        client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let totp = uchat_query::totp::get(&mut conn, session.user_id)
+            .await?
+            .ok_or_else(|| ApiError {
+                code: Some(StatusCode::BAD_REQUEST),
+                error: anyhow!(RequestFailed {
+                    msg: "Start the enrolment first".to_string()
+                }),
+            })?;
+        if totp.is_enabled() {
+            return Err(already_enabled());
+        }
+
+        let secret = TotpSecret::from_base32(&totp.secret)?;
+        let step = secret
+            .verify(&self.code, Utc::now().timestamp())
+            .ok_or_else(ServerError::invalid_code)?;
+
+        let mut rng = uchat_crypto::new_rng();
+        let recovery_codes =
+            generate_recovery_codes(&mut rng, state.config.totp.totp_recovery_codes);
+        let params = state.hash_params;
+        let mut hashes = Vec::with_capacity(recovery_codes.len());
+        for code in &recovery_codes {
+            hashes.push(
+                hash_password_with_params(normalize_recovery_code(code), &params)?
+                    .as_str()
+                    .to_string(),
+            );
+        }
+        uchat_query::totp::confirm(&mut conn, session.user_id, step, hashes).await?;
+
+        record_event(
+            &mut conn,
+            &client,
+            session.user_id,
+            SecurityEventKind::TwoFactorEnabled,
+        )
+        .await;
+
+        tracing::info!("Two-factor authentication enabled");
+        Ok((StatusCode::OK, Json(ConfirmTotpOk { recovery_codes })))
+    }
+}
+
+#[async_trait]
+impl AuthorizedApiRequest for DisableTotp {
+    type Response = (StatusCode, Json<DisableTotpOk>);
+    #[tracing::instrument(
+        name = "Disable TOTP",
+        skip_all,
+        fields(user_id = ?session.user_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        session: UserSession,
+        _state: AppState,
+        client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let user = uchat_query::user::get(&mut conn, session.user_id).await?;
+        let hashed_password =
+            deserialize_hash(&user.password_hash).map_err(|_| ServerError::wrong_password())?;
+        verify_password(self.password, &hashed_password)
+            .map_err(|_| ServerError::wrong_password())?;
+
+        if !verify_second_factor(&mut conn, session.user_id, &self.code).await? {
+            return Err(ServerError::invalid_code().into());
+        }
+        uchat_query::totp::disable(&mut conn, session.user_id).await?;
+
+        record_event(
+            &mut conn,
+            &client,
+            session.user_id,
+            SecurityEventKind::TwoFactorDisabled,
+        )
+        .await;
+
+        tracing::info!("Two-factor authentication disabled");
+        Ok((StatusCode::OK, Json(DisableTotpOk)))
+    }
+}
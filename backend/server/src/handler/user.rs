@@ -1,31 +1,54 @@
 use anyhow::anyhow;
 use axum::{async_trait, http::StatusCode, Json};
-use chrono::{Duration, Utc};
+use chrono::Utc;
 use diesel_async::AsyncPgConnection;
+use std::sync::OnceLock;
 use tracing::info;
-use uchat_crypto::{encode_base64, hash_password, password::deserialize_hash, verify_password};
+use uchat_crypto::{
+    password::{deserialize_hash, hash_password_with_params, needs_rehash, HashParams},
+    verify_password,
+};
 use uchat_domain::user::DisplayName;
 use uchat_endpoint::{
-    app_url::construct_image_url,
     user::{
         endpoint::*,
-        types::{FollowAction, PublicUserProfile},
+        types::{FollowAction, PublicUserProfile, SecurityEventKind, TokenScope},
     },
     RequestFailed, Update,
 };
 use uchat_query::{
     session::{self, Session},
-    user::{get_hashed_password, UpdateProfileParams, User},
-    ImageId, UserId,
+    user::{UpdateProfileParams, User},
+    UserId,
 };
+use url::Url;
 
 use crate::{
+    analytics,
     error::{ApiError, ApiResult, ServerError},
-    extractor::{DbConnection, UserSession},
+    extractor::{ClientInfo, DbConnection, UserSession},
+    media::Media,
+    session::{sign_session, SessionSignature},
     AppState,
 };
 
-use super::{save_image, AuthorizedApiRequest, PublicApiRequest};
+use super::{
+    moderation::restriction,
+    save_image,
+    security::{check_login_allowed, is_login_locked, login_failed, login_succeeded, record_event},
+    totp::verify_second_factor,
+    AuthorizedApiRequest, PublicApiRequest,
+};
+
+async fn profile_image_url(media: &Media, image_id: Option<&str>) -> Option<Url> {
+    match media.image_url(image_id?).await {
+        Ok(url) => Some(url),
+        Err(e) => {
+            tracing::error!("Failed to construct profile image URL: {:?}", e);
+            None
+        }
+    }
+}
 
 #[tracing::instrument(
     name = "Make the post public",
@@ -36,21 +59,20 @@ use super::{save_image, AuthorizedApiRequest, PublicApiRequest};
 )]
 pub async fn to_public(
     conn: &mut AsyncPgConnection,
+    media: &Media,
     session: Option<&UserSession>,
     user: User,
 ) -> ApiResult<PublicUserProfile> {
     tracing::info!("Make profile public");
 
-    let profile_image_url = if let Some(id) = &user.profile_image {
-        match construct_image_url(id).await {
-            Ok(url) => Some(url),
-            Err(e) => {
-                tracing::error!("Failed to construct profile image URL: {:?}", e);
-                None
-            }
-        }
-    } else {
-        None
+    let profile_image_url = profile_image_url(media, user.profile_image.as_deref()).await;
+    let profile_image_variants = match user
+        .profile_image
+        .as_deref()
+        .and_then(|image_id| image_id.parse().ok())
+    {
+        Some(image_id) => super::image_variants(conn, media, image_id).await?,
+        None => vec![],
     };
 
     Ok(PublicUserProfile {
@@ -60,6 +82,7 @@ pub async fn to_public(
             .and_then(|name| DisplayName::try_new(name).ok()),
         handle: user.handle,
         profile_image: profile_image_url,
+        profile_image_variants,
         created_at: user.created_at,
         am_following: {
             match session {
@@ -72,24 +95,25 @@ pub async fn to_public(
     })
 }
 
-#[derive(Debug, Clone)]
-pub struct SessionSignature(String);
-
 async fn new_session(
     conn: &mut AsyncPgConnection,
     state: &AppState,
     user_id: UserId,
-) -> ApiResult<(Session, SessionSignature, Duration)> {
+    remember_me: bool,
+) -> ApiResult<(Session, SessionSignature)> {
     // New session
     let fingerprint = serde_json::json!({});
-    let session_duration = Duration::weeks(3);
-    let session = session::new(conn, user_id, session_duration, fingerprint.into()).await?;
-    let mut rng = state.rng.clone();
-    let signature = state
-        .signing_keys
-        .sign(&mut rng, session.id.as_uuid().as_bytes());
-    let signature = encode_base64(signature);
-    Ok((session, SessionSignature(signature), session_duration))
+    let policy = &state.config.session;
+    let session = session::new(
+        conn,
+        user_id,
+        policy.idle_timeout(),
+        policy.absolute_timeout(remember_me),
+        fingerprint.into(),
+    )
+    .await?;
+    let signature = sign_session(state, session.id);
+    Ok((session, signature))
 }
 #[async_trait]
 impl PublicApiRequest for CreateUser {
@@ -104,8 +128,16 @@ impl PublicApiRequest for CreateUser {
         self,
         DbConnection(mut conn): DbConnection,
         state: AppState,
+        _client: ClientInfo,
     ) -> ApiResult<Self::Response> {
-        let hashed_password = hash_password(self.password)?;
+        let problems = state
+            .password_checker
+            .check(self.password.as_ref(), Some(self.username.as_ref()));
+        if !problems.is_empty() {
+            return Err(ServerError::weak_password(&problems).into());
+        }
+
+        let hashed_password = hash_password_with_params(self.password, &state.hash_params)?;
         let user_id = uchat_query::user::new(&mut conn, hashed_password, &self.username)
             .await
             .map_err(|_| ServerError::account_exists())?;
@@ -115,7 +147,7 @@ impl PublicApiRequest for CreateUser {
             "New user created successfully."
         );
 
-        let (session, signature, duration) = new_session(&mut conn, &state, user_id).await?;
+        let (session, signature) = new_session(&mut conn, &state, user_id, true).await?;
         Ok((
             StatusCode::CREATED,
             Json(CreateUserOk {
@@ -123,15 +155,93 @@ impl PublicApiRequest for CreateUser {
                 username: self.username,
                 session_id: session.id,
                 session_signature: signature.0,
-                session_expires: Utc::now() + duration,
+                session_expires: session.expires_at,
             }),
         ))
     }
 }
 
+/// Stores a new hash of a password which was just verified.
+///
+/// Logging in still works with the old hash, so failures are only logged.
+async fn rehash_password(
+    conn: &mut AsyncPgConnection,
+    user_id: UserId,
+    password: &str,
+    params: &HashParams,
+) {
+    let result = match hash_password_with_params(password, params) {
+        Ok(hash) => uchat_query::user::set_password_hash(conn, user_id, &hash)
+            .await
+            .map_err(anyhow::Error::from),
+        Err(e) => Err(e.into()),
+    };
+    match result {
+        Ok(()) => info!(user_id = ?user_id, "Password hash upgraded."),
+        Err(e) => tracing::error!(user_id = ?user_id, "Failed to upgrade password hash: {:?}", e),
+    }
+}
+
+/// Starts a session for a user who passed every authentication step.
+pub(super) async fn logged_in(
+    conn: &mut AsyncPgConnection,
+    state: &AppState,
+    user: User,
+    remember_me: bool,
+) -> ApiResult<LoginOk> {
+    if let Some(msg) = restriction(user.suspended_until, user.banned_at, Utc::now()) {
+        return Err(ServerError::account_restricted(msg).into());
+    }
+    let (session, signature) = new_session(conn, state, user.id, remember_me).await?;
+
+    let profile_image_url = profile_image_url(&state.media, user.profile_image.as_deref()).await;
+
+    Ok(LoginOk {
+        session_signature: signature.0,
+        session_id: session.id,
+        session_expires: session.expires_at,
+        display_name: user.display_name,
+        email: user.email,
+        profile_image: profile_image_url,
+        user_id: user.id,
+    })
+}
+
+#[async_trait]
+impl PublicApiRequest for CheckPassword {
+    type Response = (StatusCode, Json<CheckPasswordOk>);
+
+    #[tracing::instrument(name = "Checking password strength", skip_all)]
+    async fn process_request(
+        self,
+        _conn: DbConnection,
+        state: AppState,
+        _client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let problems = state
+            .password_checker
+            .check(&self.password, self.handle.as_deref());
+        Ok((StatusCode::OK, Json(CheckPasswordOk { problems })))
+    }
+}
+
+/// Checks `password` against a throwaway hash, to spend the same time on
+/// unknown users as on known ones.
+fn verify_dummy_password(password: &str, params: &HashParams) {
+    static DUMMY_HASH: OnceLock<Option<String>> = OnceLock::new();
+    let hash = DUMMY_HASH.get_or_init(|| {
+        hash_password_with_params("not the password", params)
+            .map(|hash| hash.to_string())
+            .ok()
+    });
+    if let Some(hash) = hash.as_deref().and_then(|hash| deserialize_hash(hash).ok()) {
+        let _ = verify_password(password, &hash);
+    }
+}
+
 #[async_trait]
 impl PublicApiRequest for Login {
-    type Response = (StatusCode, Json<LoginOk>);
+    type Response = (StatusCode, Json<LoginOutcome>);
     #[tracing::instrument(
         name = "Logging in",
         skip_all,
@@ -141,52 +251,113 @@ impl PublicApiRequest for Login {
         self,
         DbConnection(mut conn): DbConnection,
         state: AppState,
+        client: ClientInfo,
     ) -> ApiResult<Self::Response> {
-        let hashed_password = get_hashed_password(&mut conn, &self.username)
-            .await
-            .map_err(|_| ServerError::wrong_password())?;
-        let hashed_password =
-            deserialize_hash(&hashed_password).map_err(|_| ServerError::wrong_password())?;
-        verify_password(self.password, &hashed_password)
-            .map_err(|_| ServerError::wrong_password())?;
+        // Unknown users get the same answer as a wrong password, after about
+        // as long as checking one takes
+        let user = match uchat_query::user::find(&mut conn, &self.username).await {
+            Ok(user) => user,
+            Err(_) => {
+                verify_dummy_password(self.password.as_ref(), &state.hash_params);
+                return Err(ServerError::wrong_password().into());
+            }
+        };
+        let locked = is_login_locked(&mut conn, user.id, Utc::now()).await?;
+
+        let verified = match deserialize_hash(&user.password_hash) {
+            Ok(hash) => verify_password(self.password.as_ref(), &hash)
+                .is_ok()
+                .then_some(hash),
+            Err(_) => {
+                verify_dummy_password(self.password.as_ref(), &state.hash_params);
+                None
+            }
+        };
 
-        let user = uchat_query::user::find(&mut conn, &self.username)
-            .await
-            .map_err(|_| ServerError::missing_login())?;
+        // A lock is answered like a wrong password, or it would tell which
+        // handles exist. The per-ip rate limit slows down guessing meanwhile.
+        if locked {
+            info!(username = %self.username.as_ref(), "Login attempt while locked");
+            return Err(ServerError::wrong_password().into());
+        }
+        let Some(hash) = verified else {
+            login_failed(&mut conn, &state, &client, user.id).await?;
+            return Err(ServerError::wrong_password().into());
+        };
+
+        let params = state.hash_params;
+        if needs_rehash(&hash, &params) {
+            rehash_password(&mut conn, user.id, self.password.as_ref(), &params).await;
+        }
+
+        if uchat_query::totp::is_enabled(&mut conn, user.id).await? {
+            let expires_at = Utc::now() + state.config.totp.challenge_timeout();
+            let challenge =
+                uchat_query::totp::new_challenge(&mut conn, user.id, self.remember_me, expires_at)
+                    .await?;
+            info!(username = %self.username.as_ref(), "Password accepted, waiting for second factor.");
+
+            return Ok((
+                StatusCode::OK,
+                Json(LoginOutcome::TwoFactorRequired(TwoFactorChallenge {
+                    challenge_id: challenge.id,
+                    expires_at: challenge.expires_at,
+                })),
+            ));
+        }
         info!(username = %self.username.as_ref(), "Login successfully.");
+        login_succeeded(&mut conn, &client, user.id).await?;
 
-        let (session, signature, duration) = new_session(&mut conn, &state, user.id).await?;
+        let login = logged_in(&mut conn, &state, user, self.remember_me).await?;
+        Ok((StatusCode::OK, Json(LoginOutcome::LoggedIn(login))))
+    }
+}
 
-        let profile_image_url = if let Some(id) = &user.profile_image {
-            match construct_image_url(id).await {
-                Ok(url) => Some(url),
-                Err(e) => {
-                    tracing::error!("Failed to construct profile image URL: {:?}", e);
-                    None
-                }
-            }
-        } else {
-            None
-        };
+#[async_trait]
+impl PublicApiRequest for VerifyTwoFactor {
+    type Response = (StatusCode, Json<LoginOk>);
+    #[tracing::instrument(
+        name = "Verifying second factor",
+        skip_all,
+        fields(challenge_id = ?self.challenge_id)
+    )]
+    async fn process_request(
+        self,
+        DbConnection(mut conn): DbConnection,
+        state: AppState,
+        client: ClientInfo,
+    ) -> ApiResult<Self::Response> {
+        let challenge = uchat_query::totp::attempt_challenge(&mut conn, self.challenge_id)
+            .await?
+            .ok_or_else(ServerError::challenge_expired)?;
+
+        if challenge.expires_at < Utc::now()
+            || challenge.attempts > state.config.totp.totp_max_attempts
+        {
+            uchat_query::totp::delete_challenge(&mut conn, challenge.id).await?;
+            return Err(ServerError::challenge_expired().into());
+        }
 
-        Ok((
-            StatusCode::OK,
-            Json(LoginOk {
-                session_signature: signature.0,
-                session_id: session.id,
-                session_expires: Utc::now() + duration,
-                display_name: user.display_name,
-                email: user.email,
-                profile_image: profile_image_url,
-                user_id: user.id,
-            }),
-        ))
+        check_login_allowed(&mut conn, challenge.user_id, Utc::now()).await?;
+        if !verify_second_factor(&mut conn, challenge.user_id, &self.code).await? {
+            login_failed(&mut conn, &state, &client, challenge.user_id).await?;
+            return Err(ServerError::invalid_code().into());
+        }
+        uchat_query::totp::delete_challenge(&mut conn, challenge.id).await?;
+
+        let user = uchat_query::user::get(&mut conn, challenge.user_id).await?;
+        info!(user_id = ?user.id, "Login successfully.");
+        login_succeeded(&mut conn, &client, user.id).await?;
+
+        let login = logged_in(&mut conn, &state, user, challenge.remember_me).await?;
+        Ok((StatusCode::OK, Json(login)))
     }
 }
 
 #[async_trait]
 impl AuthorizedApiRequest for GetMyProfile {
     type Response = (StatusCode, Json<GetMyProfileOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
     #[tracing::instrument(
         name = "Get my profile",
         skip_all,
@@ -196,28 +367,21 @@ impl AuthorizedApiRequest for GetMyProfile {
         self,
         DbConnection(mut conn): DbConnection,
         session: UserSession,
-        _state: AppState,
+        state: AppState,
+        _client: ClientInfo,
     ) -> ApiResult<Self::Response> {
         let user = uchat_query::user::get(&mut conn, session.user_id).await?;
 
         tracing::info!("Getting profile...");
-        let profile_image_url = if let Some(id) = &user.profile_image {
-            match construct_image_url(id).await {
-                Ok(url) => Some(url),
-                Err(e) => {
-                    tracing::error!("Failed to construct profile image URL: {:?}", e);
-                    None
-                }
-            }
-        } else {
-            None
-        };
+        let profile_image_url =
+            profile_image_url(&state.media, user.profile_image.as_deref()).await;
 
         tracing::info!("Profile got sent.");
         Ok((
             StatusCode::OK,
             Json(GetMyProfileOk {
                 user_id: user.id,
+                role: session.role,
                 display_name: user.display_name,
                 email: user.email,
                 profile_image: profile_image_url,
@@ -238,22 +402,28 @@ impl AuthorizedApiRequest for UpdateProfile {
         self,
         DbConnection(mut conn): DbConnection,
         session: UserSession,
-        _state: AppState,
+        state: AppState,
+        client: ClientInfo,
     ) -> ApiResult<Self::Response> {
         let mut payload = self;
         let user = uchat_query::user::get(&mut conn, session.user_id).await?;
 
         let password = {
             if let Update::Change(ref password) = payload.password {
-                Update::Change(uchat_crypto::hash_password(password)?)
+                let problems = state
+                    .password_checker
+                    .check(password.as_ref(), Some(&user.handle));
+                if !problems.is_empty() {
+                    return Err(ServerError::weak_password(&problems).into());
+                }
+                Update::Change(hash_password_with_params(password, &state.hash_params)?)
             } else {
                 Update::NoChange
             }
         };
 
         if let Update::Change(ref img) = payload.profile_image {
-            let id = ImageId::new();
-            save_image(id, img).await?;
+            let id = save_image(&mut conn, &state, session.user_id, img).await?;
             // This line added to fix performance load image from the server
             // because everytime fetch something from the Frontend
             // The server send entire data which is huge memory
@@ -262,6 +432,13 @@ impl AuthorizedApiRequest for UpdateProfile {
         }
         tracing::info!("Fetching public posts...");
 
+        let password_changed = matches!(password, Update::Change(_));
+        let email_changed = match &payload.email {
+            Update::Change(email) => user.email.as_ref() != Some(email),
+            Update::SetNull => user.email.is_some(),
+            Update::NoChange => false,
+        };
+
         let query_params = UpdateProfileParams {
             id: session.user_id,
             display_name: payload.display_name,
@@ -272,17 +449,27 @@ impl AuthorizedApiRequest for UpdateProfile {
         tracing::info!("Updating my profile...");
         uchat_query::user::update_profile(&mut conn, query_params).await?;
 
-        let profile_image_url = if let Some(id) = &user.profile_image {
-            match construct_image_url(id).await {
-                Ok(url) => Some(url),
-                Err(e) => {
-                    tracing::error!("Failed to construct profile image URL: {:?}", e);
-                    None
-                }
-            }
-        } else {
-            None
-        };
+        if password_changed {
+            record_event(
+                &mut conn,
+                &client,
+                session.user_id,
+                SecurityEventKind::PasswordChanged,
+            )
+            .await;
+        }
+        if email_changed {
+            record_event(
+                &mut conn,
+                &client,
+                session.user_id,
+                SecurityEventKind::EmailChanged,
+            )
+            .await;
+        }
+
+        let profile_image_url =
+            profile_image_url(&state.media, user.profile_image.as_deref()).await;
 
         tracing::info!("Profile updated successfully");
 
@@ -298,6 +485,7 @@ impl AuthorizedApiRequest for UpdateProfile {
 #[async_trait]
 impl AuthorizedApiRequest for ViewProfile {
     type Response = (StatusCode, Json<ViewProfileOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Read);
     #[tracing::instrument(
         name = "View public profile",
         skip_all,
@@ -307,18 +495,19 @@ impl AuthorizedApiRequest for ViewProfile {
         self,
         DbConnection(mut conn): DbConnection,
         session: UserSession,
-        _state: AppState,
+        state: AppState,
+        _client: ClientInfo,
     ) -> ApiResult<Self::Response> {
         tracing::info!("Getting profile from database...");
         let profile = uchat_query::user::get(&mut conn, self.for_user).await?;
 
-        let profile = to_public(&mut conn, Some(&session), profile).await?;
+        let profile = to_public(&mut conn, &state.media, Some(&session), profile).await?;
 
         let mut posts = vec![];
         tracing::info!("Fetching public posts...");
         for post in uchat_query::post::get_public_posts(&mut conn, self.for_user).await? {
             let post_id = post.id;
-            match super::post::to_public(&mut conn, post, Some(&session)).await {
+            match super::post::to_public(&mut conn, &state.media, post, Some(&session)).await {
                 Ok(post) => posts.push(post),
                 Err(e) => {
                     tracing::error!(error = %e.error, post_id = ?post_id, "Post contains invalid data");
@@ -327,6 +516,8 @@ impl AuthorizedApiRequest for ViewProfile {
         }
 
         info!("Fetching public posts successfully");
+        analytics::record_profile_visit(&state, self.for_user, session.user_id);
+        analytics::record_impressions(&state, session.user_id, &posts);
 
         Ok((StatusCode::OK, Json(ViewProfileOk { profile, posts })))
     }
@@ -335,6 +526,7 @@ impl AuthorizedApiRequest for ViewProfile {
 #[async_trait]
 impl AuthorizedApiRequest for FollowUser {
     type Response = (StatusCode, Json<FollowUserOk>);
+    const SCOPE: Option<TokenScope> = Some(TokenScope::Write);
     #[tracing::instrument(
         name = "Follow a user",
         skip_all,
@@ -348,6 +540,7 @@ impl AuthorizedApiRequest for FollowUser {
         DbConnection(mut conn): DbConnection,
         session: UserSession,
         _state: AppState,
+        _client: ClientInfo,
     ) -> ApiResult<Self::Response> {
         // If the user itself: Can not follow self
         if self.user_id == session.user_id {
@@ -3,19 +3,48 @@ use axum::extract::FromRef;
 use diesel_async::pooled_connection::deadpool::Object;
 use diesel_async::AsyncPgConnection;
 use rand::rngs::StdRng;
-use uchat_crypto::sign::Keys;
+use uchat_crypto::{password::HashParams, sign::Keyring};
 use uchat_query::{AsyncConnectionPool, QueryError};
 
+pub mod analytics;
+pub mod config;
 pub mod error;
 pub mod extractor;
 pub mod handler;
+pub mod link_preview;
 pub mod logging;
+pub mod media;
+pub mod mute;
+pub mod oidc;
+pub mod password_policy;
+pub mod rate_limit;
 pub mod router;
+pub mod session;
+pub mod upload;
+
+use config::Config;
+use media::Media;
+use oidc::OidcProviders;
+use password_policy::PasswordChecker;
+use rate_limit::RateLimiter;
+use upload::video::Ffmpeg;
+
 #[derive(FromRef, Clone)]
 pub struct AppState {
     pub db_pool: AsyncConnectionPool,
-    pub signing_keys: Keys,
+    pub signing_keys: Keyring,
+    /// Every request works on a copy of this generator, so copies repeat the
+    /// same output. Secrets must come from `uchat_crypto::new_rng` instead.
     pub rng: StdRng,
+    pub config: Config,
+    pub rate_limiter: RateLimiter,
+    /// Parameters for new password hashes, checked at startup.
+    pub hash_params: HashParams,
+    pub password_checker: PasswordChecker,
+    pub oidc: OidcProviders,
+    pub media: Media,
+    /// Shared so the limit on uploads processed at once holds.
+    pub ffmpeg: Ffmpeg,
 }
 
 impl AppState {
@@ -26,8 +55,12 @@ impl AppState {
 
 pub mod cli {
     use anyhow::Context;
+    use diesel_async::AsyncPgConnection;
     use rand::{CryptoRng, RngCore};
-    use uchat_crypto::sign::{encode_private_key, EncodedPrivateKey, Keys};
+    use uchat_crypto::sign::{encode_private_key, EncodedPrivateKey, KeyId, Keyring, Keys};
+
+    /// Key id given to the key from the `API_PRIVATE_KEY` environment variable.
+    pub const ENV_KEY_ID: &str = "env";
 
     pub fn gen_keys<R>(rng: &mut R) -> anyhow::Result<(EncodedPrivateKey, Keys)>
     where
@@ -38,19 +71,51 @@ pub mod cli {
         Ok((private_key, keys))
     }
 
-    pub fn load_keys() -> anyhow::Result<Keys> {
-        let private_key = std::env::var("API_PRIVATE_KEY")
-            .with_context(|| "set API_PRIVATE_KEY environment variable".to_string())?;
-        Ok(Keys::from_encoded(private_key)?)
+    /// Loads all non-retired keys from the database.
+    ///
+    /// A key set in `API_PRIVATE_KEY` is also accepted, and is used for
+    /// signing when no stored key has been activated yet.
+    pub async fn load_keyring(conn: &mut AsyncPgConnection) -> anyhow::Result<Keyring> {
+        let mut active = None;
+        let mut others = vec![];
+
+        for key in uchat_query::signing_key::usable(conn).await? {
+            let keys = Keys::from_encoded(&key.private_key)
+                .with_context(|| format!("failed to decode signing key {}", key.id))?;
+            if key.active {
+                active = Some((KeyId::new(key.id), keys));
+            } else {
+                others.push((KeyId::new(key.id), keys));
+            }
+        }
+
+        if let Ok(private_key) = std::env::var("API_PRIVATE_KEY") {
+            let keys = Keys::from_encoded(private_key)
+                .with_context(|| "failed to decode API_PRIVATE_KEY")?;
+            let key = (KeyId::new(ENV_KEY_ID), keys);
+            match active {
+                Some(_) => others.push(key),
+                None => active = Some(key),
+            }
+        }
+
+        let (key_id, keys) = active.with_context(|| {
+            "no active signing key: run `api key add --activate` or set API_PRIVATE_KEY"
+        })?;
+        let mut keyring = Keyring::new(key_id, keys);
+        for (key_id, keys) in others {
+            keyring.insert(key_id, keys);
+        }
+        Ok(keyring)
     }
 }
 
 #[cfg(test)]
 pub mod tests {
     use axum::http::StatusCode;
-    use uchat_domain::{Password, Username};
+    use uchat_domain::{OidcSignupId, Password, Username};
     use uchat_endpoint::{
-        user::endpoint::{CreateUser, CreateUserOk},
+        user::endpoint::{CompleteOidcSignup, CreateUser, CreateUserOk, FinishOidcLogin},
         Endpoint,
     };
 
@@ -63,10 +128,17 @@ pub mod tests {
         };
         use serde::Serialize;
         use tower::ServiceExt;
-        use uchat_crypto::sign::Keys;
+        use uchat_crypto::{password::HashParams, sign::Keyring};
         use uchat_query::AsyncConnectionPool;
 
-        use crate::AppState;
+        use crate::{
+            config::{Config, MediaConfig, VideoConfig},
+            media::{LocalStore, Media, MediaLinks},
+            oidc::OidcProviders,
+            password_policy::PasswordChecker,
+            rate_limit::RateLimiter,
+            AppState,
+        };
 
         pub async fn new_state() -> AppState {
             let connection_url = dotenvy::var("TEST_DATABASE_URL")
@@ -76,8 +148,15 @@ pub mod tests {
 
             AppState {
                 db_pool: AsyncConnectionPool::new(connection_url).await.unwrap(),
-                signing_keys: Keys::generate(&mut rng).unwrap().1,
+                signing_keys: Keyring::generate(&mut rng).unwrap(),
                 rng,
+                config: Config::default(),
+                rate_limiter: RateLimiter::in_memory(),
+                hash_params: HashParams::default(),
+                password_checker: PasswordChecker::default(),
+                oidc: OidcProviders::default(),
+                media: Media::new(LocalStore::new(MediaConfig::DIR), MediaLinks::Api),
+                ffmpeg: VideoConfig::default().ffmpeg(),
             }
         }
 
@@ -109,7 +188,6 @@ pub mod tests {
         where
             P: Serialize,
         {
-            let payload = serde_json::to_string(&payload).unwrap();
             let router = new_router().await;
             api_request_with_router(router, uri, payload).await
         }
@@ -130,7 +208,7 @@ pub mod tests {
         {
             let payload = CreateUser {
                 username: Username::try_new(&username).unwrap(),
-                password: Password::try_new("password").unwrap(),
+                password: Password::try_new("correct horse battery").unwrap(),
             };
 
             let response = util::api_request(CreateUser::URL, payload).await;
@@ -148,7 +226,7 @@ pub mod tests {
         {
             let payload = CreateUser {
                 username: Username::try_new(&username).unwrap(),
-                password: Password::try_new("password").unwrap(),
+                password: Password::try_new("correct horse battery").unwrap(),
             };
 
             let response = util::api_request(CreateUser::URL, payload).await;
@@ -157,4 +235,27 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn oidc_login_needs_browser_key() {
+        let payload = FinishOidcLogin {
+            state: "state".to_string(),
+            code: "code".to_string(),
+        };
+        let router = util::new_router().await;
+        let response = util::api_request_with_router(router, FinishOidcLogin::URL, payload).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn oidc_signup_needs_browser_key() {
+        let payload = CompleteOidcSignup {
+            signup_id: OidcSignupId::new(),
+            username: Username::try_new("someone").unwrap(),
+        };
+        let router = util::new_router().await;
+        let response =
+            util::api_request_with_router(router, CompleteOidcSignup::URL, payload).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
 }
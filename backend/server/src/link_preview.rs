@@ -0,0 +1,646 @@
+//! Previews of linked pages, fetched in the background when a link is
+//! posted and kept per URL.
+//!
+//! Pages are fetched from the API host, so links must not reach into the
+//! network it runs in. Hosts are resolved up front, all their addresses
+//! must be public, and connections only go to those addresses, so a second
+//! lookup can't point elsewhere. Redirects are followed by hand and checked
+//! the same way.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use chrono::Utc;
+use reqwest::{header, redirect, StatusCode};
+use uchat_domain::UserId;
+use uchat_query::link_preview::PageDetails;
+use url::{Host, Url};
+
+use crate::{upload, AppState};
+
+const USER_AGENT: &str = concat!("uchat-link-preview/", env!("CARGO_PKG_VERSION"));
+const MAX_REDIRECTS: usize = 3;
+const MAX_TITLE_CHARS: usize = 200;
+const MAX_DESCRIPTION_CHARS: usize = 500;
+const MAX_SITE_NAME_CHARS: usize = 100;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("only http and https URLs are fetched")]
+    Scheme,
+    #[error("host could not be resolved")]
+    Resolve,
+    #[error("{0} is not a public address")]
+    PrivateAddress(IpAddr),
+    #[error("more than {MAX_REDIRECTS} redirects")]
+    Redirects,
+    #[error("unexpected status {0}")]
+    Status(StatusCode),
+    #[error("unexpected content type")]
+    ContentType,
+    #[error("response is larger than {0} bytes")]
+    TooLarge(usize),
+    #[error("timed out")]
+    Timeout,
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+}
+
+/// Whether `ip` may be reached from the internet. Loopback, private,
+/// link-local, shared, documentation and reserved ranges aren't.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_v4(mapped);
+            }
+            let segments = ip.segments();
+            // NAT64 reaches IPv4 hosts through the embedded address
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [a, b, c, d] = ip.octets()[12..] else {
+                    unreachable!()
+                };
+                return is_public_v4(Ipv4Addr::new(a, b, c, d));
+            }
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // Unique local fc00::/7
+                || segments[0] & 0xfe00 == 0xfc00
+                // Link-local fe80::/10
+                || segments[0] & 0xffc0 == 0xfe80
+                // Documentation 2001:db8::/32
+                || segments[..2] == [0x2001, 0xdb8])
+        }
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "This network" 0.0.0.0/8
+        || a == 0
+        // Shared address space 100.64.0.0/10
+        || (a == 100 && b & 0xc0 == 64)
+        // Protocol assignments 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking 198.18.0.0/15
+        || (a == 198 && b & 0xfe == 18)
+        // Reserved 240.0.0.0/4
+        || a >= 240)
+}
+
+/// Details of a page, read from its title and Open Graph tags.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PageMeta {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub site_name: Option<String>,
+    pub image: Option<Url>,
+}
+
+/// Fetches pages and images on behalf of users.
+#[derive(Clone, Debug)]
+pub struct Fetcher {
+    pub timeout: Duration,
+    /// Most of a page which is read, the head is usually well within it.
+    pub max_page_bytes: usize,
+    /// Only for tests against local servers.
+    pub allow_private: bool,
+}
+
+impl Fetcher {
+    /// Fetches an HTML page and reads its details.
+    pub async fn fetch_page(&self, url: Url) -> Result<PageMeta, FetchError> {
+        let (url, body) = self
+            .get(
+                url,
+                "text/html,application/xhtml+xml",
+                self.max_page_bytes,
+                true,
+            )
+            .await?;
+        Ok(parse_page(&String::from_utf8_lossy(&body), &url))
+    }
+
+    /// Fetches an image of at most `max_bytes`.
+    pub async fn fetch_image(&self, url: Url, max_bytes: usize) -> Result<Vec<u8>, FetchError> {
+        let (_, body) = self.get(url, "image/*", max_bytes, false).await?;
+        Ok(body)
+    }
+
+    /// Gets `url`, following redirects. Bodies beyond `max_bytes` are cut
+    /// off if `truncate` is set and refused otherwise. Returns where the
+    /// body was found.
+    async fn get(
+        &self,
+        url: Url,
+        accept: &str,
+        max_bytes: usize,
+        truncate: bool,
+    ) -> Result<(Url, Vec<u8>), FetchError> {
+        tokio::time::timeout(self.timeout, async {
+            let mut url = url;
+            for _ in 0..=MAX_REDIRECTS {
+                let response = self
+                    .client(&url)
+                    .await?
+                    .get(url.clone())
+                    .header(header::ACCEPT, accept)
+                    .send()
+                    .await?;
+                let status = response.status();
+                if status.is_redirection() {
+                    let location = response
+                        .headers()
+                        .get(header::LOCATION)
+                        .and_then(|location| location.to_str().ok())
+                        .ok_or(FetchError::Status(status))?;
+                    url = url.join(location).map_err(|_| FetchError::Status(status))?;
+                    continue;
+                }
+                if !status.is_success() {
+                    return Err(FetchError::Status(status));
+                }
+                let accepted = response
+                    .headers()
+                    .get(header::CONTENT_TYPE)
+                    .and_then(|content_type| content_type.to_str().ok())
+                    .is_some_and(|content_type| {
+                        accept
+                            .split(',')
+                            .any(|mime| content_type.starts_with(mime.trim_end_matches('*')))
+                    });
+                if !accepted {
+                    return Err(FetchError::ContentType);
+                }
+                let body = read_body(response, max_bytes, truncate).await?;
+                return Ok((url, body));
+            }
+            Err(FetchError::Redirects)
+        })
+        .await
+        .map_err(|_| FetchError::Timeout)?
+    }
+
+    /// Client which can only connect to the checked addresses of the host
+    /// of `url`.
+    async fn client(&self, url: &Url) -> Result<reqwest::Client, FetchError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(FetchError::Scheme);
+        }
+        let port = url.port_or_known_default().ok_or(FetchError::Scheme)?;
+        let builder = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .redirect(redirect::Policy::none())
+            // A proxy would connect wherever it likes
+            .no_proxy()
+            .connect_timeout(self.timeout);
+
+        let builder = match url.host().ok_or(FetchError::Resolve)? {
+            Host::Ipv4(ip) => {
+                self.check(IpAddr::V4(ip))?;
+                builder
+            }
+            Host::Ipv6(ip) => {
+                self.check(IpAddr::V6(ip))?;
+                builder
+            }
+            Host::Domain(domain) => {
+                let addrs = tokio::net::lookup_host((domain, port))
+                    .await
+                    .map_err(|_| FetchError::Resolve)?
+                    .collect::<Vec<SocketAddr>>();
+                if addrs.is_empty() {
+                    return Err(FetchError::Resolve);
+                }
+                for addr in &addrs {
+                    self.check(addr.ip())?;
+                }
+                builder.resolve_to_addrs(domain, &addrs)
+            }
+        };
+        Ok(builder.build()?)
+    }
+
+    fn check(&self, ip: IpAddr) -> Result<(), FetchError> {
+        if self.allow_private || is_public(ip) {
+            Ok(())
+        } else {
+            Err(FetchError::PrivateAddress(ip))
+        }
+    }
+}
+
+async fn read_body(
+    mut response: reqwest::Response,
+    max_bytes: usize,
+    truncate: bool,
+) -> Result<Vec<u8>, FetchError> {
+    if !truncate && response.content_length().unwrap_or(0) > max_bytes as u64 {
+        return Err(FetchError::TooLarge(max_bytes));
+    }
+    let mut body = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_bytes {
+            if !truncate {
+                return Err(FetchError::TooLarge(max_bytes));
+            }
+            body.extend_from_slice(&chunk[..max_bytes - body.len()]);
+            break;
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Reads the title and Open Graph tags in the head of a page found at
+/// `url`. Open Graph tags take precedence over Twitter cards and plain
+/// tags.
+pub fn parse_page(html: &str, url: &Url) -> PageMeta {
+    let lower = html.to_ascii_lowercase();
+    let head_end = lower.find("</head").unwrap_or(html.len());
+    let (html, lower) = (&html[..head_end], &lower[..head_end]);
+
+    let mut meta = std::collections::HashMap::new();
+    let mut title = None;
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find('<').map(|start| pos + start) {
+        let Some(end) = lower[start..].find('>').map(|end| start + end) else {
+            break;
+        };
+        pos = end + 1;
+        let tag = &html[start + 1..end];
+        let name = tag
+            .split(|c: char| c.is_ascii_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match name.as_str() {
+            "meta" => {
+                let attributes = parse_attributes(tag);
+                let key = attributes
+                    .iter()
+                    .find(|(name, _)| name == "property" || name == "name")
+                    .map(|(_, value)| value.to_ascii_lowercase());
+                let content = attributes
+                    .into_iter()
+                    .find(|(name, _)| name == "content")
+                    .map(|(_, value)| value);
+                if let (Some(key), Some(content)) = (key, content) {
+                    meta.entry(key).or_insert(content);
+                }
+            }
+            "title" if title.is_none() => {
+                if let Some(close) = lower[pos..].find("</title") {
+                    title = Some(decode_entities(&html[pos..pos + close]));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let first = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| meta.get(*key))
+            .map(|value| value.to_string())
+    };
+    PageMeta {
+        title: clean(
+            first(&["og:title", "twitter:title"]).or(title),
+            MAX_TITLE_CHARS,
+        ),
+        description: clean(
+            first(&["og:description", "twitter:description", "description"]),
+            MAX_DESCRIPTION_CHARS,
+        ),
+        site_name: clean(first(&["og:site_name"]), MAX_SITE_NAME_CHARS),
+        image: first(&["og:image:secure_url", "og:image", "twitter:image"])
+            .and_then(|image| url.join(image.trim()).ok())
+            .filter(|image| matches!(image.scheme(), "http" | "https")),
+    }
+}
+
+/// Attributes of a tag, names lowercased and values decoded.
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = vec![];
+    // Skip the tag name
+    let mut rest = tag
+        .trim_start_matches(|c: char| !c.is_ascii_whitespace())
+        .trim_start();
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_ascii_whitespace() || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+        if let Some(value) = rest.strip_prefix('=') {
+            let value = value.trim_start();
+            let (raw, remaining) = match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let value = &value[1..];
+                    let end = value.find(quote).unwrap_or(value.len());
+                    (&value[..end], value.get(end + 1..).unwrap_or_default())
+                }
+                _ => {
+                    let end = value
+                        .find(|c: char| c.is_ascii_whitespace())
+                        .unwrap_or(value.len());
+                    (&value[..end], &value[end..])
+                }
+            };
+            attributes.push((name, decode_entities(raw)));
+            rest = remaining.trim_start();
+        } else {
+            if !name.is_empty() {
+                attributes.push((name, String::new()));
+            }
+            rest = rest.trim_start_matches('/').trim_start();
+        }
+    }
+    attributes
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#')?.parse().ok(),
+                };
+                code.and_then(char::from_u32)
+            }
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Collapses whitespace and cuts off text longer than `max_chars`.
+fn clean(text: Option<String>, max_chars: usize) -> Option<String> {
+    let text = text?.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return None;
+    }
+    if text.chars().count() <= max_chars {
+        return Some(text);
+    }
+    let mut cut = text.chars().take(max_chars - 1).collect::<String>();
+    cut.push('…');
+    Some(cut)
+}
+
+/// Fetches the preview of `url` in the background, unless it is fresh or
+/// already being fetched. Images are stored as uploads of `owner`.
+pub fn refresh(state: &AppState, url: Url, owner: UserId) {
+    if state.config.link_preview.link_preview_disabled {
+        return;
+    }
+    let state = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = run(&state, &url, owner).await {
+            tracing::error!(url = %url, error = %e, "Failed to update link preview");
+        }
+    });
+}
+
+async fn run(state: &AppState, url: &Url, owner: UserId) -> anyhow::Result<()> {
+    let config = &state.config.link_preview;
+    let mut conn = state.db_pool.get().await?;
+    let stale_before = Utc::now() - config.refresh_interval();
+    if !uchat_query::link_preview::claim(&mut conn, url.as_str(), stale_before).await? {
+        return Ok(());
+    }
+
+    let fetcher = config.fetcher();
+    let page = match fetcher.fetch_page(url.clone()).await {
+        Ok(page) => page,
+        Err(e) => {
+            tracing::info!(url = %url, error = %e, "Link preview is not available");
+            uchat_query::link_preview::save(&mut conn, url.as_str(), None).await?;
+            return Ok(());
+        }
+    };
+    let image_id = match page.image {
+        Some(image) => match store_image(state, &mut conn, &fetcher, image, owner).await {
+            Ok(image_id) => Some(image_id),
+            Err(e) => {
+                tracing::info!(url = %url, error = %e, "Link preview image is not available");
+                None
+            }
+        },
+        None => None,
+    };
+    let details = PageDetails {
+        title: page.title,
+        description: page.description,
+        site_name: page.site_name,
+        image_id,
+    };
+    uchat_query::link_preview::save(&mut conn, url.as_str(), Some(&details)).await?;
+    Ok(())
+}
+
+async fn store_image(
+    state: &AppState,
+    conn: &mut diesel_async::AsyncPgConnection,
+    fetcher: &Fetcher,
+    url: Url,
+    owner: UserId,
+) -> anyhow::Result<uchat_query::ImageId> {
+    let limits = state.config.image.limits();
+    let bytes = fetcher.fetch_image(url, limits.max_bytes).await?;
+    let image = tokio::task::spawn_blocking(move || upload::check(bytes, &limits)).await??;
+    let webp_variants = state.config.image.image_webp_variants;
+    upload::store(conn, &state.media, owner, image, webp_variants).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        http::header::{CONTENT_TYPE, LOCATION},
+        response::IntoResponse,
+        routing::get,
+        Router,
+    };
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html><head>
+  <title>Plain &amp; simple</title>
+  <meta name="description" content="Plain description">
+  <meta property="og:title" content="Open &quot;Graph&quot; title" />
+  <meta content='Open Graph description' property='og:description'>
+  <meta property=og:site_name content=Example>
+  <meta property="og:image" content="/images/cover.png">
+</head><body><meta property="og:title" content="Body tag"></body></html>"#;
+
+    fn fetcher(allow_private: bool) -> Fetcher {
+        Fetcher {
+            timeout: Duration::from_millis(500),
+            max_page_bytes: 1024,
+            allow_private,
+        }
+    }
+
+    /// Stand-in for sites being linked to.
+    async fn serve() -> Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let page_url = base.join("/page").unwrap().to_string();
+        let router = Router::new()
+            .route(
+                "/page",
+                get(|| async { ([(CONTENT_TYPE, "text/html")], PAGE) }),
+            )
+            .route(
+                "/redirect",
+                get(move || async move { ([(LOCATION, page_url)], StatusCode::FOUND) }),
+            )
+            .route(
+                "/loop",
+                get(|| async { ([(LOCATION, "/loop")], StatusCode::FOUND) }),
+            )
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    ([(CONTENT_TYPE, "text/html")], PAGE)
+                }),
+            )
+            .route(
+                "/large.png",
+                get(|| async { ([(CONTENT_TYPE, "image/png")], vec![0u8; 4096]) }),
+            )
+            .route(
+                "/json",
+                get(|| async { ([(CONTENT_TYPE, "application/json")], "{}").into_response() }),
+            );
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        base
+    }
+
+    #[test]
+    fn only_public_addresses_are_allowed() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a00:1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip} is not public");
+        }
+        for ip in ["93.184.215.14", "2606:2800:21f:cb07:6820:80da:af6b:8b2c"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip} is public");
+        }
+    }
+
+    #[test]
+    fn reads_open_graph_tags_from_the_head() {
+        let url = Url::parse("https://example.com/articles/1").unwrap();
+        let meta = parse_page(PAGE, &url);
+        assert_eq!(
+            meta,
+            PageMeta {
+                title: Some("Open \"Graph\" title".to_string()),
+                description: Some("Open Graph description".to_string()),
+                site_name: Some("Example".to_string()),
+                image: Some(Url::parse("https://example.com/images/cover.png").unwrap()),
+            }
+        );
+
+        let plain = "<html><head><TITLE>\n  Just a\ttitle </TITLE><meta name=description content=\"x &#x263a; &#9731; &bogus; & y\"></head>";
+        let meta = parse_page(plain, &url);
+        assert_eq!(meta.title.as_deref(), Some("Just a title"));
+        assert_eq!(meta.description.as_deref(), Some("x ☺ ☃ &bogus; & y"));
+        assert_eq!(meta.image, None);
+
+        let long = format!("<title>{}</title>", "a".repeat(300));
+        let title = parse_page(&long, &url).title.unwrap();
+        assert_eq!(title.chars().count(), MAX_TITLE_CHARS);
+    }
+
+    #[tokio::test]
+    async fn fetches_pages_from_a_stand_in() {
+        let base = serve().await;
+
+        let meta = fetcher(true)
+            .fetch_page(base.join("/redirect").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(meta.title.as_deref(), Some("Open \"Graph\" title"));
+        // Relative to the page redirected to
+        assert_eq!(meta.image, Some(base.join("/images/cover.png").unwrap()));
+
+        let result = fetcher(true).fetch_page(base.join("/loop").unwrap()).await;
+        assert!(matches!(result, Err(FetchError::Redirects)));
+        let result = fetcher(true).fetch_page(base.join("/slow").unwrap()).await;
+        assert!(matches!(result, Err(FetchError::Timeout)));
+        let result = fetcher(true).fetch_page(base.join("/json").unwrap()).await;
+        assert!(matches!(result, Err(FetchError::ContentType)));
+        let result = fetcher(true)
+            .fetch_image(base.join("/large.png").unwrap(), 1024)
+            .await;
+        assert!(matches!(result, Err(FetchError::TooLarge(1024))));
+    }
+
+    #[tokio::test]
+    async fn refuses_private_addresses() {
+        let base = serve().await;
+
+        let result = fetcher(false).fetch_page(base.join("/page").unwrap()).await;
+        assert!(matches!(result, Err(FetchError::PrivateAddress(_))));
+        let port = base.port().unwrap();
+        let localhost = Url::parse(&format!("http://localhost:{port}/page")).unwrap();
+        let result = fetcher(false).fetch_page(localhost).await;
+        assert!(matches!(result, Err(FetchError::PrivateAddress(_))));
+        let result = fetcher(false)
+            .fetch_page(Url::parse("file:///etc/passwd").unwrap())
+            .await;
+        assert!(matches!(result, Err(FetchError::Scheme)));
+    }
+}
@@ -0,0 +1,93 @@
+//! Where uploaded media is kept.
+//!
+//! Uploads go through a [`MediaStore`], either a local directory or an
+//! S3-compatible bucket. Only the local store is limited to a single API
+//! instance.
+
+use std::{
+    ops::{Deref, Range},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{async_trait, body::Body};
+use uchat_endpoint::app_url::construct_image_url;
+use url::Url;
+
+pub mod http;
+pub mod local;
+pub mod mock;
+pub mod s3;
+
+pub use local::LocalStore;
+pub use s3::S3Store;
+
+#[async_trait]
+pub trait MediaStore: Send + Sync {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> anyhow::Result<()>;
+
+    /// Contents of the object, or `None` if there is no object under `key`.
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Body>>;
+
+    /// Bytes `range` of the object, which the caller checked against its size.
+    async fn get_range(&self, key: &str, range: Range<u64>) -> anyhow::Result<Option<Body>>;
+
+    /// Removes the object. Deleting a missing object is not an error.
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+
+    /// URL which reads the object directly from the store until it expires,
+    /// for stores which support it.
+    fn presigned_url(&self, _key: &str, _expires_in: Duration) -> Option<Url> {
+        None
+    }
+}
+
+/// How clients are sent to uploaded images.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum MediaLinks {
+    /// Through the API, which reads from the store.
+    #[default]
+    Api,
+    /// Straight to a public copy of the store, such as a CDN.
+    Public(Url),
+    /// To pre-signed store URLs valid for the given time.
+    Presigned(Duration),
+}
+
+/// The configured store, shared by all requests.
+#[derive(Clone)]
+pub struct Media {
+    store: Arc<dyn MediaStore>,
+    links: MediaLinks,
+}
+
+impl Media {
+    pub fn new<S: MediaStore + 'static>(store: S, links: MediaLinks) -> Self {
+        Self {
+            store: Arc::new(store),
+            links,
+        }
+    }
+
+    /// URL clients load the image with id `image_id` from.
+    pub async fn image_url(&self, image_id: &str) -> Result<Url, url::ParseError> {
+        match &self.links {
+            MediaLinks::Api => construct_image_url(image_id).await,
+            MediaLinks::Public(base) => base.join(image_id),
+            MediaLinks::Presigned(expires_in) => {
+                match self.store.presigned_url(image_id, *expires_in) {
+                    Some(url) => Ok(url),
+                    None => construct_image_url(image_id).await,
+                }
+            }
+        }
+    }
+}
+
+impl Deref for Media {
+    type Target = dyn MediaStore;
+
+    fn deref(&self) -> &Self::Target {
+        self.store.as_ref()
+    }
+}
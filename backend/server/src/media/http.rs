@@ -0,0 +1,190 @@
+//! Conditional and partial requests for stored media.
+//!
+//! Objects never change once stored under a key, so responses can be cached
+//! for good and revalidated by content hash alone.
+
+use std::ops::Range;
+
+use axum::http::{
+    header::{IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, RANGE},
+    HeaderMap, HeaderValue,
+};
+use chrono::{DateTime, Utc};
+
+/// `Cache-Control` of stored media.
+pub const CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Quoted strong entity tag for an object with the given content hash.
+pub fn etag(content_hash: &str) -> String {
+    format!("\"{content_hash}\"")
+}
+
+pub fn http_date(time: DateTime<Utc>) -> String {
+    time.format(HTTP_DATE_FORMAT).to_string()
+}
+
+fn parse_http_date(value: &HeaderValue) -> Option<DateTime<Utc>> {
+    let value = value.to_str().ok()?;
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Whether the client's copy is current, so a 304 can be sent instead.
+/// `If-None-Match` takes precedence over `If-Modified-Since`.
+pub fn not_modified(headers: &HeaderMap, etag: &str, last_modified: DateTime<Utc>) -> bool {
+    if let Some(value) = headers.get(IF_NONE_MATCH) {
+        let Ok(value) = value.to_str() else {
+            return false;
+        };
+        // Weak comparison, so W/ prefixed tags match as well
+        return value
+            .split(',')
+            .map(|tag| tag.trim())
+            .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag);
+    }
+    headers
+        .get(IF_MODIFIED_SINCE)
+        .and_then(parse_http_date)
+        .is_some_and(|since| last_modified.timestamp() <= since.timestamp())
+}
+
+/// Part of an object a request asks for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    /// The whole object, also for range requests which aren't supported or
+    /// whose `If-Range` doesn't match.
+    Full,
+    Partial(Range<u64>),
+    /// The range lies outside an object of this size.
+    Unsatisfiable,
+}
+
+/// Reads the `Range` header for an object of `len` bytes. Only single byte
+/// ranges are served, others get the whole object.
+pub fn byte_range(headers: &HeaderMap, etag: &str, len: u64) -> ByteRange {
+    let Some(range) = headers.get(RANGE).and_then(|value| value.to_str().ok()) else {
+        return ByteRange::Full;
+    };
+    // A strong comparison, a date can't tell if the object changed in the
+    // same second
+    if let Some(if_range) = headers.get(IF_RANGE) {
+        if if_range.to_str().ok() != Some(etag) {
+            return ByteRange::Full;
+        }
+    }
+    let Some((start, end)) = range
+        .strip_prefix("bytes=")
+        .filter(|spec| !spec.contains(','))
+        .and_then(|spec| spec.trim().split_once('-'))
+    else {
+        return ByteRange::Full;
+    };
+
+    let range = match (start, end) {
+        // Last `suffix` bytes
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(suffix) => len.saturating_sub(suffix)..len,
+            Err(_) => return ByteRange::Full,
+        },
+        (start, "") => match start.parse::<u64>() {
+            Ok(start) => start..len,
+            Err(_) => return ByteRange::Full,
+        },
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(start), Ok(end)) if start <= end => start..len.min(end.saturating_add(1)),
+            _ => return ByteRange::Full,
+        },
+    };
+    if range.start >= len {
+        ByteRange::Unsatisfiable
+    } else {
+        ByteRange::Partial(range)
+    }
+}
+
+/// Value of the `Content-Range` header for `range` of an object of `len`
+/// bytes.
+pub fn content_range(range: &Range<u64>, len: u64) -> String {
+    format!("bytes {}-{}/{len}", range.start, range.end - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const ETAG: &str = "\"abc\"";
+
+    fn headers(pairs: &[(axum::http::HeaderName, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_str(value).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn revalidates_by_etag_before_date() {
+        let modified = Utc.with_ymd_and_hms(2024, 9, 9, 12, 0, 0).unwrap();
+        let later = http_date(modified + chrono::Duration::hours(1));
+        assert_eq!(later, "Mon, 09 Sep 2024 13:00:00 GMT");
+
+        assert!(not_modified(
+            &headers(&[(IF_NONE_MATCH, "\"other\", W/\"abc\"")]),
+            ETAG,
+            modified
+        ));
+        assert!(not_modified(
+            &headers(&[(IF_NONE_MATCH, "*")]),
+            ETAG,
+            modified
+        ));
+        assert!(!not_modified(
+            &headers(&[(IF_NONE_MATCH, "\"other\""), (IF_MODIFIED_SINCE, &later)]),
+            ETAG,
+            modified
+        ));
+
+        assert!(not_modified(
+            &headers(&[(IF_MODIFIED_SINCE, &later)]),
+            ETAG,
+            modified
+        ));
+        let earlier = http_date(modified - chrono::Duration::seconds(1));
+        assert!(!not_modified(
+            &headers(&[(IF_MODIFIED_SINCE, &earlier)]),
+            ETAG,
+            modified
+        ));
+        assert!(!not_modified(&HeaderMap::new(), ETAG, modified));
+    }
+
+    #[test]
+    fn parses_single_ranges() {
+        let range = |value: &str| byte_range(&headers(&[(RANGE, value)]), ETAG, 100);
+
+        assert_eq!(byte_range(&HeaderMap::new(), ETAG, 100), ByteRange::Full);
+        assert_eq!(range("bytes=0-9"), ByteRange::Partial(0..10));
+        assert_eq!(range("bytes=90-"), ByteRange::Partial(90..100));
+        assert_eq!(range("bytes=-10"), ByteRange::Partial(90..100));
+        assert_eq!(range("bytes=50-500"), ByteRange::Partial(50..100));
+        assert_eq!(range("bytes=-500"), ByteRange::Partial(0..100));
+        assert_eq!(range("bytes=100-"), ByteRange::Unsatisfiable);
+        assert_eq!(range("bytes=-0"), ByteRange::Unsatisfiable);
+        assert_eq!(range("bytes=0-1,5-6"), ByteRange::Full);
+        assert_eq!(range("bytes=9-0"), ByteRange::Full);
+        assert_eq!(range("lines=0-9"), ByteRange::Full);
+        assert_eq!(content_range(&(90..100), 100), "bytes 90-99/100");
+    }
+
+    #[test]
+    fn if_range_must_match() {
+        let matching = headers(&[(RANGE, "bytes=0-9"), (IF_RANGE, ETAG)]);
+        assert_eq!(byte_range(&matching, ETAG, 100), ByteRange::Partial(0..10));
+        let stale = headers(&[(RANGE, "bytes=0-9"), (IF_RANGE, "\"old\"")]);
+        assert_eq!(byte_range(&stale, ETAG, 100), ByteRange::Full);
+    }
+}
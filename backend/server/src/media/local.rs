@@ -0,0 +1,97 @@
+use std::{
+    io::{ErrorKind, SeekFrom},
+    ops::Range,
+    path::PathBuf,
+};
+
+use axum::{async_trait, body::Body};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+use tokio_util::io::ReaderStream;
+
+use super::MediaStore;
+
+/// Keeps media as files in a local directory, named after their key.
+#[derive(Clone, Debug)]
+pub struct LocalStore {
+    dir: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(key)
+    }
+}
+
+#[async_trait]
+impl MediaStore for LocalStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir).await?;
+        // Replace files in one step, so readers never see half of one
+        let path = self.path(key);
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, bytes).await?;
+        fs::rename(&tmp_path, &path).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Body>> {
+        match fs::File::open(self.path(key)).await {
+            Ok(file) => Ok(Some(Body::from_stream(ReaderStream::new(file)))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_range(&self, key: &str, range: Range<u64>) -> anyhow::Result<Option<Body>> {
+        let mut file = match fs::File::open(self.path(key)).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        file.seek(SeekFrom::Start(range.start)).await?;
+        let part = file.take(range.end - range.start);
+        Ok(Some(Body::from_stream(ReaderStream::new(part))))
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match fs::remove_file(self.path(key)).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stores_and_deletes_files() {
+        let dir = std::env::temp_dir().join(format!("uchat-media-{}", uuid::Uuid::new_v4()));
+        let store = LocalStore::new(&dir);
+
+        assert!(store.get("missing").await.unwrap().is_none());
+        store
+            .put("key", b"image".to_vec(), "image/png")
+            .await
+            .unwrap();
+        let body = store.get("key").await.unwrap().unwrap();
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"image");
+        let body = store.get_range("key", 1..4).await.unwrap().unwrap();
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"mag");
+
+        store.delete("key").await.unwrap();
+        store.delete("key").await.unwrap();
+        assert!(store.get("key").await.unwrap().is_none());
+        fs::remove_dir_all(dir).await.unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+//! A minimal S3-compatible object store for local development and tests.
+//!
+//! Objects are kept in memory. Requests must be signed for the configured
+//! credentials, either in the `Authorization` header or as a pre-signed URL,
+//! like a real service would require.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{
+        header::{AUTHORIZATION, CONTENT_RANGE, CONTENT_TYPE, HOST},
+        HeaderMap, Method, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::{Duration, NaiveDateTime, Utc};
+use uchat_crypto::sigv4::{authorization, payload_hash, presigned_query, Credentials, Request};
+
+use super::{
+    http::{byte_range, content_range, ByteRange},
+    s3::AMZ_DATE_FORMAT,
+};
+
+struct Object {
+    content_type: String,
+    bytes: Bytes,
+}
+
+struct MockState {
+    credentials: Credentials,
+    objects: Mutex<HashMap<String, Object>>,
+}
+
+#[derive(Clone)]
+pub struct MockS3 {
+    state: Arc<MockState>,
+}
+
+impl MockS3 {
+    /// Store which only accepts requests signed with `credentials`.
+    pub fn new(credentials: Credentials) -> Self {
+        Self {
+            state: Arc::new(MockState {
+                credentials,
+                objects: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/:bucket/*key", get(object).put(object).delete(object))
+            .with_state(self.state.clone())
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+}
+
+/// Checks a pre-signed URL, which only allows reading.
+fn presigned_request_valid(
+    credentials: &Credentials,
+    method: &Method,
+    host: &str,
+    path: &str,
+    query: &HashMap<String, String>,
+) -> bool {
+    let (Some(amz_date), Some(expires)) = (
+        query.get("X-Amz-Date"),
+        query
+            .get("X-Amz-Expires")
+            .and_then(|secs| secs.parse::<u64>().ok()),
+    ) else {
+        return false;
+    };
+    let Ok(signed_at) = NaiveDateTime::parse_from_str(amz_date, AMZ_DATE_FORMAT) else {
+        return false;
+    };
+    if signed_at.and_utc() + Duration::seconds(expires as i64) < Utc::now() {
+        return false;
+    }
+    let expected = presigned_query(credentials, "GET", host, path, amz_date, expires);
+    *method == Method::GET
+        && expected
+            .iter()
+            .all(|(name, value)| query.get(name) == Some(value))
+}
+
+/// Checks a request signed in the `Authorization` header.
+fn signed_request_valid(
+    credentials: &Credentials,
+    method: &Method,
+    path: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> bool {
+    let auth = header(headers, AUTHORIZATION.as_str());
+    let Some(signed_headers) = auth
+        .split(", ")
+        .find_map(|part| part.strip_prefix("SignedHeaders="))
+    else {
+        return false;
+    };
+    let signed = signed_headers
+        .split(';')
+        .map(|name| (name.to_string(), header(headers, name).to_string()))
+        .collect::<Vec<_>>();
+    let body_hash = header(headers, "x-amz-content-sha256");
+    let amz_date = header(headers, "x-amz-date");
+    if body_hash != payload_hash(body)
+        || NaiveDateTime::parse_from_str(amz_date, AMZ_DATE_FORMAT).is_err()
+    {
+        return false;
+    }
+    let expected = authorization(
+        credentials,
+        &Request {
+            method: method.as_str(),
+            path,
+            query: &[],
+            headers: &signed,
+            payload_hash: body_hash,
+            amz_date,
+        },
+    );
+    auth == expected
+}
+
+async fn object(
+    State(state): State<Arc<MockState>>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    method: Method,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let path = format!("/{bucket}/{key}");
+    let host = header(&headers, HOST.as_str());
+    let authorized = if query.contains_key("X-Amz-Signature") {
+        presigned_request_valid(&state.credentials, &method, host, &path, &query)
+    } else {
+        signed_request_valid(&state.credentials, &method, &path, &headers, &body)
+    };
+    if !authorized {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let mut objects = state.objects.lock().expect("poisoned lock");
+    match method {
+        Method::PUT => {
+            let content_type = header(&headers, CONTENT_TYPE.as_str()).to_string();
+            objects.insert(
+                path,
+                Object {
+                    content_type,
+                    bytes: body,
+                },
+            );
+            StatusCode::OK.into_response()
+        }
+        Method::DELETE => {
+            objects.remove(&path);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        _ => {
+            let Some(object) = objects.get(&path) else {
+                return StatusCode::NOT_FOUND.into_response();
+            };
+            let len = object.bytes.len() as u64;
+            let content_type = (CONTENT_TYPE, object.content_type.clone());
+            match byte_range(&headers, "", len) {
+                ByteRange::Full => ([content_type], object.bytes.clone()).into_response(),
+                ByteRange::Partial(range) => (
+                    StatusCode::PARTIAL_CONTENT,
+                    [content_type, (CONTENT_RANGE, content_range(&range, len))],
+                    object.bytes.slice(range.start as usize..range.end as usize),
+                )
+                    .into_response(),
+                ByteRange::Unsatisfiable => StatusCode::RANGE_NOT_SATISFIABLE.into_response(),
+            }
+        }
+    }
+}
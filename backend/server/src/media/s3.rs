@@ -0,0 +1,304 @@
+use std::{ops::Range, time::Duration};
+
+use anyhow::bail;
+use axum::{async_trait, body::Body, http::StatusCode};
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use reqwest::{
+    header::{CACHE_CONTROL, CONTENT_TYPE, RANGE},
+    Method,
+};
+use uchat_crypto::sigv4::{
+    authorization, canonical_query, payload_hash, presigned_query, uri_encode, Credentials, Request,
+};
+use url::Url;
+
+use super::MediaStore;
+
+/// Format of the `x-amz-date` header.
+pub const AMZ_DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Keeps media in a bucket of an S3-compatible service, addressed in path
+/// style (`<endpoint>/<bucket>/<key>`) as self-hosted services expect.
+#[derive(Clone, Debug)]
+pub struct S3Store {
+    http: reqwest::Client,
+    endpoint: Url,
+    bucket: String,
+    credentials: Credentials,
+}
+
+impl S3Store {
+    /// `connect_timeout` limits connecting to the service, `timeout` whole
+    /// requests including reading the response.
+    pub fn new(
+        endpoint: Url,
+        bucket: &str,
+        credentials: Credentials,
+        connect_timeout: Duration,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        let http = reqwest::Client::builder()
+            .connect_timeout(connect_timeout)
+            .timeout(timeout)
+            .build()?;
+        Ok(Self {
+            http,
+            endpoint,
+            bucket: bucket.to_string(),
+            credentials,
+        })
+    }
+
+    /// Host header the HTTP client sends, which is part of the signature.
+    fn host(&self) -> String {
+        let host = self.endpoint.host_str().unwrap_or_default();
+        match self.endpoint.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        }
+    }
+
+    fn object_path(&self, key: &str) -> String {
+        format!("/{}/{key}", self.bucket)
+    }
+
+    fn url(&self, path: &str, query: &str) -> Url {
+        let mut url = format!(
+            "{}{}",
+            self.endpoint.origin().ascii_serialization(),
+            uri_encode(path, true)
+        );
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(query);
+        }
+        Url::parse(&url).expect("endpoint and encoded path form a valid URL")
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        body: Vec<u8>,
+        content_type: Option<&str>,
+        range: Option<Range<u64>>,
+    ) -> anyhow::Result<reqwest::Response> {
+        let path = self.object_path(key);
+        let amz_date = Utc::now().format(AMZ_DATE_FORMAT).to_string();
+        let body_hash = payload_hash(&body);
+        let headers = [
+            ("host".to_string(), self.host()),
+            ("x-amz-content-sha256".to_string(), body_hash.clone()),
+            ("x-amz-date".to_string(), amz_date.clone()),
+        ];
+        let auth = authorization(
+            &self.credentials,
+            &Request {
+                method: method.as_str(),
+                path: &path,
+                query: &[],
+                headers: &headers,
+                payload_hash: &body_hash,
+                amz_date: &amz_date,
+            },
+        );
+
+        let mut request = self
+            .http
+            .request(method, self.url(&path, ""))
+            .header("x-amz-content-sha256", body_hash)
+            .header("x-amz-date", amz_date)
+            .header(reqwest::header::AUTHORIZATION, auth);
+        if let Some(content_type) = content_type {
+            // Kept with the object, for clients reading it without the API
+            request = request
+                .header(CONTENT_TYPE, content_type)
+                .header(CACHE_CONTROL, super::http::CACHE_CONTROL);
+        }
+        // Not signed, services accept unsigned headers besides `x-amz-*`
+        if let Some(range) = range {
+            request = request.header(RANGE, format!("bytes={}-{}", range.start, range.end - 1));
+        }
+        Ok(request.body(body).send().await?)
+    }
+}
+
+/// Signing time and lifetime of a pre-signed URL requested at `now`.
+///
+/// The time is rounded down to half of `expires_in`, so the same URL is handed
+/// out for a while and browsers can cache the media. The lifetime grows by the
+/// rounding step to keep the URL valid for at least `expires_in`.
+fn presign_window(now: DateTime<Utc>, expires_in: Duration) -> (DateTime<Utc>, Duration) {
+    let step = expires_in / 2;
+    let signed_at = TimeDelta::from_std(step)
+        .ok()
+        .filter(|step| step.num_seconds() > 0)
+        .and_then(|step| now.duration_trunc(step).ok())
+        .unwrap_or(now);
+    (signed_at, expires_in + step)
+}
+
+fn read_body(key: &str, response: reqwest::Response) -> anyhow::Result<Option<Body>> {
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(None),
+        status if status.is_success() => Ok(Some(Body::from_stream(response.bytes_stream()))),
+        status => bail!("reading {key} failed with status {status}"),
+    }
+}
+
+#[async_trait]
+impl MediaStore for S3Store {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> anyhow::Result<()> {
+        let response = self
+            .send(Method::PUT, key, bytes, Some(content_type), None)
+            .await?;
+        if !response.status().is_success() {
+            bail!("storing {key} failed with status {}", response.status());
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Body>> {
+        let response = self.send(Method::GET, key, vec![], None, None).await?;
+        read_body(key, response)
+    }
+
+    async fn get_range(&self, key: &str, range: Range<u64>) -> anyhow::Result<Option<Body>> {
+        let response = self
+            .send(Method::GET, key, vec![], None, Some(range))
+            .await?;
+        if response.status() == StatusCode::OK {
+            bail!("reading part of {key} returned the whole object");
+        }
+        read_body(key, response)
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let response = self.send(Method::DELETE, key, vec![], None, None).await?;
+        let status = response.status();
+        if !status.is_success() && status != StatusCode::NOT_FOUND {
+            bail!("deleting {key} failed with status {status}");
+        }
+        Ok(())
+    }
+
+    fn presigned_url(&self, key: &str, expires_in: Duration) -> Option<Url> {
+        let path = self.object_path(key);
+        let (signed_at, expires_in) = presign_window(Utc::now(), expires_in);
+        let amz_date = signed_at.format(AMZ_DATE_FORMAT).to_string();
+        let query = presigned_query(
+            &self.credentials,
+            "GET",
+            &self.host(),
+            &path,
+            &amz_date,
+            expires_in.as_secs(),
+        );
+        Some(self.url(&path, &canonical_query(&query)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::mock::MockS3;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn credentials(secret_key: &str) -> Credentials {
+        Credentials {
+            access_key: "uchat".to_string(),
+            secret_key: secret_key.to_string(),
+            region: "us-east-1".to_string(),
+        }
+    }
+
+    async fn start_mock() -> Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let mock = MockS3::new(credentials("secret"));
+        tokio::spawn(async move { axum::serve(listener, mock.router()).await });
+        endpoint
+    }
+
+    async fn read(body: Body) -> Vec<u8> {
+        axum::body::to_bytes(body, usize::MAX)
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    #[tokio::test]
+    async fn objects_round_trip() {
+        let endpoint = start_mock().await;
+        let store =
+            S3Store::new(endpoint, "media", credentials("secret"), TIMEOUT, TIMEOUT).unwrap();
+
+        assert!(store.get("image").await.unwrap().is_none());
+        store
+            .put("image", b"png".to_vec(), "image/png")
+            .await
+            .unwrap();
+        let body = store.get("image").await.unwrap().unwrap();
+        assert_eq!(read(body).await, b"png");
+        let body = store.get_range("image", 1..3).await.unwrap().unwrap();
+        assert_eq!(read(body).await, b"ng");
+
+        store.delete("image").await.unwrap();
+        assert!(store.get("image").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn presigned_urls_read_objects() {
+        let endpoint = start_mock().await;
+        let store =
+            S3Store::new(endpoint, "media", credentials("secret"), TIMEOUT, TIMEOUT).unwrap();
+        store
+            .put("image", b"png".to_vec(), "image/png")
+            .await
+            .unwrap();
+
+        let url = store
+            .presigned_url("image", Duration::from_secs(60))
+            .unwrap();
+        let response = reqwest::get(url.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[CONTENT_TYPE.as_str()].to_str().unwrap(),
+            "image/png"
+        );
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"png");
+
+        let tampered = url
+            .as_str()
+            .replace("X-Amz-Expires=90", "X-Amz-Expires=9000");
+        let response = reqwest::get(tampered).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn presigned_urls_are_stable_within_half_their_lifetime() {
+        let expires_in = Duration::from_secs(600);
+        let start = "2024-11-11T10:05:00Z".parse::<DateTime<Utc>>().unwrap();
+        let (signed_at, lifetime) = presign_window(start, expires_in);
+        assert_eq!(signed_at, start);
+        assert_eq!(lifetime, Duration::from_secs(900));
+
+        let later = start + TimeDelta::seconds(299);
+        assert_eq!(presign_window(later, expires_in).0, start);
+        let next = start + TimeDelta::seconds(300);
+        assert_eq!(presign_window(next, expires_in).0, next);
+    }
+
+    #[tokio::test]
+    async fn wrong_credentials_are_rejected() {
+        let endpoint = start_mock().await;
+        let store =
+            S3Store::new(endpoint, "media", credentials("wrong"), TIMEOUT, TIMEOUT).unwrap();
+
+        assert!(store
+            .put("image", b"png".to_vec(), "image/png")
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,269 @@
+//! Keyword mutes applied to the posts of a feed.
+
+use uchat_endpoint::{
+    post::types::{Content, PublicPost},
+    user::types::{MuteAction, MuteScope},
+};
+
+/// What to do with a post after checking it against the mute filters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Show,
+    /// Collapse the post, naming the phrase it matched.
+    Warn(String),
+    Hide,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    phrase: String,
+    needle: String,
+    whole_word: bool,
+    scopes: Vec<MuteScope>,
+    action: MuteAction,
+}
+
+impl Rule {
+    pub fn new(phrase: &str, whole_word: bool, scopes: Vec<MuteScope>, action: MuteAction) -> Self {
+        Self {
+            phrase: phrase.to_string(),
+            needle: phrase.to_lowercase(),
+            whole_word,
+            scopes,
+            action,
+        }
+    }
+
+    fn applies_to(&self, scope: MuteScope, is_reply: bool) -> bool {
+        self.scopes.contains(&scope) || (is_reply && self.scopes.contains(&MuteScope::Threads))
+    }
+}
+
+/// Active mute filters of one user.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MuteRules {
+    rules: Vec<Rule>,
+}
+
+impl MuteRules {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    /// Rules from stored filters, skipping rows which no longer parse.
+    pub fn from_filters(filters: Vec<uchat_query::mute::MuteFilter>) -> Self {
+        let rules = filters
+            .into_iter()
+            .filter_map(|filter| {
+                let action = filter.action.parse::<MuteAction>().ok()?;
+                let scopes = filter
+                    .scopes
+                    .iter()
+                    .filter_map(|scope| scope.parse::<MuteScope>().ok())
+                    .collect();
+                Some(Rule::new(&filter.phrase, filter.whole_word, scopes, action))
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Checks `text` shown in `scope`. Hiding wins over warning, so a post
+    /// matching both kinds of filters is hidden.
+    pub fn check(&self, text: &str, scope: MuteScope, is_reply: bool) -> Verdict {
+        let text = text.to_lowercase();
+        let mut verdict = Verdict::Show;
+        for rule in &self.rules {
+            if !rule.applies_to(scope, is_reply)
+                || !contains_phrase(&text, &rule.needle, rule.whole_word)
+            {
+                continue;
+            }
+            match rule.action {
+                MuteAction::Hide => return Verdict::Hide,
+                MuteAction::Warn if verdict == Verdict::Show => {
+                    verdict = Verdict::Warn(rule.phrase.clone());
+                }
+                MuteAction::Warn => {}
+            }
+        }
+        verdict
+    }
+
+    /// Drops hidden posts and marks the ones to collapse.
+    pub fn apply(&self, posts: Vec<PublicPost>, scope: MuteScope) -> Vec<PublicPost> {
+        if self.is_empty() {
+            return posts;
+        }
+        posts
+            .into_iter()
+            .filter_map(|mut post| {
+                let text = post_text(&post.content);
+                match self.check(&text, scope, post.reply_to.is_some()) {
+                    Verdict::Show => Some(post),
+                    Verdict::Warn(phrase) => {
+                        post.muted_by = Some(phrase);
+                        Some(post)
+                    }
+                    Verdict::Hide => None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Everything readable in a post, one part per line.
+pub fn post_text(content: &Content) -> String {
+    let parts: Vec<&str> = match content {
+        Content::Chat(chat) => chat
+            .headline
+            .iter()
+            .map(|headline| headline.as_ref())
+            .chain([chat.message.as_ref()])
+            .collect(),
+        Content::Link(link) => link
+            .headline
+            .iter()
+            .map(|headline| headline.as_ref())
+            .chain([link.message.as_ref()])
+            .collect(),
+        Content::Image(image) => image
+            .caption
+            .iter()
+            .map(|caption| caption.as_ref())
+            .collect(),
+        Content::Gallery(gallery) => gallery
+            .caption
+            .iter()
+            .map(|caption| caption.as_ref())
+            .chain(
+                gallery
+                    .images
+                    .iter()
+                    .filter_map(|image| image.alt_text.as_ref())
+                    .map(|alt_text| alt_text.as_ref()),
+            )
+            .collect(),
+        Content::Video(video) => video
+            .caption
+            .iter()
+            .map(|caption| caption.as_ref())
+            .chain(video.alt_text.iter().map(|alt_text| alt_text.as_ref()))
+            .collect(),
+        Content::Poll(poll) => [poll.headline.as_ref()]
+            .into_iter()
+            .chain(
+                poll.choices
+                    .iter()
+                    .map(|choice| choice.description.as_ref()),
+            )
+            .collect(),
+    };
+    parts.join("\n")
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether lowercase `text` contains lowercase `phrase`. With `whole_word`
+/// the match may not continue a word on either side, so `rust` matches
+/// `#rust` but not `trust`.
+pub fn contains_phrase(text: &str, phrase: &str, whole_word: bool) -> bool {
+    if phrase.is_empty() {
+        return false;
+    }
+    if !whole_word {
+        return text.contains(phrase);
+    }
+    text.match_indices(phrase).any(|(start, _)| {
+        let end = start + phrase.len();
+        let starts_word = phrase.starts_with(|c: char| !is_word_char(c))
+            || !text[..start].ends_with(is_word_char);
+        let ends_word =
+            phrase.ends_with(|c: char| !is_word_char(c)) || !text[end..].starts_with(is_word_char);
+        starts_word && ends_word
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_words_only_match_on_word_boundaries() {
+        assert!(contains_phrase("i love rust", "rust", true));
+        assert!(contains_phrase("#rust is great", "rust", true));
+        assert!(contains_phrase("rust, again", "rust", true));
+        assert!(!contains_phrase("in trust we", "rust", true));
+        assert!(!contains_phrase("rusty nails", "rust", true));
+        assert!(contains_phrase("rusty nails", "rust", false));
+        assert!(contains_phrase("trust rust", "rust", true));
+        assert!(contains_phrase("big news today", "big news", true));
+    }
+
+    #[test]
+    fn hashtags_only_match_the_hashtag() {
+        assert!(contains_phrase("learning #rust", "#rust", true));
+        assert!(!contains_phrase("learning rust", "#rust", true));
+        assert!(!contains_phrase("learning #rustlang", "#rust", true));
+    }
+
+    #[test]
+    fn matching_ignores_case() {
+        let rules = MuteRules::new(vec![Rule::new(
+            "Spoiler",
+            true,
+            vec![MuteScope::Home],
+            MuteAction::Warn,
+        )]);
+        assert_eq!(
+            rules.check("SPOILER: it was him", MuteScope::Home, false),
+            Verdict::Warn("Spoiler".to_string())
+        );
+    }
+
+    #[test]
+    fn rules_only_apply_in_their_scopes() {
+        let rules = MuteRules::new(vec![
+            Rule::new(
+                "election",
+                true,
+                vec![MuteScope::Trending],
+                MuteAction::Hide,
+            ),
+            Rule::new("finale", true, vec![MuteScope::Threads], MuteAction::Hide),
+        ]);
+        assert_eq!(
+            rules.check("election day", MuteScope::Trending, false),
+            Verdict::Hide
+        );
+        assert_eq!(
+            rules.check("election day", MuteScope::Home, false),
+            Verdict::Show
+        );
+        assert_eq!(
+            rules.check("the finale", MuteScope::Home, false),
+            Verdict::Show
+        );
+        assert_eq!(
+            rules.check("the finale", MuteScope::Home, true),
+            Verdict::Hide
+        );
+    }
+
+    #[test]
+    fn hiding_wins_over_warning() {
+        let rules = MuteRules::new(vec![
+            Rule::new("match", true, vec![MuteScope::Home], MuteAction::Warn),
+            Rule::new("score", true, vec![MuteScope::Home], MuteAction::Hide),
+        ]);
+        assert_eq!(
+            rules.check("match score", MuteScope::Home, false),
+            Verdict::Hide
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! Client side of OpenID Connect logins: provider discovery, building
+//! authorization requests and exchanging codes for verified ID tokens.
+
+use std::{
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+use uchat_crypto::oidc::{
+    token_key_id, verify_id_token, Error as TokenError, IdTokenClaims, JwkSet,
+};
+use uchat_domain::Username;
+use url::Url;
+
+pub mod mock;
+
+/// Longest handle accepted by `Username`.
+const MAX_HANDLE_CHARS: usize = 20;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OidcError {
+    #[error("request to provider failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("provider returned an error: {0}")]
+    Provider(String),
+    #[error("invalid ID token: {0}")]
+    Token(#[from] TokenError),
+}
+
+/// One entry of the providers file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ProviderSettings {
+    /// Short name used in URLs and stored with linked identities.
+    pub id: String,
+    /// Name shown on the login page.
+    pub name: String,
+    pub issuer: Url,
+    pub client_id: String,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    #[serde(default = "ProviderSettings::default_scopes")]
+    pub scopes: Vec<String>,
+}
+
+impl ProviderSettings {
+    fn default_scopes() -> Vec<String> {
+        ["openid", "email", "profile"]
+            .into_iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// Reads a JSON array of providers.
+    pub fn load(path: &Path) -> anyhow::Result<Vec<Self>> {
+        let file = std::fs::read(path)
+            .with_context(|| format!("failed to read OIDC providers from {}", path.display()))?;
+        let providers: Vec<Self> = serde_json::from_slice(&file)
+            .with_context(|| format!("invalid OIDC providers file {}", path.display()))?;
+        for (i, provider) in providers.iter().enumerate() {
+            if providers[..i].iter().any(|other| other.id == provider.id) {
+                anyhow::bail!("OIDC provider '{}' is configured twice", provider.id);
+            }
+        }
+        Ok(providers)
+    }
+}
+
+/// The parts of the discovery document used by the login flow.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: Url,
+    pub token_endpoint: Url,
+    pub jwks_uri: Url,
+}
+
+#[derive(Debug)]
+pub struct Provider {
+    pub settings: ProviderSettings,
+    pub metadata: ProviderMetadata,
+    keys: RwLock<JwkSet>,
+}
+
+impl Provider {
+    /// URL the browser is sent to. `challenge` is the S256 PKCE challenge.
+    pub fn authorization_url(
+        &self,
+        redirect_url: &Url,
+        state: &str,
+        nonce: &str,
+        challenge: &str,
+    ) -> Url {
+        let mut url = self.metadata.authorization_endpoint.clone();
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.settings.client_id)
+            .append_pair("redirect_uri", redirect_url.as_str())
+            .append_pair("scope", &self.settings.scopes.join(" "))
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", challenge)
+            .append_pair("code_challenge_method", "S256");
+        url
+    }
+
+    fn keys(&self) -> JwkSet {
+        self.keys.read().expect("OIDC key lock poisoned").clone()
+    }
+
+    fn set_keys(&self, keys: JwkSet) {
+        *self.keys.write().expect("OIDC key lock poisoned") = keys;
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// Providers from the configuration, with their discovered endpoints.
+#[derive(Debug, Clone, Default)]
+pub struct OidcProviders {
+    providers: Arc<Vec<Provider>>,
+    http: reqwest::Client,
+}
+
+impl OidcProviders {
+    /// Fetches the discovery document and keys of every provider.
+    pub async fn discover(settings: Vec<ProviderSettings>) -> anyhow::Result<Self> {
+        let http = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(std::time::Duration::from_secs(10))
+            .build()?;
+
+        let mut providers = vec![];
+        for settings in settings {
+            let provider = discover(&http, settings.clone())
+                .await
+                .with_context(|| format!("discovery of OIDC provider '{}' failed", settings.id))?;
+            providers.push(provider);
+        }
+
+        Ok(Self {
+            providers: Arc::new(providers),
+            http,
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Provider> {
+        self.providers
+            .iter()
+            .find(|provider| provider.settings.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Provider> {
+        self.providers.iter()
+    }
+
+    /// Redeems an authorization code and returns the verified claims of the
+    /// ID token.
+    pub async fn exchange_code(
+        &self,
+        provider: &Provider,
+        redirect_url: &Url,
+        code: &str,
+        pkce_verifier: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, OidcError> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_url.as_str()),
+            ("client_id", &provider.settings.client_id),
+            ("code_verifier", pkce_verifier),
+        ];
+        if let Some(secret) = &provider.settings.client_secret {
+            form.push(("client_secret", secret));
+        }
+
+        let response: TokenResponse = self
+            .http
+            .post(provider.metadata.token_endpoint.clone())
+            .form(&form)
+            .send()
+            .await?
+            .json()
+            .await?;
+        if let Some(error) = response.error {
+            let description = response.error_description.unwrap_or_default();
+            return Err(OidcError::Provider(format!("{error} {description}")));
+        }
+        let id_token = response
+            .id_token
+            .ok_or_else(|| OidcError::Provider("no id_token in token response".to_string()))?;
+
+        // Providers rotate keys, so an unknown key id triggers one refetch.
+        let kid = token_key_id(&id_token)?;
+        let mut keys = provider.keys();
+        if kid.is_some() && !keys.keys.iter().any(|key| key.kid == kid) {
+            keys = fetch_keys(&self.http, &provider.metadata.jwks_uri).await?;
+            provider.set_keys(keys.clone());
+        }
+
+        let claims = verify_id_token(&id_token, &keys)?;
+        claims.validate(
+            &provider.metadata.issuer,
+            &provider.settings.client_id,
+            nonce,
+            chrono::Utc::now().timestamp(),
+        )?;
+        Ok(claims)
+    }
+}
+
+async fn discover(http: &reqwest::Client, settings: ProviderSettings) -> anyhow::Result<Provider> {
+    let discovery_url = Url::parse(&format!(
+        "{}/.well-known/openid-configuration",
+        settings.issuer.as_str().trim_end_matches('/')
+    ))?;
+
+    let metadata: ProviderMetadata = http
+        .get(discovery_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if metadata.issuer.trim_end_matches('/') != settings.issuer.as_str().trim_end_matches('/') {
+        anyhow::bail!(
+            "discovery document is for issuer {}, expected {}",
+            metadata.issuer,
+            settings.issuer
+        );
+    }
+
+    let keys = fetch_keys(http, &metadata.jwks_uri).await?;
+    Ok(Provider {
+        settings,
+        metadata,
+        keys: RwLock::new(keys),
+    })
+}
+
+async fn fetch_keys(http: &reqwest::Client, jwks_uri: &Url) -> Result<JwkSet, reqwest::Error> {
+    http.get(jwks_uri.clone())
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+/// Handle proposed to a new user, based on the name they use at the provider.
+pub fn suggested_handle(claims: &IdTokenClaims) -> Option<String> {
+    let name = claims
+        .preferred_username
+        .as_deref()
+        .or_else(|| claims.email.as_deref()?.split('@').next())?;
+    let handle = name
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '_')
+        .take(MAX_HANDLE_CHARS)
+        .collect::<String>();
+    Username::try_new(&handle).ok().map(|_| handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::oidc::mock::MockProvider;
+    use uchat_crypto::oidc::{Pkce, RsaPrivateKey};
+
+    fn claims(preferred_username: Option<&str>, email: Option<&str>) -> IdTokenClaims {
+        IdTokenClaims {
+            iss: "http://localhost:9000".to_string(),
+            sub: "1".to_string(),
+            aud: uchat_crypto::oidc::Audience::One("uchat".to_string()),
+            exp: 0,
+            iat: 0,
+            nonce: None,
+            email: email.map(ToString::to_string),
+            email_verified: None,
+            preferred_username: preferred_username.map(ToString::to_string),
+            name: None,
+        }
+    }
+
+    #[test]
+    fn handles_are_suggested_from_profile() {
+        assert_eq!(
+            suggested_handle(&claims(Some("Jane Doe"), Some("jd@example.com"))).as_deref(),
+            Some("JaneDoe")
+        );
+        assert_eq!(
+            suggested_handle(&claims(None, Some("jane.doe@example.com"))).as_deref(),
+            Some("janedoe")
+        );
+        assert_eq!(
+            suggested_handle(&claims(Some("a_very_long_name_from_a_provider"), None)).as_deref(),
+            Some("a_very_long_name_fro")
+        );
+        assert_eq!(suggested_handle(&claims(Some("j.d"), None)), None);
+        assert_eq!(suggested_handle(&claims(None, None)), None);
+    }
+
+    /// Follows the mock's auto-approving authorization endpoint and returns
+    /// the code from the redirect.
+    async fn authorize(providers: &OidcProviders, url: Url) -> String {
+        let mut url = url;
+        url.query_pairs_mut().append_pair("login_hint", "alice");
+        let response = providers.http.get(url).send().await.unwrap();
+        let location = Url::parse(response.headers()["location"].to_str().unwrap()).unwrap();
+        let params = location.query_pairs().collect::<HashMap<_, _>>();
+        assert_eq!(params["state"], "state");
+        params["code"].to_string()
+    }
+
+    #[tokio::test]
+    async fn login_against_mock_provider() {
+        let mut rng = uchat_crypto::new_rng();
+        let key = RsaPrivateKey::new(&mut rng, 1024).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let mock = MockProvider::new(issuer.clone(), "mock-client", key);
+        tokio::spawn(async move { axum::serve(listener, mock.router()).await });
+
+        let providers = OidcProviders::discover(vec![ProviderSettings {
+            id: "mock".to_string(),
+            name: "Mock".to_string(),
+            issuer,
+            client_id: "mock-client".to_string(),
+            client_secret: None,
+            scopes: ProviderSettings::default_scopes(),
+        }])
+        .await
+        .unwrap();
+        let provider = providers.get("mock").unwrap();
+        let redirect_url = Url::parse("http://localhost:8080/account/oidc/callback").unwrap();
+        let pkce = Pkce::generate(&mut rng);
+        let authorization_url =
+            provider.authorization_url(&redirect_url, "state", "nonce", &pkce.challenge);
+
+        // A wrong verifier is rejected, and burns the code
+        let code = authorize(&providers, authorization_url.clone()).await;
+        let wrong = Pkce::generate(&mut rng);
+        assert!(providers
+            .exchange_code(provider, &redirect_url, &code, &wrong.verifier, "nonce")
+            .await
+            .is_err());
+        assert!(providers
+            .exchange_code(provider, &redirect_url, &code, &pkce.verifier, "nonce")
+            .await
+            .is_err());
+
+        let code = authorize(&providers, authorization_url.clone()).await;
+        assert!(providers
+            .exchange_code(provider, &redirect_url, &code, &pkce.verifier, "other")
+            .await
+            .is_err());
+
+        let code = authorize(&providers, authorization_url).await;
+        let claims = providers
+            .exchange_code(provider, &redirect_url, &code, &pkce.verifier, "nonce")
+            .await
+            .unwrap();
+        assert_eq!(claims.sub, "alice");
+        assert_eq!(claims.email.as_deref(), Some("alice@example.com"));
+    }
+}
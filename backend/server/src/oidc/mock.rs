@@ -0,0 +1,217 @@
+//! A minimal OpenID Connect provider for local development and tests.
+//!
+//! Every authorization request is approved right away. The user is taken
+//! from the `login_hint` parameter and defaults to `alice`, so different
+//! accounts can be tried by editing the URL.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Form, Json, Router,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+use uchat_crypto::oidc::{
+    random_token, sign_id_token, Audience, IdTokenClaims, Jwk, JwkSet, Pkce, RsaPrivateKey,
+};
+use url::Url;
+
+const KEY_ID: &str = "mock";
+const DEFAULT_USER: &str = "alice";
+
+struct PendingCode {
+    client_id: String,
+    redirect_uri: String,
+    code_challenge: String,
+    nonce: Option<String>,
+    user: String,
+}
+
+struct MockState {
+    issuer: String,
+    client_id: String,
+    key: RsaPrivateKey,
+    codes: Mutex<HashMap<String, PendingCode>>,
+}
+
+#[derive(Clone)]
+pub struct MockProvider {
+    state: Arc<MockState>,
+}
+
+impl MockProvider {
+    /// Provider reachable at `issuer` which only serves `client_id`.
+    pub fn new(issuer: Url, client_id: &str, key: RsaPrivateKey) -> Self {
+        Self {
+            state: Arc::new(MockState {
+                issuer: issuer.as_str().trim_end_matches('/').to_string(),
+                client_id: client_id.to_string(),
+                key,
+                codes: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/authorize", get(authorize))
+            .route("/token", post(token))
+            .route("/jwks", get(jwks))
+            .with_state(self.state.clone())
+    }
+}
+
+async fn discovery(State(state): State<Arc<MockState>>) -> Json<serde_json::Value> {
+    let issuer = &state.issuer;
+    Json(json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{issuer}/authorize"),
+        "token_endpoint": format!("{issuer}/token"),
+        "jwks_uri": format!("{issuer}/jwks"),
+        "response_types_supported": ["code"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["RS256"],
+        "code_challenge_methods_supported": ["S256"],
+    }))
+}
+
+async fn jwks(State(state): State<Arc<MockState>>) -> Json<JwkSet> {
+    Json(JwkSet {
+        keys: vec![Jwk::from_public_key(KEY_ID, &state.key.to_public_key())],
+    })
+}
+
+#[derive(Deserialize)]
+struct AuthorizeParams {
+    response_type: String,
+    client_id: String,
+    redirect_uri: String,
+    state: Option<String>,
+    nonce: Option<String>,
+    code_challenge: Option<String>,
+    code_challenge_method: Option<String>,
+    login_hint: Option<String>,
+}
+
+async fn authorize(
+    State(state): State<Arc<MockState>>,
+    Query(params): Query<AuthorizeParams>,
+) -> Response {
+    if params.client_id != state.client_id {
+        return (StatusCode::BAD_REQUEST, "unknown client_id").into_response();
+    }
+    let Ok(mut redirect) = Url::parse(&params.redirect_uri) else {
+        return (StatusCode::BAD_REQUEST, "invalid redirect_uri").into_response();
+    };
+
+    let error = if params.response_type != "code" {
+        Some("unsupported_response_type")
+    } else if params.code_challenge.is_none()
+        || params.code_challenge_method.as_deref() != Some("S256")
+    {
+        Some("invalid_request")
+    } else {
+        None
+    };
+
+    match error {
+        Some(error) => {
+            redirect.query_pairs_mut().append_pair("error", error);
+        }
+        None => {
+            let code = random_token(&mut uchat_crypto::new_rng());
+            let pending = PendingCode {
+                client_id: params.client_id,
+                redirect_uri: params.redirect_uri,
+                code_challenge: params.code_challenge.unwrap_or_default(),
+                nonce: params.nonce,
+                user: params
+                    .login_hint
+                    .filter(|hint| !hint.is_empty())
+                    .unwrap_or_else(|| DEFAULT_USER.to_string()),
+            };
+            state
+                .codes
+                .lock()
+                .expect("mock provider lock poisoned")
+                .insert(code.clone(), pending);
+            redirect.query_pairs_mut().append_pair("code", &code);
+        }
+    }
+    if let Some(value) = params.state {
+        redirect.query_pairs_mut().append_pair("state", &value);
+    }
+    Redirect::to(redirect.as_str()).into_response()
+}
+
+#[derive(Deserialize)]
+struct TokenParams {
+    grant_type: String,
+    code: String,
+    redirect_uri: String,
+    client_id: String,
+    code_verifier: String,
+}
+
+fn token_error(error: &str) -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": error }))).into_response()
+}
+
+async fn token(State(state): State<Arc<MockState>>, Form(params): Form<TokenParams>) -> Response {
+    if params.grant_type != "authorization_code" {
+        return token_error("unsupported_grant_type");
+    }
+    // Codes are single use, even when the exchange fails
+    let pending = state
+        .codes
+        .lock()
+        .expect("mock provider lock poisoned")
+        .remove(&params.code);
+    let Some(pending) = pending else {
+        return token_error("invalid_grant");
+    };
+    if pending.client_id != params.client_id || pending.redirect_uri != params.redirect_uri {
+        return token_error("invalid_grant");
+    }
+    if Pkce::challenge_for(&params.code_verifier) != pending.code_challenge {
+        return token_error("invalid_grant");
+    }
+
+    let now = Utc::now().timestamp();
+    let claims = IdTokenClaims {
+        iss: state.issuer.clone(),
+        sub: pending.user.clone(),
+        aud: Audience::One(state.client_id.clone()),
+        exp: now + 300,
+        iat: now,
+        nonce: pending.nonce,
+        email: Some(format!("{}@example.com", pending.user)),
+        email_verified: Some(true),
+        preferred_username: Some(pending.user.clone()),
+        name: Some(pending.user),
+    };
+    let id_token = match sign_id_token(&mut uchat_crypto::new_rng(), &state.key, KEY_ID, &claims) {
+        Ok(id_token) => id_token,
+        Err(e) => {
+            tracing::error!("Failed to sign mock ID token: {:?}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "signing failed").into_response();
+        }
+    };
+
+    Json(json!({
+        "access_token": random_token(&mut uchat_crypto::new_rng()),
+        "token_type": "Bearer",
+        "expires_in": 300,
+        "id_token": id_token,
+    }))
+    .into_response()
+}
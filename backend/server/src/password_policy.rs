@@ -0,0 +1,139 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::Context;
+use uchat_domain::password_policy::{PasswordPolicy, PasswordProblem};
+
+/// Bloom filter of common and breached passwords.
+///
+/// Lists with millions of entries fit in a few megabytes this way. A false
+/// positive only means a password gets rejected as common, which is fine.
+#[derive(Debug, Clone)]
+pub struct Blocklist {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl Blocklist {
+    /// Chance that a password which isn't listed is reported as listed.
+    pub const FALSE_POSITIVE_RATE: f64 = 0.001;
+
+    pub fn from_passwords<I, S>(passwords: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let passwords = passwords
+            .into_iter()
+            .map(|password| normalize(password.as_ref()))
+            .filter(|password| !password.is_empty())
+            .collect::<Vec<_>>();
+
+        let n = passwords.len().max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-n * Self::FALSE_POSITIVE_RATE.ln() / (ln2 * ln2)).ceil() as u64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().max(1.0) as u32;
+
+        let mut blocklist = Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+        };
+        for password in passwords {
+            let positions = blocklist.positions(&password).collect::<Vec<_>>();
+            for bit in positions {
+                blocklist.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+            }
+        }
+        blocklist
+    }
+
+    /// Reads a file with one password per line.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read password blocklist {}", path.display()))?;
+        Ok(Self::from_passwords(content.lines()))
+    }
+
+    pub fn contains(&self, password: &str) -> bool {
+        let password = normalize(password);
+        self.positions(&password)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    fn positions(&self, password: &str) -> impl Iterator<Item = u64> + '_ {
+        // Double hashing: the k positions are derived from two base hashes
+        let h1 = hash_with_seed(password, 0);
+        let h2 = hash_with_seed(password, 1) | 1;
+        (0..self.num_hashes as u64)
+            .map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+}
+
+fn normalize(password: &str) -> String {
+    password.trim().to_lowercase()
+}
+
+fn hash_with_seed(value: &str, seed: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Applies the [`PasswordPolicy`] and the optional blocklist.
+#[derive(Debug, Clone, Default)]
+pub struct PasswordChecker {
+    pub policy: PasswordPolicy,
+    pub blocklist: Option<Arc<Blocklist>>,
+}
+
+impl PasswordChecker {
+    pub fn check(&self, password: &str, handle: Option<&str>) -> Vec<PasswordProblem> {
+        let mut problems = self.policy.check(password, handle);
+        if let Some(blocklist) = &self.blocklist {
+            if blocklist.contains(password) {
+                problems.push(PasswordProblem::Common);
+            }
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocklist_finds_listed_passwords() {
+        let listed = ["123456", "password", "qwerty", "iloveyou"];
+        let blocklist = Blocklist::from_passwords(listed);
+
+        for password in listed {
+            assert!(blocklist.contains(password));
+        }
+        assert!(blocklist.contains(" PassWord "));
+        assert!(!blocklist.contains("correct horse battery staple"));
+    }
+
+    #[test]
+    fn checker_reports_common_passwords() {
+        let checker = PasswordChecker {
+            blocklist: Some(Arc::new(Blocklist::from_passwords([
+                "correct horse battery",
+            ]))),
+            ..PasswordChecker::default()
+        };
+
+        assert_eq!(
+            checker.check("correct horse battery", None),
+            vec![PasswordProblem::Common]
+        );
+        assert!(checker.check("staple horse battery", None).is_empty());
+    }
+}
@@ -0,0 +1,414 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, Request, State},
+    http::{header::RETRY_AFTER, request::Parts, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json, RequestPartsExt,
+};
+use uchat_endpoint::{
+    moderation::endpoint::{ReportPost, ReportUser},
+    post::endpoint::{NewPost, NewThread, PublishDraft},
+    user::endpoint::{
+        CheckPassword, CompleteOidcSignup, CreateUser, FinishOidcLogin, Login, StartOidcLogin,
+        VerifyTwoFactor,
+    },
+    Endpoint, RequestFailed,
+};
+
+use crate::{extractor::UserSession, AppState};
+
+/// Token bucket parameters: `burst` requests, refilled evenly over `period`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    pub const fn new(burst: u32, period: Duration) -> Self {
+        Self { burst, period }
+    }
+
+    pub const fn per_minute(burst: u32) -> Self {
+        Self::new(burst, Duration::from_secs(60))
+    }
+
+    pub const fn per_hour(burst: u32) -> Self {
+        Self::new(burst, Duration::from_secs(60 * 60))
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.burst as f64 / self.period.as_secs_f64()
+    }
+}
+
+/// Limits applied to a single endpoint.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EndpointLimits {
+    pub per_ip: Option<RateLimit>,
+    pub per_user: Option<RateLimit>,
+}
+
+impl EndpointLimits {
+    pub fn per_ip(mut self, limit: RateLimit) -> Self {
+        self.per_ip = Some(limit);
+        self
+    }
+
+    pub fn per_user(mut self, limit: RateLimit) -> Self {
+        self.per_user = Some(limit);
+        self
+    }
+}
+
+//------------------------------------------------------------------------------
+/// Storage for token buckets.
+///
+/// The in-memory store only works for a single server. When running several
+/// instances, implement this on top of a shared store so limits are global.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Takes a token from the bucket `key`. Returns how long to wait until a
+    /// token is available if the bucket is empty.
+    async fn take(&self, key: &str, limit: RateLimit) -> anyhow::Result<Option<Duration>>;
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn full(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst as f64,
+            updated_at: now,
+        }
+    }
+
+    fn refill(&mut self, limit: RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.refill_per_sec()).min(limit.burst as f64);
+        self.updated_at = now;
+    }
+
+    fn take(&mut self, limit: RateLimit, now: Instant) -> Option<Duration> {
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            let missing = 1.0 - self.tokens;
+            Some(Duration::from_secs_f64(missing / limit.refill_per_sec()))
+        }
+    }
+}
+
+/// Keeps buckets in process memory.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    buckets: Mutex<HashMap<String, (Bucket, RateLimit)>>,
+}
+
+impl MemoryStore {
+    /// How often idle buckets get dropped.
+    const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+    fn take_at(&self, key: &str, limit: RateLimit, now: Instant) -> Option<Duration> {
+        let mut buckets = self.buckets.lock().expect("rate limit lock poisoned");
+        let (bucket, _) = buckets
+            .entry(key.to_string())
+            .or_insert_with(|| (Bucket::full(limit, now), limit));
+        bucket.take(limit, now)
+    }
+
+    /// Drops buckets untouched for a whole period. Those are full again, so
+    /// a fresh bucket behaves the same.
+    fn prune(&self, now: Instant) {
+        let mut buckets = self.buckets.lock().expect("rate limit lock poisoned");
+        buckets.retain(|_, (bucket, limit)| {
+            now.saturating_duration_since(bucket.updated_at) < limit.period
+        });
+    }
+
+    /// Prunes `store` every [`Self::PRUNE_INTERVAL`] until it is dropped.
+    fn spawn_pruning(store: &Arc<Self>) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let store = Arc::downgrade(store);
+        runtime.spawn(async move {
+            let mut interval = tokio::time::interval(Self::PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                match Weak::upgrade(&store) {
+                    Some(store) => store.prune(Instant::now()),
+                    None => break,
+                }
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn take(&self, key: &str, limit: RateLimit) -> anyhow::Result<Option<Duration>> {
+        Ok(self.take_at(key, limit, Instant::now()))
+    }
+}
+
+//------------------------------------------------------------------------------
+#[derive(Clone)]
+pub struct RateLimiter {
+    store: Arc<dyn RateLimitStore>,
+    limits: Arc<HashMap<&'static str, EndpointLimits>>,
+}
+
+impl RateLimiter {
+    pub fn new(store: Arc<dyn RateLimitStore>) -> Self {
+        Self {
+            store,
+            limits: Arc::new(default_limits()),
+        }
+    }
+
+    pub fn in_memory() -> Self {
+        let store = Arc::new(MemoryStore::default());
+        MemoryStore::spawn_pruning(&store);
+        Self::new(store)
+    }
+
+    /// Replaces the limits of the endpoint at `url`.
+    pub fn with_limits(mut self, url: &'static str, limits: EndpointLimits) -> Self {
+        Arc::make_mut(&mut self.limits).insert(url, limits);
+        self
+    }
+
+    pub fn limits(&self, url: &str) -> Option<EndpointLimits> {
+        self.limits.get(url).copied()
+    }
+
+    async fn take(&self, key: String, limit: RateLimit) -> Option<Duration> {
+        match self.store.take(&key, limit).await {
+            Ok(wait) => wait,
+            Err(e) => {
+                // Failing open keeps the site usable when a shared store is down.
+                tracing::error!(key = %key, "Rate limit store failed: {:?}", e);
+                None
+            }
+        }
+    }
+}
+
+pub fn default_limits() -> HashMap<&'static str, EndpointLimits> {
+    HashMap::from([
+        (
+            CreateUser::URL,
+            EndpointLimits::default().per_ip(RateLimit::per_hour(5)),
+        ),
+        (
+            Login::URL,
+            EndpointLimits::default().per_ip(RateLimit::per_minute(10)),
+        ),
+        (
+            VerifyTwoFactor::URL,
+            EndpointLimits::default().per_ip(RateLimit::per_minute(10)),
+        ),
+        (
+            StartOidcLogin::URL,
+            EndpointLimits::default().per_ip(RateLimit::per_minute(10)),
+        ),
+        (
+            FinishOidcLogin::URL,
+            EndpointLimits::default().per_ip(RateLimit::per_minute(10)),
+        ),
+        (
+            CompleteOidcSignup::URL,
+            EndpointLimits::default().per_ip(RateLimit::per_hour(5)),
+        ),
+        (
+            CheckPassword::URL,
+            EndpointLimits::default().per_ip(RateLimit::per_minute(30)),
+        ),
+        (
+            ReportPost::URL,
+            EndpointLimits::default().per_user(RateLimit::per_hour(30)),
+        ),
+        (
+            ReportUser::URL,
+            EndpointLimits::default().per_user(RateLimit::per_hour(30)),
+        ),
+        (
+            NewPost::URL,
+            EndpointLimits::default()
+                .per_ip(RateLimit::per_minute(60))
+                .per_user(RateLimit::per_minute(10)),
+        ),
+        (
+            PublishDraft::URL,
+            EndpointLimits::default()
+                .per_ip(RateLimit::per_minute(60))
+                .per_user(RateLimit::per_minute(10)),
+        ),
+        (
+            NewThread::URL,
+            EndpointLimits::default()
+                .per_ip(RateLimit::per_minute(30))
+                .per_user(RateLimit::per_minute(5)),
+        ),
+    ])
+}
+
+/// Address of the client, taken from `X-Forwarded-For` when `trust_forwarded`.
+///
+/// Only the rightmost hop is used: it is the one appended by the trusted
+/// proxy, while anything left of it is whatever the client chose to send.
+pub fn client_ip(parts: &Parts, trust_forwarded: bool) -> Option<IpAddr> {
+    if trust_forwarded {
+        let forwarded = parts
+            .headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .next_back()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+        if forwarded.is_some() {
+            return forwarded;
+        }
+    }
+    parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+}
+
+fn too_many_requests(wait: Duration) -> Response {
+    let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(RETRY_AFTER, secs.to_string())],
+        Json(RequestFailed {
+            msg: format!("Too many requests, try again in {secs} seconds"),
+        }),
+    )
+        .into_response()
+}
+
+/// Applies the per-endpoint limits of [`RateLimiter`] to incoming requests.
+///
+/// Per-user limits need the session, so the extracted [`UserSession`] is kept
+/// in the request extensions for the handler to reuse.
+pub async fn rate_limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let config = &state.config.rate_limit;
+    let limiter = &state.rate_limiter;
+    let url = request.uri().path().to_string();
+
+    let limits = match limiter.limits(&url) {
+        Some(limits) if !config.rate_limit_disabled => limits,
+        _ => return next.run(request).await,
+    };
+
+    let (mut parts, body) = request.into_parts();
+
+    if let Some(limit) = limits.per_ip {
+        if let Some(ip) = client_ip(&parts, config.rate_limit_trust_forwarded) {
+            if let Some(wait) = limiter.take(format!("{url}|ip|{ip}"), limit).await {
+                tracing::info!(url = %url, ip = %ip, "Rate limited by ip");
+                return too_many_requests(wait);
+            }
+        }
+    }
+
+    if let Some(limit) = limits.per_user {
+        if let Ok(session) = parts.extract::<UserSession>().await {
+            parts.extensions.insert(session);
+            let key = format!("{url}|user|{}", session.user_id);
+            if let Some(wait) = limiter.take(key, limit).await {
+                tracing::info!(url = %url, user_id = ?session.user_id, "Rate limited by user");
+                return too_many_requests(wait);
+            }
+        }
+    }
+
+    next.run(Request::from_parts(parts, body)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_burst_then_waits() {
+        let limit = RateLimit::per_minute(3);
+        let now = Instant::now();
+        let mut bucket = Bucket::full(limit, now);
+
+        for _ in 0..3 {
+            assert_eq!(bucket.take(limit, now), None);
+        }
+        let wait = bucket.take(limit, now).unwrap();
+        assert_eq!(wait.as_secs_f64().round(), 20.0);
+
+        let later = now + Duration::from_secs(21);
+        assert_eq!(bucket.take(limit, later), None);
+        assert!(bucket.take(limit, later).is_some());
+    }
+
+    #[test]
+    fn memory_store_keeps_keys_apart() {
+        let store = MemoryStore::default();
+        let limit = RateLimit::per_minute(1);
+        let now = Instant::now();
+
+        assert_eq!(store.take_at("a", limit, now), None);
+        assert!(store.take_at("a", limit, now).is_some());
+        assert_eq!(store.take_at("b", limit, now), None);
+    }
+
+    #[test]
+    fn memory_store_prunes_idle_buckets() {
+        let store = MemoryStore::default();
+        let limit = RateLimit::per_minute(1);
+        let now = Instant::now();
+
+        assert_eq!(store.take_at("a", limit, now), None);
+        let later = now + Duration::from_secs(30);
+        assert_eq!(store.take_at("b", limit, later), None);
+
+        store.prune(now + Duration::from_secs(60));
+        let buckets = store.buckets.lock().unwrap();
+        assert!(!buckets.contains_key("a"));
+        assert!(buckets.contains_key("b"));
+    }
+
+    #[test]
+    fn client_ip_uses_rightmost_forwarded_hop() {
+        let (parts, _) = axum::http::Request::builder()
+            .header("x-forwarded-for", "10.0.0.1, 203.0.113.7")
+            .body(())
+            .unwrap()
+            .into_parts();
+        assert_eq!(
+            client_ip(&parts, true),
+            Some("203.0.113.7".parse().unwrap())
+        );
+        assert_eq!(client_ip(&parts, false), None);
+    }
+
+    #[test]
+    fn retry_after_is_rounded_up() {
+        let response = too_many_requests(Duration::from_millis(1500));
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "2");
+    }
+}
@@ -1,6 +1,10 @@
 use axum::{
     extract::DefaultBodyLimit,
-    http::{header::CONTENT_TYPE, HeaderValue, Method},
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER},
+        HeaderValue, Method,
+    },
+    middleware,
     routing::{get, post},
     Router,
 };
@@ -15,17 +19,37 @@ use tower_http::{
 };
 use tracing::Level;
 use uchat_endpoint::{
+    analytics::endpoint::{PostAnalytics, ProfileAnalytics},
+    moderation::endpoint::{
+        GetModerationLog, GetModerationQueue, ReportPost, ReportUser, TakeModerationAction,
+    },
     post::endpoint::{
-        Bookmark, BookmarkedPost, Boost, HomePost, LikedPost, NewPost, React, TrendingPost, Vote,
+        Bookmark, BookmarkedPost, Boost, CreateBookmarkFolder, DeleteBookmarkFolder, DeleteDraft,
+        GetBookmarkFolders, HomePost, LikedPost, ListDrafts, ListTimeline, MoveBookmark, NewPost,
+        NewThread, PublishDraft, React, RenameBookmarkFolder, SaveDraft, ServerLimits,
+        SetBookmarkNote, TrendingPost, Vote,
+    },
+    user::endpoint::{
+        AddListMember, CheckPassword, CompleteOidcSignup, ConfirmTotp, CreateApiToken, CreateList,
+        CreateMuteFilter, CreateUser, DeleteList, DeleteMuteFilter, DisableTotp, EnrollTotp,
+        FinishOidcLogin, FollowUser, GetList, GetListMemberships, GetLists, GetMyProfile,
+        GetSecurityEvents, GetTwoFactorStatus, ListApiTokens, ListIdentities, ListMuteFilters,
+        ListOidcProviders, Login, Logout, RemoveListMember, RevokeApiToken, StartOidcLink,
+        StartOidcLogin, UnlinkIdentity, UpdateList, UpdateProfile, VerifyTwoFactor, ViewProfile,
     },
-    user::endpoint::{CreateUser, FollowUser, GetMyProfile, Login, UpdateProfile, ViewProfile},
     Endpoint,
 };
 
 use uchat_endpoint::app_url::user_content;
 
 use crate::{
-    handler::{load_image, with_handler, with_public_handler},
+    handler::{
+        load_image,
+        oidc::{complete_signup, finish_login},
+        with_handler, with_public_handler,
+    },
+    rate_limit::rate_limit,
+    session::refresh_cookies,
     AppState,
 };
 
@@ -33,12 +57,37 @@ pub async fn new_router(state: AppState) -> Router {
     let image_route = format!("{}{}", user_content::ROOT, user_content::IMAGE);
     let public_router = Router::new()
         .route("/", get(move || async { "This is a route page" }))
-        .route(&format!("/{}:id", image_route), get(load_image))
+        .route(&format!("/{}:key", image_route), get(load_image))
         .route(CreateUser::URL, post(with_public_handler::<CreateUser>))
-        .route(Login::URL, post(with_public_handler::<Login>));
+        .route(Login::URL, post(with_public_handler::<Login>))
+        .route(
+            CheckPassword::URL,
+            post(with_public_handler::<CheckPassword>),
+        )
+        .route(
+            VerifyTwoFactor::URL,
+            post(with_public_handler::<VerifyTwoFactor>),
+        )
+        .route(
+            ListOidcProviders::URL,
+            post(with_public_handler::<ListOidcProviders>),
+        )
+        .route(
+            StartOidcLogin::URL,
+            post(with_public_handler::<StartOidcLogin>),
+        )
+        .route(FinishOidcLogin::URL, post(finish_login))
+        .route(CompleteOidcSignup::URL, post(complete_signup))
+        .route(ServerLimits::URL, post(with_public_handler::<ServerLimits>))
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit));
 
     let authorized_router = Router::new()
         .route(NewPost::URL, post(with_handler::<NewPost>))
+        .route(NewThread::URL, post(with_handler::<NewThread>))
+        .route(SaveDraft::URL, post(with_handler::<SaveDraft>))
+        .route(ListDrafts::URL, post(with_handler::<ListDrafts>))
+        .route(DeleteDraft::URL, post(with_handler::<DeleteDraft>))
+        .route(PublishDraft::URL, post(with_handler::<PublishDraft>))
         .route(Bookmark::URL, post(with_handler::<Bookmark>))
         .route(Boost::URL, post(with_handler::<Boost>))
         .route(Vote::URL, post(with_handler::<Vote>))
@@ -51,8 +100,89 @@ pub async fn new_router(state: AppState) -> Router {
         .route(HomePost::URL, post(with_handler::<HomePost>))
         .route(LikedPost::URL, post(with_handler::<LikedPost>))
         .route(BookmarkedPost::URL, post(with_handler::<BookmarkedPost>))
+        .route(ListTimeline::URL, post(with_handler::<ListTimeline>))
+        .route(
+            GetBookmarkFolders::URL,
+            post(with_handler::<GetBookmarkFolders>),
+        )
+        .route(
+            CreateBookmarkFolder::URL,
+            post(with_handler::<CreateBookmarkFolder>),
+        )
+        .route(
+            RenameBookmarkFolder::URL,
+            post(with_handler::<RenameBookmarkFolder>),
+        )
+        .route(
+            DeleteBookmarkFolder::URL,
+            post(with_handler::<DeleteBookmarkFolder>),
+        )
+        .route(MoveBookmark::URL, post(with_handler::<MoveBookmark>))
+        .route(SetBookmarkNote::URL, post(with_handler::<SetBookmarkNote>))
+        .route(CreateList::URL, post(with_handler::<CreateList>))
+        .route(UpdateList::URL, post(with_handler::<UpdateList>))
+        .route(DeleteList::URL, post(with_handler::<DeleteList>))
+        .route(GetLists::URL, post(with_handler::<GetLists>))
+        .route(GetList::URL, post(with_handler::<GetList>))
+        .route(AddListMember::URL, post(with_handler::<AddListMember>))
+        .route(
+            RemoveListMember::URL,
+            post(with_handler::<RemoveListMember>),
+        )
+        .route(
+            GetListMemberships::URL,
+            post(with_handler::<GetListMemberships>),
+        )
+        .route(
+            GetTwoFactorStatus::URL,
+            post(with_handler::<GetTwoFactorStatus>),
+        )
+        .route(EnrollTotp::URL, post(with_handler::<EnrollTotp>))
+        .route(ConfirmTotp::URL, post(with_handler::<ConfirmTotp>))
+        .route(DisableTotp::URL, post(with_handler::<DisableTotp>))
+        .route(Logout::URL, post(with_handler::<Logout>))
+        .route(CreateApiToken::URL, post(with_handler::<CreateApiToken>))
+        .route(ListApiTokens::URL, post(with_handler::<ListApiTokens>))
+        .route(RevokeApiToken::URL, post(with_handler::<RevokeApiToken>))
+        .route(StartOidcLink::URL, post(with_handler::<StartOidcLink>))
+        .route(ListIdentities::URL, post(with_handler::<ListIdentities>))
+        .route(UnlinkIdentity::URL, post(with_handler::<UnlinkIdentity>))
+        .route(
+            GetSecurityEvents::URL,
+            post(with_handler::<GetSecurityEvents>),
+        )
+        .route(
+            CreateMuteFilter::URL,
+            post(with_handler::<CreateMuteFilter>),
+        )
+        .route(ListMuteFilters::URL, post(with_handler::<ListMuteFilters>))
+        .route(
+            DeleteMuteFilter::URL,
+            post(with_handler::<DeleteMuteFilter>),
+        )
+        .route(ReportPost::URL, post(with_handler::<ReportPost>))
+        .route(ReportUser::URL, post(with_handler::<ReportUser>))
+        .route(
+            GetModerationQueue::URL,
+            post(with_handler::<GetModerationQueue>),
+        )
+        .route(
+            TakeModerationAction::URL,
+            post(with_handler::<TakeModerationAction>),
+        )
+        .route(
+            GetModerationLog::URL,
+            post(with_handler::<GetModerationLog>),
+        )
+        .route(PostAnalytics::URL, post(with_handler::<PostAnalytics>))
+        .route(
+            ProfileAnalytics::URL,
+            post(with_handler::<ProfileAnalytics>),
+        )
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit))
+        .layer(middleware::from_fn(refresh_cookies))
         .layer(DefaultBodyLimit::disable())
-        .layer(RequestBodyLimitLayer::new(8 * 1024 * 1024))
+        .layer(RequestBodyLimitLayer::new(state.config.body_limit()))
         .layer(CompressionLayer::new());
 
     Router::new()
@@ -81,7 +211,8 @@ pub async fn new_router(state: AppState) -> Router {
                                 .parse::<HeaderValue>()
                                 .unwrap(),
                         )
-                        .allow_headers(vec![CONTENT_TYPE]),
+                        .allow_headers(vec![AUTHORIZATION, CONTENT_TYPE])
+                        .expose_headers(vec![RETRY_AFTER]),
                 )
                 .layer(axum::Extension(state.clone())),
         )
@@ -0,0 +1,92 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::Request,
+    http::{header::SET_COOKIE, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use uchat_query::SessionId;
+
+use crate::AppState;
+
+#[derive(Debug, Clone)]
+pub struct SessionSignature(pub String);
+
+/// Signs `session_id` with the active key. The resulting signature carries
+/// the key id so it can still be verified after the active key is rotated.
+pub fn sign_session(state: &AppState, session_id: SessionId) -> SessionSignature {
+    let mut rng = uchat_crypto::new_rng();
+    let signature = state
+        .signing_keys
+        .sign(&mut rng, session_id.as_uuid().as_bytes());
+    SessionSignature(signature.encode())
+}
+
+/// A session that got extended while handling the current request.
+#[derive(Debug, Clone)]
+pub struct RefreshedSession {
+    pub session_id: SessionId,
+    pub signature: SessionSignature,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl RefreshedSession {
+    pub fn cookies(&self) -> [String; 2] {
+        [
+            session_cookie(
+                uchat_cookie::SESSION_ID,
+                self.session_id.to_string(),
+                self.expires_at,
+            ),
+            session_cookie(
+                uchat_cookie::SESSION_SIGNATURE,
+                &self.signature.0,
+                self.expires_at,
+            ),
+        ]
+    }
+}
+
+fn session_cookie<V: AsRef<str>>(key: &str, value: V, expires: DateTime<Utc>) -> String {
+    let value = value.as_ref();
+    let expires = expires.format("%a, %d %b %Y %T GMT");
+    format!("{key}={value}; expires={expires}; SameSite=Strict; Path=/")
+}
+
+/// Slot shared between [`refresh_cookies`] and the `UserSession` extractor.
+///
+/// Extractors cannot touch the response, so the extractor leaves the
+/// refreshed session here and the middleware turns it into cookies.
+#[derive(Debug, Clone, Default)]
+pub struct SessionRefresh(Arc<Mutex<Option<RefreshedSession>>>);
+
+impl SessionRefresh {
+    pub fn set(&self, refreshed: RefreshedSession) {
+        *self.0.lock().expect("session refresh lock poisoned") = Some(refreshed);
+    }
+
+    pub fn take(&self) -> Option<RefreshedSession> {
+        self.0.lock().expect("session refresh lock poisoned").take()
+    }
+}
+
+pub async fn refresh_cookies(mut request: Request, next: Next) -> Response {
+    let refresh = SessionRefresh::default();
+    request.extensions_mut().insert(refresh.clone());
+
+    let mut response = next.run(request).await;
+
+    if let Some(refreshed) = refresh.take() {
+        for cookie in refreshed.cookies() {
+            match HeaderValue::from_str(&cookie) {
+                Ok(value) => {
+                    response.headers_mut().append(SET_COOKIE, value);
+                }
+                Err(e) => tracing::error!("Failed to build session cookie: {:?}", e),
+            }
+        }
+    }
+    response
+}
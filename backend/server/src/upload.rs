@@ -0,0 +1,397 @@
+//! Checking and storing uploaded images.
+//!
+//! Clients send images as base64 data URLs. They are decoded once, checked
+//! against the allowed formats and limits, stripped of metadata and put in
+//! the media store as plain binary, along with resized [variants]. Metadata
+//! rows go to the `images` and `image_variants` tables.
+
+use std::{io::Cursor, path::Path};
+
+use axum::http::StatusCode;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use diesel_async::AsyncPgConnection;
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use uchat_domain::{ImageId, UserId};
+
+use crate::media::Media;
+
+pub mod gc;
+pub mod variant;
+pub mod video;
+
+use variant::Variant;
+
+/// Image formats accepted for upload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn mime(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+        }
+    }
+
+    fn from_decoder(format: image::ImageFormat) -> Option<Self> {
+        match format {
+            image::ImageFormat::Png => Some(Self::Png),
+            image::ImageFormat::Jpeg => Some(Self::Jpeg),
+            image::ImageFormat::Gif => Some(Self::Gif),
+            image::ImageFormat::WebP => Some(Self::Webp),
+            _ => None,
+        }
+    }
+
+    fn to_decoder(self) -> image::ImageFormat {
+        match self {
+            Self::Png => image::ImageFormat::Png,
+            Self::Jpeg => image::ImageFormat::Jpeg,
+            Self::Gif => image::ImageFormat::Gif,
+            Self::Webp => image::ImageFormat::WebP,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum UploadError {
+    #[error("Images must be sent as a base64 data URL")]
+    NotDataUrl,
+    #[error("Image data is not valid base64")]
+    InvalidBase64,
+    #[error("Unsupported image format, use PNG, JPEG, GIF or WebP")]
+    UnsupportedFormat,
+    #[error("Image file is damaged")]
+    Unreadable,
+    #[error("Images may be at most {max_bytes} bytes")]
+    TooLarge { max_bytes: usize },
+    #[error("Images may be at most {max_dimension} pixels wide and high")]
+    TooManyPixels { max_dimension: u32 },
+    #[error("Image could not be processed")]
+    EncodingFailed,
+}
+
+impl UploadError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::EncodingFailed => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageLimits {
+    pub max_bytes: usize,
+    pub max_dimension: u32,
+}
+
+/// Image which passed all checks and can be stored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckedImage {
+    pub bytes: Vec<u8>,
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Decodes the payload of a base64 data URL. The MIME type declared in the
+/// URL is ignored, the format is sniffed from the bytes instead.
+pub fn decode_data_url(data_url: &str, max_bytes: usize) -> Result<Vec<u8>, UploadError> {
+    let (header, data) = data_url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(','))
+        .ok_or(UploadError::NotDataUrl)?;
+    if !header.ends_with(";base64") {
+        return Err(UploadError::NotDataUrl);
+    }
+    // Reject before decoding, every 4 characters hold 3 bytes
+    if data.len() / 4 * 3 > max_bytes.saturating_add(2) {
+        return Err(UploadError::TooLarge { max_bytes });
+    }
+    general_purpose::STANDARD
+        .decode(data.trim_end())
+        .map_err(|_| UploadError::InvalidBase64)
+}
+
+/// Sniffs the format of `bytes` and checks it against the limits.
+pub fn inspect(bytes: Vec<u8>, limits: &ImageLimits) -> Result<CheckedImage, UploadError> {
+    if bytes.len() > limits.max_bytes {
+        return Err(UploadError::TooLarge {
+            max_bytes: limits.max_bytes,
+        });
+    }
+    let format = image::guess_format(&bytes)
+        .ok()
+        .and_then(ImageFormat::from_decoder)
+        .ok_or(UploadError::UnsupportedFormat)?;
+    let (width, height) = image::ImageReader::with_format(Cursor::new(&bytes), format.to_decoder())
+        .into_dimensions()
+        .map_err(|_| UploadError::Unreadable)?;
+    if width == 0 || height == 0 || width > limits.max_dimension || height > limits.max_dimension {
+        return Err(UploadError::TooManyPixels {
+            max_dimension: limits.max_dimension,
+        });
+    }
+    Ok(CheckedImage {
+        bytes,
+        format,
+        width,
+        height,
+    })
+}
+
+/// Image ready to be stored, with its variants.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreparedImage {
+    pub image: CheckedImage,
+    pub variants: Vec<Variant>,
+}
+
+/// Checks `bytes` and removes their metadata. Slow for large images, so run
+/// it on a blocking thread.
+pub fn check(bytes: Vec<u8>, limits: &ImageLimits) -> Result<CheckedImage, UploadError> {
+    variant::strip_metadata(inspect(bytes, limits)?)
+}
+
+/// Checks `bytes`, removes their metadata and renders the variants. Slow
+/// for large images, so run it on a blocking thread.
+pub fn prepare(
+    bytes: Vec<u8>,
+    limits: &ImageLimits,
+    webp_variants: bool,
+) -> Result<PreparedImage, UploadError> {
+    let image = check(bytes, limits)?;
+    let variants = variant::render(&image, webp_variants)?;
+    Ok(PreparedImage { image, variants })
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Metadata row describing `image`.
+pub fn metadata(
+    image_id: ImageId,
+    owner: Option<UserId>,
+    image: &CheckedImage,
+) -> uchat_query::image::Image {
+    let now = Utc::now();
+    uchat_query::image::Image {
+        id: image_id,
+        user_id: owner,
+        mime: image.format.mime().to_string(),
+        byte_size: image.bytes.len() as i64,
+        width: image.width as i32,
+        height: image.height as i32,
+        sha256: sha256_hex(&image.bytes),
+        created_at: now,
+        used_at: now,
+    }
+}
+
+/// Puts the image and its variants in the media store and records their
+/// metadata. An image which is already stored is used again instead.
+pub async fn store(
+    conn: &mut AsyncPgConnection,
+    media: &Media,
+    owner: UserId,
+    image: CheckedImage,
+    webp_variants: bool,
+) -> anyhow::Result<ImageId> {
+    if let Some(stored) =
+        uchat_query::image::find_by_sha256(conn, &sha256_hex(&image.bytes)).await?
+    {
+        tracing::info!(image_id = ?stored.id, "Image is already stored");
+        uchat_query::image::mark_used(conn, stored.id).await?;
+        return Ok(stored.id);
+    }
+    let prepared = tokio::task::spawn_blocking(move || {
+        variant::render(&image, webp_variants).map(|variants| PreparedImage { image, variants })
+    })
+    .await??;
+
+    let image_id = ImageId::new();
+    let keys = std::iter::once(image_id.to_string())
+        .chain(
+            prepared
+                .variants
+                .iter()
+                .map(|variant| variant::key(image_id, variant.width)),
+        )
+        .collect::<Vec<_>>();
+    if let Err(e) = put(conn, media, image_id, Some(owner), prepared).await {
+        // Don't leave objects behind which nothing knows about
+        for key in keys {
+            let _ = media.delete(&key).await;
+        }
+        return Err(e);
+    }
+    Ok(image_id)
+}
+
+async fn put(
+    conn: &mut AsyncPgConnection,
+    media: &Media,
+    image_id: ImageId,
+    owner: Option<UserId>,
+    prepared: PreparedImage,
+) -> anyhow::Result<()> {
+    let row = metadata(image_id, owner, &prepared.image);
+    let variant_rows = prepared
+        .variants
+        .iter()
+        .map(|variant| uchat_query::image::ImageVariant {
+            image_id,
+            width: variant.width as i32,
+            height: variant.height as i32,
+            mime: variant.format.mime().to_string(),
+            byte_size: variant.bytes.len() as i64,
+        })
+        .collect::<Vec<_>>();
+
+    media
+        .put(&image_id.to_string(), prepared.image.bytes, &row.mime)
+        .await?;
+    for variant in prepared.variants {
+        let key = variant::key(image_id, variant.width);
+        media
+            .put(&key, variant.bytes, variant.format.mime())
+            .await?;
+    }
+    uchat_query::image::new(conn, &row, &variant_rows).await?;
+    Ok(())
+}
+
+/// Outcome of migrating one file of the user content directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Migration {
+    Converted,
+    AlreadyStored,
+    Skipped(String),
+}
+
+/// Converts an image which older versions stored in `legacy_dir` as data URL
+/// text into binary, puts it in the media store with its variants and
+/// records their metadata.
+/// Limits don't apply to these files, as they were accepted before. Running
+/// it again on the same file is safe.
+pub async fn migrate_legacy(
+    conn: &mut AsyncPgConnection,
+    media: &Media,
+    legacy_dir: &Path,
+    image_id: ImageId,
+    webp_variants: bool,
+) -> anyhow::Result<Migration> {
+    if uchat_query::image::get(conn, image_id).await?.is_some() {
+        return Ok(Migration::AlreadyStored);
+    }
+
+    let key = image_id.to_string();
+    let raw = fs::read(legacy_dir.join(&key)).await?;
+    // A previous run may have converted the file but failed to record it
+    let bytes = match std::str::from_utf8(&raw) {
+        Ok(text) if text.starts_with("data:") => match decode_data_url(text.trim(), usize::MAX) {
+            Ok(bytes) => bytes,
+            Err(e) => return Ok(Migration::Skipped(e.to_string())),
+        },
+        _ => raw,
+    };
+    let limits = ImageLimits {
+        max_bytes: usize::MAX,
+        max_dimension: u32::MAX,
+    };
+    let prepared =
+        match tokio::task::spawn_blocking(move || prepare(bytes, &limits, webp_variants)).await? {
+            Ok(prepared) => prepared,
+            Err(e) => return Ok(Migration::Skipped(e.to_string())),
+        };
+    let owner = uchat_query::image::find_legacy_owner(conn, image_id).await?;
+
+    put(conn, media, image_id, owner, prepared).await?;
+    Ok(Migration::Converted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1x1 transparent PNG.
+    const PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+
+    const LIMITS: ImageLimits = ImageLimits {
+        max_bytes: 1024,
+        max_dimension: 16,
+    };
+
+    fn png_bytes() -> Vec<u8> {
+        general_purpose::STANDARD.decode(PNG).unwrap()
+    }
+
+    #[test]
+    fn declared_mime_is_ignored() {
+        let data_url = format!("data:image/gif;base64,{PNG}");
+        let bytes = decode_data_url(&data_url, LIMITS.max_bytes).unwrap();
+        let image = inspect(bytes, &LIMITS).unwrap();
+        assert_eq!(image.format, ImageFormat::Png);
+        assert_eq!((image.width, image.height), (1, 1));
+    }
+
+    #[test]
+    fn rejects_malformed_data_urls() {
+        assert_eq!(
+            decode_data_url(PNG, LIMITS.max_bytes),
+            Err(UploadError::NotDataUrl)
+        );
+        assert_eq!(
+            decode_data_url(&format!("data:image/png,{PNG}"), LIMITS.max_bytes),
+            Err(UploadError::NotDataUrl)
+        );
+        assert_eq!(
+            decode_data_url("data:image/png;base64,not base64!", LIMITS.max_bytes),
+            Err(UploadError::InvalidBase64)
+        );
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        let svg = b"<svg xmlns=\"http://www.w3.org/2000/svg\"></svg>".to_vec();
+        assert_eq!(inspect(svg, &LIMITS), Err(UploadError::UnsupportedFormat));
+    }
+
+    #[test]
+    fn enforces_limits() {
+        let small = ImageLimits {
+            max_bytes: 10,
+            ..LIMITS
+        };
+        assert_eq!(
+            inspect(png_bytes(), &small),
+            Err(UploadError::TooLarge { max_bytes: 10 })
+        );
+        let data_url = format!("data:image/png;base64,{PNG}");
+        assert_eq!(
+            decode_data_url(&data_url, 10),
+            Err(UploadError::TooLarge { max_bytes: 10 })
+        );
+
+        let tiny = ImageLimits {
+            max_dimension: 0,
+            ..LIMITS
+        };
+        assert_eq!(
+            inspect(png_bytes(), &tiny),
+            Err(UploadError::TooManyPixels { max_dimension: 0 })
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//! Removing images which no profile or post uses anymore, like replaced
+//! profile images and uploads whose post was never created.
+
+use chrono::{DateTime, Duration, Utc};
+use diesel_async::AsyncPgConnection;
+use uchat_domain::{ImageId, UserId};
+
+use super::variant;
+use crate::media::Media;
+
+/// Image nothing refers to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Orphan {
+    pub image_id: ImageId,
+    pub owner: Option<UserId>,
+    /// Size of the image and all its variants.
+    pub byte_size: i64,
+    pub used_at: DateTime<Utc>,
+    keys: Vec<String>,
+}
+
+/// Images which can be removed, found by [`Sweep::find`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sweep {
+    pub cutoff: DateTime<Utc>,
+    pub orphans: Vec<Orphan>,
+}
+
+impl Sweep {
+    /// Finds the images nothing refers to which weren't uploaded within
+    /// `grace`. The grace period covers uploads whose post or profile is
+    /// still being saved.
+    pub async fn find(conn: &mut AsyncPgConnection, grace: Duration) -> anyhow::Result<Self> {
+        let cutoff = Utc::now() - grace;
+        // Candidates first, so anything referring to them by the time the
+        // references are read is seen
+        let candidates = uchat_query::image::used_before(conn, cutoff).await?;
+        let referenced = uchat_query::image::referenced(conn).await?;
+
+        let mut orphans = vec![];
+        for image in candidates {
+            if referenced.contains(&image.id) {
+                continue;
+            }
+            let mut byte_size = image.byte_size;
+            let mut keys = vec![image.id.to_string()];
+            for image_variant in uchat_query::image::variants(conn, image.id).await? {
+                byte_size += image_variant.byte_size;
+                keys.push(variant::key(image.id, image_variant.width as u32));
+            }
+            orphans.push(Orphan {
+                image_id: image.id,
+                owner: image.user_id,
+                byte_size,
+                used_at: image.used_at,
+                keys,
+            });
+        }
+        Ok(Self { cutoff, orphans })
+    }
+
+    pub fn byte_size(&self) -> i64 {
+        self.orphans.iter().map(|orphan| orphan.byte_size).sum()
+    }
+
+    /// Removes the orphans, except those uploaded again since they were
+    /// found. Returns how many were removed.
+    pub async fn run(self, conn: &mut AsyncPgConnection, media: &Media) -> anyhow::Result<usize> {
+        let mut removed = 0;
+        for orphan in self.orphans {
+            if !uchat_query::image::delete_unused(conn, orphan.image_id, self.cutoff).await? {
+                continue;
+            }
+            // The metadata is gone, so the image can't be served anymore
+            // even if removing an object fails
+            for key in &orphan.keys {
+                if let Err(e) = media.delete(key).await {
+                    tracing::warn!(key, error = %e, "Failed to remove image from the media store");
+                }
+            }
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
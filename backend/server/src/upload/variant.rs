@@ -0,0 +1,304 @@
+//! Resized copies of uploaded images, for clients to pick the smallest one
+//! which fits with `srcset`.
+
+use std::io::Cursor;
+
+use image::{
+    codecs::{gif::GifEncoder, jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
+    metadata::Orientation,
+    DynamicImage, ImageDecoder,
+};
+use uchat_domain::ImageId;
+
+use super::{CheckedImage, ImageFormat, UploadError};
+
+/// Widths variants are rendered in: two for profile portraits, two for feeds.
+/// Only widths below the width of the original are rendered.
+pub const WIDTHS: [u32; 4] = [64, 128, 640, 1280];
+
+const JPEG_QUALITY: u8 = 85;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variant {
+    pub width: u32,
+    pub height: u32,
+    pub format: ImageFormat,
+    pub bytes: Vec<u8>,
+}
+
+/// Media store key of a variant.
+pub fn key(image_id: ImageId, width: u32) -> String {
+    format!("{image_id}_w{width}")
+}
+
+/// Splits a media store key into the image id and, for variants, the width.
+pub fn parse_key(key: &str) -> Option<(ImageId, Option<u32>)> {
+    match key.split_once("_w") {
+        Some((image_id, width)) => Some((image_id.parse().ok()?, Some(width.parse().ok()?))),
+        None => Some((key.parse().ok()?, None)),
+    }
+}
+
+/// Removes EXIF, XMP and text metadata, which can hold the GPS position of
+/// photos. Lossless, unless the image needs turning upright first.
+pub fn strip_metadata(image: CheckedImage) -> Result<CheckedImage, UploadError> {
+    let orientation = decoder(&image)?
+        .orientation()
+        .map_err(|_| UploadError::Unreadable)?;
+    if orientation != Orientation::NoTransforms {
+        // The orientation is about to be removed, turn the pixels instead
+        let mut decoded =
+            DynamicImage::from_decoder(decoder(&image)?).map_err(|_| UploadError::Unreadable)?;
+        decoded.apply_orientation(orientation);
+        return Ok(CheckedImage {
+            bytes: encode(&decoded, image.format)?,
+            format: image.format,
+            width: decoded.width(),
+            height: decoded.height(),
+        });
+    }
+
+    let stripped = match image.format {
+        ImageFormat::Jpeg => strip_jpeg(&image.bytes),
+        ImageFormat::Png => strip_png(&image.bytes),
+        ImageFormat::Webp => strip_webp(&image.bytes),
+        // GIFs carry no photo metadata
+        ImageFormat::Gif => return Ok(image),
+    };
+    Ok(CheckedImage {
+        bytes: stripped.ok_or(UploadError::Unreadable)?,
+        ..image
+    })
+}
+
+/// Renders the variants of `image`, as WebP if `webp` is set and otherwise
+/// as JPEG for photos and PNG for everything else. GIFs get none, as
+/// resizing would drop all but the first frame.
+pub fn render(image: &CheckedImage, webp: bool) -> Result<Vec<Variant>, UploadError> {
+    let widths = WIDTHS
+        .into_iter()
+        .filter(|width| *width < image.width)
+        .collect::<Vec<_>>();
+    if image.format == ImageFormat::Gif || widths.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let decoded =
+        DynamicImage::from_decoder(decoder(image)?).map_err(|_| UploadError::Unreadable)?;
+    let format = match (webp, image.format) {
+        (true, _) => ImageFormat::Webp,
+        (false, ImageFormat::Jpeg) => ImageFormat::Jpeg,
+        _ => ImageFormat::Png,
+    };
+    let mut variants = vec![];
+    for width in widths {
+        let resized = decoded.thumbnail(width, image.height);
+        let bytes = encode(&resized, format)?;
+        // Not worth keeping when the original is smaller anyway
+        if bytes.len() >= image.bytes.len() {
+            continue;
+        }
+        variants.push(Variant {
+            width,
+            height: resized.height(),
+            format,
+            bytes,
+        });
+    }
+    Ok(variants)
+}
+
+fn decoder(image: &CheckedImage) -> Result<impl ImageDecoder + '_, UploadError> {
+    image::ImageReader::with_format(Cursor::new(&image.bytes), image.format.to_decoder())
+        .into_decoder()
+        .map_err(|_| UploadError::Unreadable)
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, UploadError> {
+    let mut bytes = vec![];
+    let result = match format {
+        // Neither encoder takes an alpha channel or high bit depths
+        ImageFormat::Jpeg => DynamicImage::from(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY)),
+        ImageFormat::Webp => DynamicImage::from(image.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut bytes)),
+        ImageFormat::Png => image.write_with_encoder(PngEncoder::new(&mut bytes)),
+        ImageFormat::Gif => {
+            DynamicImage::from(image.to_rgba8()).write_with_encoder(GifEncoder::new(&mut bytes))
+        }
+    };
+    result.map_err(|_| UploadError::EncodingFailed)?;
+    Ok(bytes)
+}
+
+/// Drops the APP1 (EXIF, XMP), APP13 (IPTC) and comment segments in front
+/// of the image data.
+fn strip_jpeg(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut out = bytes.get(..2)?.to_vec();
+    let mut pos = 2;
+    loop {
+        if *bytes.get(pos)? != 0xFF {
+            return None;
+        }
+        match *bytes.get(pos + 1)? {
+            // Padding
+            0xFF => pos += 1,
+            // Start of scan, everything after is image data
+            0xDA => {
+                out.extend_from_slice(&bytes[pos..]);
+                return Some(out);
+            }
+            marker => {
+                let len = u16::from_be_bytes([*bytes.get(pos + 2)?, *bytes.get(pos + 3)?]);
+                let end = pos + 2 + len as usize;
+                let segment = bytes.get(pos..end)?;
+                if !matches!(marker, 0xE1 | 0xED | 0xFE) {
+                    out.extend_from_slice(segment);
+                }
+                pos = end;
+            }
+        }
+    }
+}
+
+/// Drops the EXIF, text and timestamp chunks.
+fn strip_png(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut out = bytes.get(..8)?.to_vec();
+    let mut pos = 8;
+    while pos < bytes.len() {
+        let len = u32::from_be_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        // Length, type, data and CRC
+        let end = pos.checked_add(len)?.checked_add(12)?;
+        let chunk = bytes.get(pos..end)?;
+        if !matches!(
+            &chunk[4..8],
+            b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME"
+        ) {
+            out.extend_from_slice(chunk);
+        }
+        pos = end;
+    }
+    Some(out)
+}
+
+/// Drops the EXIF and XMP chunks and their flags in the extended header.
+fn strip_webp(bytes: &[u8]) -> Option<Vec<u8>> {
+    const EXIF_FLAG: u8 = 0x08;
+    const XMP_FLAG: u8 = 0x04;
+
+    if bytes.get(..4)? != b"RIFF" || bytes.get(8..12)? != b"WEBP" {
+        return None;
+    }
+    let mut out = bytes[..12].to_vec();
+    let mut pos = 12;
+    while pos < bytes.len() {
+        let len = u32::from_le_bytes(bytes.get(pos + 4..pos + 8)?.try_into().ok()?) as usize;
+        // Type, length and data padded to an even length
+        let end = pos.checked_add(len)?.checked_add(8 + len % 2)?;
+        let chunk = bytes.get(pos..end)?;
+        match &chunk[..4] {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                let start = out.len();
+                out.extend_from_slice(chunk);
+                *out.get_mut(start + 8)? &= !(EXIF_FLAG | XMP_FLAG);
+            }
+            _ => out.extend_from_slice(chunk),
+        }
+        pos = end;
+    }
+    let riff_len = u32::try_from(out.len() - 8).ok()?;
+    out[4..8].copy_from_slice(&riff_len.to_le_bytes());
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upload::{inspect, ImageLimits};
+
+    const LIMITS: ImageLimits = ImageLimits {
+        max_bytes: 10 * 1024 * 1024,
+        max_dimension: 4096,
+    };
+
+    fn photo(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::from(image::RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([(x * 7) as u8, (y * 13) as u8, ((x ^ y) * 3) as u8])
+        }))
+    }
+
+    /// JPEG with an EXIF segment in front of the image data.
+    fn jpeg_with_exif(image: &DynamicImage, exif: &[u8]) -> Vec<u8> {
+        let plain = encode(image, ImageFormat::Jpeg).unwrap();
+        let mut segment = vec![0xFF, 0xE1];
+        segment.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+        segment.extend_from_slice(exif);
+        [&plain[..2], &segment, &plain[2..]].concat()
+    }
+
+    /// Minimal big endian EXIF block holding only the orientation tag.
+    fn exif_orientation(orientation: u16) -> Vec<u8> {
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+        exif.extend_from_slice(&orientation.to_be_bytes());
+        exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        exif
+    }
+
+    #[test]
+    fn strips_jpeg_metadata() {
+        let bytes = jpeg_with_exif(&photo(32, 16), &exif_orientation(1));
+        let image = inspect(bytes.clone(), &LIMITS).unwrap();
+
+        let stripped = strip_metadata(image).unwrap();
+        assert!(stripped.bytes.len() < bytes.len());
+        assert!(!stripped.bytes.windows(4).any(|window| window == b"Exif"));
+        assert_eq!((stripped.width, stripped.height), (32, 16));
+        assert!(inspect(stripped.bytes, &LIMITS).is_ok());
+    }
+
+    #[test]
+    fn turns_photos_upright() {
+        // Rotated 90° clockwise for display
+        let bytes = jpeg_with_exif(&photo(32, 16), &exif_orientation(6));
+        let image = inspect(bytes, &LIMITS).unwrap();
+
+        let stripped = strip_metadata(image).unwrap();
+        assert_eq!((stripped.width, stripped.height), (16, 32));
+        assert!(!stripped.bytes.windows(4).any(|window| window == b"Exif"));
+    }
+
+    #[test]
+    fn renders_narrower_variants_only() {
+        let bytes = encode(&photo(800, 400), ImageFormat::Jpeg).unwrap();
+        let image = inspect(bytes, &LIMITS).unwrap();
+
+        let variants = render(&image, false).unwrap();
+        let sizes = variants
+            .iter()
+            .map(|variant| (variant.width, variant.height, variant.format))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sizes,
+            vec![
+                (64, 32, ImageFormat::Jpeg),
+                (128, 64, ImageFormat::Jpeg),
+                (640, 320, ImageFormat::Jpeg),
+            ]
+        );
+
+        let webp = render(&image, true).unwrap();
+        assert!(webp
+            .iter()
+            .all(|variant| variant.format == ImageFormat::Webp));
+    }
+
+    #[test]
+    fn parses_keys() {
+        let image_id = ImageId::new();
+        assert_eq!(parse_key(&image_id.to_string()), Some((image_id, None)));
+        assert_eq!(parse_key(&key(image_id, 640)), Some((image_id, Some(640))));
+        assert_eq!(parse_key("not-an-id_w640"), None);
+        assert_eq!(parse_key(&format!("{image_id}_wide")), None);
+    }
+}
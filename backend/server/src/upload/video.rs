@@ -0,0 +1,663 @@
+//! Checking and storing uploaded videos and GIFs.
+//!
+//! Videos are inspected and processed with `ffprobe` and `ffmpeg`, which
+//! must be installed on the API host. Uploads are remuxed without their
+//! metadata, with the index up front so playback can start before the
+//! whole file is loaded. A frame is stored as an image to show until the
+//! video plays.
+//!
+//! The tools only read the uploaded file, with the demuxer of the sniffed
+//! container. Each run is killed after a time limit, and only a few uploads
+//! are processed at once.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use diesel_async::AsyncPgConnection;
+use serde::Deserialize;
+use tokio::{process::Command, sync::Semaphore};
+use uchat_domain::{UserId, VideoId};
+
+use super::{sha256_hex, ImageLimits, UploadError};
+use crate::media::Media;
+
+/// Containers accepted for upload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoFormat {
+    Mp4,
+    Webm,
+    Gif,
+}
+
+impl VideoFormat {
+    pub fn mime(&self) -> &'static str {
+        match self {
+            Self::Mp4 => "video/mp4",
+            Self::Webm => "video/webm",
+            Self::Gif => "image/gif",
+        }
+    }
+
+    /// Demuxer `ffmpeg` reads the container with, so it doesn't guess.
+    fn demuxer(&self) -> &'static str {
+        match self {
+            Self::Mp4 => "mov",
+            Self::Webm => "matroska",
+            Self::Gif => "gif",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Webm => "webm",
+            Self::Gif => "gif",
+        }
+    }
+
+    /// Tells the container from the first bytes. Matroska files pass as
+    /// WebM, their codecs are checked later.
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.get(4..8) == Some(b"ftyp") {
+            Some(Self::Mp4)
+        } else if bytes.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+            Some(Self::Webm)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else {
+            None
+        }
+    }
+
+    fn video_codecs(&self) -> &'static [&'static str] {
+        match self {
+            Self::Mp4 => &["h264", "vp9", "av1"],
+            Self::Webm => &["vp8", "vp9", "av1"],
+            Self::Gif => &["gif"],
+        }
+    }
+
+    fn audio_codecs(&self) -> &'static [&'static str] {
+        match self {
+            Self::Mp4 => &["aac", "mp3", "opus"],
+            Self::Webm => &["opus", "vorbis"],
+            Self::Gif => &[],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum VideoError {
+    #[error(transparent)]
+    Upload(#[from] UploadError),
+    #[error("Unsupported video format, use MP4 (H.264, VP9 or AV1), WebM or GIF")]
+    UnsupportedFormat,
+    #[error("Video file is damaged")]
+    Unreadable,
+    #[error("Videos may be at most {max_bytes} bytes")]
+    TooLarge { max_bytes: usize },
+    #[error("Videos may be at most {max_seconds} seconds long")]
+    TooLong { max_seconds: u32 },
+    #[error("Videos may be at most {max_dimension} pixels wide and high")]
+    TooManyPixels { max_dimension: u32 },
+    #[error("Video could not be processed")]
+    ProcessingFailed,
+}
+
+impl VideoError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Upload(e) => e.status_code(),
+            Self::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::ProcessingFailed => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VideoLimits {
+    pub max_bytes: usize,
+    pub max_seconds: u32,
+    pub max_dimension: u32,
+}
+
+/// What `ffprobe` found in a video.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Probe {
+    pub video_codec: String,
+    pub audio_codec: Option<String>,
+    /// Size as displayed, after rotation.
+    pub width: u32,
+    pub height: u32,
+    pub duration_ms: u32,
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: ProbeFormat,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_type: String,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    duration: Option<String>,
+    #[serde(default)]
+    side_data_list: Vec<ProbeSideData>,
+}
+
+#[derive(Deserialize)]
+struct ProbeSideData {
+    rotation: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+}
+
+/// Reads what `ffprobe -print_format json -show_format -show_streams`
+/// prints. Only the first video and audio stream count.
+pub fn parse_probe(json: &[u8]) -> Result<Probe, VideoError> {
+    let output: ProbeOutput = serde_json::from_slice(json).map_err(|_| VideoError::Unreadable)?;
+    let video = output
+        .streams
+        .iter()
+        .find(|stream| stream.codec_type == "video")
+        .ok_or(VideoError::Unreadable)?;
+    let audio_codec = output
+        .streams
+        .iter()
+        .find(|stream| stream.codec_type == "audio")
+        .and_then(|stream| stream.codec_name.clone());
+
+    let (Some(width), Some(height)) = (video.width, video.height) else {
+        return Err(VideoError::Unreadable);
+    };
+    // Phones record upright videos sideways and rotate them on playback
+    let rotation = video
+        .side_data_list
+        .iter()
+        .find_map(|side_data| side_data.rotation)
+        .unwrap_or(0);
+    let (width, height) = if rotation.rem_euclid(180) == 90 {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    let seconds = output
+        .format
+        .duration
+        .as_deref()
+        .or(video.duration.as_deref())
+        .and_then(|duration| duration.parse::<f64>().ok())
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .ok_or(VideoError::Unreadable)?;
+
+    Ok(Probe {
+        video_codec: video.codec_name.clone().ok_or(VideoError::Unreadable)?,
+        audio_codec,
+        width,
+        height,
+        duration_ms: (seconds * 1000.0).round().min(u32::MAX as f64) as u32,
+    })
+}
+
+/// Checks a probed video against the allowed codecs and the limits.
+pub fn validate(
+    probe: &Probe,
+    format: VideoFormat,
+    limits: &VideoLimits,
+) -> Result<(), VideoError> {
+    if !format.video_codecs().contains(&probe.video_codec.as_str()) {
+        return Err(VideoError::UnsupportedFormat);
+    }
+    if let Some(audio_codec) = &probe.audio_codec {
+        if !format.audio_codecs().contains(&audio_codec.as_str()) {
+            return Err(VideoError::UnsupportedFormat);
+        }
+    }
+    if probe.width == 0
+        || probe.height == 0
+        || probe.width > limits.max_dimension
+        || probe.height > limits.max_dimension
+    {
+        return Err(VideoError::TooManyPixels {
+            max_dimension: limits.max_dimension,
+        });
+    }
+    if probe.duration_ms > limits.max_seconds.saturating_mul(1000) {
+        return Err(VideoError::TooLong {
+            max_seconds: limits.max_seconds,
+        });
+    }
+    Ok(())
+}
+
+/// Media store key of a video.
+pub fn key(video_id: VideoId) -> String {
+    format!("{video_id}_video")
+}
+
+pub fn parse_key(key: &str) -> Option<VideoId> {
+    key.strip_suffix("_video")?.parse().ok()
+}
+
+/// Runs the `ffmpeg` and `ffprobe` executables. Clones share the limit on
+/// uploads processed at once.
+#[derive(Clone, Debug)]
+pub struct Ffmpeg {
+    ffmpeg: PathBuf,
+    ffprobe: PathBuf,
+    timeout: Duration,
+    jobs: Arc<Semaphore>,
+}
+
+/// Arguments which make `ffmpeg` and `ffprobe` read `path` as `format` from
+/// the file system only, rather than follow references to other files or
+/// URLs in it.
+fn input_args(path: &Path, format: VideoFormat) -> Vec<&std::ffi::OsStr> {
+    vec![
+        "-protocol_whitelist".as_ref(),
+        "file".as_ref(),
+        "-f".as_ref(),
+        format.demuxer().as_ref(),
+        "-i".as_ref(),
+        path.as_os_str(),
+    ]
+}
+
+impl Ffmpeg {
+    /// `timeout` limits each run of a tool, `max_jobs` the uploads checked
+    /// at once.
+    pub fn new(ffmpeg: PathBuf, ffprobe: PathBuf, timeout: Duration, max_jobs: usize) -> Self {
+        Self {
+            ffmpeg,
+            ffprobe,
+            timeout,
+            jobs: Arc::new(Semaphore::new(max_jobs.max(1))),
+        }
+    }
+
+    pub async fn probe(&self, path: &Path, format: VideoFormat) -> Result<Probe, VideoError> {
+        let json = self
+            .run(
+                Command::new(&self.ffprobe)
+                    .args(["-v", "error", "-print_format", "json"])
+                    .args(["-show_format", "-show_streams"])
+                    .args(input_args(path, format)),
+                VideoError::Unreadable,
+            )
+            .await?;
+        parse_probe(&json)
+    }
+
+    /// Grabs a frame one second in, or halfway through shorter videos, as
+    /// PNG.
+    pub async fn poster(
+        &self,
+        path: &Path,
+        format: VideoFormat,
+        duration_ms: u32,
+    ) -> Result<Vec<u8>, VideoError> {
+        let at = duration_ms.min(2000) / 2;
+        self.run(
+            Command::new(&self.ffmpeg)
+                .args(["-v", "error", "-ss", &format!("{}ms", at)])
+                .args(input_args(path, format))
+                .args(["-frames:v", "1", "-c:v", "png", "-f", "image2pipe", "-"]),
+            VideoError::ProcessingFailed,
+        )
+        .await
+    }
+
+    /// Copies the streams into a new file without the metadata, which can
+    /// hold where the video was recorded.
+    pub async fn remux(
+        &self,
+        input: &Path,
+        output: &Path,
+        format: VideoFormat,
+    ) -> Result<(), VideoError> {
+        let mut command = Command::new(&self.ffmpeg);
+        command
+            .args(["-v", "error", "-y"])
+            .args(input_args(input, format))
+            .args(["-map", "0:v:0", "-map", "0:a:0?", "-c", "copy"])
+            .args(["-map_metadata", "-1", "-map_chapters", "-1"]);
+        if format == VideoFormat::Mp4 {
+            command.args(["-movflags", "+faststart"]);
+        }
+        self.run(command.arg(output), VideoError::ProcessingFailed)
+            .await?;
+        Ok(())
+    }
+
+    /// Encodes a GIF as a silent H.264 MP4, mostly a fraction of the size.
+    pub async fn gif_to_mp4(&self, input: &Path, output: &Path) -> Result<(), VideoError> {
+        self.run(
+            Command::new(&self.ffmpeg)
+                .args(["-v", "error", "-y"])
+                .args(input_args(input, VideoFormat::Gif))
+                // H.264 wants even dimensions
+                .args(["-vf", "scale=trunc(iw/2)*2:trunc(ih/2)*2"])
+                .args(["-c:v", "libx264", "-pix_fmt", "yuv420p", "-an"])
+                .args(["-map_metadata", "-1", "-movflags", "+faststart"])
+                .arg(output),
+            VideoError::ProcessingFailed,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Runs `command`, killing it once it takes longer than the timeout.
+    async fn run(&self, command: &mut Command, failed: VideoError) -> Result<Vec<u8>, VideoError> {
+        let running = command.stdin(Stdio::null()).kill_on_drop(true).output();
+        let output = tokio::time::timeout(self.timeout, running)
+            .await
+            .map_err(|_| {
+                tracing::warn!(timeout = ?self.timeout, "ffmpeg took too long");
+                VideoError::ProcessingFailed
+            })?
+            .map_err(|e| {
+                tracing::error!(error = %e, "Failed to run ffmpeg, is it installed?");
+                VideoError::ProcessingFailed
+            })?;
+        if !output.status.success() {
+            tracing::warn!(
+                stderr = %String::from_utf8_lossy(&output.stderr),
+                "ffmpeg failed"
+            );
+            return Err(failed);
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// File in the temporary directory, removed when dropped.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(format: VideoFormat) -> Self {
+        let name = format!("uchat-{}.{}", uuid::Uuid::new_v4(), format.extension());
+        Self(std::env::temp_dir().join(name))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Video which passed all checks and can be stored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckedVideo {
+    pub bytes: Vec<u8>,
+    pub format: VideoFormat,
+    pub probe: Probe,
+    /// Converted from a GIF, or still one.
+    pub looping: bool,
+    /// Frame shown until the video plays, as PNG.
+    pub poster: Vec<u8>,
+}
+
+/// Checks `bytes` and prepares them for storing. GIFs become MP4 videos if
+/// `convert_gifs` is set.
+pub async fn check(
+    ffmpeg: &Ffmpeg,
+    bytes: Vec<u8>,
+    limits: &VideoLimits,
+    convert_gifs: bool,
+) -> Result<CheckedVideo, VideoError> {
+    if bytes.len() > limits.max_bytes {
+        return Err(VideoError::TooLarge {
+            max_bytes: limits.max_bytes,
+        });
+    }
+    let format = VideoFormat::sniff(&bytes).ok_or(VideoError::UnsupportedFormat)?;
+    let looping = format == VideoFormat::Gif;
+    // Waits for a running upload to finish, the semaphore is never closed
+    let _job = ffmpeg
+        .jobs
+        .acquire()
+        .await
+        .map_err(|_| VideoError::ProcessingFailed)?;
+    let input = TempFile::new(format);
+    tokio::fs::write(input.path(), &bytes)
+        .await
+        .map_err(|_| VideoError::ProcessingFailed)?;
+
+    let probe = ffmpeg.probe(input.path(), format).await?;
+    validate(&probe, format, limits)?;
+    let poster = ffmpeg
+        .poster(input.path(), format, probe.duration_ms)
+        .await?;
+
+    let (output, format) = match format {
+        // GIFs carry no metadata worth removing
+        VideoFormat::Gif if !convert_gifs => {
+            return Ok(CheckedVideo {
+                bytes,
+                format,
+                probe,
+                looping,
+                poster,
+            })
+        }
+        VideoFormat::Gif => {
+            let output = TempFile::new(VideoFormat::Mp4);
+            ffmpeg.gif_to_mp4(input.path(), output.path()).await?;
+            (output, VideoFormat::Mp4)
+        }
+        _ => {
+            let output = TempFile::new(format);
+            ffmpeg.remux(input.path(), output.path(), format).await?;
+            (output, format)
+        }
+    };
+    let bytes = tokio::fs::read(output.path())
+        .await
+        .map_err(|_| VideoError::ProcessingFailed)?;
+    Ok(CheckedVideo {
+        bytes,
+        format,
+        probe: ffmpeg.probe(output.path(), format).await?,
+        looping,
+        poster,
+    })
+}
+
+/// Puts the video in the media store, its poster frame with the images,
+/// and records its metadata. A video which is already stored is used again
+/// instead.
+pub async fn store(
+    conn: &mut AsyncPgConnection,
+    media: &Media,
+    owner: UserId,
+    video: CheckedVideo,
+    poster_limits: ImageLimits,
+    webp_variants: bool,
+) -> anyhow::Result<VideoId> {
+    let sha256 = sha256_hex(&video.bytes);
+    if let Some(stored) = uchat_query::video::find_by_sha256(conn, &sha256).await? {
+        tracing::info!(video_id = ?stored.id, "Video is already stored");
+        uchat_query::video::mark_used(conn, stored.id).await?;
+        return Ok(stored.id);
+    }
+
+    let poster_bytes = video.poster;
+    let poster =
+        tokio::task::spawn_blocking(move || super::check(poster_bytes, &poster_limits)).await??;
+    let poster_image_id = super::store(conn, media, owner, poster, webp_variants).await?;
+
+    let video_id = VideoId::new();
+    let now = Utc::now();
+    let row = uchat_query::video::Video {
+        id: video_id,
+        user_id: Some(owner),
+        poster_image_id,
+        mime: video.format.mime().to_string(),
+        codec: video.probe.video_codec,
+        byte_size: video.bytes.len() as i64,
+        width: video.probe.width as i32,
+        height: video.probe.height as i32,
+        duration_ms: i32::try_from(video.probe.duration_ms).unwrap_or(i32::MAX),
+        looping: video.looping,
+        sha256,
+        created_at: now,
+        used_at: now,
+    };
+    let key = key(video_id);
+    media.put(&key, video.bytes, &row.mime).await?;
+    if let Err(e) = uchat_query::video::new(conn, &row).await {
+        // Don't leave an object behind which nothing knows about
+        let _ = media.delete(&key).await;
+        return Err(e.into());
+    }
+    Ok(video_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: VideoLimits = VideoLimits {
+        max_bytes: 1024 * 1024,
+        max_seconds: 60,
+        max_dimension: 1920,
+    };
+
+    #[test]
+    fn sniffs_containers() {
+        let mp4 = b"\0\0\0\x20ftypisom\0\0\x02\0";
+        assert_eq!(VideoFormat::sniff(mp4), Some(VideoFormat::Mp4));
+        let webm = [0x1A, 0x45, 0xDF, 0xA3, 0x9F, 0x42, 0x86, 0x81];
+        assert_eq!(VideoFormat::sniff(&webm), Some(VideoFormat::Webm));
+        assert_eq!(VideoFormat::sniff(b"GIF89a\x01\0"), Some(VideoFormat::Gif));
+        assert_eq!(VideoFormat::sniff(b"\x89PNG\r\n\x1a\n"), None);
+        assert_eq!(VideoFormat::sniff(b""), None);
+    }
+
+    #[test]
+    fn parses_probe_output() {
+        let json = br#"{
+            "streams": [
+                {"codec_type": "audio", "codec_name": "aac"},
+                {
+                    "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080,
+                    "side_data_list": [{"side_data_type": "Display Matrix", "rotation": -90}]
+                }
+            ],
+            "format": {"format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "12.345678"}
+        }"#;
+        let probe = parse_probe(json).unwrap();
+        assert_eq!(
+            probe,
+            Probe {
+                video_codec: "h264".to_string(),
+                audio_codec: Some("aac".to_string()),
+                width: 1080,
+                height: 1920,
+                duration_ms: 12346,
+            }
+        );
+
+        let audio_only = br#"{"streams": [{"codec_type": "audio", "codec_name": "opus"}],
+            "format": {"duration": "1.0"}}"#;
+        assert_eq!(parse_probe(audio_only), Err(VideoError::Unreadable));
+        assert_eq!(parse_probe(b"not json"), Err(VideoError::Unreadable));
+    }
+
+    #[test]
+    fn validates_codecs_and_limits() {
+        let probe = Probe {
+            video_codec: "vp9".to_string(),
+            audio_codec: Some("opus".to_string()),
+            width: 1280,
+            height: 720,
+            duration_ms: 30_000,
+        };
+        assert_eq!(validate(&probe, VideoFormat::Webm, &LIMITS), Ok(()));
+        assert_eq!(validate(&probe, VideoFormat::Mp4, &LIMITS), Ok(()));
+
+        let hevc = Probe {
+            video_codec: "hevc".to_string(),
+            ..probe.clone()
+        };
+        assert_eq!(
+            validate(&hevc, VideoFormat::Mp4, &LIMITS),
+            Err(VideoError::UnsupportedFormat)
+        );
+        let long = Probe {
+            duration_ms: 60_001,
+            ..probe.clone()
+        };
+        assert_eq!(
+            validate(&long, VideoFormat::Webm, &LIMITS),
+            Err(VideoError::TooLong { max_seconds: 60 })
+        );
+        let wide = Probe {
+            width: 3840,
+            ..probe
+        };
+        assert_eq!(
+            validate(&wide, VideoFormat::Webm, &LIMITS),
+            Err(VideoError::TooManyPixels {
+                max_dimension: 1920
+            })
+        );
+    }
+
+    #[test]
+    fn inputs_are_local_files_of_the_sniffed_format() {
+        let path = Path::new("/tmp/upload.webm");
+        assert_eq!(
+            input_args(path, VideoFormat::Webm),
+            [
+                "-protocol_whitelist",
+                "file",
+                "-f",
+                "matroska",
+                "-i",
+                "/tmp/upload.webm"
+            ]
+        );
+        assert_eq!(input_args(path, VideoFormat::Mp4)[3], "mov");
+        assert_eq!(input_args(path, VideoFormat::Gif)[3], "gif");
+    }
+
+    #[tokio::test]
+    async fn slow_runs_are_killed() {
+        let ffmpeg = Ffmpeg::new("sleep".into(), "sleep".into(), Duration::from_millis(50), 1);
+        let started = std::time::Instant::now();
+        let result = ffmpeg
+            .run(Command::new("sleep").arg("5"), VideoError::Unreadable)
+            .await;
+        assert_eq!(result, Err(VideoError::ProcessingFailed));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn parses_keys() {
+        let video_id = VideoId::new();
+        assert_eq!(parse_key(&key(video_id)), Some(video_id));
+        assert_eq!(parse_key(&video_id.to_string()), None);
+        assert_eq!(parse_key("not-an-id_video"), None);
+    }
+}
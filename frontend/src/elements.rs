@@ -3,6 +3,7 @@ pub mod keyed_notifications_box;
 pub mod local_profile;
 pub mod navbar;
 pub mod post;
+pub mod report;
 pub mod sidebar;
 pub mod toaster;
 
@@ -10,5 +11,6 @@ pub use appbar::Appbar;
 pub use keyed_notifications_box::KeyedNotificationsBox;
 pub use navbar::Navbar;
 pub use post::PostManager;
+pub use report::{ReportForm, ReportTarget};
 pub use sidebar::*;
 pub use toaster::*;
@@ -1,10 +1,12 @@
 #![allow(non_snake_case)]
 
 use uchat_domain::UserId;
+use uchat_endpoint::moderation::types::Role;
 use url::Url;
 
 #[derive(Default)]
 pub struct LocalProfile {
     pub image: Option<Url>,
     pub user_id: Option<UserId>,
+    pub role: Role,
 }
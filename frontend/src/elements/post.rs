@@ -7,10 +7,11 @@ use crate::prelude::*;
 use dioxus::prelude::*;
 use indexmap::IndexMap;
 use uchat_domain::PostId;
-use uchat_endpoint::post::types::PublicPost;
+use uchat_endpoint::post::types::{srcset, PublicPost};
 
 mod actionbar;
-mod content;
+pub mod content;
+mod markup;
 mod quick_response;
 
 #[derive(Default, Clone)]
@@ -77,6 +78,7 @@ pub fn ProfileImage(post: PublicPost) -> Element {
         .as_ref()
         .map(|url| url.as_str())
         .unwrap_or_else(|| "");
+    let profile_img_srcset = srcset(&poster_info.profile_image_variants);
 
     rsx!(
         div {
@@ -86,6 +88,8 @@ pub fn ProfileImage(post: PublicPost) -> Element {
                     navigator().push(ViewProfile { user_id: post.by_user.id.to_string()});
                 },
                 src: "{profile_img_src}",
+                srcset: "{profile_img_srcset}",
+                "sizes": "3.5rem",
             }
         }
     )
@@ -131,6 +135,7 @@ pub fn Header(post: PublicPost) -> Element {
 
 #[component]
 pub fn PublicPostEntry(post_id: PostId) -> Element {
+    let mut revealed = use_signal(|| false);
     let post_manager = POSTMANAGER.read();
     let this_post = match post_manager.get(&post_id) {
         Some(post) => post,
@@ -139,6 +144,21 @@ pub fn PublicPostEntry(post_id: PostId) -> Element {
         }
     };
 
+    if let Some(phrase) = this_post.muted_by.as_ref().filter(|_| !*revealed.read()) {
+        return rsx!(
+            div {
+                key: "{this_post.id.to_string()}",
+                class: "flex flex-row justify-between items-center mb-4 text-sm opacity-70",
+                span { "Hidden because it mentions \"{phrase}\"" }
+                button {
+                    class: "btn",
+                    onclick: move |_| revealed.set(true),
+                    "Show"
+                }
+            }
+        );
+    }
+
     rsx!(
         div {
             key: "{this_post.id.to_string()}",
@@ -212,6 +212,21 @@ pub fn Actionbar(post_id: PostId) -> Element {
     };
     let this_post_id = this_post.id;
     let quick_response_opened = use_signal(|| false);
+    let is_own_post = LOCAL_PROFILE.read().user_id == Some(this_post.by_user.id);
+    let Report = (!is_own_post).then(|| {
+        rsx!(ReportForm {
+            target: ReportTarget::Post(this_post_id)
+        })
+    });
+    let AnalyticsLink = is_own_post.then(|| {
+        rsx!(
+            Link {
+                class: "link text-sm self-end",
+                to: Route::PostStats { post_id: this_post_id.to_string() },
+                "View analytics"
+            }
+        )
+    });
 
     rsx!(
         div {
@@ -247,5 +262,11 @@ pub fn Actionbar(post_id: PostId) -> Element {
             post_id: this_post_id,
             opened: quick_response_opened
         }
+
+        div {
+            class: "flex flex-col w-full",
+            {AnalyticsLink}
+            {Report}
+        }
     )
 }
@@ -1,5 +1,6 @@
 #![allow(non_snake_case)]
 
+use super::markup::Markup;
 use crate::prelude::*;
 use chrono::Duration;
 use dioxus::prelude::*;
@@ -9,8 +10,9 @@ use uchat_domain::{PollChoiceId, PostId};
 use uchat_endpoint::post::{
     endpoint::{Vote, VoteOk},
     types::{
-        Chat as EndpointChat, Content as EndpointContent, Image as EndpointImage, ImageKind,
-        Poll as EndpointPoll, PublicPost, VoteCast,
+        srcset, Chat as EndpointChat, Content as EndpointContent, Gallery as EndpointGallery,
+        Image as EndpointImage, ImageKind, Link as EndpointLink, Poll as EndpointPoll, PublicPost,
+        Video as EndpointVideo, VideoKind, VoteCast,
     },
 };
 
@@ -27,7 +29,59 @@ pub fn Chat(post_id: PostId, content: EndpointChat) -> Element {
 
     rsx!(
         {HeadLine},
-        p { "{content.message.as_ref()}" }
+        Markup { text: content.message.as_ref().to_string() }
+    )
+}
+
+#[component]
+pub fn Link(post_id: PostId, content: EndpointLink) -> Element {
+    let HeadLine = content.headline.as_ref().map(|headline| {
+        rsx!(
+            div {
+                class: "font-bold",
+                {headline.as_ref()}
+            }
+        )
+    });
+    let host = content.url.host_str().unwrap_or_default().to_string();
+
+    let Card = content.preview.as_ref().map(|preview| {
+        let image = preview.image.as_ref().map(|image| {
+            rsx!(img {
+                class: "w-full object-cover max-h-64",
+                src: "{image}",
+                alt: "",
+            })
+        });
+        let title = preview
+            .title
+            .as_ref()
+            .map(|title| rsx!(div { class: "font-bold", "{title}" }));
+        let description = preview
+            .description
+            .as_ref()
+            .map(|description| rsx!(div { class: "text-sm", "{description}" }));
+        let site = preview.site_name.clone().unwrap_or_else(|| host.clone());
+
+        rsx!(a {
+            class: "flex flex-col border rounded overflow-hidden",
+            href: "{content.url}",
+            target: "_blank",
+            rel: "noopener noreferrer nofollow",
+            {image},
+            div {
+                class: "flex flex-col gap-1 p-2",
+                div { class: "text-xs text-gray-500", "{site}" },
+                {title},
+                {description}
+            }
+        })
+    });
+
+    rsx!(
+        {HeadLine},
+        Markup { text: content.message.as_ref().to_string() },
+        {Card}
     )
 }
 
@@ -42,6 +96,7 @@ pub fn Image(post_id: PostId, content: EndpointImage) -> Element {
         .caption
         .as_ref()
         .map(|caption| rsx!( figcaption { em { "{caption.as_ref()}"}}));
+    let srcset = srcset(&content.variants);
 
     rsx!(
         figure {
@@ -49,12 +104,139 @@ pub fn Image(post_id: PostId, content: EndpointImage) -> Element {
             {caption},
             img {
                 class: "w-full object-contain max-h-[80vh]",
-                src: "{url}"
+                src: "{url}",
+                srcset: "{srcset}",
+                "sizes": "(max-width: 640px) 100vw, 640px",
+            }
+        }
+    )
+}
+
+#[component]
+pub fn Gallery(post_id: PostId, content: EndpointGallery) -> Element {
+    // Sensitive images the reader chose to see
+    let mut revealed = use_signal(HashSet::<usize>::new);
+    let caption = content
+        .caption
+        .as_ref()
+        .map(|caption| rsx!( figcaption { em { "{caption.as_ref()}"}}));
+    let (columns, sizes) = if content.images.len() > 1 {
+        ("grid-cols-2", "(max-width: 640px) 50vw, 320px")
+    } else {
+        ("grid-cols-1", "(max-width: 640px) 100vw, 640px")
+    };
+
+    let Images = content.images.iter().enumerate().map(|(i, image)| {
+        let ImageKind::Url(url) = &image.kind else {
+            return rsx!(div { key: "{i}", "Image not found" });
+        };
+        let alt = image
+            .alt_text
+            .as_ref()
+            .map(|alt_text| alt_text.as_ref().to_string())
+            .unwrap_or_default();
+        let hidden = image.sensitive && !revealed.read().contains(&i);
+        let blur = maybe_class!("blur-xl cursor-pointer", hidden);
+        let srcset = srcset(&image.variants);
+        rsx!(
+            div {
+                key: "{i}",
+                class: "relative overflow-hidden",
+                img {
+                    class: "w-full h-full object-cover max-h-[80vh] {blur}",
+                    src: "{url}",
+                    srcset: "{srcset}",
+                    "sizes": "{sizes}",
+                    alt: "{alt}",
+                    title: "{alt}",
+                    onclick: move |_| {
+                        revealed.write().insert(i);
+                    },
+                }
+                if hidden {
+                    div {
+                        class: "absolute inset-0 flex items-center justify-center pointer-events-none font-bold",
+                        "Sensitive content, click to show"
+                    }
+                }
+            }
+        )
+    });
+
+    rsx!(
+        figure {
+            class: "flex flex-col gap-2",
+            {caption},
+            div {
+                class: "grid {columns} gap-1",
+                {Images}
             }
         }
     )
 }
 
+#[component]
+pub fn Video(post_id: PostId, content: EndpointVideo) -> Element {
+    let (VideoKind::Url(url), Some(details)) = (&content.kind, &content.details) else {
+        return rsx!(div { "Video not found"});
+    };
+    let caption = content
+        .caption
+        .as_ref()
+        .map(|caption| rsx!( figcaption { em { "{caption.as_ref()}"}}));
+    let alt = content
+        .alt_text
+        .as_ref()
+        .map(|alt_text| alt_text.as_ref().to_string())
+        .unwrap_or_default();
+    let class = "w-full object-contain max-h-[80vh]";
+
+    let Player = if details.mime.starts_with("image/") {
+        // GIFs which weren't converted
+        rsx!(img {
+            class: class,
+            src: "{url}",
+            alt: "{alt}",
+            width: "{details.width}",
+            height: "{details.height}",
+        })
+    } else if details.looping {
+        // Converted GIFs play like one
+        rsx!(video {
+            class: class,
+            poster: "{details.poster}",
+            width: "{details.width}",
+            height: "{details.height}",
+            aria_label: "{alt}",
+            autoplay: true,
+            muted: true,
+            r#loop: true,
+            playsinline: true,
+            source { src: "{url}", r#type: "{details.mime}" }
+        })
+    } else {
+        rsx!(video {
+            class: class,
+            poster: "{details.poster}",
+            width: "{details.width}",
+            height: "{details.height}",
+            aria_label: "{alt}",
+            controls: true,
+            preload: "metadata",
+            playsinline: true,
+            source { src: "{url}", r#type: "{details.mime}" }
+        })
+    };
+
+    rsx!(
+        figure {
+            class: "flex flex-col gap-2",
+            {caption},
+            {Player}
+        }
+    )
+}
+
 #[component]
 pub fn Poll(post_id: PostId, content: EndpointPoll) -> Element {
     let api_client = ApiClient::global();
@@ -151,10 +333,22 @@ pub fn Content(post: PublicPost) -> Element {
             post_id: post.id,
             content: content
         }),
+        EndpointContent::Link(content) => rsx!(Link {
+            post_id: post.id,
+            content: content
+        }),
         EndpointContent::Image(content) => rsx!(Image {
             post_id: post.id,
             content: content
         }),
+        EndpointContent::Gallery(content) => rsx!(Gallery {
+            post_id: post.id,
+            content: content
+        }),
+        EndpointContent::Video(content) => rsx!(Video {
+            post_id: post.id,
+            content: content
+        }),
         EndpointContent::Poll(content) => rsx!(Poll {
             post_id: post.id,
             content: content
@@ -0,0 +1,56 @@
+#![allow(non_snake_case)]
+
+use dioxus::prelude::*;
+use uchat_markup::{Block, Inline};
+
+/// Renders a chat message. The message is parsed into spans which become
+/// elements, so markup in it can never turn into HTML of its own.
+#[component]
+pub fn Markup(text: String) -> Element {
+    let Blocks = uchat_markup::parse(&text)
+        .blocks
+        .into_iter()
+        .map(|block| match block {
+            Block::Paragraph(inlines) => rsx!(p { {inline_elements(inlines)} }),
+            Block::Code { language, code } => {
+                let language = language
+                    .map(|language| format!("language-{language}"))
+                    .unwrap_or_default();
+                rsx!(pre {
+                    class: "p-2 overflow-x-auto bg-gray-100 rounded",
+                    code { class: "{language}", "{code}" }
+                })
+            }
+        });
+
+    rsx!(
+        div {
+            class: "flex flex-col gap-2 break-words",
+            {Blocks}
+        }
+    )
+}
+
+fn inline_elements(inlines: Vec<Inline>) -> Element {
+    let Parts = inlines.into_iter().map(|inline| match inline {
+        Inline::Text(text) => rsx!("{text}"),
+        Inline::Bold(inner) => rsx!(strong { {inline_elements(inner)} }),
+        Inline::Italic(inner) => rsx!(em { {inline_elements(inner)} }),
+        Inline::Code(code) => rsx!(code {
+            class: "px-1 bg-gray-100 rounded",
+            "{code}"
+        }),
+        Inline::Link { text, url } => rsx!(a {
+            class: "link",
+            href: "{url}",
+            target: "_blank",
+            rel: "noopener noreferrer nofollow",
+            {inline_elements(text)}
+        }),
+        Inline::Mention(name) => rsx!(span { class: "font-bold", "@{name}" }),
+        Inline::Hashtag(tag) => rsx!(span { class: "text-blue-700", "#{tag}" }),
+        Inline::LineBreak => rsx!(br {}),
+    });
+
+    rsx!({ Parts })
+}
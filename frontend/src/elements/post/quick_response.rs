@@ -13,12 +13,14 @@ use uchat_endpoint::post::{
 
 //------------------------------------------------------------------------------------------
 fn can_submit(message: &str) -> bool {
-    message.len() <= Message::MAX_CHARS && !message.is_empty()
+    Message::try_new(message).is_ok()
+        && uchat_markup::parse(message).visible_chars() <= SERVER_LIMITS.read().message_max_chars
 }
 
 #[component]
 pub fn MessageInput(message: String, on_input: EventHandler<FormEvent>) -> Element {
     let wrong_len = maybe_class!("err-text-color", !can_submit(&message));
+    let visible_chars = uchat_markup::parse(&message).visible_chars();
 
     rsx!(
         div {
@@ -35,7 +37,7 @@ pub fn MessageInput(message: String, on_input: EventHandler<FormEvent>) -> Eleme
             },
             div {
                 class: "text-right {wrong_len}",
-                "{message.len()}/{Message::MAX_CHARS}"
+                "{visible_chars}/{SERVER_LIMITS.read().message_max_chars}"
             }
         }
     )
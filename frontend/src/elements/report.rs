@@ -0,0 +1,116 @@
+#![allow(non_snake_case)]
+
+use crate::prelude::*;
+use chrono::Duration;
+use dioxus::prelude::*;
+use uchat_domain::{PostId, UserId};
+use uchat_endpoint::moderation::{
+    endpoint::{ReportPost, ReportPostOk, ReportUser, ReportUserOk},
+    types::ReportReason,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportTarget {
+    Post(PostId),
+    User(UserId),
+}
+
+/// "Report" link which opens a small form to report a post or a user.
+#[component]
+pub fn ReportForm(target: ReportTarget) -> Element {
+    let api_client = ApiClient::global();
+    let mut opened = use_signal(|| false);
+    let mut reason = use_signal(|| ReportReason::Spam);
+    let mut details = use_signal(String::new);
+
+    let report_onsubmit = async_handler!([api_client, target], move |_| async move {
+        let details_text = details.read().trim().to_string();
+        let details_text = (!details_text.is_empty()).then_some(details_text);
+        let reason = *reason.read();
+        let response = match target {
+            ReportTarget::Post(post_id) => {
+                let request_data = ReportPost {
+                    post_id,
+                    reason,
+                    details: details_text,
+                };
+                fetch_json!(<ReportPostOk>, api_client, request_data).map(|_| ())
+            }
+            ReportTarget::User(user_id) => {
+                let request_data = ReportUser {
+                    user_id,
+                    reason,
+                    details: details_text,
+                };
+                fetch_json!(<ReportUserOk>, api_client, request_data).map(|_| ())
+            }
+        };
+        match response {
+            Ok(()) => {
+                TOASTER.write().success(
+                    "Thanks, a moderator will have a look",
+                    Duration::milliseconds(1200),
+                );
+                details.set(String::new());
+                opened.set(false);
+            }
+            Err(err) => TOASTER.write().error(
+                format!("Failed to send report: {err}"),
+                Duration::milliseconds(1200),
+            ),
+        }
+    });
+
+    if !*opened.read() {
+        return rsx!(
+            button {
+                class: "text-sm opacity-70 self-end",
+                onclick: move |_| opened.set(true),
+                "Report"
+            }
+        );
+    }
+
+    rsx!(
+        form {
+            class: "flex flex-col gap-2 w-full",
+            onsubmit: report_onsubmit,
+            select {
+                class: "input-field",
+                value: "{reason.read().as_str()}",
+                onchange: move |ev| {
+                    if let Ok(selected) = ev.value().parse::<ReportReason>() {
+                        reason.set(selected);
+                    }
+                },
+                for option_reason in ReportReason::ALL {
+                    option {
+                        value: "{option_reason.as_str()}",
+                        "{option_reason.description()}"
+                    }
+                }
+            }
+            textarea {
+                class: "input-field",
+                rows: 2,
+                placeholder: "Anything a moderator should know (optional)",
+                value: "{details.read()}",
+                oninput: move |ev| details.set(ev.value()),
+            }
+            div {
+                class: "flex flex-row justify-end gap-2",
+                button {
+                    class: "btn",
+                    r#type: "button",
+                    onclick: move |_| opened.set(false),
+                    "Cancel"
+                }
+                button {
+                    class: "btn",
+                    r#type: "submit",
+                    "Send report"
+                }
+            }
+        }
+    )
+}
@@ -3,7 +3,9 @@
 use crate::prelude::*;
 use chrono::Utc;
 use dioxus::prelude::*;
+use dioxus_logger::tracing::error;
 use uchat_domain::SessionId;
+use uchat_endpoint::user::endpoint::{Logout, LogoutOk};
 
 #[derive(Default)]
 pub struct SidebarManager {
@@ -45,7 +47,62 @@ pub fn Sidebar() -> Element {
         }
     };
     let navigator = use_navigator();
+    let api_client = ApiClient::global();
+
+    let logout_onclick = async_handler!([api_client, navigator], move |_| async move {
+        // The local session is dropped even if the server could not be reached
+        if let Err(err) = fetch_json!(<LogoutOk>, api_client, Logout) {
+            error!("Failed to revoke session: {:?}", err);
+        }
+        crate::util::cookie::set_session("".to_string(), SessionId::new(), Utc::now());
+        SIDEBAR.write().close();
+        LOCAL_PROFILE.write().user_id = None;
+        LOCAL_PROFILE.write().image = None;
+        LOCAL_PROFILE.write().role = Default::default();
+        navigator.replace(Route::Login {});
+    });
+
+    // Refetched whenever the sidebar opens, so new lists show up
+    let own_lists = use_resource(move || async move {
+        if SIDEBAR.read().is_open() {
+            fetch_own_lists().await.unwrap_or_default()
+        } else {
+            vec![]
+        }
+    });
+    let ListLinks = own_lists
+        .read()
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|list| {
+            let list_id = list.id.to_string();
+            rsx!(
+                a {
+                    key: "{list_id}",
+                    class: "sidebar-navlink pl-8",
+                    onclick: move |_| {
+                        SIDEBAR.write().close();
+                        navigator.push(Route::ListFeed { list_id: list_id.clone() });
+                    },
+                    "{list.name}"
+                }
+            )
+        });
+
     let read_local_profile = LOCAL_PROFILE.read();
+    let ModerationLink = read_local_profile.role.is_moderator().then(|| {
+        rsx!(
+            a {
+                class: "sidebar-navlink",
+                onclick: move |_| {
+                    SIDEBAR.write().close();
+                    navigator.push(Route::Moderation {});
+                },
+                "Moderation"
+            }
+        )
+    });
     let profile_img_src = read_local_profile
         .image
         .as_ref()
@@ -81,6 +138,72 @@ pub fn Sidebar() -> Element {
                 },
                 "Edit Profile"
             }
+            a {
+                class: "sidebar-navlink",
+                onclick: move |_| {
+                    SIDEBAR.write().close();
+                    navigator.push(Route::TwoFactor {});
+                },
+                "Two-factor Auth"
+            }
+            a {
+                class: "sidebar-navlink",
+                onclick: move |_| {
+                    SIDEBAR.write().close();
+                    navigator.push(Route::SecurityEvents {});
+                },
+                "Security Activity"
+            }
+            a {
+                class: "sidebar-navlink",
+                onclick: move |_| {
+                    SIDEBAR.write().close();
+                    navigator.push(Route::ApiTokens {});
+                },
+                "Access Tokens"
+            }
+            a {
+                class: "sidebar-navlink",
+                onclick: move |_| {
+                    SIDEBAR.write().close();
+                    navigator.push(Route::MuteFilters {});
+                },
+                "Muted Words"
+            }
+            a {
+                class: "sidebar-navlink",
+                onclick: move |_| {
+                    SIDEBAR.write().close();
+                    navigator.push(Route::LinkedAccounts {});
+                },
+                "Linked Accounts"
+            }
+            {ModerationLink}
+            a {
+                class: "sidebar-navlink",
+                onclick: move |_| {
+                    SIDEBAR.write().close();
+                    navigator.push(Route::Drafts {});
+                },
+                "Drafts"
+            }
+            a {
+                class: "sidebar-navlink",
+                onclick: move |_| {
+                    SIDEBAR.write().close();
+                    navigator.push(Route::Lists {});
+                },
+                "Lists"
+            }
+            {ListLinks}
+            a {
+                class: "sidebar-navlink",
+                onclick: move |_| {
+                    SIDEBAR.write().close();
+                    navigator.push(Route::Analytics {});
+                },
+                "Analytics"
+            }
             a {
                 class: "sidebar-navlink",
                 onclick: move |_| {
@@ -99,13 +222,7 @@ pub fn Sidebar() -> Element {
             }
             a {
                 class: "sidebar-navlink",
-                onclick: move |_| {
-                    crate::util::cookie::set_session("".to_string(), SessionId::new(), Utc::now());
-                    SIDEBAR.write().close();
-                    LOCAL_PROFILE.write().user_id = None;
-                    LOCAL_PROFILE.write().image = None;
-                    navigator.replace(Route::Login {});
-                },
+                onclick: logout_onclick,
                 "Logout"
             }
 
@@ -14,7 +14,13 @@ use chrono::Duration;
 use dioxus::prelude::*;
 use dioxus_logger::tracing::{info, Level};
 use page::{local_profile::LocalProfile, PostManager, Route, SidebarManager, Toaster};
-use uchat_endpoint::user::endpoint::{GetMyProfile, GetMyProfileOk};
+use uchat_endpoint::{
+    post::{
+        endpoint::{ServerLimits, ServerLimitsOk},
+        types::Draft,
+    },
+    user::endpoint::{GetMyProfile, GetMyProfileOk},
+};
 use util::ApiClient;
 
 pub const ROOT_API_URL: &str = uchat_endpoint::app_url::API_URL;
@@ -23,6 +29,10 @@ pub static TOASTER: GlobalSignal<Toaster> = Signal::global(Toaster::default);
 pub static POSTMANAGER: GlobalSignal<PostManager> = Signal::global(PostManager::default);
 pub static LOCAL_PROFILE: GlobalSignal<LocalProfile> = Signal::global(LocalProfile::default);
 pub static SIDEBAR: GlobalSignal<SidebarManager> = Signal::global(SidebarManager::default);
+/// Limits the server puts on posts, the defaults until they are fetched.
+pub static SERVER_LIMITS: GlobalSignal<ServerLimitsOk> = Signal::global(ServerLimitsOk::default);
+/// Draft picked on the drafts page, for the new post page to continue.
+pub static OPEN_DRAFT: GlobalSignal<Option<Draft>> = Signal::global(|| None);
 
 #[component]
 pub fn Init() -> Element {
@@ -36,6 +46,7 @@ pub fn Init() -> Element {
                 tracing::info!("Successfully retrieved trending posts.");
                 LOCAL_PROFILE.write().image = data.profile_image;
                 LOCAL_PROFILE.write().user_id = Some(data.user_id);
+                LOCAL_PROFILE.write().role = data.role;
             }
             Err(err) => {
                 TOASTER.write().error(
@@ -46,6 +57,12 @@ pub fn Init() -> Element {
             }
         }
     });
+    let _fetch_server_limits = use_future(move || async move {
+        match fetch_json!(<ServerLimitsOk>, api_client, ServerLimits) {
+            Ok(limits) => *SERVER_LIMITS.write() = limits,
+            Err(err) => tracing::error!("Failed to fetch server limits: {err}"),
+        }
+    });
 
     None
 }
@@ -65,5 +82,5 @@ mod prelude {
     pub use crate::page::*;
     pub use crate::util::api_client::fetch_json;
     pub use crate::util::{async_handler, maybe_class, sync_handler, ApiClient};
-    pub use crate::{LOCAL_PROFILE, POSTMANAGER, SIDEBAR, TOASTER};
+    pub use crate::{LOCAL_PROFILE, POSTMANAGER, SERVER_LIMITS, SIDEBAR, TOASTER};
 }
@@ -1,22 +1,40 @@
+mod analytics;
+mod api_tokens;
 mod edit_profile;
 mod home;
+mod linked_accounts;
+mod lists;
 mod login;
+mod moderation;
+mod mute_filters;
 mod new_post;
 mod not_found;
+mod oidc_callback;
 mod register;
+mod security_events;
 mod trending;
+mod two_factor;
 mod view_profile;
 
 pub use crate::elements::*;
 use crate::Init;
+pub use analytics::{Analytics, PostStats};
+pub use api_tokens::ApiTokens;
 use dioxus::prelude::*;
 pub use edit_profile::EditProfile;
 pub use home::{bookmarked::HomeBookmarked, liked::HomeLiked, Home};
+pub use linked_accounts::LinkedAccounts;
+pub use lists::{fetch_own_lists, ListFeed, ListMembers, ListMembership, Lists};
 pub use login::Login;
+pub use moderation::Moderation;
+pub use mute_filters::MuteFilters;
 pub use new_post::*;
 pub use not_found::PageNotFound;
+pub use oidc_callback::OidcCallback;
 pub use register::Register;
+pub use security_events::SecurityEvents;
 pub use trending::Trending;
+pub use two_factor::TwoFactor;
 pub use view_profile::ViewProfile;
 
 #[derive(Routable, Clone, Debug, PartialEq)]
@@ -38,6 +56,24 @@ pub enum Route {
         #[route("/account/login")]
         Login {},
 
+        #[route("/account/oidc/callback")]
+        OidcCallback {},
+
+        #[route("/account/identities")]
+        LinkedAccounts {},
+
+        #[route("/account/2fa")]
+        TwoFactor {},
+
+        #[route("/account/security")]
+        SecurityEvents {},
+
+        #[route("/account/tokens")]
+        ApiTokens {},
+
+        #[route("/account/mutes")]
+        MuteFilters {},
+
         #[route("/post/new_chat")]
         NewChat {},
 
@@ -47,9 +83,36 @@ pub enum Route {
         #[route("/post/new_poll")]
         NewPoll {},
 
+        #[route("/post/drafts")]
+        Drafts {},
+
+        #[route("/lists")]
+        Lists {},
+
+        #[route("/lists/:list_id")]
+        ListFeed {
+            list_id: String,
+        },
+
+        #[route("/lists/:list_id/members")]
+        ListMembers {
+            list_id: String,
+        },
+
         #[route("/posts/trending")]
         Trending {},
 
+        #[route("/analytics")]
+        Analytics {},
+
+        #[route("/analytics/post/:post_id")]
+        PostStats {
+            post_id: String,
+        },
+
+        #[route("/moderation")]
+        Moderation {},
+
         #[route("/profile/edit")]
         EditProfile {},
 
@@ -0,0 +1,300 @@
+#![allow(non_snake_case)]
+
+use crate::elements::post::PublicPostEntry;
+use crate::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use dioxus::prelude::*;
+use dioxus_logger::tracing::error;
+use std::str::FromStr;
+use uchat_domain::PostId;
+use uchat_endpoint::analytics::{
+    endpoint::{PostAnalytics, PostAnalyticsOk, ProfileAnalytics, ProfileAnalyticsOk},
+    types::{AnalyticsPeriod, PostActivity, ProfileVisits},
+};
+
+/// Bucket start in local time, by the hour for a day and by the date otherwise.
+fn bucket_label(start: DateTime<Utc>, period: AnalyticsPeriod) -> String {
+    let start = start.with_timezone(&chrono::Local);
+    match period {
+        AnalyticsPeriod::Day => start.format("%H:%M").to_string(),
+        AnalyticsPeriod::Week | AnalyticsPeriod::Month => start.format("%b %d").to_string(),
+    }
+}
+
+/// Width of a bar showing `value` next to the largest value `max`.
+fn bar_width(value: i64, max: i64) -> String {
+    if max <= 0 {
+        return "0%".to_string();
+    }
+    format!("{}%", value * 100 / max)
+}
+
+#[component]
+pub fn PeriodPicker(period: Signal<AnalyticsPeriod>) -> Element {
+    let options = AnalyticsPeriod::ALL.map(|option| {
+        let selected_class = maybe_class!("bg-slate-800", period() == option);
+        (option, option.label(), selected_class)
+    });
+
+    rsx!(
+        div {
+            class: "flex flex-row gap-2 my-2",
+            for (option, label, selected_class) in options {
+                button {
+                    key: "{label}",
+                    class: "btn {selected_class}",
+                    onclick: move |_| period.set(option),
+                    "{label}"
+                }
+            }
+        }
+    )
+}
+
+#[component]
+pub fn VisitsChart(visits: Vec<ProfileVisits>, period: AnalyticsPeriod) -> Element {
+    let max = visits.iter().map(|bucket| bucket.visits).max().unwrap_or(0);
+    let total: i64 = visits.iter().map(|bucket| bucket.visits).sum();
+    let rows = visits
+        .into_iter()
+        .map(|bucket| {
+            (
+                bucket.start.timestamp(),
+                bucket_label(bucket.start, period),
+                bar_width(bucket.visits, max),
+                bucket.visits,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    rsx!(
+        div {
+            class: "flex flex-col gap-1",
+            div { class: "font-bold", "Profile visits: {total}" }
+            for (key, label, width, visits) in rows {
+                div {
+                    key: "{key}",
+                    class: "flex flex-row items-center gap-2 text-sm",
+                    span { class: "w-16 shrink-0", "{label}" }
+                    div {
+                        class: "h-3 bg-slate-600 rounded",
+                        style: "width: {width}",
+                    }
+                    span { "{visits}" }
+                }
+            }
+        }
+    )
+}
+
+#[component]
+pub fn ActivityTable(activity: Vec<PostActivity>, period: AnalyticsPeriod) -> Element {
+    let totals = activity
+        .iter()
+        .fold(PostActivity::default(), |mut totals, bucket| {
+            totals.impressions += bucket.impressions;
+            totals.reactions += bucket.reactions;
+            totals.boosts += bucket.boosts;
+            totals.replies += bucket.replies;
+            totals.votes += bucket.votes;
+            totals
+        });
+    let max = activity
+        .iter()
+        .map(|bucket| bucket.impressions)
+        .max()
+        .unwrap_or(0);
+    let rows = activity
+        .into_iter()
+        .map(|bucket| {
+            (
+                bucket_label(bucket.start, period),
+                bar_width(bucket.impressions, max),
+                bucket,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    rsx!(
+        table {
+            class: "w-full text-sm text-right",
+            thead {
+                tr {
+                    th { class: "text-left", "" }
+                    th { "Impressions" }
+                    th { "Reactions" }
+                    th { "Boosts" }
+                    th { "Replies" }
+                    th { "Votes" }
+                }
+            }
+            tbody {
+                for (label, width, bucket) in rows {
+                    tr {
+                        key: "{bucket.start.timestamp()}",
+                        td { class: "text-left", "{label}" }
+                        td {
+                            div {
+                                class: "flex flex-row justify-end items-center gap-1",
+                                div {
+                                    class: "h-3 bg-slate-600 rounded",
+                                    style: "width: {width}",
+                                }
+                                "{bucket.impressions}"
+                            }
+                        }
+                        td { "{bucket.reactions}" }
+                        td { "{bucket.boosts}" }
+                        td { "{bucket.replies}" }
+                        td { "{bucket.votes}" }
+                    }
+                }
+                tr {
+                    class: "font-bold border-t",
+                    td { class: "text-left", "Total" }
+                    td { "{totals.impressions}" }
+                    td { "{totals.reactions}" }
+                    td { "{totals.boosts}" }
+                    td { "{totals.replies}" }
+                    td { "{totals.votes}" }
+                }
+            }
+        }
+    )
+}
+
+pub fn Analytics() -> Element {
+    let api_client = ApiClient::global();
+    let period = use_signal(AnalyticsPeriod::default);
+    let mut analytics = use_signal(|| None::<ProfileAnalyticsOk>);
+
+    // Refetches when another period is picked
+    let _fetch_analytics = use_resource(move || async move {
+        let request_data = ProfileAnalytics { period: period() };
+        match fetch_json!(<ProfileAnalyticsOk>, api_client, request_data) {
+            Ok(res) => {
+                POSTMANAGER
+                    .write()
+                    .populate(res.most_seen.iter().map(|seen| seen.post.clone()));
+                analytics.set(Some(res));
+            }
+            Err(err) => {
+                error!("Failed to fetch analytics: {:?}", err);
+                TOASTER.write().error(
+                    format!("Failed to retrieve analytics: {err}"),
+                    Duration::milliseconds(1500),
+                );
+            }
+        }
+    });
+
+    let Body = match analytics.read().clone() {
+        None => rsx!( div { "Loading..." } ),
+        Some(res) => rsx!(
+            VisitsChart { visits: res.visits, period: period() }
+            div { class: "font-bold mt-4", "Seen most" }
+            if res.most_seen.is_empty() {
+                div { "None of your posts were seen in this time." }
+            }
+            for seen in res.most_seen {
+                div {
+                    key: "{seen.post.id.to_string()}",
+                    PublicPostEntry { post_id: seen.post.id }
+                    div {
+                        class: "flex flex-row justify-between mx-2 mb-4 text-sm",
+                        span { "{seen.impressions} impressions" }
+                        Link {
+                            class: "link",
+                            to: Route::PostStats { post_id: seen.post.id.to_string() },
+                            "Details"
+                        }
+                    }
+                }
+            }
+        ),
+    };
+
+    rsx!(
+        Appbar {
+            title: "Analytics",
+            AppbarImgButton {
+                click_handler: move |_| {
+                    navigator().go_back();
+                },
+                img: ICON_BACK,
+                label: "Back",
+                title: "Go to the previous page",
+            }
+        }
+        div {
+            class: "flex flex-col gap-3 w-full",
+            p { "Impressions and visits are counted once per user and hour. Your own views aren't counted." }
+            PeriodPicker { period: period }
+            {Body}
+        }
+    )
+}
+
+#[component]
+pub fn PostStats(post_id: ReadOnlySignal<String>) -> Element {
+    let api_client = ApiClient::global();
+    let period = use_signal(AnalyticsPeriod::default);
+    let mut activity = use_signal(|| None::<Vec<PostActivity>>);
+
+    // Refetches when another period is picked
+    let _fetch_analytics = use_resource(move || async move {
+        let Ok(post_id) = PostId::from_str(&post_id.read()) else {
+            navigator().replace(Route::Analytics {});
+            return;
+        };
+        let request_data = PostAnalytics {
+            post_id,
+            period: period(),
+        };
+        match fetch_json!(<PostAnalyticsOk>, api_client, request_data) {
+            Ok(res) => {
+                POSTMANAGER.write().populate(std::iter::once(res.post));
+                activity.set(Some(res.activity));
+            }
+            Err(err) => {
+                error!("Failed to fetch post analytics: {:?}", err);
+                TOASTER.write().error(
+                    format!("Failed to retrieve analytics: {err}"),
+                    Duration::milliseconds(1500),
+                );
+            }
+        }
+    });
+
+    let Post = PostId::from_str(&post_id.read())
+        .ok()
+        .filter(|post_id| POSTMANAGER.read().get(post_id).is_some())
+        .map(|post_id| rsx!(PublicPostEntry { post_id: post_id }));
+    let Body = match activity.read().clone() {
+        None => rsx!( div { "Loading..." } ),
+        Some(activity) => rsx!(ActivityTable {
+            activity: activity,
+            period: period()
+        }),
+    };
+
+    rsx!(
+        Appbar {
+            title: "Post analytics",
+            AppbarImgButton {
+                click_handler: move |_| {
+                    navigator().go_back();
+                },
+                img: ICON_BACK,
+                label: "Back",
+                title: "Go to the previous page",
+            }
+        }
+        {Post}
+        div {
+            class: "flex flex-col gap-3 w-full",
+            PeriodPicker { period: period }
+            {Body}
+        }
+    )
+}
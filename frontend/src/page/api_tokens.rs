@@ -0,0 +1,264 @@
+#![allow(non_snake_case)]
+
+use crate::prelude::*;
+use chrono::Duration;
+use dioxus::prelude::*;
+use dioxus_logger::tracing::{error, info};
+use keyed_notifications_box::KeyedNotifications;
+use uchat_endpoint::user::{
+    endpoint::{
+        CreateApiToken, CreateApiTokenOk, ListApiTokens, ListApiTokensOk, RevokeApiToken,
+        RevokeApiTokenOk,
+    },
+    types::{ApiToken, TokenScope},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct PageState {
+    pub tokens: Option<Vec<ApiToken>>,
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    pub expires_in_days: String,
+    /// Secret of the token created last, shown only once.
+    pub new_secret: Option<String>,
+    pub form_error: KeyedNotifications,
+}
+
+impl PageState {
+    fn toggle_scope(&mut self, scope: TokenScope) {
+        match self.scopes.iter().position(|s| *s == scope) {
+            Some(idx) => {
+                self.scopes.remove(idx);
+            }
+            None => self.scopes.push(scope),
+        }
+    }
+}
+
+const EXPIRY_OPTIONS: [(&str, &str); 4] = [
+    ("30", "30 days"),
+    ("90", "90 days"),
+    ("365", "1 year"),
+    ("", "Never"),
+];
+
+#[component]
+pub fn TokenEntry(token: ApiToken, page_state: Signal<PageState>) -> Element {
+    let api_client = ApiClient::global();
+    let token_id = token.id;
+    let scopes = token
+        .scopes
+        .iter()
+        .map(|scope| scope.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let expires = token
+        .expires_at
+        .map(|at| format!("expires {}", at.format("%Y-%m-%d")))
+        .unwrap_or_else(|| "never expires".to_string());
+    let last_used = token
+        .last_used_at
+        .map(|at| format!("last used {}", at.format("%Y-%m-%d %H:%M")))
+        .unwrap_or_else(|| "never used".to_string());
+
+    let revoke_onclick = async_handler!([api_client, page_state], move |_| async move {
+        match fetch_json!(<RevokeApiTokenOk>, api_client, RevokeApiToken { id: token_id }) {
+            Ok(_) => {
+                info!("Token revoked");
+                page_state.with_mut(|state| {
+                    if let Some(tokens) = state.tokens.as_mut() {
+                        tokens.retain(|token| token.id != token_id);
+                    }
+                });
+                TOASTER
+                    .write()
+                    .success("Token revoked", Duration::milliseconds(1200));
+            }
+            Err(err) => TOASTER.write().error(
+                format!("Failed to revoke token: {err}"),
+                Duration::milliseconds(1200),
+            ),
+        }
+    });
+
+    rsx!(
+        li {
+            class: "flex flex-row justify-between items-center border-b py-2",
+            div {
+                class: "flex flex-col",
+                span { class: "font-bold", "{token.name}" }
+                span { class: "font-mono text-sm", "{token.prefix}…" }
+                span { class: "text-sm", "{scopes} · {expires} · {last_used}" }
+            }
+            button {
+                class: "btn",
+                onclick: revoke_onclick,
+                "Revoke"
+            }
+        }
+    )
+}
+
+#[component]
+pub fn NewToken(page_state: Signal<PageState>) -> Element {
+    let api_client = ApiClient::global();
+
+    let create_onsubmit = async_handler!([api_client, page_state], move |_| async move {
+        let request_data = page_state.with(|state| CreateApiToken {
+            name: state.name.trim().to_string(),
+            scopes: state.scopes.clone(),
+            expires_in_days: state.expires_in_days.parse().ok(),
+        });
+        match fetch_json!(<CreateApiTokenOk>, api_client, request_data) {
+            Ok(res) => {
+                info!("Token created");
+                page_state.with_mut(|state| {
+                    state.name.clear();
+                    state.scopes.clear();
+                    state.form_error.remove("token");
+                    state.new_secret = Some(res.secret);
+                    state
+                        .tokens
+                        .get_or_insert_with(Vec::new)
+                        .insert(0, res.token);
+                });
+            }
+            Err(err) => {
+                page_state.with_mut(|state| state.form_error.set("token", err.to_string()));
+            }
+        }
+    });
+
+    let checkboxes = TokenScope::ALL.into_iter().map(|scope| {
+        let id = format!("scope-{}", scope.as_str());
+        let checked = page_state.with(|state| state.scopes.contains(&scope));
+        rsx!(
+            div {
+                key: "{id}",
+                class: "flex flex-row gap-2 items-center",
+                input {
+                    id: "{id}",
+                    r#type: "checkbox",
+                    checked: checked,
+                    oninput: move |_| page_state.with_mut(|state| state.toggle_scope(scope)),
+                }
+                label {
+                    r#for: "{id}",
+                    "{scope.as_str()}: {scope.description()}"
+                }
+            }
+        )
+    });
+
+    rsx!(
+        form {
+            class: "flex flex-col gap-3",
+            onsubmit: create_onsubmit,
+            fieldset {
+                class: "fieldset",
+                legend { "New token" }
+                div {
+                    label {
+                        r#for: "token-name",
+                        "Name"
+                    }
+                    input {
+                        id: "token-name",
+                        class: "input-field",
+                        placeholder: "My bot",
+                        value: "{page_state.read().name}",
+                        oninput: move |ev| page_state.with_mut(|state| state.name = ev.value()),
+                    }
+                }
+                {checkboxes}
+                div {
+                    label {
+                        r#for: "token-expiry",
+                        "Expires after"
+                    }
+                    select {
+                        id: "token-expiry",
+                        class: "input-field",
+                        value: "{page_state.read().expires_in_days}",
+                        onchange: move |ev| page_state.with_mut(|state| state.expires_in_days = ev.value()),
+                        for (value, label) in EXPIRY_OPTIONS {
+                            option { value: "{value}", "{label}" }
+                        }
+                    }
+                }
+            }
+            button {
+                class: "btn",
+                r#type: "submit",
+                "Create token"
+            }
+        }
+    )
+}
+
+pub fn ApiTokens() -> Element {
+    let api_client = ApiClient::global();
+    let mut page_state = use_signal(|| PageState {
+        expires_in_days: EXPIRY_OPTIONS[0].0.to_string(),
+        ..PageState::default()
+    });
+
+    let _fetch_tokens = use_resource(move || async move {
+        match fetch_json!(<ListApiTokensOk>, api_client, ListApiTokens) {
+            Ok(res) => page_state.with_mut(|state| state.tokens = Some(res.tokens)),
+            Err(err) => {
+                error!("Failed to fetch tokens: {:?}", err);
+                TOASTER.write().error(
+                    format!("Failed to retrieve tokens: {err}"),
+                    Duration::milliseconds(1200),
+                );
+            }
+        }
+    });
+
+    let new_secret = page_state.read().new_secret.clone();
+    let tokens = match page_state.read().tokens.clone() {
+        None => rsx!( div { "Loading..." } ),
+        Some(tokens) if tokens.is_empty() => rsx!( div { "No tokens yet." } ),
+        Some(tokens) => rsx!(
+            ul {
+                class: "flex flex-col",
+                for token in tokens {
+                    TokenEntry { key: "{token.id}", token: token, page_state: page_state }
+                }
+            }
+        ),
+    };
+
+    rsx!(
+        Appbar {
+            title: "Access tokens",
+            AppbarImgButton {
+                click_handler: move |_| {
+                    navigator().go_back();
+                },
+                img: ICON_BACK,
+                label: "Back",
+                title: "Go to the previous page",
+            }
+        }
+        div {
+            class: "flex flex-col gap-3 w-full",
+            p { "Tokens let scripts and bots use the API on your behalf. Send them in an Authorization: Bearer header." }
+            if let Some(secret) = new_secret {
+                fieldset {
+                    class: "fieldset",
+                    legend { "Your new token" }
+                    p { "Copy it now, it won't be shown again." }
+                    span { class: "font-mono break-all", "{secret}" }
+                }
+            }
+            {tokens}
+            NewToken { page_state: page_state }
+            KeyedNotificationsBox {
+                legend: "Errors",
+                notification: page_state.read().form_error.clone()
+            }
+        }
+    )
+}
@@ -1,28 +1,274 @@
 #![allow(non_snake_case)]
 
+use std::collections::HashMap;
+
+use crate::elements::post::PublicPostEntry;
 use crate::prelude::*;
 use chrono::Duration;
 use dioxus::prelude::*;
-use uchat_endpoint::post::endpoint::{BookmarkedPost, BookmarkedPostOk};
+use uchat_domain::{BookmarkFolderId, PostId};
+use uchat_endpoint::post::{
+    endpoint::{
+        BookmarkedPost, BookmarkedPostOk, CreateBookmarkFolder, CreateBookmarkFolderOk,
+        DeleteBookmarkFolder, DeleteBookmarkFolderOk, GetBookmarkFolders, GetBookmarkFoldersOk,
+        MoveBookmark, MoveBookmarkOk, SetBookmarkNote, SetBookmarkNoteOk,
+    },
+    types::{BookmarkDetails, BookmarkFolder},
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct PageState {
+    pub folders: Vec<BookmarkFolder>,
+    pub bookmarks: HashMap<PostId, BookmarkDetails>,
+    pub new_folder: String,
+}
+
+#[component]
+pub fn FolderTabs(
+    page_state: Signal<PageState>,
+    selected: Signal<Option<BookmarkFolderId>>,
+) -> Element {
+    let api_client = ApiClient::global();
+    let state = page_state.read();
+    let tab_class =
+        |folder_id: Option<BookmarkFolderId>| maybe_class!("bg-slate-800", selected() == folder_id);
+    let all_class = tab_class(None);
+    let folders = state
+        .folders
+        .iter()
+        .map(|folder| (folder.clone(), tab_class(Some(folder.id))))
+        .collect::<Vec<_>>();
+    let new_folder = state.new_folder.clone();
+    let name_chars = new_folder.trim().chars().count();
+    let can_create = (1..=BookmarkFolder::MAX_NAME_CHARS).contains(&name_chars);
+    let create_btn_style = maybe_class!("btn-disabled", !can_create);
+    drop(state);
+
+    let create_onsubmit = async_handler!([api_client, page_state], move |_| async move {
+        let request_data = CreateBookmarkFolder {
+            name: page_state.read().new_folder.trim().to_string(),
+        };
+        match fetch_json!(<CreateBookmarkFolderOk>, api_client, request_data) {
+            Ok(res) => page_state.with_mut(|state| {
+                state.new_folder.clear();
+                state.folders.push(res.folder);
+                state.folders.sort_by(|a, b| a.name.cmp(&b.name));
+            }),
+            Err(err) => TOASTER.write().error(
+                format!("Failed to create folder: {err}"),
+                Duration::milliseconds(1200),
+            ),
+        }
+    });
+
+    let delete_onclick = async_handler!([api_client, page_state, selected], move |_| async move {
+        let Some(folder_id) = selected() else {
+            return;
+        };
+        let request_data = DeleteBookmarkFolder { folder_id };
+        match fetch_json!(<DeleteBookmarkFolderOk>, api_client, request_data) {
+            Ok(_) => {
+                page_state.with_mut(|state| state.folders.retain(|folder| folder.id != folder_id));
+                selected.set(None);
+            }
+            Err(err) => TOASTER.write().error(
+                format!("Failed to delete folder: {err}"),
+                Duration::milliseconds(1200),
+            ),
+        }
+    });
+
+    rsx!(
+        div {
+            class: "flex flex-col gap-2 my-2",
+            div {
+                class: "flex flex-row flex-wrap gap-2",
+                button {
+                    class: "btn {all_class}",
+                    onclick: move |_| selected.set(None),
+                    "All"
+                }
+                for (folder, class) in folders {
+                    button {
+                        key: "{folder.id.to_string()}",
+                        class: "btn {class}",
+                        onclick: move |_| selected.set(Some(folder.id)),
+                        "{folder.name} ({folder.bookmark_count})"
+                    }
+                }
+            }
+            form {
+                class: "flex flex-row gap-2",
+                onsubmit: create_onsubmit,
+                input {
+                    class: "input-field",
+                    placeholder: "New folder",
+                    value: "{new_folder}",
+                    oninput: move |ev| page_state.with_mut(|state| state.new_folder = ev.value()),
+                }
+                button {
+                    class: "btn {create_btn_style}",
+                    r#type: "submit",
+                    disabled: !can_create,
+                    "Add"
+                }
+                if selected().is_some() {
+                    button {
+                        class: "btn bg-red-700",
+                        r#type: "button",
+                        onclick: delete_onclick,
+                        "Delete folder"
+                    }
+                }
+            }
+        }
+    )
+}
+
+/// Folder picker and private note under a bookmarked post.
+#[component]
+pub fn BookmarkNote(
+    post_id: PostId,
+    page_state: Signal<PageState>,
+    selected: Signal<Option<BookmarkFolderId>>,
+) -> Element {
+    let api_client = ApiClient::global();
+    let details = page_state.read().bookmarks.get(&post_id).cloned();
+    let mut note = use_signal(|| {
+        details
+            .as_ref()
+            .and_then(|details| details.note.clone())
+            .unwrap_or_default()
+    });
+    let details = details?;
+    let folders = page_state.read().folders.clone();
+    let folder_value = details
+        .folder_id
+        .map(|folder_id| folder_id.to_string())
+        .unwrap_or_default();
+    let note_changed = *note.read() != details.note.clone().unwrap_or_default();
+    let too_long = note.read().trim().chars().count() > BookmarkDetails::MAX_NOTE_CHARS;
+    let save_btn_style = maybe_class!("btn-disabled", !note_changed || too_long);
+
+    let move_onchange = move |ev: Event<FormData>| {
+        let folder_id = ev.value().parse::<BookmarkFolderId>().ok();
+        spawn(async move {
+            let request_data = MoveBookmark { post_id, folder_id };
+            match fetch_json!(<MoveBookmarkOk>, api_client, request_data) {
+                Ok(_) => page_state.with_mut(|state| {
+                    let previous = state
+                        .bookmarks
+                        .get(&post_id)
+                        .and_then(|bookmark| bookmark.folder_id);
+                    for folder in state.folders.iter_mut() {
+                        if Some(folder.id) == previous {
+                            folder.bookmark_count -= 1;
+                        }
+                        if Some(folder.id) == folder_id {
+                            folder.bookmark_count += 1;
+                        }
+                    }
+                    if selected().is_some() && selected() != folder_id {
+                        POSTMANAGER.write().posts.shift_remove(&post_id);
+                    }
+                    if let Some(bookmark) = state.bookmarks.get_mut(&post_id) {
+                        bookmark.folder_id = folder_id;
+                    }
+                }),
+                Err(err) => TOASTER.write().error(
+                    format!("Failed to move bookmark: {err}"),
+                    Duration::milliseconds(1200),
+                ),
+            }
+        });
+    };
+
+    let save_onclick = async_handler!([api_client, page_state], move |_| async move {
+        let request_data = SetBookmarkNote {
+            post_id,
+            note: note.read().clone(),
+        };
+        match fetch_json!(<SetBookmarkNoteOk>, api_client, request_data) {
+            Ok(res) => {
+                note.set(res.note.clone().unwrap_or_default());
+                page_state.with_mut(|state| {
+                    if let Some(bookmark) = state.bookmarks.get_mut(&post_id) {
+                        bookmark.note = res.note;
+                    }
+                });
+            }
+            Err(err) => TOASTER.write().error(
+                format!("Failed to save note: {err}"),
+                Duration::milliseconds(1200),
+            ),
+        }
+    });
+
+    rsx!(
+        div {
+            class: "flex flex-col gap-2 mx-2 mb-4",
+            select {
+                class: "input-field",
+                value: "{folder_value}",
+                onchange: move_onchange,
+                option { value: "", "No folder" }
+                for folder in folders {
+                    option {
+                        key: "{folder.id.to_string()}",
+                        value: "{folder.id.to_string()}",
+                        "{folder.name}"
+                    }
+                }
+            }
+            textarea {
+                class: "input-field",
+                rows: 2,
+                placeholder: "Private note",
+                value: "{note}",
+                oninput: move |ev| note.set(ev.value()),
+            }
+            div {
+                class: "flex flex-row justify-end",
+                button {
+                    class: "btn {save_btn_style}",
+                    disabled: !note_changed || too_long,
+                    onclick: save_onclick,
+                    "Save note"
+                }
+            }
+        }
+    )
+}
 
 #[component]
 pub fn HomeBookmarked() -> Element {
     let api_client = ApiClient::global();
+    let mut page_state = use_signal(PageState::default);
+    // Folder whose bookmarks are shown, or all bookmarks with `None`
+    let selected = use_signal(|| None::<BookmarkFolderId>);
 
-    // Initialize tracing
-    tracing::info!("Initializing Bookmarked component.");
+    let _fetch_folders = use_resource(move || async move {
+        match fetch_json!(<GetBookmarkFoldersOk>, api_client, GetBookmarkFolders) {
+            Ok(res) => page_state.with_mut(|state| state.folders = res.folders),
+            Err(err) => tracing::error!("Failed to fetch bookmark folders: {:?}", err),
+        }
+    });
 
-    // Fetch trending posts asynchronously
+    // Refetches when another folder is picked
     let _fetch_posts = use_resource(move || async move {
+        let folder_id = selected();
         POSTMANAGER.write().clear();
-        // Define a timeout duration and start fetching data
-        match fetch_json!(<BookmarkedPostOk>, api_client, BookmarkedPost) {
+        match fetch_json!(<BookmarkedPostOk>, api_client, BookmarkedPost { folder_id }) {
             Ok(data) => {
                 tracing::info!("Successfully retrieved bookmarked posts.");
+                page_state.with_mut(|state| {
+                    state.bookmarks = data
+                        .bookmarks
+                        .into_iter()
+                        .map(|bookmark| (bookmark.post_id, bookmark))
+                        .collect();
+                });
                 POSTMANAGER.write().populate(data.posts.into_iter());
-                TOASTER
-                    .write()
-                    .info("Retrieving bookmarked posts", Duration::milliseconds(1500));
             }
             Err(err) => {
                 tracing::error!("Failed to fetch bookmarked posts: {:?}", err);
@@ -34,32 +280,44 @@ pub fn HomeBookmarked() -> Element {
         }
     });
 
-    let post_manager = POSTMANAGER.read();
+    let post_ids = POSTMANAGER.read().posts.keys().copied().collect::<Vec<_>>();
 
-    let Posts = {
-        let posts = post_manager.all_to_public();
-        if posts.is_empty() {
-            let TrendingLink = rsx!(
-                Link {
-                    to: Route::Trending {},
-                    class: "link",
-                    "trending"
+    let Posts = if post_ids.is_empty() && selected().is_some() {
+        rsx!(
+            div {
+                class: "text-center my-6",
+                "There are no bookmarks in this folder."
+            }
+        )
+    } else if post_ids.is_empty() {
+        let TrendingLink = rsx!(
+            Link {
+                to: Route::Trending {},
+                class: "link",
+                "trending"
+            }
+        );
+        rsx!(
+            div {
+                // Tailwind doesn't support spaces so we have to use underscore
+                class: "flex flex-col text-center justify-center
+                h-[calc(100vh_-_var(--navbar-height)_-_var(--appbar-height))]",
+                span {
+                    "You haven't bookmarked any posts yet. Checkout what's:   " {TrendingLink}
+                    "   and follow some users to get started."
                 }
-            );
-            rsx!(
+            }
+        )
+    } else {
+        rsx!(
+            for post_id in post_ids {
                 div {
-                    // Tailwind doesn't support spaces so we have to use underscore
-                    class: "flex flex-col text-center justify-center
-                    h-[calc(100vh_-_var(--navbar-height)_-_var(--appbar-height))]",
-                    span {
-                        "You haven't bookmarked any posts yet. Checkout what's:   " {TrendingLink}
-                        "   and follow some users to get started."
-                    }
+                    key: "{post_id.to_string()}",
+                    PublicPostEntry { post_id: post_id }
+                    BookmarkNote { post_id: post_id, page_state: page_state, selected: selected }
                 }
-            )
-        } else {
-            rsx!({ posts.into_iter() })
-        }
+            }
+        )
     };
 
     rsx!(
@@ -90,6 +348,7 @@ pub fn HomeBookmarked() -> Element {
                 title: "Go to Home page",
             },
         }
+        FolderTabs { page_state: page_state, selected: selected }
         {Posts}
     )
 }
@@ -0,0 +1,184 @@
+#![allow(non_snake_case)]
+
+use crate::prelude::*;
+use chrono::Duration;
+use dioxus::prelude::*;
+use dioxus_logger::tracing::error;
+use uchat_endpoint::user::{
+    endpoint::{
+        ListIdentities, ListIdentitiesOk, ListOidcProviders, ListOidcProvidersOk, StartOidcLink,
+        StartOidcLoginOk, UnlinkIdentity, UnlinkIdentityOk,
+    },
+    types::{LinkedIdentity, OidcProvider},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct PageState {
+    pub identities: Option<Vec<LinkedIdentity>>,
+    pub providers: Vec<OidcProvider>,
+}
+
+#[component]
+pub fn IdentityEntry(identity: LinkedIdentity, page_state: Signal<PageState>) -> Element {
+    let api_client = ApiClient::global();
+    let provider_id = identity.provider.id.clone();
+    let email = identity.email.clone().unwrap_or_default();
+    let linked_on = identity.created_at.format("%Y-%m-%d");
+    let last_login = identity
+        .last_login_at
+        .map(|at| format!("last used {}", at.format("%Y-%m-%d %H:%M")))
+        .unwrap_or_else(|| "never used to sign in".to_string());
+
+    let unlink_onclick = move |_| {
+        let provider_id = provider_id.clone();
+        spawn(async move {
+            let request_data = UnlinkIdentity {
+                provider: provider_id.clone(),
+            };
+            match fetch_json!(<UnlinkIdentityOk>, api_client, request_data) {
+                Ok(_) => {
+                    page_state.with_mut(|state| {
+                        if let Some(identities) = state.identities.as_mut() {
+                            identities.retain(|identity| identity.provider.id != provider_id);
+                        }
+                    });
+                    TOASTER
+                        .write()
+                        .success("Account unlinked", Duration::milliseconds(1200));
+                }
+                Err(err) => TOASTER.write().error(
+                    format!("Failed to unlink account: {err}"),
+                    Duration::milliseconds(1200),
+                ),
+            }
+        });
+    };
+
+    rsx!(
+        li {
+            class: "flex flex-row justify-between items-center border-b py-2",
+            div {
+                class: "flex flex-col",
+                span { class: "font-bold", "{identity.provider.name}" }
+                span { class: "text-sm", "{email}" }
+                span { class: "text-sm", "linked {linked_on} · {last_login}" }
+            }
+            button {
+                class: "btn",
+                onclick: unlink_onclick,
+                "Unlink"
+            }
+        }
+    )
+}
+
+#[component]
+pub fn LinkButton(provider: OidcProvider) -> Element {
+    let api_client = ApiClient::global();
+    let provider_id = provider.id.clone();
+
+    let onclick = move |_| {
+        let request_data = StartOidcLink {
+            provider: provider_id.clone(),
+        };
+        spawn(async move {
+            match fetch_json!(<StartOidcLoginOk>, api_client, request_data) {
+                Ok(res) => {
+                    if let Err(e) = crate::util::window()
+                        .location()
+                        .set_href(res.authorization_url.as_str())
+                    {
+                        error!("Failed to redirect to provider: {:?}", e);
+                    }
+                }
+                Err(err) => TOASTER.write().error(
+                    format!("Failed to link account: {err}"),
+                    Duration::milliseconds(1200),
+                ),
+            }
+        });
+    };
+
+    rsx!(
+        button {
+            class: "btn",
+            onclick: onclick,
+            "Link {provider.name}"
+        }
+    )
+}
+
+pub fn LinkedAccounts() -> Element {
+    let api_client = ApiClient::global();
+    let mut page_state = use_signal(PageState::default);
+
+    let _fetch_identities = use_resource(move || async move {
+        match fetch_json!(<ListIdentitiesOk>, api_client, ListIdentities) {
+            Ok(res) => page_state.with_mut(|state| state.identities = Some(res.identities)),
+            Err(err) => {
+                error!("Failed to fetch linked accounts: {:?}", err);
+                TOASTER.write().error(
+                    format!("Failed to retrieve linked accounts: {err}"),
+                    Duration::milliseconds(1200),
+                );
+            }
+        }
+        match fetch_json!(<ListOidcProvidersOk>, api_client, ListOidcProviders) {
+            Ok(res) => page_state.with_mut(|state| state.providers = res.providers),
+            Err(err) => error!("Failed to fetch sign in providers: {:?}", err),
+        }
+    });
+
+    let identities = match page_state.read().identities.clone() {
+        None => rsx!( div { "Loading..." } ),
+        Some(identities) if identities.is_empty() => rsx!( div { "No linked accounts." } ),
+        Some(identities) => rsx!(
+            ul {
+                class: "flex flex-col",
+                for identity in identities {
+                    IdentityEntry {
+                        key: "{identity.provider.id}",
+                        identity: identity,
+                        page_state: page_state
+                    }
+                }
+            }
+        ),
+    };
+
+    let unlinked = page_state.with(|state| {
+        let linked = state.identities.clone().unwrap_or_default();
+        state
+            .providers
+            .iter()
+            .filter(|provider| {
+                !linked
+                    .iter()
+                    .any(|identity| identity.provider.id == provider.id)
+            })
+            .cloned()
+            .collect::<Vec<_>>()
+    });
+
+    rsx!(
+        Appbar {
+            title: "Linked accounts",
+            AppbarImgButton {
+                click_handler: move |_| {
+                    navigator().go_back();
+                },
+                img: ICON_BACK,
+                label: "Back",
+                title: "Go to the previous page",
+            }
+        }
+        div {
+            class: "flex flex-col gap-3 w-full",
+            p { "Sign in with an account from another service instead of your password." }
+            {identities}
+            for provider in unlinked {
+                LinkButton { key: "{provider.id}", provider: provider }
+            }
+        }
+    )
+}
@@ -0,0 +1,483 @@
+#![allow(non_snake_case)]
+
+use crate::prelude::*;
+use chrono::Duration;
+use dioxus::prelude::*;
+use dioxus_logger::tracing::{error, info};
+use std::str::FromStr;
+use uchat_domain::{ListId, UserId};
+use uchat_endpoint::{
+    post::endpoint::{ListTimeline, ListTimelineOk},
+    user::{
+        endpoint::{
+            AddListMember, AddListMemberOk, CreateList, CreateListOk, DeleteList, DeleteListOk,
+            GetList, GetListMemberships, GetListMembershipsOk, GetListOk, GetLists, GetListsOk,
+            RemoveListMember, RemoveListMemberOk,
+        },
+        types::{PublicUserProfile, UserList},
+    },
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct PageState {
+    pub lists: Option<Vec<UserList>>,
+    pub name: String,
+    pub description: String,
+    pub private: bool,
+}
+
+impl PageState {
+    pub fn can_submit(&self) -> bool {
+        let name = self.name.trim().chars().count();
+        (1..=UserList::MAX_NAME_CHARS).contains(&name)
+            && self.description.trim().chars().count() <= UserList::MAX_DESCRIPTION_CHARS
+    }
+}
+
+/// Lists of the logged in user, for the sidebar and profiles.
+pub async fn fetch_own_lists() -> Option<Vec<UserList>> {
+    let api_client = ApiClient::global();
+    match fetch_json!(<GetListsOk>, api_client, GetLists { owner: None }) {
+        Ok(res) => Some(res.lists),
+        Err(err) => {
+            error!("Failed to fetch lists: {:?}", err);
+            None
+        }
+    }
+}
+
+#[component]
+pub fn ListEntry(list: UserList, page_state: Signal<PageState>) -> Element {
+    let api_client = ApiClient::global();
+    let list_id = list.id;
+    let visibility = if list.private { "private" } else { "public" };
+    let description = list.description.clone().unwrap_or_default();
+
+    let delete_onclick = async_handler!([api_client, page_state], move |_| async move {
+        match fetch_json!(<DeleteListOk>, api_client, DeleteList { list_id }) {
+            Ok(_) => {
+                info!("List deleted");
+                page_state.with_mut(|state| {
+                    if let Some(lists) = state.lists.as_mut() {
+                        lists.retain(|list| list.id != list_id);
+                    }
+                });
+            }
+            Err(err) => TOASTER.write().error(
+                format!("Failed to delete list: {err}"),
+                Duration::milliseconds(1200),
+            ),
+        }
+    });
+
+    rsx!(
+        li {
+            class: "flex flex-row justify-between items-center border-b py-2",
+            div {
+                class: "flex flex-col",
+                Link {
+                    class: "font-bold link",
+                    to: Route::ListFeed { list_id: list_id.to_string() },
+                    "{list.name}"
+                }
+                span { class: "text-sm", "{list.member_count} members · {visibility}" }
+                span { class: "text-sm", "{description}" }
+            }
+            div {
+                class: "flex flex-row gap-2",
+                button {
+                    class: "btn",
+                    onclick: move |_| {
+                        navigator().push(Route::ListMembers { list_id: list_id.to_string() });
+                    },
+                    "Members"
+                }
+                button {
+                    class: "btn bg-red-700",
+                    onclick: delete_onclick,
+                    "Delete"
+                }
+            }
+        }
+    )
+}
+
+#[component]
+pub fn NewList(page_state: Signal<PageState>) -> Element {
+    let api_client = ApiClient::global();
+    let can_submit = page_state.read().can_submit();
+    let submit_btn_style = maybe_class!("btn-disabled", !can_submit);
+
+    let create_onsubmit = async_handler!([api_client, page_state], move |_| async move {
+        let request_data = page_state.with(|state| CreateList {
+            name: state.name.trim().to_string(),
+            description: Some(state.description.trim().to_string())
+                .filter(|description| !description.is_empty()),
+            private: state.private,
+        });
+        match fetch_json!(<CreateListOk>, api_client, request_data) {
+            Ok(res) => {
+                info!("List created");
+                page_state.with_mut(|state| {
+                    state.name.clear();
+                    state.description.clear();
+                    let lists = state.lists.get_or_insert_with(Vec::new);
+                    lists.push(res.list);
+                    lists.sort_by(|a, b| a.name.cmp(&b.name));
+                });
+            }
+            Err(err) => TOASTER.write().error(
+                format!("Failed to create list: {err}"),
+                Duration::milliseconds(1200),
+            ),
+        }
+    });
+
+    rsx!(
+        form {
+            class: "flex flex-col gap-3",
+            onsubmit: create_onsubmit,
+            fieldset {
+                class: "fieldset",
+                legend { "New list" }
+                div {
+                    label {
+                        r#for: "list-name",
+                        "Name"
+                    }
+                    input {
+                        id: "list-name",
+                        class: "input-field",
+                        value: "{page_state.read().name}",
+                        oninput: move |ev| page_state.with_mut(|state| state.name = ev.value()),
+                    }
+                }
+                div {
+                    label {
+                        r#for: "list-description",
+                        "Description (optional)"
+                    }
+                    input {
+                        id: "list-description",
+                        class: "input-field",
+                        value: "{page_state.read().description}",
+                        oninput: move |ev| page_state.with_mut(|state| state.description = ev.value()),
+                    }
+                }
+                div {
+                    class: "flex flex-row gap-2 items-center",
+                    input {
+                        id: "list-private",
+                        r#type: "checkbox",
+                        checked: page_state.read().private,
+                        oninput: move |_| page_state.with_mut(|state| state.private = !state.private),
+                    }
+                    label {
+                        r#for: "list-private",
+                        "Private, only you can see it"
+                    }
+                }
+            }
+            button {
+                class: "btn {submit_btn_style}",
+                r#type: "submit",
+                disabled: !can_submit,
+                "Create"
+            }
+        }
+    )
+}
+
+pub fn Lists() -> Element {
+    let mut page_state = use_signal(PageState::default);
+
+    let _fetch_lists = use_resource(move || async move {
+        match fetch_own_lists().await {
+            Some(lists) => page_state.with_mut(|state| state.lists = Some(lists)),
+            None => TOASTER.write().error(
+                "Failed to retrieve lists".to_string(),
+                Duration::milliseconds(1200),
+            ),
+        }
+    });
+
+    let Entries = match page_state.read().lists.clone() {
+        None => rsx!( div { "Loading..." } ),
+        Some(lists) if lists.is_empty() => rsx!( div { "You haven't made any lists." } ),
+        Some(lists) => rsx!(
+            ul {
+                class: "flex flex-col",
+                for list in lists {
+                    ListEntry { key: "{list.id.to_string()}", list: list, page_state: page_state }
+                }
+            }
+        ),
+    };
+
+    rsx!(
+        Appbar {
+            title: "Lists",
+            AppbarImgButton {
+                click_handler: move |_| {
+                    navigator().go_back();
+                },
+                img: ICON_BACK,
+                label: "Back",
+                title: "Go to the previous page",
+            }
+        }
+        div {
+            class: "flex flex-col gap-3 w-full",
+            p { "Lists gather users whose posts you want to read together. Add users to a list from their profile." }
+            {Entries}
+            NewList { page_state: page_state }
+        }
+    )
+}
+
+#[component]
+pub fn ListFeed(list_id: ReadOnlySignal<String>) -> Element {
+    let api_client = ApiClient::global();
+    let mut list = use_signal(|| None::<UserList>);
+
+    let _fetch_posts = use_resource(move || async move {
+        POSTMANAGER.write().clear();
+        let Ok(list_id) = ListId::from_str(&list_id.read()) else {
+            navigator().replace(Route::Lists {});
+            return;
+        };
+        match fetch_json!(<GetListOk>, api_client, GetList { list_id }) {
+            Ok(res) => list.set(Some(res.list)),
+            Err(err) => {
+                TOASTER.write().error(
+                    format!("Failed to retrieve list: {err}"),
+                    Duration::milliseconds(1500),
+                );
+                return;
+            }
+        }
+        match fetch_json!(<ListTimelineOk>, api_client, ListTimeline { list_id }) {
+            Ok(res) => POSTMANAGER.write().populate(res.posts.into_iter()),
+            Err(err) => {
+                error!("Failed to fetch list posts: {:?}", err);
+                TOASTER.write().error(
+                    format!("Failed to retrieve posts: {err}"),
+                    Duration::milliseconds(1500),
+                );
+            }
+        }
+    });
+
+    let (title, description) = match list.read().as_ref() {
+        Some(list) => (
+            list.name.clone(),
+            list.description.clone().unwrap_or_default(),
+        ),
+        None => ("List".to_string(), String::new()),
+    };
+    let post_manager = POSTMANAGER.read();
+    let posts = post_manager.all_to_public();
+    let Posts = if posts.is_empty() {
+        rsx!(
+            div {
+                class: "flex flex-col text-center justify-center
+                h-[calc(100vh_-_var(--navbar-height)_-_var(--appbar-height))]",
+                "Nobody on this list has posted yet."
+            }
+        )
+    } else {
+        rsx!({ posts.into_iter() })
+    };
+
+    rsx!(
+        Appbar {
+            title: "{title}",
+            AppbarImgButton {
+                click_handler: move |_| {
+                    navigator().push(Route::ListMembers { list_id: list_id.read().clone() });
+                },
+                img: ICON_MESSAGES,
+                label: "Members",
+                title: "Show who is on the list",
+            },
+            AppbarImgButton {
+                click_handler: move |_| {
+                    navigator().go_back();
+                },
+                img: ICON_BACK,
+                label: "Back",
+                title: "Go to the previous page",
+            }
+        }
+        if !description.is_empty() {
+            p { class: "text-center my-2", "{description}" }
+        }
+        {Posts}
+    )
+}
+
+#[component]
+pub fn ListMembers(list_id: ReadOnlySignal<String>) -> Element {
+    let api_client = ApiClient::global();
+    let mut list = use_signal(|| None::<UserList>);
+    let mut members = use_signal(|| None::<Vec<PublicUserProfile>>);
+
+    let _fetch_members = use_resource(move || async move {
+        let Ok(list_id) = ListId::from_str(&list_id.read()) else {
+            navigator().replace(Route::Lists {});
+            return;
+        };
+        match fetch_json!(<GetListOk>, api_client, GetList { list_id }) {
+            Ok(res) => {
+                list.set(Some(res.list));
+                members.set(Some(res.members));
+            }
+            Err(err) => TOASTER.write().error(
+                format!("Failed to retrieve list: {err}"),
+                Duration::milliseconds(1500),
+            ),
+        }
+    });
+
+    let is_owner = list
+        .read()
+        .as_ref()
+        .is_some_and(|list| LOCAL_PROFILE.read().user_id == Some(list.owner));
+    let title = list
+        .read()
+        .as_ref()
+        .map(|list| list.name.clone())
+        .unwrap_or_else(|| "List".to_string());
+
+    let remove = move |user_id: UserId| {
+        spawn(async move {
+            let Some(list_id) = list.read().as_ref().map(|list| list.id) else {
+                return;
+            };
+            let request_data = RemoveListMember { list_id, user_id };
+            match fetch_json!(<RemoveListMemberOk>, api_client, request_data) {
+                Ok(_) => members.with_mut(|members| {
+                    if let Some(members) = members.as_mut() {
+                        members.retain(|member| member.id != user_id);
+                    }
+                }),
+                Err(err) => TOASTER.write().error(
+                    format!("Failed to remove from list: {err}"),
+                    Duration::milliseconds(1200),
+                ),
+            }
+        });
+    };
+
+    let Members = match members.read().clone() {
+        None => rsx!( div { "Loading..." } ),
+        Some(members) if members.is_empty() => rsx!( div { "Nobody is on this list yet." } ),
+        Some(members) => rsx!(
+            ul {
+                class: "flex flex-col",
+                for member in members {
+                    li {
+                        key: "{member.id.to_string()}",
+                        class: "flex flex-row justify-between items-center border-b py-2",
+                        Link {
+                            class: "link",
+                            to: Route::ViewProfile { user_id: member.id.to_string() },
+                            "{member.handle}"
+                        }
+                        if is_owner {
+                            button {
+                                class: "btn",
+                                onclick: move |_| remove(member.id),
+                                "Remove"
+                            }
+                        }
+                    }
+                }
+            }
+        ),
+    };
+
+    rsx!(
+        Appbar {
+            title: "{title}",
+            AppbarImgButton {
+                click_handler: move |_| {
+                    navigator().go_back();
+                },
+                img: ICON_BACK,
+                label: "Back",
+                title: "Go to the previous page",
+            }
+        }
+        div {
+            class: "flex flex-col gap-3 w-full",
+            {Members}
+        }
+    )
+}
+
+/// Checkboxes putting a user on the lists of the logged in user.
+#[component]
+pub fn ListMembership(user_id: UserId) -> Element {
+    let api_client = ApiClient::global();
+    let mut lists = use_signal(|| None::<Vec<UserList>>);
+    let mut memberships = use_signal(Vec::<ListId>::new);
+
+    let _fetch_memberships = use_resource(move || async move {
+        lists.set(fetch_own_lists().await);
+        match fetch_json!(<GetListMembershipsOk>, api_client, GetListMemberships { user_id }) {
+            Ok(res) => memberships.set(res.list_ids),
+            Err(err) => error!("Failed to fetch list memberships: {:?}", err),
+        }
+    });
+
+    let toggle = move |list_id: ListId| {
+        spawn(async move {
+            let on_list = memberships.read().contains(&list_id);
+            let response = if on_list {
+                let request_data = RemoveListMember { list_id, user_id };
+                fetch_json!(<RemoveListMemberOk>, api_client, request_data).map(|_| ())
+            } else {
+                let request_data = AddListMember { list_id, user_id };
+                fetch_json!(<AddListMemberOk>, api_client, request_data).map(|_| ())
+            };
+            match response {
+                Ok(()) => memberships.with_mut(|memberships| {
+                    if on_list {
+                        memberships.retain(|id| *id != list_id);
+                    } else {
+                        memberships.push(list_id);
+                    }
+                }),
+                Err(err) => TOASTER.write().error(
+                    format!("Failed to update list: {err}"),
+                    Duration::milliseconds(1200),
+                ),
+            }
+        });
+    };
+
+    let lists = lists.read().clone().filter(|lists| !lists.is_empty())?;
+    rsx!(
+        fieldset {
+            class: "fieldset",
+            legend { "Lists" }
+            for list in lists {
+                div {
+                    key: "{list.id.to_string()}",
+                    class: "flex flex-row gap-2 items-center",
+                    input {
+                        id: "list-{list.id.to_string()}",
+                        r#type: "checkbox",
+                        checked: memberships.read().contains(&list.id),
+                        oninput: move |_| toggle(list.id),
+                    }
+                    label {
+                        r#for: "list-{list.id.to_string()}",
+                        "{list.name}"
+                    }
+                }
+            }
+        }
+    )
+}
@@ -6,11 +6,17 @@ use chrono::Duration;
 use dioxus::prelude::*;
 use dioxus_logger::tracing::{error, info};
 use uchat_domain::{Password, Username};
-use uchat_endpoint::user::endpoint::{Login, LoginOk};
+use uchat_endpoint::user::endpoint::{
+    ListOidcProviders, ListOidcProvidersOk, Login, LoginOk, LoginOutcome, StartOidcLogin,
+    StartOidcLoginOk, TwoFactorChallenge, VerifyTwoFactor,
+};
 
 pub struct PageState {
     pub username: Signal<String>,
     pub password: Signal<String>,
+    pub remember_me: Signal<bool>,
+    pub challenge: Signal<Option<TwoFactorChallenge>>,
+    pub code: Signal<String>,
     pub form_error: KeyedNotifications,
     pub server_messages: KeyedNotifications,
 }
@@ -20,6 +26,9 @@ impl PageState {
         Self {
             username: use_signal(String::new),
             password: use_signal(String::new),
+            remember_me: use_signal(|| false),
+            challenge: use_signal(|| None),
+            code: use_signal(String::new),
             form_error: KeyedNotifications::default(),
             server_messages: KeyedNotifications::default(),
         }
@@ -75,6 +84,121 @@ pub fn PasswordInput(state: Signal<String>, oninput: EventHandler<FormEvent>) ->
     }
 }
 
+#[component]
+pub fn RememberMeInput(state: Signal<bool>) -> Element {
+    rsx! {
+        div {
+            class: "flex flex-row gap-2 items-center",
+            input {
+                id: "remember-me",
+                r#type: "checkbox",
+                checked: *state.read(),
+                oninput: move |ev| state.set(ev.value() == "true"),
+            }
+            label {
+                r#for: "remember-me",
+                "Remember me",
+            }
+        }
+    }
+}
+
+#[component]
+pub fn TwoFactorCodeInput(state: Signal<String>) -> Element {
+    rsx! {
+        div {
+            class: "flex flex-col",
+            label {
+                r#for: "two-factor-code",
+                "Authentication code",
+            },
+            input {
+                id: "two-factor-code",
+                name: "two-factor-code",
+                class: "input-field",
+                autocomplete: "one-time-code",
+                placeholder: "Code from your app or a recovery code",
+                value: "{state.read()}",
+                oninput: move |ev| state.set(ev.value()),
+            }
+        }
+    }
+}
+
+pub fn finish_login(res: LoginOk, navigator: Navigator) {
+    info!("Login successfully!");
+    TOASTER
+        .write()
+        .success("Login successfully", Duration::milliseconds(1200));
+    crate::util::cookie::set_session(res.session_signature, res.session_id, res.session_expires);
+    LOCAL_PROFILE.write().image = res.profile_image;
+    LOCAL_PROFILE.write().user_id = Some(res.user_id);
+    navigator.replace(Route::Home {});
+}
+
+/// Buttons to sign in with the configured OpenID Connect providers.
+#[component]
+pub fn OidcProviderButtons(remember_me: Signal<bool>) -> Element {
+    let api_client = ApiClient::global();
+    let providers = use_resource(move || async move {
+        match fetch_json!(<ListOidcProvidersOk>, api_client, ListOidcProviders) {
+            Ok(res) => res.providers,
+            Err(err) => {
+                error!("Failed to fetch sign in providers: {:?}", err);
+                vec![]
+            }
+        }
+    });
+
+    let providers = providers.read().clone().unwrap_or_default();
+    if providers.is_empty() {
+        return None;
+    }
+
+    let buttons = providers.into_iter().map(|provider| {
+        let provider_id = provider.id.clone();
+        let onclick = move |_| {
+            let request_data = StartOidcLogin {
+                provider: provider_id.clone(),
+                remember_me: *remember_me.read(),
+            };
+            spawn(async move {
+                match fetch_json!(<StartOidcLoginOk>, api_client, request_data) {
+                    Ok(res) => {
+                        if let Err(e) = crate::util::window()
+                            .location()
+                            .set_href(res.authorization_url.as_str())
+                        {
+                            error!("Failed to redirect to provider: {:?}", e);
+                        }
+                    }
+                    Err(err) => TOASTER.write().error(
+                        format!("Failed to sign in: {err}"),
+                        Duration::milliseconds(1200),
+                    ),
+                }
+            });
+        };
+        rsx!(
+            button {
+                key: "{provider.id}",
+                class: "btn",
+                r#type: "button",
+                onclick: onclick,
+                "Sign in with {provider.name}"
+            }
+        )
+    });
+
+    rsx!(
+        div {
+            class: "flex flex-col gap-2",
+            span { class: "text-center", "or" }
+            {buttons}
+        }
+    )
+}
+
 #[component]
 pub fn RegisterLink() -> Element {
     rsx!(
@@ -102,24 +226,19 @@ pub fn Login() -> Element {
                 .expect("Username is not valid!"),
             password: Password::try_new(page_state.with(|state| state.password.read().to_string()))
                 .expect("There is somthing wrong with password"),
+            remember_me: page_state.with(|state| *state.remember_me.read()),
         };
 
-        let response = fetch_json!(<LoginOk>, api_client, request_data);
+        let response = fetch_json!(<LoginOutcome>, api_client, request_data);
 
         match response {
-            Ok(res) => {
-                info!("Login successfully!");
-                TOASTER
-                    .write()
-                    .success("Login successfully", Duration::milliseconds(1200));
-                crate::util::cookie::set_session(
-                    res.session_signature,
-                    res.session_id,
-                    res.session_expires,
-                );
-                LOCAL_PROFILE.write().image = res.profile_image;
-                LOCAL_PROFILE.write().user_id = Some(res.user_id);
-                navigator.replace(Route::Home {});
+            Ok(LoginOutcome::LoggedIn(res)) => finish_login(res, navigator),
+            Ok(LoginOutcome::TwoFactorRequired(challenge)) => {
+                info!("Second factor required");
+                page_state.with_mut(|state| {
+                    state.server_messages.remove("Login-fail");
+                    state.challenge.set(Some(challenge));
+                });
             }
             Err(err) => {
                 error!("Login failed: {:?}", err);
@@ -132,6 +251,30 @@ pub fn Login() -> Element {
         }
     });
 
+    let code_onsubmit = async_handler!([api_client, page_state], move |_| async move {
+        let Some(challenge) = page_state.with(|state| state.challenge.read().clone()) else {
+            return;
+        };
+        let request_data = VerifyTwoFactor {
+            challenge_id: challenge.challenge_id,
+            code: page_state.with(|state| state.code.read().trim().to_string()),
+        };
+
+        match fetch_json!(<LoginOk>, api_client, request_data) {
+            Ok(res) => finish_login(res, navigator),
+            Err(err) => {
+                error!("Second factor failed: {:?}", err);
+                page_state.with_mut(|state| {
+                    state.code.set(String::new());
+                    state.server_messages.set("Login-fail", err.to_string());
+                });
+                TOASTER
+                    .write()
+                    .error("Failed to login", Duration::milliseconds(1200));
+            }
+        }
+    });
+
     let username_oninput = sync_handler!([page_state], move |ev: FormEvent| {
         info!("Username input changed: {}", ev.value());
         if let Err(e) = Username::try_new(ev.value()) {
@@ -151,11 +294,51 @@ pub fn Login() -> Element {
         page_state.with_mut(|state| state.password.set(ev.value()));
     });
 
+    let cancel_challenge = sync_handler!([page_state], move |_| {
+        page_state.with_mut(|state| {
+            state.code.set(String::new());
+            state.challenge.set(None);
+        });
+    });
+
     let btn_submit_style = match page_state.with(|state| state.can_submit()) {
         false => "btn-disabled",
         true => "",
     };
 
+    if page_state.with(|state| state.challenge.read().is_some()) {
+        let can_verify = page_state.with(|state| !state.code.read().trim().is_empty());
+        let btn_verify_style = maybe_class!("btn-disabled", !can_verify);
+
+        return rsx! {
+            form {
+                class: "flex flex-col gap-5",
+                onsubmit: code_onsubmit,
+                KeyedNotificationsBox {
+                    legend: "Login errors",
+                    notification: page_state.with(|state| state.server_messages.clone())
+                }
+
+                TwoFactorCodeInput {
+                    state: page_state.with(|state| state.code)
+                }
+
+                button {
+                    class: "btn {btn_verify_style}",
+                    r#type: "submit",
+                    disabled: !can_verify,
+                    "Verify",
+                }
+                button {
+                    class: "link text-center",
+                    r#type: "button",
+                    onclick: cancel_challenge,
+                    "Use another account",
+                }
+            }
+        };
+    }
+
     rsx! {
         form {
             class: "flex flex-col gap-5",
@@ -177,6 +360,11 @@ pub fn Login() -> Element {
                 state: page_state.with(|state| state.password),
                 oninput: password_oninput
             }
+
+            // Session lifetime
+            RememberMeInput {
+                state: page_state.with(|state| state.remember_me)
+            }
             // Register link
             RegisterLink {}
             // Error notifications component
@@ -192,6 +380,10 @@ pub fn Login() -> Element {
                 disabled: !page_state.with(|state| state.can_submit()),
                 "Login",
             }
+
+            OidcProviderButtons {
+                remember_me: page_state.with(|state| state.remember_me)
+            }
         }
     }
 }
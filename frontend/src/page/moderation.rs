@@ -0,0 +1,295 @@
+#![allow(non_snake_case)]
+
+use crate::elements::post::content::Content;
+use crate::prelude::*;
+use chrono::Duration;
+use dioxus::prelude::*;
+use dioxus_logger::tracing::error;
+use uchat_domain::ReportId;
+use uchat_endpoint::moderation::{
+    endpoint::{
+        GetModerationLog, GetModerationLogOk, GetModerationQueue, GetModerationQueueOk,
+        TakeModerationAction, TakeModerationActionOk,
+    },
+    types::{ModerationAction, ModerationLogEntry, QueuedReport},
+};
+
+/// Length of the suspension offered on the moderation page.
+const SUSPENSION_DAYS: u16 = 7;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Tab {
+    #[default]
+    Queue,
+    Log,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PageState {
+    pub tab: Tab,
+    pub reports: Option<Vec<QueuedReport>>,
+    pub log: Option<Vec<ModerationLogEntry>>,
+}
+
+async fn load_queue(api_client: &ApiClient, mut page_state: Signal<PageState>) {
+    match fetch_json!(<GetModerationQueueOk>, api_client, GetModerationQueue) {
+        Ok(res) => page_state.with_mut(|state| state.reports = Some(res.reports)),
+        Err(err) => {
+            error!("Failed to fetch moderation queue: {:?}", err);
+            TOASTER.write().error(
+                format!("Failed to retrieve reports: {err}"),
+                Duration::milliseconds(1200),
+            );
+        }
+    }
+}
+
+#[component]
+pub fn ActionButton(
+    label: String,
+    action: ModerationAction,
+    report_id: ReportId,
+    page_state: Signal<PageState>,
+) -> Element {
+    let api_client = ApiClient::global();
+
+    let onclick = async_handler!([api_client, page_state], move |_| async move {
+        let request_data = TakeModerationAction {
+            action,
+            report_id: Some(report_id),
+            note: None,
+        };
+        match fetch_json!(<TakeModerationActionOk>, api_client, request_data) {
+            Ok(res) => {
+                page_state.with_mut(|state| {
+                    if let Some(log) = state.log.as_mut() {
+                        log.insert(0, res.entry);
+                    }
+                });
+                // Other reports about the same target may have been closed too
+                load_queue(api_client, page_state).await;
+                TOASTER
+                    .write()
+                    .success("Done", Duration::milliseconds(1200));
+            }
+            Err(err) => TOASTER.write().error(
+                format!("Moderation action failed: {err}"),
+                Duration::milliseconds(1200),
+            ),
+        }
+    });
+
+    rsx!(
+        button {
+            class: "btn",
+            onclick: onclick,
+            "{label}"
+        }
+    )
+}
+
+#[component]
+pub fn ReportEntry(report: QueuedReport, page_state: Signal<PageState>) -> Element {
+    let created_at = report
+        .created_at
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M");
+    let details = report.details.clone().unwrap_or_default();
+    let post_id = report.post.as_ref().map(|post| post.id);
+    let post_actions = post_id.map(|post_id| {
+        rsx!(
+            ActionButton {
+                label: "Hide post",
+                action: ModerationAction::HidePost(post_id),
+                report_id: report.id,
+                page_state: page_state
+            }
+            ActionButton {
+                label: "Delete post",
+                action: ModerationAction::DeletePost(post_id),
+                report_id: report.id,
+                page_state: page_state
+            }
+        )
+    });
+    let suspend_label = format!("Suspend {SUSPENSION_DAYS} days");
+
+    rsx!(
+        li {
+            class: "flex flex-col gap-2 border-b py-2",
+            div {
+                class: "flex flex-row justify-between",
+                span { class: "font-bold", "{report.reason.description()}" }
+                span { class: "text-sm", "{created_at}" }
+            }
+            span { class: "text-sm", "@{report.reporter} reported @{report.handle}" }
+            if !details.is_empty() {
+                p { class: "text-sm italic", "{details}" }
+            }
+            if let Some(post) = report.post.clone() {
+                div {
+                    class: "border rounded p-2",
+                    Content { post: post }
+                }
+            }
+            div {
+                class: "flex flex-row flex-wrap gap-2",
+                {post_actions}
+                ActionButton {
+                    label: suspend_label,
+                    action: ModerationAction::SuspendUser {
+                        user_id: report.user_id,
+                        days: SUSPENSION_DAYS,
+                    },
+                    report_id: report.id,
+                    page_state: page_state
+                }
+                ActionButton {
+                    label: "Ban user",
+                    action: ModerationAction::BanUser(report.user_id),
+                    report_id: report.id,
+                    page_state: page_state
+                }
+                ActionButton {
+                    label: "Dismiss",
+                    action: ModerationAction::DismissReport,
+                    report_id: report.id,
+                    page_state: page_state
+                }
+            }
+        }
+    )
+}
+
+#[component]
+pub fn LogEntry(entry: ModerationLogEntry) -> Element {
+    let created_at = entry
+        .created_at
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M");
+    let target = entry
+        .handle
+        .as_ref()
+        .map(|handle| format!("@{handle}"))
+        .unwrap_or_default();
+    let expires = entry
+        .expires_at
+        .map(|at| {
+            format!(
+                "until {}",
+                at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+            )
+        })
+        .unwrap_or_default();
+    let note = entry.note.clone().unwrap_or_default();
+    let replies = match entry.deleted_post_ids.len() {
+        0 => String::new(),
+        1 => "· 1 reply deleted".to_string(),
+        n => format!("· {n} replies deleted"),
+    };
+
+    rsx!(
+        li {
+            class: "flex flex-col border-b py-2",
+            div {
+                class: "flex flex-row justify-between",
+                span { class: "font-bold", "{entry.action.description()}" }
+                span { class: "text-sm", "{created_at}" }
+            }
+            span { class: "text-sm", "@{entry.moderator} · {target} {expires} {replies}" }
+            if !note.is_empty() {
+                p { class: "text-sm italic", "{note}" }
+            }
+        }
+    )
+}
+
+pub fn Moderation() -> Element {
+    let api_client = ApiClient::global();
+    let mut page_state = use_signal(PageState::default);
+
+    let _fetch_queue = use_resource(move || load_queue(api_client, page_state));
+
+    let show_log = move |_| {
+        page_state.with_mut(|state| state.tab = Tab::Log);
+        if page_state.read().log.is_some() {
+            return;
+        }
+        spawn(async move {
+            let request_data = GetModerationLog { older_than: None };
+            match fetch_json!(<GetModerationLogOk>, api_client, request_data) {
+                Ok(res) => page_state.with_mut(|state| state.log = Some(res.entries)),
+                Err(err) => {
+                    error!("Failed to fetch moderation log: {:?}", err);
+                    TOASTER.write().error(
+                        format!("Failed to retrieve moderation log: {err}"),
+                        Duration::milliseconds(1200),
+                    );
+                }
+            }
+        });
+    };
+
+    let state = page_state.read().clone();
+    let body = match state.tab {
+        Tab::Queue => match state.reports {
+            None => rsx!( div { "Loading..." } ),
+            Some(reports) if reports.is_empty() => rsx!( div { "Nothing to review." } ),
+            Some(reports) => rsx!(
+                ul {
+                    class: "flex flex-col",
+                    for report in reports {
+                        ReportEntry {
+                            key: "{report.id.to_string()}",
+                            report: report,
+                            page_state: page_state
+                        }
+                    }
+                }
+            ),
+        },
+        Tab::Log => match state.log {
+            None => rsx!( div { "Loading..." } ),
+            Some(log) if log.is_empty() => rsx!( div { "No moderation actions yet." } ),
+            Some(log) => rsx!(
+                ul {
+                    class: "flex flex-col",
+                    for entry in log {
+                        LogEntry { key: "{entry.id.to_string()}", entry: entry }
+                    }
+                }
+            ),
+        },
+    };
+
+    rsx!(
+        Appbar {
+            title: "Moderation",
+            AppbarImgButton {
+                click_handler: move |_| {
+                    navigator().go_back();
+                },
+                img: ICON_BACK,
+                label: "Back",
+                title: "Go to the previous page",
+            }
+        }
+        div {
+            class: "flex flex-col gap-3 w-full",
+            div {
+                class: "flex flex-row gap-2",
+                button {
+                    class: "btn",
+                    onclick: move |_| page_state.with_mut(|state| state.tab = Tab::Queue),
+                    "Reports"
+                }
+                button {
+                    class: "btn",
+                    onclick: show_log,
+                    "Log"
+                }
+            }
+            {body}
+        }
+    )
+}
@@ -0,0 +1,304 @@
+#![allow(non_snake_case)]
+
+use crate::prelude::*;
+use chrono::Duration;
+use dioxus::prelude::*;
+use dioxus_logger::tracing::{error, info};
+use keyed_notifications_box::KeyedNotifications;
+use uchat_endpoint::user::{
+    endpoint::{
+        CreateMuteFilter, CreateMuteFilterOk, DeleteMuteFilter, DeleteMuteFilterOk,
+        ListMuteFilters, ListMuteFiltersOk,
+    },
+    types::{MuteAction, MuteFilter, MuteScope},
+};
+
+#[derive(Debug, Clone)]
+pub struct PageState {
+    pub filters: Option<Vec<MuteFilter>>,
+    pub phrase: String,
+    pub whole_word: bool,
+    pub scopes: Vec<MuteScope>,
+    pub action: MuteAction,
+    pub expires_in_days: String,
+    pub form_error: KeyedNotifications,
+}
+
+impl Default for PageState {
+    fn default() -> Self {
+        Self {
+            filters: None,
+            phrase: String::new(),
+            whole_word: true,
+            scopes: vec![MuteScope::Home, MuteScope::Trending],
+            action: MuteAction::Hide,
+            expires_in_days: EXPIRY_OPTIONS[0].0.to_string(),
+            form_error: KeyedNotifications::default(),
+        }
+    }
+}
+
+impl PageState {
+    fn toggle_scope(&mut self, scope: MuteScope) {
+        match self.scopes.iter().position(|s| *s == scope) {
+            Some(idx) => {
+                self.scopes.remove(idx);
+            }
+            None => self.scopes.push(scope),
+        }
+    }
+}
+
+const EXPIRY_OPTIONS: [(&str, &str); 4] = [
+    ("", "Forever"),
+    ("1", "24 hours"),
+    ("7", "7 days"),
+    ("30", "30 days"),
+];
+
+#[component]
+pub fn FilterEntry(filter: MuteFilter, page_state: Signal<PageState>) -> Element {
+    let api_client = ApiClient::global();
+    let filter_id = filter.id;
+    let scopes = filter
+        .scopes
+        .iter()
+        .map(|scope| scope.description())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let action = match filter.action {
+        MuteAction::Hide => "hidden",
+        MuteAction::Warn => "collapsed",
+    };
+    let matching = if filter.whole_word {
+        "whole word"
+    } else {
+        "anywhere"
+    };
+    let expires = filter
+        .expires_at
+        .map(|at| {
+            format!(
+                "until {}",
+                at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+            )
+        })
+        .unwrap_or_else(|| "forever".to_string());
+
+    let delete_onclick = async_handler!([api_client, page_state], move |_| async move {
+        let request_data = DeleteMuteFilter { filter_id };
+        match fetch_json!(<DeleteMuteFilterOk>, api_client, request_data) {
+            Ok(_) => {
+                info!("Mute filter deleted");
+                page_state.with_mut(|state| {
+                    if let Some(filters) = state.filters.as_mut() {
+                        filters.retain(|filter| filter.id != filter_id);
+                    }
+                });
+            }
+            Err(err) => TOASTER.write().error(
+                format!("Failed to unmute: {err}"),
+                Duration::milliseconds(1200),
+            ),
+        }
+    });
+
+    rsx!(
+        li {
+            class: "flex flex-row justify-between items-center border-b py-2",
+            div {
+                class: "flex flex-col",
+                span { class: "font-bold", "{filter.phrase}" }
+                span { class: "text-sm", "{scopes} · {action} · {matching} · {expires}" }
+            }
+            button {
+                class: "btn",
+                onclick: delete_onclick,
+                "Unmute"
+            }
+        }
+    )
+}
+
+#[component]
+pub fn NewFilter(page_state: Signal<PageState>) -> Element {
+    let api_client = ApiClient::global();
+
+    let create_onsubmit = async_handler!([api_client, page_state], move |_| async move {
+        let request_data = page_state.with(|state| CreateMuteFilter {
+            phrase: state.phrase.trim().to_string(),
+            whole_word: state.whole_word,
+            scopes: state.scopes.clone(),
+            action: state.action,
+            expires_in_days: state.expires_in_days.parse().ok(),
+        });
+        match fetch_json!(<CreateMuteFilterOk>, api_client, request_data) {
+            Ok(res) => {
+                info!("Mute filter created");
+                page_state.with_mut(|state| {
+                    state.phrase.clear();
+                    state.form_error.remove("mute");
+                    state
+                        .filters
+                        .get_or_insert_with(Vec::new)
+                        .insert(0, res.filter);
+                });
+            }
+            Err(err) => {
+                page_state.with_mut(|state| state.form_error.set("mute", err.to_string()));
+            }
+        }
+    });
+
+    let checkboxes = MuteScope::ALL.into_iter().map(|scope| {
+        let id = format!("mute-scope-{}", scope.as_str());
+        let checked = page_state.with(|state| state.scopes.contains(&scope));
+        rsx!(
+            div {
+                key: "{id}",
+                class: "flex flex-row gap-2 items-center",
+                input {
+                    id: "{id}",
+                    r#type: "checkbox",
+                    checked: checked,
+                    oninput: move |_| page_state.with_mut(|state| state.toggle_scope(scope)),
+                }
+                label {
+                    r#for: "{id}",
+                    "{scope.description()}"
+                }
+            }
+        )
+    });
+
+    rsx!(
+        form {
+            class: "flex flex-col gap-3",
+            onsubmit: create_onsubmit,
+            fieldset {
+                class: "fieldset",
+                legend { "Mute a word or phrase" }
+                div {
+                    label {
+                        r#for: "mute-phrase",
+                        "Word, phrase or #hashtag"
+                    }
+                    input {
+                        id: "mute-phrase",
+                        class: "input-field",
+                        placeholder: "#spoilers",
+                        value: "{page_state.read().phrase}",
+                        oninput: move |ev| page_state.with_mut(|state| state.phrase = ev.value()),
+                    }
+                }
+                div {
+                    class: "flex flex-row gap-2 items-center",
+                    input {
+                        id: "mute-whole-word",
+                        r#type: "checkbox",
+                        checked: page_state.read().whole_word,
+                        oninput: move |_| page_state.with_mut(|state| state.whole_word = !state.whole_word),
+                    }
+                    label {
+                        r#for: "mute-whole-word",
+                        "Only match whole words"
+                    }
+                }
+                {checkboxes}
+                div {
+                    label {
+                        r#for: "mute-action",
+                        "Matching posts are"
+                    }
+                    select {
+                        id: "mute-action",
+                        class: "input-field",
+                        value: "{page_state.read().action.as_str()}",
+                        onchange: move |ev| {
+                            if let Ok(action) = ev.value().parse::<MuteAction>() {
+                                page_state.with_mut(|state| state.action = action);
+                            }
+                        },
+                        option { value: "{MuteAction::Hide.as_str()}", "Hidden" }
+                        option { value: "{MuteAction::Warn.as_str()}", "Collapsed behind a warning" }
+                    }
+                }
+                div {
+                    label {
+                        r#for: "mute-expiry",
+                        "Mute for"
+                    }
+                    select {
+                        id: "mute-expiry",
+                        class: "input-field",
+                        value: "{page_state.read().expires_in_days}",
+                        onchange: move |ev| page_state.with_mut(|state| state.expires_in_days = ev.value()),
+                        for (value, label) in EXPIRY_OPTIONS {
+                            option { value: "{value}", "{label}" }
+                        }
+                    }
+                }
+            }
+            button {
+                class: "btn",
+                r#type: "submit",
+                "Mute"
+            }
+        }
+    )
+}
+
+pub fn MuteFilters() -> Element {
+    let api_client = ApiClient::global();
+    let mut page_state = use_signal(PageState::default);
+
+    let _fetch_filters = use_resource(move || async move {
+        match fetch_json!(<ListMuteFiltersOk>, api_client, ListMuteFilters) {
+            Ok(res) => page_state.with_mut(|state| state.filters = Some(res.filters)),
+            Err(err) => {
+                error!("Failed to fetch mute filters: {:?}", err);
+                TOASTER.write().error(
+                    format!("Failed to retrieve muted words: {err}"),
+                    Duration::milliseconds(1200),
+                );
+            }
+        }
+    });
+
+    let filters = match page_state.read().filters.clone() {
+        None => rsx!( div { "Loading..." } ),
+        Some(filters) if filters.is_empty() => rsx!( div { "You haven't muted anything." } ),
+        Some(filters) => rsx!(
+            ul {
+                class: "flex flex-col",
+                for filter in filters {
+                    FilterEntry { key: "{filter.id}", filter: filter, page_state: page_state }
+                }
+            }
+        ),
+    };
+
+    rsx!(
+        Appbar {
+            title: "Muted words",
+            AppbarImgButton {
+                click_handler: move |_| {
+                    navigator().go_back();
+                },
+                img: ICON_BACK,
+                label: "Back",
+                title: "Go to the previous page",
+            }
+        }
+        div {
+            class: "flex flex-col gap-3 w-full",
+            p { "Posts mentioning a muted word are hidden or collapsed in your feeds. Muting is case-insensitive." }
+            {filters}
+            NewFilter { page_state: page_state }
+            KeyedNotificationsBox {
+                legend: "Errors",
+                notification: page_state.read().form_error.clone()
+            }
+        }
+    )
+}
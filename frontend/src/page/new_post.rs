@@ -1,7 +1,9 @@
 pub mod chat;
+pub mod drafts;
 pub mod image;
 pub mod poll;
 
 pub use chat::NewChat;
+pub use drafts::Drafts;
 pub use image::NewImage;
 pub use poll::NewPoll;
@@ -7,34 +7,110 @@ use dioxus_logger::tracing::{error, info};
 use serde::{Deserialize, Serialize};
 use uchat_domain::{Headline, Message};
 use uchat_endpoint::post::{
-    endpoint::{NewPost, NewPostOk},
-    types::{Chat, NewPostOptions},
+    endpoint::{NewPost, NewPostOk, NewThread, NewThreadOk, ServerLimitsOk},
+    types::{Chat, Content, Draft, Link, NewPostOptions},
 };
 
+use super::drafts::{take_open_draft, use_autosave};
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct PageState {
     pub message: String,
     pub headline: String,
+    /// Post a message too long for a single post as a thread of them.
+    pub thread: bool,
 }
 
 impl PageState {
-    pub fn can_submit(&self) -> bool {
-        if Message::try_new(&self.message).is_err() {
+    pub fn can_submit(&self, limits: &ServerLimitsOk) -> bool {
+        if self.thread {
+            let parts = self.thread_parts(limits);
+            if parts.is_empty()
+                || parts.len() > limits.thread_max_posts
+                || parts.iter().any(|part| Message::try_new(part).is_err())
+            {
+                return false;
+            }
+        } else if Message::try_new(&self.message).is_err()
+            || self.visible_chars() > limits.message_max_chars
+        {
             return false;
         }
-        if Headline::try_new(&self.headline).is_err() && !self.headline.is_empty() {
+        if !self.headline.is_empty()
+            && (Headline::try_new(&self.headline).is_err()
+                || self.headline.chars().count() > limits.headline_max_chars)
+        {
             return false;
         }
         true
     }
+
+    /// Length of the message as readers see it, without markup.
+    pub fn visible_chars(&self) -> usize {
+        uchat_markup::parse(&self.message).visible_chars()
+    }
+
+    /// The numbered posts the message is split into in a thread.
+    pub fn thread_parts(&self, limits: &ServerLimitsOk) -> Vec<String> {
+        if self.message.trim().is_empty() {
+            return vec![];
+        }
+        uchat_markup::split_thread(&self.message, limits.message_max_chars)
+    }
+
+    /// Continues a chat or link draft, as a thread if it's too long for one
+    /// post.
+    fn from_draft(draft: &Draft) -> Self {
+        let (Content::Chat(Chat { headline, message })
+        | Content::Link(Link {
+            headline, message, ..
+        })) = &draft.content
+        else {
+            return Self::default();
+        };
+        let mut state = Self {
+            message: message.as_ref().to_string(),
+            headline: headline
+                .as_ref()
+                .map(|headline| headline.as_ref().to_string())
+                .unwrap_or_default(),
+            thread: false,
+        };
+        state.thread = state.visible_chars() > SERVER_LIMITS.read().message_max_chars;
+        state
+    }
+
+    /// What to keep as a draft, once there is a message.
+    fn draft_content(&self) -> Option<Content> {
+        let chat = Chat {
+            message: Message::try_new(&self.message).ok()?,
+            headline: Headline::try_new(&self.headline).ok(),
+        };
+        Some(chat.into())
+    }
+
+    fn headline(&self) -> Option<Headline> {
+        if self.headline.is_empty() {
+            None
+        } else {
+            Some(Headline::try_new(&self.headline).unwrap())
+        }
+    }
 }
 
 #[component]
 pub fn MessageInput(page_state: Signal<PageState>) -> Element {
+    let limits = SERVER_LIMITS.read().clone();
+    let (count, max, unit) = if page_state.read().thread {
+        let posts = page_state.read().thread_parts(&limits).len();
+        (posts, limits.thread_max_posts, " posts")
+    } else {
+        let chars = page_state.read().visible_chars();
+        (chars, limits.message_max_chars, "")
+    };
     let wrong_len = maybe_class!(
         "err-text-color",
-        page_state.read().message.len() > Message::MAX_CHARS
-            || page_state.read().message.is_empty()
+        count > max || page_state.read().message.is_empty()
     );
 
     rsx!(
@@ -46,7 +122,7 @@ pub fn MessageInput(page_state: Signal<PageState>) -> Element {
                     span {"Message"},
                     span {
                         class: "text-right {wrong_len}",
-                        "{page_state.read().message.len()}/{Message::MAX_CHARS}"
+                        "{count}/{max}{unit}"
                     }
                 }
             }
@@ -65,12 +141,50 @@ pub fn MessageInput(page_state: Signal<PageState>) -> Element {
     )
 }
 
+#[component]
+pub fn ThreadInput(page_state: Signal<PageState>) -> Element {
+    let limits = SERVER_LIMITS.read().clone();
+    let parts = if page_state.read().thread {
+        page_state.read().thread_parts(&limits)
+    } else {
+        vec![]
+    };
+    let Parts = parts.into_iter().map(|part| {
+        rsx!(li {
+            class: "p-2 border rounded whitespace-pre-wrap break-words",
+            "{part}"
+        })
+    });
+
+    rsx!(
+        div {
+            class: "flex flex-col gap-2",
+            div {
+                class: "flex flex-row gap-2 items-center",
+                input {
+                    id: "thread",
+                    r#type: "checkbox",
+                    checked: page_state.read().thread,
+                    oninput: move |_| page_state.with_mut(|state| state.thread = !state.thread)
+                }
+                label {
+                    r#for: "thread",
+                    "post as a thread, split into replies of {limits.message_max_chars} characters"
+                }
+            }
+            ol {
+                class: "flex flex-col gap-2",
+                {Parts}
+            }
+        }
+    )
+}
+
 #[component]
 pub fn HeadlineInput(page_state: Signal<PageState>) -> Element {
-    let wrong_len = maybe_class!(
-        "err-text-color",
-        page_state.read().headline.len() > Headline::MAX_CHARS
-    );
+    let max_chars = SERVER_LIMITS.read().headline_max_chars;
+    let headline_chars = page_state.read().headline.chars().count();
+    let wrong_len = maybe_class!("err-text-color", headline_chars > max_chars);
 
     rsx!(
         div {
@@ -81,7 +195,7 @@ pub fn HeadlineInput(page_state: Signal<PageState>) -> Element {
                     span {"Headline"},
                     span {
                         class: "text-right {wrong_len}",
-                        "{page_state.read().headline.len()}/{Headline::MAX_CHARS}"
+                        "{headline_chars}/{max_chars}"
                     }
                 }
             }
@@ -104,31 +218,53 @@ pub fn NewChat() -> Element {
     info!("NewChat component initialized!");
 
     let api_client = ApiClient::global();
-    let page_state = use_signal(PageState::default);
-    let submit_btn_style = maybe_class!("btn-disabled", !page_state.read().can_submit());
-    let form_onsubmit = async_handler!([api_client, page_state], move |_| async move {
+    let draft = use_hook(|| {
+        take_open_draft(|content| matches!(content, Content::Chat(_) | Content::Link(_)))
+    });
+    let page_state = use_signal(|| {
+        draft
+            .as_ref()
+            .map(PageState::from_draft)
+            .unwrap_or_default()
+    });
+    let autosave = use_autosave(page_state, draft, PageState::draft_content, |_, _| {});
+    let can_submit = page_state.read().can_submit(&SERVER_LIMITS.read());
+    let submit_btn_style = maybe_class!("btn-disabled", !can_submit);
+    let form_onsubmit = async_handler!([api_client, page_state, autosave], move |_| async move {
         info!("Form submitted!");
-        let request_data = NewPost {
-            content: Chat {
-                message: Message::try_new(&page_state.read().message).unwrap(),
-                headline: {
-                    let headline = &page_state.read().headline;
-                    if headline.is_empty() {
-                        None
-                    } else {
-                        Some(Headline::try_new(headline).unwrap())
-                    }
-                },
-            }
-            .into(),
-            options: NewPostOptions::default(),
+        let state = page_state.read().clone();
+        let response = if state.thread {
+            // The headline goes on the first post only
+            let mut headline = state.headline();
+            let chats = state
+                .thread_parts(&SERVER_LIMITS.read())
+                .into_iter()
+                .map(|part| Chat {
+                    message: Message::try_new(part).unwrap(),
+                    headline: headline.take(),
+                })
+                .collect();
+            let request_data = NewThread {
+                chats,
+                options: NewPostOptions::default(),
+            };
+            fetch_json!(<NewThreadOk>, api_client, request_data).map(|_| ())
+        } else {
+            let request_data = NewPost {
+                content: Chat {
+                    message: Message::try_new(&state.message).unwrap(),
+                    headline: state.headline(),
+                }
+                .into(),
+                options: NewPostOptions::default(),
+            };
+            fetch_json!(<NewPostOk>, api_client, request_data).map(|_| ())
         };
 
-        let response = fetch_json!(<NewPostOk>, api_client, request_data);
-
         match response {
             Ok(_res) => {
                 info!("Post new chat successfully!");
+                autosave.discard().await;
                 TOASTER
                     .write()
                     .success("Posted successfully", Duration::milliseconds(600));
@@ -186,6 +322,9 @@ pub fn NewChat() -> Element {
             MessageInput {
                 page_state: page_state
             }
+            ThreadInput {
+                page_state: page_state
+            }
             // Headline input
             HeadlineInput {
                 page_state: page_state
@@ -193,7 +332,7 @@ pub fn NewChat() -> Element {
             button {
                 class: "btn {submit_btn_style}",
                 r#type: "submit",
-                disabled: !page_state.read().can_submit(),
+                disabled: !can_submit,
                 "Post"
             }
         }
@@ -0,0 +1,280 @@
+#![allow(non_snake_case)]
+
+use crate::prelude::*;
+use crate::OPEN_DRAFT;
+use chrono::Duration;
+use dioxus::prelude::*;
+use dioxus_logger::tracing::{error, info};
+use gloo_timers::future::TimeoutFuture;
+use uchat_domain::DraftId;
+use uchat_endpoint::post::{
+    endpoint::{
+        DeleteDraft, DeleteDraftOk, ListDrafts, ListDraftsOk, PublishDraft, PublishDraftOk,
+        SaveDraft, SaveDraftOk,
+    },
+    types::{Content, Draft, NewPostOptions},
+};
+
+/// How long typing has to pause before the draft is saved.
+const AUTOSAVE_DELAY_MS: u32 = 2000;
+
+/// The draft picked on the drafts page, if it's written on the page
+/// `is_for_page` accepts its content for.
+pub fn take_open_draft(is_for_page: fn(&Content) -> bool) -> Option<Draft> {
+    let draft = OPEN_DRAFT.write().take()?;
+    is_for_page(&draft.content).then_some(draft)
+}
+
+/// Draft a new post page keeps saving.
+#[derive(Clone, Copy)]
+pub struct Autosave {
+    draft_id: Signal<Option<DraftId>>,
+    task: Resource<()>,
+}
+
+impl Autosave {
+    /// Stops saving and deletes the draft, once the post is posted.
+    pub async fn discard(mut self) {
+        let api_client = ApiClient::global();
+        self.task.cancel();
+        let Some(draft_id) = *self.draft_id.peek() else {
+            return;
+        };
+        if let Err(err) = fetch_json!(<DeleteDraftOk>, api_client, DeleteDraft { draft_id }) {
+            error!("Failed to delete posted draft: {err}");
+        }
+    }
+}
+
+/// Saves the page state as a draft once typing pauses, continuing the
+/// `opened` draft if there is one. `to_content` is `None` while there is
+/// nothing worth saving, and `saved` takes over what the server stored,
+/// such as ids of uploaded images.
+pub fn use_autosave<T: 'static>(
+    mut page_state: Signal<T>,
+    opened: Option<Draft>,
+    to_content: fn(&T) -> Option<Content>,
+    saved: fn(&mut T, &Draft),
+) -> Autosave {
+    let api_client = ApiClient::global();
+    let mut draft_id = use_signal(|| opened.as_ref().map(|draft| draft.id));
+    let mut saved_content = use_signal(|| opened.map(|draft| draft.content));
+
+    let task = use_resource(move || async move {
+        let Some(content) = to_content(&page_state.read()) else {
+            return;
+        };
+        if saved_content.peek().as_ref() == Some(&content) {
+            return;
+        }
+        // Changing the page state again restarts this, so only the last
+        // change is saved
+        TimeoutFuture::new(AUTOSAVE_DELAY_MS).await;
+
+        // Saving goes on when the page changes meanwhile, so the id of a
+        // new draft isn't lost
+        spawn(async move {
+            let request_data = SaveDraft {
+                draft_id: *draft_id.peek(),
+                content: content.clone(),
+                options: NewPostOptions::default(),
+            };
+            match fetch_json!(<SaveDraftOk>, api_client, request_data) {
+                Ok(res) => {
+                    info!("Draft saved");
+                    draft_id.set(Some(res.draft.id));
+                    saved_content.set(Some(res.draft.content.clone()));
+                    if to_content(&page_state.peek()).as_ref() == Some(&content) {
+                        page_state.with_mut(|state| saved(state, &res.draft));
+                    }
+                }
+                Err(err) => error!("Failed to save draft: {err}"),
+            }
+        });
+    });
+
+    Autosave { draft_id, task }
+}
+
+/// Kind of post and the text it starts with.
+fn summary(content: &Content) -> (&'static str, String) {
+    match content {
+        Content::Chat(chat) => ("Chat", chat.message.as_ref().to_string()),
+        Content::Link(link) => ("Link", link.message.as_ref().to_string()),
+        Content::Image(image) => (
+            "Image",
+            image
+                .caption
+                .as_ref()
+                .map(|caption| caption.as_ref().to_string())
+                .unwrap_or_default(),
+        ),
+        Content::Gallery(gallery) => (
+            "Images",
+            gallery
+                .caption
+                .as_ref()
+                .map(|caption| caption.as_ref().to_string())
+                .unwrap_or_default(),
+        ),
+        Content::Video(video) => (
+            "Video",
+            video
+                .caption
+                .as_ref()
+                .map(|caption| caption.as_ref().to_string())
+                .unwrap_or_default(),
+        ),
+        Content::Poll(poll) => ("Poll", poll.headline.as_ref().to_string()),
+    }
+}
+
+/// Page the draft is written on, if there is one for its kind of post.
+fn editor(content: &Content) -> Option<Route> {
+    match content {
+        Content::Chat(_) | Content::Link(_) => Some(Route::NewChat {}),
+        Content::Image(_) | Content::Gallery(_) => Some(Route::NewImage {}),
+        Content::Poll(_) => Some(Route::NewPoll {}),
+        Content::Video(_) => None,
+    }
+}
+
+#[component]
+pub fn DraftEntry(draft: Draft, drafts: Signal<Option<Vec<Draft>>>) -> Element {
+    let api_client = ApiClient::global();
+    let draft_id = draft.id;
+    let (kind, text) = summary(&draft.content);
+    let text: String = text.chars().take(140).collect();
+    let updated_at = draft
+        .updated_at
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M");
+    let route = editor(&draft.content);
+    let can_open = route.is_some();
+    let open_onclick = {
+        let draft = draft.clone();
+        move |_| {
+            if let Some(route) = route.clone() {
+                *OPEN_DRAFT.write() = Some(draft.clone());
+                navigator().push(route);
+            }
+        }
+    };
+
+    let publish_onclick = async_handler!([api_client, drafts], move |_| async move {
+        match fetch_json!(<PublishDraftOk>, api_client, PublishDraft { draft_id }) {
+            Ok(_) => {
+                info!("Draft published");
+                TOASTER
+                    .write()
+                    .success("Posted successfully", Duration::milliseconds(600));
+                drafts.with_mut(|drafts| {
+                    if let Some(drafts) = drafts.as_mut() {
+                        drafts.retain(|draft| draft.id != draft_id);
+                    }
+                });
+            }
+            Err(err) => TOASTER.write().error(
+                format!("Posted failed: {err}"),
+                Duration::milliseconds(1200),
+            ),
+        }
+    });
+
+    let delete_onclick = async_handler!([api_client, drafts], move |_| async move {
+        match fetch_json!(<DeleteDraftOk>, api_client, DeleteDraft { draft_id }) {
+            Ok(_) => {
+                info!("Draft deleted");
+                drafts.with_mut(|drafts| {
+                    if let Some(drafts) = drafts.as_mut() {
+                        drafts.retain(|draft| draft.id != draft_id);
+                    }
+                });
+            }
+            Err(err) => TOASTER.write().error(
+                format!("Failed to delete draft: {err}"),
+                Duration::milliseconds(1200),
+            ),
+        }
+    });
+
+    rsx!(
+        li {
+            class: "flex flex-col gap-2 border-b py-2",
+            div {
+                class: "flex flex-col",
+                span { class: "text-sm", "{kind} · saved {updated_at}" }
+                span { class: "break-words", "{text}" }
+            }
+            div {
+                class: "flex flex-row gap-2 justify-end",
+                button {
+                    class: "btn",
+                    disabled: !can_open,
+                    onclick: open_onclick,
+                    "Open"
+                }
+                button {
+                    class: "btn",
+                    onclick: publish_onclick,
+                    "Post"
+                }
+                button {
+                    class: "btn bg-red-700",
+                    onclick: delete_onclick,
+                    "Delete"
+                }
+            }
+        }
+    )
+}
+
+pub fn Drafts() -> Element {
+    let api_client = ApiClient::global();
+    let mut drafts = use_signal(|| None::<Vec<Draft>>);
+
+    let _fetch_drafts = use_resource(move || async move {
+        match fetch_json!(<ListDraftsOk>, api_client, ListDrafts) {
+            Ok(res) => drafts.set(Some(res.drafts)),
+            Err(err) => {
+                error!("Failed to fetch drafts: {:?}", err);
+                TOASTER.write().error(
+                    format!("Failed to retrieve drafts: {err}"),
+                    Duration::milliseconds(1200),
+                );
+            }
+        }
+    });
+
+    let Entries = match drafts.read().clone() {
+        None => rsx!( div { "Loading..." } ),
+        Some(list) if list.is_empty() => rsx!( div { "You have no drafts." } ),
+        Some(list) => rsx!(
+            ul {
+                class: "flex flex-col",
+                for draft in list {
+                    DraftEntry { key: "{draft.id.to_string()}", draft: draft, drafts: drafts }
+                }
+            }
+        ),
+    };
+
+    rsx!(
+        Appbar {
+            title: "Drafts",
+            AppbarImgButton {
+                click_handler: move |_| {
+                    navigator().go_back();
+                },
+                img: ICON_BACK,
+                label: "Back",
+                title: "Go to the previous page",
+            }
+        }
+        div {
+            class: "flex flex-col gap-3 w-full",
+            p { "Posts you are writing are saved here as you type." }
+            {Entries}
+        }
+    )
+}
@@ -5,17 +5,32 @@ use chrono::Duration;
 use dioxus::prelude::*;
 use dioxus_logger::tracing::{error, info};
 use serde::{Deserialize, Serialize};
-use uchat_domain::Caption;
+use uchat_domain::{AltText, Caption, ImageId};
 use uchat_endpoint::post::{
     endpoint::{NewPost, NewPostOk},
-    types::{Image, ImageKind, NewPostOptions},
+    types::{Content, Draft, Gallery, GalleryImage, ImageKind, NewPostOptions},
 };
 use web_sys::HtmlInputElement;
 
+use super::drafts::{take_open_draft, use_autosave};
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PickedImage {
+    /// Identifies the image in the list while it is moved around.
+    pub key: usize,
+    /// Data URL of a picked file, or URL of an image stored with a draft.
+    pub data: String,
+    /// Set once the image is stored with a draft.
+    pub id: Option<ImageId>,
+    pub alt_text: String,
+    pub sensitive: bool,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct PageState {
     pub caption: String,
-    pub image: Option<String>,
+    pub images: Vec<PickedImage>,
+    next_key: usize,
 }
 
 impl PageState {
@@ -23,10 +38,130 @@ impl PageState {
         if !self.caption.is_empty() && Caption::try_new(&self.caption).is_err() {
             return false;
         }
-        if self.image.is_none() {
+        if self.images.is_empty() || self.images.len() > Gallery::MAX_IMAGES {
             return false;
         }
-        true
+        self.images
+            .iter()
+            .all(|image| image.alt_text.is_empty() || AltText::try_new(&image.alt_text).is_ok())
+    }
+
+    pub fn push_image(&mut self, data: String) {
+        self.images.push(PickedImage {
+            key: self.next_key,
+            data,
+            ..PickedImage::default()
+        });
+        self.next_key += 1;
+    }
+
+    /// Continues an image or gallery draft.
+    fn from_draft(draft: &Draft) -> Self {
+        let (images, caption) = match &draft.content {
+            Content::Image(image) => (
+                vec![GalleryImage {
+                    kind: image.kind.clone(),
+                    alt_text: None,
+                    sensitive: false,
+                    variants: vec![],
+                }],
+                &image.caption,
+            ),
+            Content::Gallery(gallery) => (gallery.images.clone(), &gallery.caption),
+            _ => return Self::default(),
+        };
+        let mut state = Self {
+            caption: caption
+                .as_ref()
+                .map(|caption| caption.as_ref().to_string())
+                .unwrap_or_default(),
+            ..Self::default()
+        };
+        for image in images {
+            let ImageKind::Id(id) = image.kind else {
+                continue;
+            };
+            let Some(url) = draft.image_url(id) else {
+                continue;
+            };
+            state.push_image(url.to_string());
+            if let Some(picked) = state.images.last_mut() {
+                picked.id = Some(id);
+                picked.alt_text = image
+                    .alt_text
+                    .map(|alt_text| alt_text.as_ref().to_string())
+                    .unwrap_or_default();
+                picked.sensitive = image.sensitive;
+            }
+        }
+        state
+    }
+
+    /// What to keep as a draft, once an image is picked.
+    fn draft_content(&self) -> Option<Content> {
+        if self.images.is_empty() || self.images.len() > Gallery::MAX_IMAGES {
+            return None;
+        }
+        Some(self.gallery().into())
+    }
+
+    /// Takes over the ids of the images stored with the draft, which are in
+    /// the same order as on the page.
+    fn draft_saved(&mut self, draft: &Draft) {
+        let Content::Gallery(gallery) = &draft.content else {
+            return;
+        };
+        for (picked, image) in self.images.iter_mut().zip(&gallery.images) {
+            if let (None, ImageKind::Id(id)) = (picked.id, &image.kind) {
+                if let Some(url) = draft.image_url(*id) {
+                    picked.id = Some(*id);
+                    picked.data = url.to_string();
+                }
+            }
+        }
+    }
+
+    fn gallery(&self) -> Gallery {
+        Gallery {
+            images: self
+                .images
+                .iter()
+                .map(|image| GalleryImage {
+                    kind: match image.id {
+                        Some(id) => ImageKind::Id(id),
+                        None => ImageKind::DataUrl(image.data.clone()),
+                    },
+                    alt_text: AltText::try_new(&image.alt_text).ok(),
+                    sensitive: image.sensitive,
+                    variants: vec![],
+                })
+                .collect(),
+            caption: if self.caption.is_empty() {
+                None
+            } else {
+                Caption::try_new(&self.caption).ok()
+            },
+        }
+    }
+
+    pub fn image_mut(&mut self, key: usize) -> Option<&mut PickedImage> {
+        self.images.iter_mut().find(|image| image.key == key)
+    }
+
+    /// Moves an image `by` places towards the end, staying within the list.
+    pub fn move_image(&mut self, key: usize, by: isize) {
+        let Some(from) = self.images.iter().position(|image| image.key == key) else {
+            return;
+        };
+        let to = from
+            .saturating_add_signed(by)
+            .min(self.images.len().saturating_sub(1));
+        let image = self.images.remove(from);
+        self.images.insert(to, image);
+    }
+
+    pub fn remove_image(&mut self, key: usize) {
+        self.images.retain(|image| image.key != key);
     }
 }
 
@@ -35,35 +170,49 @@ pub fn ImageInput(page_state: Signal<PageState>) -> Element {
     let image_oninput = move |_| {
         async move {
             // this is used for unchecked_into()
-            use gloo_file::{futures::read_as_data_url, File};
+            use gloo_file::{futures::read_as_data_url, FileList};
             use wasm_bindgen::JsCast;
 
             let element_html = crate::util::document()
                 .get_element_by_id("image-input")
                 .unwrap()
                 .unchecked_into::<HtmlInputElement>();
-            let file: File = element_html.files().unwrap().get(0).unwrap().into();
+            let files = FileList::from(element_html.files().unwrap());
+            // Allows picking the same files again
+            element_html.set_value("");
 
-            match read_as_data_url(&file).await {
-                Ok(data) => page_state.with_mut(|state| state.image = Some(data)),
-                Err(e) => TOASTER
-                    .write()
-                    .error(format!("Failed to load file: {}", e), Duration::seconds(5)),
+            let free = Gallery::MAX_IMAGES.saturating_sub(page_state.read().images.len());
+            if files.len() > free {
+                TOASTER.write().info(
+                    format!("A post holds up to {} images", Gallery::MAX_IMAGES),
+                    Duration::seconds(5),
+                );
+            }
+            for file in files.iter().take(free) {
+                match read_as_data_url(file).await {
+                    Ok(data) => page_state.with_mut(|state| state.push_image(data)),
+                    Err(e) => TOASTER
+                        .write()
+                        .error(format!("Failed to load file: {}", e), Duration::seconds(5)),
+                }
             }
         }
     };
+    let full = page_state.read().images.len() >= Gallery::MAX_IMAGES;
 
     rsx!(
         div {
             label {
                 r#for: "image-input",
-                "Upload image"
+                "Upload images (up to {Gallery::MAX_IMAGES})"
             }
             input {
                 class: "w-full",
                 id: "image-input",
                 r#type: "file",
                 accept: "image/*",
+                multiple: true,
+                disabled: full,
                 oninput: image_oninput
             }
         }
@@ -71,21 +220,111 @@ pub fn ImageInput(page_state: Signal<PageState>) -> Element {
 }
 
 #[component]
-pub fn ImagePreview(page_state: Signal<PageState>) -> Element {
-    let image_data = &page_state.read().image;
-    let Preview = if let Some(ref image) = image_data {
-        rsx!(img {
-            class: "max-w-[calc(var(--content-max-width)/2)] max-h[40vh]",
-            src: "{image}"
-        })
-    } else {
-        rsx!( div {"No image uploaded"} )
-    };
+pub fn ImageList(page_state: Signal<PageState>) -> Element {
+    let images = page_state.read().images.clone();
+    if images.is_empty() {
+        return rsx!(
+            div {
+                class: "flex flex-row justify-center",
+                "No image uploaded"
+            }
+        );
+    }
+
+    let last = images.len() - 1;
+    let Images = images.into_iter().enumerate().map(|(position, image)| {
+        let key = image.key;
+        let alt_id = format!("alt-text-{key}");
+        let sensitive_id = format!("sensitive-{key}");
+        let wrong_len = maybe_class!(
+            "err-text-color",
+            image.alt_text.chars().count() > AltText::MAX_CHARS
+        );
+        rsx!(
+            li {
+                key: "{key}",
+                class: "flex flex-col gap-2",
+                div {
+                    class: "flex flex-row justify-center",
+                    img {
+                        class: "max-w-[calc(var(--content-max-width)/2)] max-h-[40vh]",
+                        src: "{image.data}"
+                    }
+                }
+                label {
+                    r#for: "{alt_id}",
+                    div {
+                        class: "flex flex-row justify-between",
+                        span {"alt text (optional)"},
+                        span {
+                            class: "text-right {wrong_len}",
+                            "{image.alt_text.chars().count()}/{AltText::MAX_CHARS}"
+                        }
+                    }
+                }
+                textarea {
+                    class: "input-field",
+                    id: "{alt_id}",
+                    rows: 2,
+                    placeholder: "Describe the image for people who can't see it",
+                    value: "{image.alt_text}",
+                    oninput: move |ev| page_state.with_mut(|state| {
+                        if let Some(image) = state.image_mut(key) {
+                            image.alt_text = ev.value();
+                        }
+                    })
+                }
+                div {
+                    class: "flex flex-row gap-2 items-center",
+                    input {
+                        id: "{sensitive_id}",
+                        r#type: "checkbox",
+                        checked: image.sensitive,
+                        oninput: move |_| page_state.with_mut(|state| {
+                            if let Some(image) = state.image_mut(key) {
+                                image.sensitive = !image.sensitive;
+                            }
+                        })
+                    }
+                    label {
+                        r#for: "{sensitive_id}",
+                        "sensitive, hide until clicked"
+                    }
+                    div {
+                        class: "flex flex-row gap-2 ml-auto",
+                        button {
+                            class: "btn w-12",
+                            r#type: "button",
+                            title: "Move up",
+                            disabled: position == 0,
+                            onclick: move |_| page_state.with_mut(|state| state.move_image(key, -1)),
+                            "↑"
+                        }
+                        button {
+                            class: "btn w-12",
+                            r#type: "button",
+                            title: "Move down",
+                            disabled: position == last,
+                            onclick: move |_| page_state.with_mut(|state| state.move_image(key, 1)),
+                            "↓"
+                        }
+                        button {
+                            class: "btn w-12 bg-red-700",
+                            r#type: "button",
+                            title: "Remove",
+                            onclick: move |_| page_state.with_mut(|state| state.remove_image(key)),
+                            "X"
+                        }
+                    }
+                }
+            }
+        )
+    });
 
     rsx!(
-        div {
-            class: "flex flex-row justify-center",
-            {Preview}
+        ol {
+            class: "flex flex-col gap-4",
+            {Images}
         }
     )
 }
@@ -129,23 +368,26 @@ pub fn NewImage() -> Element {
     info!("NewChat component initialized!");
 
     let api_client = ApiClient::global();
-    let page_state = use_signal(PageState::default);
+    let draft = use_hook(|| {
+        take_open_draft(|content| matches!(content, Content::Image(_) | Content::Gallery(_)))
+    });
+    let page_state = use_signal(|| {
+        draft
+            .as_ref()
+            .map(PageState::from_draft)
+            .unwrap_or_default()
+    });
+    let autosave = use_autosave(
+        page_state,
+        draft,
+        PageState::draft_content,
+        PageState::draft_saved,
+    );
     let submit_btn_style = maybe_class!("btn-disabled", !page_state.read().can_submit());
-    let form_onsubmit = async_handler!([api_client, page_state], move |_| async move {
+    let form_onsubmit = async_handler!([api_client, page_state, autosave], move |_| async move {
         info!("Form submitted!");
         let request_data = NewPost {
-            content: Image {
-                caption: {
-                    let caption = &page_state.read().caption;
-                    if caption.is_empty() {
-                        None
-                    } else {
-                        Some(Caption::try_new(caption).unwrap())
-                    }
-                },
-                kind: ImageKind::DataUrl(page_state.read().image.clone().unwrap()),
-            }
-            .into(),
+            content: page_state.read().gallery().into(),
             options: NewPostOptions::default(),
         };
 
@@ -154,6 +396,7 @@ pub fn NewImage() -> Element {
         match response {
             Ok(_res) => {
                 info!("Post new image successfully!");
+                autosave.discard().await;
                 TOASTER
                     .write()
                     .success("Posted successfully", Duration::milliseconds(600));
@@ -211,8 +454,8 @@ pub fn NewImage() -> Element {
             ImageInput {
                 page_state: page_state
             }
-            // Image preview
-            ImagePreview {
+            // Image previews with alt text and order
+            ImageList {
                 page_state: page_state
             }
             CaptionInput {
@@ -10,9 +10,11 @@ use serde::{Deserialize, Serialize};
 use uchat_domain::{PollChoiceDescription, PollChoiceId, PollHeadline};
 use uchat_endpoint::post::{
     endpoint::{NewPost, NewPostOk},
-    types::{NewPostOptions, Poll, PollChoice},
+    types::{Content, Draft, NewPostOptions, Poll, PollChoice},
 };
 
+use super::drafts::{take_open_draft, use_autosave};
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PageState {
     pub headline: String,
@@ -57,6 +59,45 @@ impl PageState {
         true
     }
 
+    /// Continues a poll draft.
+    fn from_draft(draft: &Draft) -> Self {
+        let Content::Poll(poll) = &draft.content else {
+            return Self::default();
+        };
+        let mut state = Self {
+            headline: poll.headline.as_ref().to_string(),
+            poll_choices: BTreeMap::new(),
+            next_id: 0,
+        };
+        for choice in poll.choices.iter() {
+            state.push_choice(choice.description.as_ref());
+        }
+        while state.poll_choices.len() < 2 {
+            state.push_choice("");
+        }
+        state
+    }
+
+    /// What to keep as a draft, once there is a headline. Choices are kept
+    /// once they are filled in.
+    fn draft_content(&self) -> Option<Content> {
+        let poll = Poll {
+            headline: PollHeadline::try_new(&self.headline).ok()?,
+            choices: self
+                .poll_choices
+                .values()
+                .filter_map(|choice| PollChoiceDescription::try_new(choice).ok())
+                .map(|description| PollChoice {
+                    id: PollChoiceId::new(),
+                    num_votes: 0,
+                    description,
+                })
+                .collect(),
+            voted: None,
+        };
+        Some(poll.into())
+    }
+
     pub fn push_choice<T: Into<String>>(&mut self, choice: T) {
         self.poll_choices.insert(self.next_id, choice.into());
         self.next_id += 1;
@@ -168,9 +209,16 @@ pub fn NewPoll() -> Element {
     info!("NewChat component initialized!");
 
     let api_client = ApiClient::global();
-    let page_state = use_signal(PageState::default);
+    let draft = use_hook(|| take_open_draft(|content| matches!(content, Content::Poll(_))));
+    let page_state = use_signal(|| {
+        draft
+            .as_ref()
+            .map(PageState::from_draft)
+            .unwrap_or_default()
+    });
+    let autosave = use_autosave(page_state, draft, PageState::draft_content, |_, _| {});
     let submit_btn_style = maybe_class!("btn-disabled", !page_state.read().can_submit());
-    let form_onsubmit = async_handler!([api_client, page_state], move |_| async move {
+    let form_onsubmit = async_handler!([api_client, page_state, autosave], move |_| async move {
         info!("Form submitted!");
         let request_data = NewPost {
             content: Poll {
@@ -204,6 +252,7 @@ pub fn NewPoll() -> Element {
         match response {
             Ok(_res) => {
                 info!("Post new poll successfully!");
+                autosave.discard().await;
                 TOASTER
                     .write()
                     .success("Posted successfully", Duration::microseconds(1500));
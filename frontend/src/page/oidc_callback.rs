@@ -0,0 +1,220 @@
+#![allow(non_snake_case)]
+
+use crate::page::login::{finish_login, TwoFactorCodeInput};
+use crate::prelude::*;
+use chrono::Duration;
+use dioxus::prelude::*;
+use dioxus_logger::tracing::{error, info};
+use keyed_notifications_box::KeyedNotifications;
+use uchat_domain::{OidcSignupId, Username};
+use uchat_endpoint::user::endpoint::{
+    CompleteOidcSignup, FinishOidcLogin, LoginOk, OidcOutcome, TwoFactorChallenge, VerifyTwoFactor,
+};
+
+#[derive(Debug, Clone)]
+pub enum Step {
+    Waiting,
+    TwoFactor(TwoFactorChallenge),
+    ChooseHandle(OidcSignupId),
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct PageState {
+    pub step: Step,
+    pub handle: String,
+    pub form_error: KeyedNotifications,
+}
+
+/// `state` and `code` from the query string, or the error the provider sent.
+fn callback_params() -> Result<FinishOidcLogin, String> {
+    let search = crate::util::window()
+        .location()
+        .search()
+        .unwrap_or_default();
+    let mut state = None;
+    let mut code = None;
+    for (key, value) in url::form_urlencoded::parse(search.trim_start_matches('?').as_bytes()) {
+        match key.as_ref() {
+            "state" => state = Some(value.into_owned()),
+            "code" => code = Some(value.into_owned()),
+            "error" => return Err(format!("The provider refused to sign you in ({value})")),
+            _ => {}
+        }
+    }
+    match (state, code) {
+        (Some(state), Some(code)) => Ok(FinishOidcLogin { state, code }),
+        _ => Err("The sign in link is incomplete".to_string()),
+    }
+}
+
+#[component]
+pub fn HandleForm(signup_id: OidcSignupId, page_state: Signal<PageState>) -> Element {
+    let api_client = ApiClient::global();
+    let navigator = use_navigator();
+
+    let onsubmit = async_handler!([api_client, page_state], move |_| async move {
+        let handle = page_state.with(|state| state.handle.trim().to_string());
+        let username = match Username::try_new(handle) {
+            Ok(username) => username,
+            Err(e) => {
+                page_state.with_mut(|state| state.form_error.set("handle", e.to_string()));
+                return;
+            }
+        };
+        let request_data = CompleteOidcSignup {
+            signup_id,
+            username,
+        };
+        match fetch_json!(<LoginOk>, api_client, request_data) {
+            Ok(res) => finish_login(res, navigator),
+            Err(err) => {
+                page_state.with_mut(|state| state.form_error.set("handle", err.to_string()));
+            }
+        }
+    });
+
+    rsx!(
+        form {
+            class: "flex flex-col gap-5",
+            onsubmit: onsubmit,
+            p { "Welcome! Pick the handle others will see." }
+            div {
+                class: "flex flex-col",
+                label {
+                    r#for: "handle",
+                    "Username"
+                }
+                input {
+                    id: "handle",
+                    class: "input-field",
+                    placeholder: "User name",
+                    value: "{page_state.read().handle}",
+                    oninput: move |ev| page_state.with_mut(|state| {
+                        state.form_error.remove("handle");
+                        state.handle = ev.value();
+                    }),
+                }
+            }
+            KeyedNotificationsBox {
+                legend: "Form errors",
+                notification: page_state.read().form_error.clone()
+            }
+            button {
+                class: "btn",
+                r#type: "submit",
+                "Create account"
+            }
+        }
+    )
+}
+
+#[component]
+pub fn SecondFactorForm(challenge: TwoFactorChallenge, page_state: Signal<PageState>) -> Element {
+    let api_client = ApiClient::global();
+    let navigator = use_navigator();
+    let code = use_signal(String::new);
+    let challenge_id = challenge.challenge_id;
+
+    let onsubmit = async_handler!([api_client, page_state, code], move |_| async move {
+        let request_data = VerifyTwoFactor {
+            challenge_id,
+            code: code.read().trim().to_string(),
+        };
+        match fetch_json!(<LoginOk>, api_client, request_data) {
+            Ok(res) => finish_login(res, navigator),
+            Err(err) => {
+                code.set(String::new());
+                page_state.with_mut(|state| state.form_error.set("code", err.to_string()));
+            }
+        }
+    });
+
+    rsx!(
+        form {
+            class: "flex flex-col gap-5",
+            onsubmit: onsubmit,
+            TwoFactorCodeInput { state: code }
+            KeyedNotificationsBox {
+                legend: "Login errors",
+                notification: page_state.read().form_error.clone()
+            }
+            button {
+                class: "btn",
+                r#type: "submit",
+                "Verify"
+            }
+        }
+    )
+}
+
+/// Page the provider redirects back to after signing in.
+pub fn OidcCallback() -> Element {
+    let api_client = ApiClient::global();
+    let navigator = use_navigator();
+    let mut page_state = use_signal(|| PageState {
+        step: Step::Waiting,
+        handle: String::new(),
+        form_error: KeyedNotifications::default(),
+    });
+
+    let _finish = use_resource(move || async move {
+        let request_data = match callback_params() {
+            Ok(request_data) => request_data,
+            Err(msg) => {
+                page_state.with_mut(|state| state.step = Step::Failed(msg));
+                return;
+            }
+        };
+
+        match fetch_json!(<OidcOutcome>, api_client, request_data) {
+            Ok(OidcOutcome::LoggedIn(res)) => finish_login(res, navigator),
+            Ok(OidcOutcome::TwoFactorRequired(challenge)) => {
+                info!("Second factor required");
+                page_state.with_mut(|state| state.step = Step::TwoFactor(challenge));
+            }
+            Ok(OidcOutcome::ChooseHandle {
+                signup_id,
+                suggested_handle,
+            }) => page_state.with_mut(|state| {
+                state.handle = suggested_handle.unwrap_or_default();
+                state.step = Step::ChooseHandle(signup_id);
+            }),
+            Ok(OidcOutcome::Linked(identity)) => {
+                TOASTER.write().success(
+                    format!("Linked {}", identity.provider.name),
+                    Duration::milliseconds(1200),
+                );
+                navigator.replace(Route::LinkedAccounts {});
+            }
+            Err(err) => {
+                error!("OIDC login failed: {:?}", err);
+                page_state.with_mut(|state| state.step = Step::Failed(err.to_string()));
+            }
+        }
+    });
+
+    let step = page_state.read().step.clone();
+    match step {
+        Step::Waiting => rsx!( div { "Signing in..." } ),
+        Step::TwoFactor(challenge) => rsx!(SecondFactorForm {
+            challenge: challenge,
+            page_state: page_state
+        }),
+        Step::ChooseHandle(signup_id) => rsx!(HandleForm {
+            signup_id: signup_id,
+            page_state: page_state
+        }),
+        Step::Failed(msg) => rsx!(
+            div {
+                class: "flex flex-col gap-5",
+                p { "{msg}" }
+                Link {
+                    class: "link text-center",
+                    to: Route::Login {},
+                    "Back to login"
+                }
+            }
+        ),
+    }
+}
@@ -5,8 +5,11 @@ use crate::prelude::*;
 use chrono::Duration;
 use dioxus::prelude::*;
 use dioxus_logger::tracing::{error, info};
-use uchat_domain::{Password, Username};
-use uchat_endpoint::user::endpoint::{CreateUser, CreateUserOk};
+use uchat_domain::{password_policy::PasswordProblem, Password, Username};
+use uchat_endpoint::{
+    user::endpoint::{CheckPassword, CheckPasswordOk, CreateUser, CreateUserOk, PasswordRejected},
+    Endpoint,
+};
 
 pub struct PageState {
     pub username: Signal<String>,
@@ -29,6 +32,64 @@ impl PageState {
     }
 }
 
+fn problem_key(problem: &PasswordProblem) -> &'static str {
+    match problem {
+        PasswordProblem::TooShort { .. } => "Bad password",
+        PasswordProblem::TooWeak { .. } => "Weak password",
+        PasswordProblem::ContainsHandle => "Password contains username",
+        PasswordProblem::Common => "Common password",
+    }
+}
+
+/// Replaces the password messages with `problems`.
+fn show_password_problems(form_error: &mut KeyedNotifications, problems: &[PasswordProblem]) {
+    for key in [
+        "Bad password",
+        "Weak password",
+        "Password contains username",
+        "Common password",
+    ] {
+        form_error.remove(key);
+    }
+    for problem in problems {
+        form_error.set(problem_key(problem), problem.to_string());
+    }
+}
+
+/// Sends `request_data` like `fetch_json!`, but keeps the password problems
+/// the server answers with.
+async fn create_user(
+    api_client: &ApiClient,
+    request_data: CreateUser,
+) -> Result<CreateUserOk, PasswordRejected> {
+    let failed = |msg: String| PasswordRejected {
+        msg,
+        problems: Vec::new(),
+    };
+    let duration = std::time::Duration::from_millis(6000);
+    let res = api_client
+        .post_json(request_data.url(), &request_data, duration)
+        .await
+        .map_err(|err| failed(err.to_string()))?;
+
+    let status = res.status();
+    if status.is_success() {
+        return res
+            .json::<CreateUserOk>()
+            .await
+            .map_err(|err| failed(err.to_string()));
+    }
+    match res.json::<PasswordRejected>().await {
+        Ok(rejected) => Err(rejected),
+        Err(_) => Err(failed(
+            status
+                .canonical_reason()
+                .unwrap_or("An error occurred. Please try again.")
+                .to_string(),
+        )),
+    }
+}
+
 #[component]
 pub fn UsernameInput(state: Signal<String>, oninput: EventHandler<FormEvent>) -> Element {
     rsx! {
@@ -47,7 +108,11 @@ pub fn UsernameInput(state: Signal<String>, oninput: EventHandler<FormEvent>) ->
 }
 
 #[component]
-pub fn PasswordInput(state: Signal<String>, oninput: EventHandler<FormEvent>) -> Element {
+pub fn PasswordInput(
+    state: Signal<String>,
+    oninput: EventHandler<FormEvent>,
+    onchange: EventHandler<FormEvent>,
+) -> Element {
     rsx! {
         div { class: "flex flex-col",
             label { r#for: "password", "Password" }
@@ -58,7 +123,8 @@ pub fn PasswordInput(state: Signal<String>, oninput: EventHandler<FormEvent>) ->
                 class: "input-field",
                 placeholder: "Password",
                 value: "{state.read()}",
-                oninput: move |ev| oninput.call(ev)
+                oninput: move |ev| oninput.call(ev),
+                onchange: move |ev| onchange.call(ev)
             }
         }
     }
@@ -90,7 +156,7 @@ pub fn Register() -> Element {
                 .expect("There is somthing wrong with password"),
         };
 
-        let response = fetch_json!(<CreateUserOk>, api_client, request_data);
+        let response = create_user(api_client, request_data).await;
 
         match response {
             Ok(res) => {
@@ -108,9 +174,12 @@ pub fn Register() -> Element {
 
                 navigator().replace(Route::Home {});
             }
-            Err(err) => {
+            Err(rejected) => {
+                page_state.with_mut(|state| {
+                    show_password_problems(&mut state.form_error, &rejected.problems)
+                });
                 TOASTER.write().error(
-                    format!("Failed to register: {}", err),
+                    format!("Failed to register: {}", rejected.msg),
                     Duration::milliseconds(1200),
                 );
             }
@@ -124,17 +193,34 @@ pub fn Register() -> Element {
         } else {
             page_state.with_mut(|state| state.form_error.remove("Bad username"));
         }
-        page_state.with_mut(|state| state.username.set(ev.value()));
+        // Password problems depend on the handle, so they are checked again
+        // by the server once the password changes or the form is sent
+        page_state.with_mut(|state| {
+            state.username.set(ev.value());
+            show_password_problems(&mut state.form_error, &[]);
+        });
     });
 
     let password_oninput = sync_handler!([page_state], move |ev: FormEvent| {
-        if let Err(e) = Password::try_new(ev.value()) {
-            page_state.with_mut(|state| state.form_error.set("Bad password", e.to_string()));
-        } else {
-            page_state.with_mut(|state| state.form_error.remove("Bad password"));
-        }
-        page_state.with_mut(|state| state.password.set(ev.value()));
+        page_state.with_mut(|state| {
+            show_password_problems(&mut state.form_error, &[]);
+            state.password.set(ev.value());
+        });
     });
+
+    // Only the server knows its policy and blocklist, so ask it once typing is done
+    let password_onchange =
+        async_handler!([api_client, page_state], move |ev: FormEvent| async move {
+            let request_data = page_state.with(|state| CheckPassword {
+                password: ev.value(),
+                handle: Some(state.username.read().to_string()),
+            });
+            match fetch_json!(<CheckPasswordOk>, api_client, request_data) {
+                Ok(res) => page_state
+                    .with_mut(|state| show_password_problems(&mut state.form_error, &res.problems)),
+                Err(err) => error!("Failed to check password: {:?}", err),
+            }
+        });
     let btn_submit_style = match page_state.with(|state| state.can_submit()) {
         false => "btn-disabled",
         true => "",
@@ -153,7 +239,8 @@ pub fn Register() -> Element {
             // Password input component
             PasswordInput {
                 state: page_state.with(|state| state.password),
-                oninput: password_oninput
+                oninput: password_oninput,
+                onchange: password_onchange
             }
             // Login link
             LoginLink {}
@@ -0,0 +1,124 @@
+#![allow(non_snake_case)]
+
+use crate::prelude::*;
+use chrono::Duration;
+use dioxus::prelude::*;
+use dioxus_logger::tracing::error;
+use uchat_endpoint::user::{
+    endpoint::{GetSecurityEvents, GetSecurityEventsOk},
+    types::{SecurityEvent, SecurityEventKind},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct PageState {
+    pub events: Vec<SecurityEvent>,
+    pub loaded: bool,
+    pub has_more: bool,
+}
+
+/// Matches the page size used by the server.
+const EVENTS_PER_PAGE: usize = 50;
+
+#[component]
+pub fn EventEntry(event: SecurityEvent) -> Element {
+    let created_at = event
+        .created_at
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M");
+    let ip_address = event
+        .ip_address
+        .unwrap_or_else(|| "unknown address".to_string());
+    let user_agent = event
+        .user_agent
+        .unwrap_or_else(|| "unknown device".to_string());
+    let alert = match event.kind {
+        SecurityEventKind::LoginFailed | SecurityEventKind::AccountLocked => "text-red-600",
+        _ => "",
+    };
+
+    rsx!(
+        li {
+            class: "flex flex-col border-b py-2",
+            div {
+                class: "flex flex-row justify-between",
+                span { class: "font-bold {alert}", "{event.kind.description()}" }
+                span { class: "text-sm", "{created_at}" }
+            }
+            span { class: "text-sm", "{ip_address}" }
+            span { class: "text-xs break-all", "{user_agent}" }
+        }
+    )
+}
+
+pub fn SecurityEvents() -> Element {
+    let api_client = ApiClient::global();
+    let mut page_state = use_signal(PageState::default);
+
+    let fetch_events = move |older_than| async move {
+        match fetch_json!(<GetSecurityEventsOk>, api_client, GetSecurityEvents { older_than }) {
+            Ok(res) => page_state.with_mut(|state| {
+                state.loaded = true;
+                state.has_more = res.events.len() == EVENTS_PER_PAGE;
+                state.events.extend(res.events);
+            }),
+            Err(err) => {
+                error!("Failed to fetch security events: {:?}", err);
+                TOASTER.write().error(
+                    format!("Failed to retrieve security activity: {err}"),
+                    Duration::milliseconds(1200),
+                );
+            }
+        }
+    };
+
+    let _fetch_first = use_resource(move || fetch_events(None));
+
+    let more_onclick = move |_| {
+        let older_than = page_state.with(|state| state.events.last().map(|e| e.created_at));
+        spawn(fetch_events(older_than));
+    };
+
+    let content = page_state.with(|state| {
+        if !state.loaded {
+            rsx!( div { "Loading..." } )
+        } else if state.events.is_empty() {
+            rsx!( div { "No security activity yet." } )
+        } else {
+            let events = state.events.clone();
+            rsx!(
+                ul {
+                    class: "flex flex-col",
+                    for event in events {
+                        EventEntry { event: event }
+                    }
+                }
+            )
+        }
+    });
+
+    rsx!(
+        Appbar {
+            title: "Security activity",
+            AppbarImgButton {
+                click_handler: move |_| {
+                    navigator().go_back();
+                },
+                img: ICON_BACK,
+                label: "Back",
+                title: "Go to the previous page",
+            }
+        }
+        div {
+            class: "flex flex-col gap-3 w-full",
+            p { "Recent sign ins and changes to your account. If you don't recognize an entry, change your password." }
+            {content}
+            if page_state.read().has_more {
+                button {
+                    class: "btn",
+                    onclick: more_onclick,
+                    "Load more"
+                }
+            }
+        }
+    )
+}
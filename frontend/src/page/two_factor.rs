@@ -0,0 +1,301 @@
+#![allow(non_snake_case)]
+
+use crate::prelude::*;
+use chrono::Duration;
+use dioxus::prelude::*;
+use dioxus_logger::tracing::{error, info};
+use keyed_notifications_box::KeyedNotifications;
+use qrcode::{render::svg, QrCode};
+use uchat_domain::Password;
+use uchat_endpoint::user::endpoint::{
+    ConfirmTotp, ConfirmTotpOk, DisableTotp, DisableTotpOk, EnrollTotp, EnrollTotpOk,
+    GetTwoFactorStatus, GetTwoFactorStatusOk,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct PageState {
+    pub status: Option<GetTwoFactorStatusOk>,
+    pub enrollment: Option<EnrollTotpOk>,
+    pub recovery_codes: Vec<String>,
+    pub code: String,
+    pub password: String,
+    pub form_error: KeyedNotifications,
+}
+
+impl PageState {
+    fn is_enabled(&self) -> bool {
+        self.status.as_ref().map(|s| s.enabled).unwrap_or(false)
+    }
+}
+
+fn qr_code_svg(uri: &str) -> Option<String> {
+    QrCode::new(uri.as_bytes())
+        .ok()
+        .map(|code| code.render::<svg::Color>().min_dimensions(200, 200).build())
+}
+
+#[component]
+pub fn CodeInput(page_state: Signal<PageState>) -> Element {
+    rsx!(
+        div {
+            label {
+                r#for: "totp-code",
+                "Authentication code"
+            }
+            input {
+                id: "totp-code",
+                class: "input-field",
+                autocomplete: "one-time-code",
+                placeholder: "123456",
+                value: "{page_state.read().code}",
+                oninput: move |ev| page_state.with_mut(|state| state.code = ev.value()),
+            }
+        }
+    )
+}
+
+#[component]
+pub fn RecoveryCodes(page_state: Signal<PageState>) -> Element {
+    let codes = page_state.read().recovery_codes.clone();
+    if codes.is_empty() {
+        return None;
+    }
+
+    rsx!(
+        fieldset {
+            class: "fieldset",
+            legend { "Recovery codes" }
+            p {
+                "Store these codes somewhere safe. Each code can be used once
+                to sign in if you lose access to your authenticator app."
+            }
+            ul {
+                class: "font-mono grid grid-cols-2 gap-1",
+                for code in codes {
+                    li { "{code}" }
+                }
+            }
+        }
+    )
+}
+
+#[component]
+pub fn Enrollment(page_state: Signal<PageState>) -> Element {
+    let api_client = ApiClient::global();
+    let enrollment = page_state.read().enrollment.clone();
+
+    let start_onclick = async_handler!([api_client, page_state], move |_| async move {
+        match fetch_json!(<EnrollTotpOk>, api_client, EnrollTotp) {
+            Ok(res) => page_state.with_mut(|state| {
+                state.code.clear();
+                state.form_error.remove("totp");
+                state.enrollment = Some(res);
+            }),
+            Err(err) => {
+                error!("Failed to start enrolment: {:?}", err);
+                TOASTER.write().error(
+                    format!("Failed to start setup: {err}"),
+                    Duration::milliseconds(1200),
+                );
+            }
+        }
+    });
+
+    let confirm_onsubmit = async_handler!([api_client, page_state], move |_| async move {
+        let request_data = ConfirmTotp {
+            code: page_state.with(|state| state.code.trim().to_string()),
+        };
+        match fetch_json!(<ConfirmTotpOk>, api_client, request_data) {
+            Ok(res) => {
+                info!("Two-factor authentication enabled");
+                page_state.with_mut(|state| {
+                    state.code.clear();
+                    state.form_error.remove("totp");
+                    state.enrollment = None;
+                    state.status = Some(GetTwoFactorStatusOk {
+                        enabled: true,
+                        recovery_codes_left: res.recovery_codes.len(),
+                    });
+                    state.recovery_codes = res.recovery_codes;
+                });
+                TOASTER.write().success(
+                    "Two-factor authentication enabled",
+                    Duration::milliseconds(1200),
+                );
+            }
+            Err(err) => {
+                page_state.with_mut(|state| state.form_error.set("totp", err.to_string()));
+            }
+        }
+    });
+
+    match enrollment {
+        None => rsx!(
+            p { "Protect your account with a code from an authenticator app." }
+            button {
+                class: "btn",
+                onclick: start_onclick,
+                "Set up two-factor authentication"
+            }
+        ),
+        Some(enrollment) => {
+            let qr_code = qr_code_svg(&enrollment.otpauth_uri).unwrap_or_default();
+            rsx!(
+                form {
+                    class: "flex flex-col gap-3",
+                    onsubmit: confirm_onsubmit,
+                    p { "Scan the code with your authenticator app, then enter the code it shows." }
+                    div {
+                        class: "flex flex-row justify-center",
+                        dangerous_inner_html: "{qr_code}",
+                    }
+                    a {
+                        class: "link text-center",
+                        href: "{enrollment.otpauth_uri}",
+                        "Open in authenticator app"
+                    }
+                    p {
+                        class: "text-center",
+                        "Or enter this key manually: "
+                        span { class: "font-mono break-all", "{enrollment.secret}" }
+                    }
+                    CodeInput { page_state: page_state }
+                    button {
+                        class: "btn",
+                        r#type: "submit",
+                        "Enable"
+                    }
+                }
+            )
+        }
+    }
+}
+
+#[component]
+pub fn Disable(page_state: Signal<PageState>) -> Element {
+    let api_client = ApiClient::global();
+    let codes_left = page_state
+        .read()
+        .status
+        .as_ref()
+        .map(|s| s.recovery_codes_left)
+        .unwrap_or_default();
+
+    let disable_onsubmit = async_handler!([api_client, page_state], move |_| async move {
+        let password = match Password::try_new(page_state.with(|state| state.password.clone())) {
+            Ok(password) => password,
+            Err(e) => {
+                page_state.with_mut(|state| state.form_error.set("totp", e.to_string()));
+                return;
+            }
+        };
+        let request_data = DisableTotp {
+            password,
+            code: page_state.with(|state| state.code.trim().to_string()),
+        };
+        match fetch_json!(<DisableTotpOk>, api_client, request_data) {
+            Ok(_) => {
+                info!("Two-factor authentication disabled");
+                page_state.with_mut(|state| {
+                    *state = PageState {
+                        status: Some(GetTwoFactorStatusOk {
+                            enabled: false,
+                            recovery_codes_left: 0,
+                        }),
+                        ..PageState::default()
+                    }
+                });
+                TOASTER.write().success(
+                    "Two-factor authentication disabled",
+                    Duration::milliseconds(1200),
+                );
+            }
+            Err(err) => {
+                page_state.with_mut(|state| state.form_error.set("totp", err.to_string()));
+            }
+        }
+    });
+
+    rsx!(
+        p { "Two-factor authentication is enabled. Recovery codes left: {codes_left}" }
+        RecoveryCodes { page_state: page_state }
+        form {
+            class: "flex flex-col gap-3",
+            onsubmit: disable_onsubmit,
+            fieldset {
+                class: "fieldset",
+                legend { "Disable two-factor authentication" }
+                div {
+                    label {
+                        r#for: "password",
+                        "Password"
+                    }
+                    input {
+                        id: "password",
+                        class: "input-field",
+                        r#type: "password",
+                        placeholder: "Password",
+                        value: "{page_state.read().password}",
+                        oninput: move |ev| page_state.with_mut(|state| state.password = ev.value()),
+                    }
+                }
+                CodeInput { page_state: page_state }
+            }
+            button {
+                class: "btn",
+                r#type: "submit",
+                "Disable"
+            }
+        }
+    )
+}
+
+pub fn TwoFactor() -> Element {
+    let api_client = ApiClient::global();
+    let mut page_state = use_signal(PageState::default);
+
+    let _fetch_status = use_resource(move || async move {
+        match fetch_json!(<GetTwoFactorStatusOk>, api_client, GetTwoFactorStatus) {
+            Ok(status) => page_state.with_mut(|state| state.status = Some(status)),
+            Err(err) => {
+                error!("Failed to fetch two-factor status: {:?}", err);
+                TOASTER.write().error(
+                    format!("Failed to retrieve two-factor status: {err}"),
+                    Duration::milliseconds(1200),
+                );
+            }
+        }
+    });
+
+    let content = match page_state.with(|state| (state.status.is_some(), state.is_enabled())) {
+        (false, _) => rsx!( div { "Loading..." } ),
+        (true, true) => rsx!(Disable {
+            page_state: page_state
+        }),
+        (true, false) => rsx!(Enrollment {
+            page_state: page_state
+        }),
+    };
+
+    rsx!(
+        Appbar {
+            title: "Two-factor authentication",
+            AppbarImgButton {
+                click_handler: move |_| {
+                    navigator().go_back();
+                },
+                img: ICON_BACK,
+                label: "Back",
+                title: "Go to the previous page",
+            }
+        }
+        div {
+            class: "flex flex-col gap-3 w-full",
+            {content}
+            KeyedNotificationsBox {
+                legend: "Errors",
+                notification: page_state.read().form_error.clone()
+            }
+        }
+    )
+}
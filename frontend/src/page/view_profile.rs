@@ -6,6 +6,7 @@ use dioxus::prelude::*;
 use dioxus_logger::tracing::{error, info};
 use std::str::FromStr;
 use uchat_domain::UserId;
+use uchat_endpoint::post::types::srcset;
 use uchat_endpoint::user::{
     endpoint::{FollowUser, FollowUserOk, ViewProfile, ViewProfileOk},
     types::{FollowAction, PublicUserProfile},
@@ -60,6 +61,7 @@ pub fn ViewProfile(user_id: ReadOnlySignal<String>) -> Element {
                     .profile_image
                     .map(|url| url.to_string())
                     .unwrap_or("".to_string());
+                let profile_image_srcset = srcset(&profile.profile_image_variants);
 
                 let follow_button_text = match profile.am_following {
                     true => "Unfollow",
@@ -80,6 +82,26 @@ pub fn ViewProfile(user_id: ReadOnlySignal<String>) -> Element {
                     }
                 });
 
+                let Lists = LOCAL_PROFILE.read().user_id.map(|id| {
+                    if id == profile.id {
+                        None
+                    } else {
+                        rsx!(ListMembership {
+                            user_id: profile.id
+                        })
+                    }
+                });
+
+                let Report = LOCAL_PROFILE.read().user_id.map(|id| {
+                    if id == profile.id {
+                        None
+                    } else {
+                        rsx!(ReportForm {
+                            target: ReportTarget::User(profile.id)
+                        })
+                    }
+                });
+
                 rsx! {
                     div {
                         class: "flex flex-col gap-3",
@@ -88,11 +110,15 @@ pub fn ViewProfile(user_id: ReadOnlySignal<String>) -> Element {
                             img {
                                 class: "profile-portrait-lg",
                                 src: "{profile_image}",
+                                srcset: "{profile_image_srcset}",
+                                "sizes": "7rem",
                             }
                         },
                         div { "Handle: {profile.handle}" },
                         div { "Name: {display_name} "},
                         {FollowButton}
+                        {Lists}
+                        {Report}
                     }
                 }
             }
@@ -134,6 +134,19 @@ macro_rules! fetch_json {
                     }
                 } else {
                     let status = res.status();
+                    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                        let retry_after = res
+                            .headers()
+                            .get(reqwest::header::RETRY_AFTER)
+                            .and_then(|value| value.to_str().ok())
+                            .and_then(|value| value.parse::<i64>().ok());
+                        if let Some(secs) = retry_after {
+                            $crate::TOASTER.write().error(
+                                format!("Too many requests, please wait {secs} seconds"),
+                                chrono::Duration::seconds(secs.clamp(2, 10)),
+                            );
+                        }
+                    }
                     match res.json::<uchat_endpoint::RequestFailed>().await {
                         Ok(payload) => Err(RequestError::BadRequest(payload)),
                         Err(_) => Err(RequestError::BadRequest(uchat_endpoint::RequestFailed {
@@ -1,5 +1,7 @@
 pub const SESSION_ID: &str = "SESSION_ID";
 pub const SESSION_SIGNATURE: &str = "SESSION_SIGNATURE";
+/// Ties a pending OIDC login to the browser which started it.
+pub const OIDC_BROWSER_KEY: &str = "OIDC_BROWSER_KEY";
 
 pub fn get_from_str<'a>(cookies: &'a str, key: &str) -> Option<&'a str> {
     cookies
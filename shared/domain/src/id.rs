@@ -30,9 +30,11 @@ macro_rules! new_id {
             pub fn as_uuid(&self) -> &Uuid {
                 &self.0
             }
+        }
 
-            pub fn to_string(&self) -> String {
-                self.0.to_string()
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fmt(f)
             }
         }
 
@@ -65,5 +67,15 @@ macro_rules! new_id {
 new_id!(UserId);
 new_id!(SessionId);
 new_id!(PostId);
+new_id!(DraftId);
+new_id!(ListId);
+new_id!(BookmarkFolderId);
 new_id!(ImageId);
+new_id!(VideoId);
 new_id!(PollChoiceId);
+new_id!(LoginChallengeId);
+new_id!(ApiTokenId);
+new_id!(OidcSignupId);
+new_id!(ReportId);
+new_id!(ModerationActionId);
+new_id!(MuteFilterId);
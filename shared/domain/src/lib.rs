@@ -3,6 +3,7 @@
 extern crate diesel_derive_newtype;
 
 pub mod id;
+pub mod password_policy;
 pub mod post;
 pub mod user;
 
@@ -0,0 +1,146 @@
+//! Password strength rules shared by the server and the frontend, so forms
+//! can explain a rejection before anything is submitted.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, thiserror::Error)]
+pub enum PasswordProblem {
+    #[error("Password must have at least {min} characters")]
+    TooShort { min: usize },
+    #[error("Password is too easy to guess, add more words or characters")]
+    TooWeak { bits: u32, min_bits: u32 },
+    #[error("Password must not contain your username")]
+    ContainsHandle,
+    #[error("Password is too common")]
+    Common,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PasswordPolicy {
+    pub min_chars: usize,
+    pub min_entropy_bits: u32,
+}
+
+impl PasswordPolicy {
+    pub const MIN_CHARS: usize = 8;
+    pub const MIN_ENTROPY_BITS: u32 = 40;
+
+    /// Checks everything which doesn't need the blocklist.
+    pub fn check(&self, password: &str, handle: Option<&str>) -> Vec<PasswordProblem> {
+        let mut problems = vec![];
+
+        if password.chars().count() < self.min_chars {
+            problems.push(PasswordProblem::TooShort {
+                min: self.min_chars,
+            });
+        }
+
+        let bits = estimate_entropy(password) as u32;
+        if bits < self.min_entropy_bits {
+            problems.push(PasswordProblem::TooWeak {
+                bits,
+                min_bits: self.min_entropy_bits,
+            });
+        }
+
+        if let Some(handle) = handle {
+            if contains_handle(password, handle) {
+                problems.push(PasswordProblem::ContainsHandle);
+            }
+        }
+
+        problems
+    }
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_chars: Self::MIN_CHARS,
+            min_entropy_bits: Self::MIN_ENTROPY_BITS,
+        }
+    }
+}
+
+/// Rough strength estimate in bits: the size of the character classes used,
+/// times the length, where repeated and sequential characters count less.
+pub fn estimate_entropy(password: &str) -> f64 {
+    let (mut lower, mut upper, mut digit, mut symbol, mut other) =
+        (false, false, false, false, false);
+    for c in password.chars() {
+        match c {
+            'a'..='z' => lower = true,
+            'A'..='Z' => upper = true,
+            '0'..='9' => digit = true,
+            c if c.is_ascii() => symbol = true,
+            _ => other = true,
+        }
+    }
+    let pool = [
+        (lower, 26),
+        (upper, 26),
+        (digit, 10),
+        (symbol, 33),
+        (other, 100),
+    ]
+    .iter()
+    .filter(|(used, _)| *used)
+    .map(|(_, size)| size)
+    .sum::<u32>();
+    if pool == 0 {
+        return 0.0;
+    }
+
+    let mut length = 0.0;
+    let mut prev: Option<char> = None;
+    for c in password.chars() {
+        length += match prev {
+            Some(p) if p == c => 0.25,
+            Some(p) if (p as i64 - c as i64).abs() == 1 => 0.5,
+            _ => 1.0,
+        };
+        prev = Some(c);
+    }
+
+    length * (pool as f64).log2()
+}
+
+fn contains_handle(password: &str, handle: &str) -> bool {
+    // Very short handles would match too many unrelated passwords
+    handle.chars().count() >= 3 && password.to_lowercase().contains(&handle.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_simple_passwords() {
+        let policy = PasswordPolicy::default();
+
+        assert!(matches!(
+            policy.check("password", None)[..],
+            [PasswordProblem::TooWeak { .. }]
+        ));
+        assert!(matches!(
+            policy.check("abcdefghijkl", None)[..],
+            [PasswordProblem::TooWeak { .. }]
+        ));
+        assert_eq!(
+            policy.check("short", None)[0],
+            PasswordProblem::TooShort { min: 8 }
+        );
+        assert!(policy.check("correct horse battery", None).is_empty());
+    }
+
+    #[test]
+    fn rejects_handle_in_password() {
+        let policy = PasswordPolicy::default();
+
+        assert_eq!(
+            policy.check("my-Alice-is-safe-42", Some("alice")),
+            vec![PasswordProblem::ContainsHandle]
+        );
+        assert!(policy.check("my-Alice-is-safe-42", Some("bob")).is_empty());
+    }
+}
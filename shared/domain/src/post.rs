@@ -11,23 +11,30 @@ impl PollHeadline {
 }
 
 #[nutype(
-    validate(not_empty, len_char_max = 30),
+    validate(not_empty, len_char_max = 300),
     derive(Debug, Display, Clone, Serialize, Deserialize, PartialEq, AsRef)
 )]
 pub struct Headline(String);
 
 impl Headline {
-    pub const MAX_CHARS: usize = 30;
+    /// Most characters any server allows, each one configures its own limit
+    /// below this.
+    pub const MAX_CHARS: usize = 300;
 }
 
+/// Chat message, written in the markup of `uchat_markup`.
 #[nutype(
-    validate(not_empty, len_char_max = 100),
+    validate(not_empty, len_char_max = 10000),
     derive(Clone, Debug, Serialize, Deserialize, PartialEq, AsRef)
 )]
 pub struct Message(String);
 
 impl Message {
-    pub const MAX_CHARS: usize = 100;
+    /// Most characters readers may see, markup aside, on any server. Each
+    /// one configures its own limit below this.
+    pub const MAX_CHARS: usize = 5000;
+    /// Most characters including markup, leaving room for link URLs.
+    pub const MAX_SOURCE_CHARS: usize = 10000;
 }
 
 #[nutype(
@@ -40,6 +47,18 @@ impl Caption {
     pub const MAX_CHARS: usize = 60;
 }
 
+/// Describes an image for people who can't see it, unlike a [`Caption`]
+/// which adds to it.
+#[nutype(
+    validate(not_empty, len_char_max = 1000),
+    derive(Clone, Debug, Serialize, Deserialize, PartialEq, AsRef)
+)]
+pub struct AltText(String);
+
+impl AltText {
+    pub const MAX_CHARS: usize = 1000;
+}
+
 #[nutype(
     validate(not_empty, len_char_max = 80),
     derive(Clone, Debug, Serialize, Deserialize, PartialEq, AsRef)
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use uchat_domain::PostId;
+
+use super::types::{AnalyticsPeriod, PostActivity, ProfileVisits, SeenPost};
+use crate::post::types::PublicPost;
+
+/// Activity on a post over `period`, oldest bucket first. Authors only see
+/// their own posts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PostAnalytics {
+    pub post_id: PostId,
+    pub period: AnalyticsPeriod,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PostAnalyticsOk {
+    pub post: PublicPost,
+    pub activity: Vec<PostActivity>,
+}
+
+/// Visits of the profile of the user over `period`, and their posts seen
+/// most in it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProfileAnalytics {
+    pub period: AnalyticsPeriod,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProfileAnalyticsOk {
+    pub visits: Vec<ProfileVisits>,
+    pub most_seen: Vec<SeenPost>,
+}
@@ -0,0 +1,69 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::post::types::PublicPost;
+
+/// How far back analytics reach and how finely they are bucketed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnalyticsPeriod {
+    /// The last 24 hours, by the hour.
+    Day,
+    /// The last 7 days, by the day.
+    #[default]
+    Week,
+    /// The last 30 days, by the day.
+    Month,
+}
+
+impl AnalyticsPeriod {
+    pub const ALL: [AnalyticsPeriod; 3] = [Self::Day, Self::Week, Self::Month];
+
+    pub fn bucket_hours(&self) -> i64 {
+        match self {
+            Self::Day => 1,
+            Self::Week | Self::Month => 24,
+        }
+    }
+
+    pub fn bucket_count(&self) -> i64 {
+        match self {
+            Self::Day => 24,
+            Self::Week => 7,
+            Self::Month => 30,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Day => "24 hours",
+            Self::Week => "7 days",
+            Self::Month => "30 days",
+        }
+    }
+}
+
+/// What happened to a post in the bucket starting at `start`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PostActivity {
+    pub start: DateTime<Utc>,
+    /// Times the post was served to other users, once per user and hour.
+    pub impressions: i64,
+    pub reactions: i64,
+    pub boosts: i64,
+    pub replies: i64,
+    pub votes: i64,
+}
+
+/// Visits of the profile in the bucket starting at `start`, once per
+/// visitor and hour.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProfileVisits {
+    pub start: DateTime<Utc>,
+    pub visits: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SeenPost {
+    pub post: PublicPost,
+    pub impressions: i64,
+}
@@ -1,10 +1,26 @@
+use analytics::endpoint::{PostAnalytics, ProfileAnalytics};
 use load_dotenv::load_dotenv;
+use moderation::endpoint::{
+    GetModerationLog, GetModerationQueue, ReportPost, ReportUser, TakeModerationAction,
+};
 use post::endpoint::{
-    Bookmark, BookmarkedPost, Boost, HomePost, LikedPost, NewPost, React, TrendingPost, Vote,
+    Bookmark, BookmarkedPost, Boost, CreateBookmarkFolder, DeleteBookmarkFolder, DeleteDraft,
+    GetBookmarkFolders, HomePost, LikedPost, ListDrafts, ListTimeline, MoveBookmark, NewPost,
+    NewThread, PublishDraft, React, RenameBookmarkFolder, SaveDraft, ServerLimits, SetBookmarkNote,
+    TrendingPost, Vote,
 };
 use serde::{Deserialize, Serialize};
-use user::endpoint::{CreateUser, FollowUser, GetMyProfile, Login, UpdateProfile, ViewProfile};
+use user::endpoint::{
+    AddListMember, CheckPassword, CompleteOidcSignup, ConfirmTotp, CreateApiToken, CreateList,
+    CreateMuteFilter, CreateUser, DeleteList, DeleteMuteFilter, DisableTotp, EnrollTotp,
+    FinishOidcLogin, FollowUser, GetList, GetListMemberships, GetLists, GetMyProfile,
+    GetSecurityEvents, GetTwoFactorStatus, ListApiTokens, ListIdentities, ListMuteFilters,
+    ListOidcProviders, Login, Logout, RemoveListMember, RevokeApiToken, StartOidcLink,
+    StartOidcLogin, UnlinkIdentity, UpdateList, UpdateProfile, VerifyTwoFactor, ViewProfile,
+};
 
+pub mod analytics;
+pub mod moderation;
 pub mod post;
 pub mod user;
 
@@ -65,9 +81,21 @@ pub mod app_url {
 // public routes
 route!("/account/create" => CreateUser);
 route!("/account/login" => Login);
+route!("/account/login/verify" => VerifyTwoFactor);
+route!("/account/password/check" => CheckPassword);
+route!("/account/oidc/providers" => ListOidcProviders);
+route!("/account/oidc/start" => StartOidcLogin);
+route!("/account/oidc/finish" => FinishOidcLogin);
+route!("/account/oidc/signup" => CompleteOidcSignup);
+route!("/server/limits" => ServerLimits);
 
 // authorized routes
 route!("/post/new" => NewPost);
+route!("/post/new_thread" => NewThread);
+route!("/draft/save" => SaveDraft);
+route!("/draft/list" => ListDrafts);
+route!("/draft/delete" => DeleteDraft);
+route!("/draft/publish" => PublishDraft);
 route!("/post/bookmark" => Bookmark);
 route!("/post/boost" => Boost);
 route!("/post/react" => React);
@@ -76,10 +104,47 @@ route!("/posts/trending" => TrendingPost);
 route!("/posts/home" => HomePost);
 route!("/posts/liked" => LikedPost);
 route!("/posts/bookmarked" => BookmarkedPost);
+route!("/posts/list" => ListTimeline);
+route!("/bookmarks/folders" => GetBookmarkFolders);
+route!("/bookmarks/folders/create" => CreateBookmarkFolder);
+route!("/bookmarks/folders/rename" => RenameBookmarkFolder);
+route!("/bookmarks/folders/delete" => DeleteBookmarkFolder);
+route!("/bookmarks/move" => MoveBookmark);
+route!("/bookmarks/note" => SetBookmarkNote);
 route!("/profile/update" => UpdateProfile);
 route!("/profile/me" => GetMyProfile);
 route!("/profile/view" => ViewProfile);
 route!("/user/follow" => FollowUser);
+route!("/lists/create" => CreateList);
+route!("/lists/update" => UpdateList);
+route!("/lists/delete" => DeleteList);
+route!("/lists/view" => GetLists);
+route!("/lists/members" => GetList);
+route!("/lists/members/add" => AddListMember);
+route!("/lists/members/remove" => RemoveListMember);
+route!("/lists/memberships" => GetListMemberships);
+route!("/account/2fa/status" => GetTwoFactorStatus);
+route!("/account/2fa/enroll" => EnrollTotp);
+route!("/account/2fa/confirm" => ConfirmTotp);
+route!("/account/2fa/disable" => DisableTotp);
+route!("/account/logout" => Logout);
+route!("/account/security/events" => GetSecurityEvents);
+route!("/account/tokens/create" => CreateApiToken);
+route!("/account/tokens/list" => ListApiTokens);
+route!("/account/tokens/revoke" => RevokeApiToken);
+route!("/account/oidc/link" => StartOidcLink);
+route!("/account/identities/list" => ListIdentities);
+route!("/account/identities/unlink" => UnlinkIdentity);
+route!("/account/mutes/create" => CreateMuteFilter);
+route!("/account/mutes/list" => ListMuteFilters);
+route!("/account/mutes/delete" => DeleteMuteFilter);
+route!("/report/post" => ReportPost);
+route!("/report/user" => ReportUser);
+route!("/moderation/queue" => GetModerationQueue);
+route!("/moderation/action" => TakeModerationAction);
+route!("/moderation/log" => GetModerationLog);
+route!("/analytics/post" => PostAnalytics);
+route!("/analytics/profile" => ProfileAnalytics);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Update<T> {
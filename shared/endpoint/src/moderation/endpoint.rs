@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uchat_domain::{PostId, ReportId, UserId};
+
+use super::types::{ModerationAction, ModerationLogEntry, QueuedReport, ReportReason};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReportPost {
+    pub post_id: PostId,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReportPostOk {
+    pub report_id: ReportId,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReportUser {
+    pub user_id: UserId,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReportUserOk {
+    pub report_id: ReportId,
+}
+
+/// Open reports, oldest first. Moderators only.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetModerationQueue;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetModerationQueueOk {
+    pub reports: Vec<QueuedReport>,
+}
+
+/// Applies `action` and records it in the moderation log. The report, when
+/// given, is closed along with every other open report about the same target.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TakeModerationAction {
+    pub action: ModerationAction,
+    pub report_id: Option<ReportId>,
+    pub note: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TakeModerationActionOk {
+    pub entry: ModerationLogEntry,
+}
+
+/// Moderation log, newest first.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetModerationLog {
+    pub older_than: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetModerationLogOk {
+    pub entries: Vec<ModerationLogEntry>,
+}
@@ -0,0 +1,240 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uchat_domain::{ModerationActionId, PostId, ReportId, UserId};
+
+use crate::post::types::PublicPost;
+
+/// What an account may do besides posting. Roles are ordered, every role
+/// may do everything the roles before it may do.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Role {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Self::User, Self::Moderator, Self::Admin];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Moderator => "moderator",
+            Self::Admin => "admin",
+        }
+    }
+
+    pub fn is_moderator(&self) -> bool {
+        *self >= Self::Moderator
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|role| role.as_str() == s)
+            .ok_or_else(|| format!("unknown role '{s}'"))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    HateSpeech,
+    Violence,
+    Nudity,
+    Misinformation,
+    Other,
+}
+
+impl ReportReason {
+    pub const ALL: [ReportReason; 7] = [
+        Self::Spam,
+        Self::Harassment,
+        Self::HateSpeech,
+        Self::Violence,
+        Self::Nudity,
+        Self::Misinformation,
+        Self::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Spam => "spam",
+            Self::Harassment => "harassment",
+            Self::HateSpeech => "hate_speech",
+            Self::Violence => "violence",
+            Self::Nudity => "nudity",
+            Self::Misinformation => "misinformation",
+            Self::Other => "other",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Spam => "Spam or scam",
+            Self::Harassment => "Harassment or bullying",
+            Self::HateSpeech => "Hate speech",
+            Self::Violence => "Violence or threats",
+            Self::Nudity => "Nudity or sexual content",
+            Self::Misinformation => "False information",
+            Self::Other => "Something else",
+        }
+    }
+}
+
+impl std::str::FromStr for ReportReason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|reason| reason.as_str() == s)
+            .ok_or_else(|| format!("unknown report reason '{s}'"))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportStatus {
+    Open,
+    Resolved,
+    Dismissed,
+}
+
+impl ReportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Resolved => "resolved",
+            Self::Dismissed => "dismissed",
+        }
+    }
+}
+
+impl std::str::FromStr for ReportStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Self::Open, Self::Resolved, Self::Dismissed]
+            .into_iter()
+            .find(|status| status.as_str() == s)
+            .ok_or_else(|| format!("unknown report status '{s}'"))
+    }
+}
+
+/// Something a moderator does about a post or a user.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ModerationAction {
+    /// Leaves the post out of every feed.
+    HidePost(PostId),
+    DeletePost(PostId),
+    /// Blocks the account from signing in and using the API for `days` days.
+    SuspendUser {
+        user_id: UserId,
+        days: u16,
+    },
+    /// Blocks the account for good and leaves its posts out of every feed.
+    BanUser(UserId),
+    /// Closes the report without doing anything else.
+    DismissReport,
+}
+
+impl ModerationAction {
+    pub fn kind(&self) -> ModerationActionKind {
+        match self {
+            Self::HidePost(_) => ModerationActionKind::HidePost,
+            Self::DeletePost(_) => ModerationActionKind::DeletePost,
+            Self::SuspendUser { .. } => ModerationActionKind::SuspendUser,
+            Self::BanUser(_) => ModerationActionKind::BanUser,
+            Self::DismissReport => ModerationActionKind::DismissReport,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModerationActionKind {
+    HidePost,
+    DeletePost,
+    SuspendUser,
+    BanUser,
+    DismissReport,
+}
+
+impl ModerationActionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::HidePost => "hide_post",
+            Self::DeletePost => "delete_post",
+            Self::SuspendUser => "suspend_user",
+            Self::BanUser => "ban_user",
+            Self::DismissReport => "dismiss_report",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::HidePost => "Hid a post",
+            Self::DeletePost => "Deleted a post",
+            Self::SuspendUser => "Suspended a user",
+            Self::BanUser => "Banned a user",
+            Self::DismissReport => "Dismissed a report",
+        }
+    }
+}
+
+impl std::str::FromStr for ModerationActionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Self::HidePost,
+            Self::DeletePost,
+            Self::SuspendUser,
+            Self::BanUser,
+            Self::DismissReport,
+        ]
+        .into_iter()
+        .find(|kind| kind.as_str() == s)
+        .ok_or_else(|| format!("unknown moderation action '{s}'"))
+    }
+}
+
+/// Open report waiting for a moderator.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QueuedReport {
+    pub id: ReportId,
+    pub reporter: String,
+    /// Reported user, or the author of the reported post.
+    pub user_id: UserId,
+    pub handle: String,
+    /// Missing for reports about a user.
+    pub post: Option<PublicPost>,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModerationLogEntry {
+    pub id: ModerationActionId,
+    pub moderator: String,
+    pub action: ModerationActionKind,
+    pub user_id: Option<UserId>,
+    pub handle: Option<String>,
+    pub post_id: Option<PostId>,
+    pub report_id: Option<ReportId>,
+    pub note: Option<String>,
+    /// End of a suspension.
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// Replies deleted along with the post.
+    #[serde(default)]
+    pub deleted_post_ids: Vec<PostId>,
+}
@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
-use uchat_domain::{PollChoiceId, PostId};
+use uchat_domain::{BookmarkFolderId, DraftId, ListId, PollChoiceId, PostId};
 
 use super::types::{
-    BookmarkAction, BoostAction, Content, LikeStatus, NewPostOptions, PublicPost, VoteCast,
+    BookmarkAction, BookmarkDetails, BookmarkFolder, BoostAction, Chat, Content, Draft, LikeStatus,
+    NewPostOptions, PublicPost, VoteCast,
 };
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -16,6 +17,87 @@ pub struct NewPostOk {
     pub post_id: PostId,
 }
 
+/// Posts a thread, where every chat replies to the one before it and the
+/// first one to `options.reply_to`. Either all chats are posted or none.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NewThread {
+    pub chats: Vec<Chat>,
+    pub options: NewPostOptions,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NewThreadOk {
+    /// Ids of the posts, in the order of the chats.
+    pub post_ids: Vec<PostId>,
+}
+
+/// Limits this server puts on posts, for clients to check against.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ServerLimits;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ServerLimitsOk {
+    /// Most characters readers see in a message, markup aside.
+    pub message_max_chars: usize,
+    pub headline_max_chars: usize,
+    /// Most chats in a [`NewThread`].
+    pub thread_max_posts: usize,
+}
+
+impl Default for ServerLimitsOk {
+    /// The limits of servers which don't configure any.
+    fn default() -> Self {
+        Self {
+            message_max_chars: 100,
+            headline_max_chars: 30,
+            thread_max_posts: 10,
+        }
+    }
+}
+
+/// Saves a draft, as a new one unless `draft_id` is given.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveDraft {
+    pub draft_id: Option<DraftId>,
+    pub content: Content,
+    pub options: NewPostOptions,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveDraftOk {
+    /// The draft as stored, with uploads replaced by their ids.
+    pub draft: Draft,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ListDrafts;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ListDraftsOk {
+    /// Most recently saved first.
+    pub drafts: Vec<Draft>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeleteDraft {
+    pub draft_id: DraftId,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeleteDraftOk;
+
+/// Posts a draft and deletes it. Drafts scheduled for a time that has
+/// passed are posted now.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PublishDraft {
+    pub draft_id: DraftId,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PublishDraftOk {
+    pub post_id: PostId,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TrendingPost;
 
@@ -78,6 +160,17 @@ pub struct HomePostOk {
     pub posts: Vec<PublicPost>,
 }
 
+/// Timeline of the members of a list, like the home timeline.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ListTimeline {
+    pub list_id: ListId,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ListTimelineOk {
+    pub posts: Vec<PublicPost>,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct LikedPost;
 
@@ -86,10 +179,75 @@ pub struct LikedPostOk {
     pub posts: Vec<PublicPost>,
 }
 
+/// Bookmarked posts, most recently bookmarked first, from all folders or
+/// only from `folder_id`.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct BookmarkedPost;
+pub struct BookmarkedPost {
+    pub folder_id: Option<BookmarkFolderId>,
+}
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct BookmarkedPostOk {
     pub posts: Vec<PublicPost>,
+    pub bookmarks: Vec<BookmarkDetails>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GetBookmarkFolders;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GetBookmarkFoldersOk {
+    pub folders: Vec<BookmarkFolder>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreateBookmarkFolder {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreateBookmarkFolderOk {
+    pub folder: BookmarkFolder,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RenameBookmarkFolder {
+    pub folder_id: BookmarkFolderId,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RenameBookmarkFolderOk {
+    pub folder: BookmarkFolder,
+}
+
+/// Deletes a folder. Its bookmarks are kept without a folder.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeleteBookmarkFolder {
+    pub folder_id: BookmarkFolderId,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeleteBookmarkFolderOk;
+
+/// Moves a bookmark into a folder, or out of its folder with `None`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MoveBookmark {
+    pub post_id: PostId,
+    pub folder_id: Option<BookmarkFolderId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MoveBookmarkOk;
+
+/// Sets the note on a bookmark. An empty note removes it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SetBookmarkNote {
+    pub post_id: PostId,
+    pub note: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SetBookmarkNoteOk {
+    pub note: Option<String>,
 }
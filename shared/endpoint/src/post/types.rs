@@ -1,8 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uchat_domain::{
-    Caption, Headline, ImageId, Message, PollChoiceDescription, PollChoiceId, PollHeadline, PostId,
-    UserId, Username,
+    AltText, BookmarkFolderId, Caption, DraftId, Headline, ImageId, Message, PollChoiceDescription,
+    PollChoiceId, PollHeadline, PostId, UserId, Username, VideoId,
 };
 use url::Url;
 
@@ -15,6 +15,41 @@ pub struct Chat {
     pub message: Message,
 }
 
+/// A chat sharing a link, shown with a preview of the linked page. Chats
+/// whose message holds a URL are posted as links.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Link {
+    pub headline: Option<Headline>,
+    pub message: Message,
+    /// The first URL in the message.
+    pub url: Url,
+    /// Filled in by the server once the page was fetched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview: Option<LinkPreview>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LinkPreview {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub site_name: Option<String>,
+    pub image: Option<Url>,
+}
+
+impl Link {
+    /// Turns a chat into a link if its message holds a URL.
+    pub fn from_chat(chat: &Chat) -> Option<Self> {
+        let message = uchat_markup::parse(chat.message.as_ref());
+        let url = message.urls().into_iter().next()?.clone();
+        Some(Self {
+            headline: chat.headline.clone(),
+            message: chat.message.clone(),
+            url,
+            preview: None,
+        })
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ImageKind {
     DataUrl(String),
@@ -25,6 +60,80 @@ pub enum ImageKind {
 pub struct Image {
     pub kind: ImageKind,
     pub caption: Option<Caption>,
+    /// Sizes to pick from with `srcset`, filled in by the server.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<ImageVariant>,
+}
+
+/// One image of a [`Gallery`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GalleryImage {
+    pub kind: ImageKind,
+    pub alt_text: Option<AltText>,
+    /// Hidden behind a warning until clicked.
+    #[serde(default)]
+    pub sensitive: bool,
+    /// Sizes to pick from with `srcset`, filled in by the server.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<ImageVariant>,
+}
+
+/// Several images shown together, in the order given.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gallery {
+    pub images: Vec<GalleryImage>,
+    pub caption: Option<Caption>,
+}
+
+impl Gallery {
+    pub const MAX_IMAGES: usize = 4;
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum VideoKind {
+    DataUrl(String),
+    Id(VideoId),
+    Url(Url),
+}
+
+/// A video or GIF. Uploads are MP4, WebM or GIF data URLs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Video {
+    pub kind: VideoKind,
+    pub caption: Option<Caption>,
+    pub alt_text: Option<AltText>,
+    /// Filled in by the server for stored videos.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<VideoDetails>,
+}
+
+/// What players need to know about a stored video.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VideoDetails {
+    pub mime: String,
+    pub width: u32,
+    pub height: u32,
+    pub duration_ms: u32,
+    /// Plays muted and on repeat, like a GIF.
+    pub looping: bool,
+    /// Frame shown until the video plays.
+    pub poster: Url,
+}
+
+/// An image in one size, `width` pixels wide.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImageVariant {
+    pub width: u32,
+    pub url: Url,
+}
+
+/// Value of the `srcset` attribute offering all `variants`.
+pub fn srcset(variants: &[ImageVariant]) -> String {
+    variants
+        .iter()
+        .map(|variant| format!("{} {}w", variant.url, variant.width))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -44,22 +153,64 @@ pub struct Poll {
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Content {
     Chat(Chat),
+    Link(Link),
     Image(Image),
+    Gallery(Gallery),
+    Video(Video),
     Poll(Poll),
 }
 
+impl Content {
+    /// Stored images the content shows.
+    pub fn image_ids(&self) -> Vec<ImageId> {
+        match self {
+            Content::Image(Image {
+                kind: ImageKind::Id(image_id),
+                ..
+            }) => vec![*image_id],
+            Content::Gallery(gallery) => gallery
+                .images
+                .iter()
+                .filter_map(|image| match image.kind {
+                    ImageKind::Id(image_id) => Some(image_id),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
 impl From<Chat> for Content {
     fn from(value: Chat) -> Self {
         Content::Chat(value)
     }
 }
 
+impl From<Link> for Content {
+    fn from(value: Link) -> Self {
+        Content::Link(value)
+    }
+}
+
 impl From<Image> for Content {
     fn from(value: Image) -> Self {
         Content::Image(value)
     }
 }
 
+impl From<Gallery> for Content {
+    fn from(value: Gallery) -> Self {
+        Content::Gallery(value)
+    }
+}
+
+impl From<Video> for Content {
+    fn from(value: Video) -> Self {
+        Content::Video(value)
+    }
+}
+
 impl From<Poll> for Content {
     fn from(value: Poll) -> Self {
         Content::Poll(value)
@@ -84,6 +235,28 @@ impl Default for NewPostOptions {
     }
 }
 
+/// A post being written, kept on the server until it's published or
+/// deleted.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Draft {
+    pub id: DraftId,
+    /// Uploads are stored as ids, so saving the draft again keeps them.
+    pub content: Content,
+    pub options: NewPostOptions,
+    /// Where to show the stored images of the content.
+    pub image_urls: Vec<(ImageId, Url)>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Draft {
+    pub fn image_url(&self, image_id: ImageId) -> Option<&Url> {
+        self.image_urls
+            .iter()
+            .find(|(id, _)| *id == image_id)
+            .map(|(_, url)| url)
+    }
+}
+
 //-------------------------------------------------------------------------------------
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum LikeStatus {
@@ -107,6 +280,9 @@ pub struct PublicPost {
     pub likes: i64,
     pub dislikes: i64,
     pub boosts: i64,
+    /// Phrase of a mute filter the post matched. Such posts are shown
+    /// collapsed behind a warning.
+    pub muted_by: Option<String>,
 }
 
 //-------------------------------------------------------------------------------------
@@ -125,6 +301,33 @@ impl From<BookmarkAction> for bool {
     }
 }
 
+/// Named collection of bookmarks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BookmarkFolder {
+    pub id: BookmarkFolderId,
+    pub name: String,
+    pub bookmark_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl BookmarkFolder {
+    pub const MAX_NAME_CHARS: usize = 50;
+}
+
+/// Where a post is bookmarked and what the user noted about it. Notes are
+/// only ever sent to the user who wrote them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BookmarkDetails {
+    pub post_id: PostId,
+    pub folder_id: Option<BookmarkFolderId>,
+    pub note: Option<String>,
+    pub bookmarked_at: DateTime<Utc>,
+}
+
+impl BookmarkDetails {
+    pub const MAX_NOTE_CHARS: usize = 500;
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum BoostAction {
     Add,
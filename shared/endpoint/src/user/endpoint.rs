@@ -1,11 +1,17 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use uchat_domain::{Password, SessionId, UserId, Username};
+use uchat_domain::{
+    password_policy::PasswordProblem, ApiTokenId, ListId, LoginChallengeId, MuteFilterId,
+    OidcSignupId, Password, SessionId, UserId, Username,
+};
 use url::Url;
 
-use crate::{post::types::PublicPost, Update};
+use crate::{moderation::types::Role, post::types::PublicPost, Update};
 
-use super::types::{FollowAction, PublicUserProfile};
+use super::types::{
+    ApiToken, FollowAction, LinkedIdentity, MuteAction, MuteFilter, MuteScope, OidcProvider,
+    PublicUserProfile, SecurityEvent, TokenScope, UserList,
+};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct CreateUser {
@@ -26,6 +32,8 @@ pub struct CreateUserOk {
 pub struct Login {
     pub username: Username,
     pub password: Password,
+    #[serde(default)]
+    pub remember_me: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -39,12 +47,236 @@ pub struct LoginOk {
     pub user_id: UserId,
 }
 
+/// Response to [`Login`]. Accounts with two-factor authentication enabled
+/// receive a challenge which must be answered with [`VerifyTwoFactor`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum LoginOutcome {
+    LoggedIn(LoginOk),
+    TwoFactorRequired(TwoFactorChallenge),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TwoFactorChallenge {
+    pub challenge_id: LoginChallengeId,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Second login step. `code` is either a TOTP code or a recovery code.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VerifyTwoFactor {
+    pub challenge_id: LoginChallengeId,
+    pub code: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListOidcProviders;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListOidcProvidersOk {
+    pub providers: Vec<OidcProvider>,
+}
+
+/// Starts signing in with an external provider. The browser is sent to
+/// `authorization_url` and comes back with the values for [`FinishOidcLogin`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StartOidcLogin {
+    pub provider: String,
+    pub remember_me: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StartOidcLoginOk {
+    pub authorization_url: Url,
+}
+
+/// Like [`StartOidcLogin`], but links the identity to the signed in account.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StartOidcLink {
+    pub provider: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FinishOidcLogin {
+    pub state: String,
+    pub code: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum OidcOutcome {
+    LoggedIn(LoginOk),
+    TwoFactorRequired(TwoFactorChallenge),
+    /// The identity is new, the user picks a handle with [`CompleteOidcSignup`].
+    ChooseHandle {
+        signup_id: OidcSignupId,
+        suggested_handle: Option<String>,
+    },
+    Linked(LinkedIdentity),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CompleteOidcSignup {
+    pub signup_id: OidcSignupId,
+    pub username: Username,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListIdentities;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListIdentitiesOk {
+    pub identities: Vec<LinkedIdentity>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UnlinkIdentity {
+    pub provider: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UnlinkIdentityOk;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetTwoFactorStatus;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetTwoFactorStatusOk {
+    pub enabled: bool,
+    pub recovery_codes_left: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnrollTotp;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnrollTotpOk {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfirmTotp {
+    pub code: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfirmTotpOk {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DisableTotp {
+    pub password: Password,
+    pub code: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DisableTotpOk;
+
+/// Checks a password against the server policy, including the blocklist.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CheckPassword {
+    pub password: String,
+    pub handle: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CheckPasswordOk {
+    pub problems: Vec<PasswordProblem>,
+}
+
+/// Error body returned when a new password is refused. Other errors have no
+/// `problems`, so this also reads any [`RequestFailed`](crate::RequestFailed).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PasswordRejected {
+    pub msg: String,
+    #[serde(default)]
+    pub problems: Vec<PasswordProblem>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Logout;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogoutOk;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetSecurityEvents {
+    pub older_than: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetSecurityEventsOk {
+    pub events: Vec<SecurityEvent>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateApiToken {
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    pub expires_in_days: Option<i64>,
+}
+
+/// The secret is only returned here, the server keeps just its hash.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateApiTokenOk {
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListApiTokens;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListApiTokensOk {
+    pub tokens: Vec<ApiToken>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RevokeApiToken {
+    pub id: ApiTokenId,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RevokeApiTokenOk;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateMuteFilter {
+    pub phrase: String,
+    pub whole_word: bool,
+    pub scopes: Vec<MuteScope>,
+    pub action: MuteAction,
+    /// `None` mutes the phrase until the filter is deleted.
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateMuteFilterOk {
+    pub filter: MuteFilter,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListMuteFilters;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListMuteFiltersOk {
+    pub filters: Vec<MuteFilter>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeleteMuteFilter {
+    pub filter_id: MuteFilterId,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeleteMuteFilterOk;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GetMyProfile;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GetMyProfileOk {
     pub user_id: UserId,
+    pub role: Role,
     pub display_name: Option<String>,
     pub email: Option<String>,
     pub profile_image: Option<Url>,
@@ -83,3 +315,91 @@ pub struct FollowUser {
 pub struct FollowUserOk {
     pub status: FollowAction,
 }
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateList {
+    pub name: String,
+    pub description: Option<String>,
+    pub private: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateListOk {
+    pub list: UserList,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UpdateList {
+    pub list_id: ListId,
+    pub name: String,
+    pub description: Option<String>,
+    pub private: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UpdateListOk {
+    pub list: UserList,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeleteList {
+    pub list_id: ListId,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeleteListOk;
+
+/// Lists of a user: all of the logged in user's own, and only the public
+/// ones of others.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetLists {
+    /// `None` for the lists of the logged in user.
+    pub owner: Option<UserId>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetListsOk {
+    /// Sorted by name.
+    pub lists: Vec<UserList>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetList {
+    pub list_id: ListId,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetListOk {
+    pub list: UserList,
+    /// In the order they were added.
+    pub members: Vec<PublicUserProfile>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AddListMember {
+    pub list_id: ListId,
+    pub user_id: UserId,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AddListMemberOk;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoveListMember {
+    pub list_id: ListId,
+    pub user_id: UserId,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoveListMemberOk;
+
+/// Lists of the logged in user which a user is on.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetListMemberships {
+    pub user_id: UserId,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetListMembershipsOk {
+    pub list_ids: Vec<ListId>,
+}
@@ -1,14 +1,19 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use uchat_domain::{user::DisplayName, UserId};
+use uchat_domain::{user::DisplayName, ApiTokenId, ListId, MuteFilterId, UserId};
 use url::Url;
 
+use crate::post::types::ImageVariant;
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PublicUserProfile {
     pub id: UserId,
     pub display_name: Option<DisplayName>,
     pub handle: String,
     pub profile_image: Option<Url>,
+    /// Sizes of the profile image to pick from with `srcset`.
+    #[serde(default)]
+    pub profile_image_variants: Vec<ImageVariant>,
     pub created_at: DateTime<Utc>,
     pub am_following: bool,
 }
@@ -27,3 +32,253 @@ impl From<FollowAction> for bool {
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SecurityEventKind {
+    LoginSucceeded,
+    LoginFailed,
+    AccountLocked,
+    PasswordChanged,
+    EmailChanged,
+    SessionRevoked,
+    TwoFactorEnabled,
+    TwoFactorDisabled,
+    TokenCreated,
+    TokenRevoked,
+    IdentityLinked,
+    IdentityUnlinked,
+}
+
+impl SecurityEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LoginSucceeded => "login_succeeded",
+            Self::LoginFailed => "login_failed",
+            Self::AccountLocked => "account_locked",
+            Self::PasswordChanged => "password_changed",
+            Self::EmailChanged => "email_changed",
+            Self::SessionRevoked => "session_revoked",
+            Self::TwoFactorEnabled => "two_factor_enabled",
+            Self::TwoFactorDisabled => "two_factor_disabled",
+            Self::TokenCreated => "token_created",
+            Self::TokenRevoked => "token_revoked",
+            Self::IdentityLinked => "identity_linked",
+            Self::IdentityUnlinked => "identity_unlinked",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::LoginSucceeded => "Signed in",
+            Self::LoginFailed => "Failed sign in attempt",
+            Self::AccountLocked => "Account temporarily locked",
+            Self::PasswordChanged => "Password changed",
+            Self::EmailChanged => "Email changed",
+            Self::SessionRevoked => "Signed out",
+            Self::TwoFactorEnabled => "Two-factor authentication enabled",
+            Self::TwoFactorDisabled => "Two-factor authentication disabled",
+            Self::TokenCreated => "Access token created",
+            Self::TokenRevoked => "Access token revoked",
+            Self::IdentityLinked => "External sign-in linked",
+            Self::IdentityUnlinked => "External sign-in removed",
+        }
+    }
+}
+
+impl std::str::FromStr for SecurityEventKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Self::LoginSucceeded,
+            Self::LoginFailed,
+            Self::AccountLocked,
+            Self::PasswordChanged,
+            Self::EmailChanged,
+            Self::SessionRevoked,
+            Self::TwoFactorEnabled,
+            Self::TwoFactorDisabled,
+            Self::TokenCreated,
+            Self::TokenRevoked,
+            Self::IdentityLinked,
+            Self::IdentityUnlinked,
+        ]
+        .into_iter()
+        .find(|kind| kind.as_str() == s)
+        .ok_or_else(|| format!("unknown security event kind '{s}'"))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SecurityEvent {
+    pub kind: SecurityEventKind,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What a personal access token may be used for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TokenScope {
+    Read,
+    Write,
+    Dm,
+}
+
+impl TokenScope {
+    pub const ALL: [TokenScope; 3] = [Self::Read, Self::Write, Self::Dm];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Dm => "dm",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Read => "Read posts and profiles",
+            Self::Write => "Post, react and follow",
+            Self::Dm => "Read and send direct messages",
+        }
+    }
+}
+
+impl std::str::FromStr for TokenScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s)
+            .ok_or_else(|| format!("unknown token scope '{s}'"))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: ApiTokenId,
+    pub name: String,
+    /// Beginning of the token, to recognize it.
+    pub prefix: String,
+    pub scopes: Vec<TokenScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An OpenID Connect provider users can sign in with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OidcProvider {
+    pub id: String,
+    pub name: String,
+}
+
+/// External identity linked to the signed in account.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LinkedIdentity {
+    pub provider: OidcProvider,
+    pub email: Option<String>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Where a mute filter applies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MuteScope {
+    Home,
+    Trending,
+    /// Replies to other posts, wherever they are shown.
+    Threads,
+}
+
+impl MuteScope {
+    pub const ALL: [MuteScope; 3] = [Self::Home, Self::Trending, Self::Threads];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Home => "home",
+            Self::Trending => "trending",
+            Self::Threads => "threads",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Home => "Home feed",
+            Self::Trending => "Trending",
+            Self::Threads => "Replies",
+        }
+    }
+}
+
+impl std::str::FromStr for MuteScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s)
+            .ok_or_else(|| format!("unknown mute scope '{s}'"))
+    }
+}
+
+/// What happens to posts matching a mute filter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MuteAction {
+    Hide,
+    /// Collapses the post behind a warning naming the phrase.
+    Warn,
+}
+
+impl MuteAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hide => "hide",
+            Self::Warn => "warn",
+        }
+    }
+}
+
+impl std::str::FromStr for MuteAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Self::Hide, Self::Warn]
+            .into_iter()
+            .find(|action| action.as_str() == s)
+            .ok_or_else(|| format!("unknown mute action '{s}'"))
+    }
+}
+
+/// Word, phrase or hashtag the user does not want to see.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MuteFilter {
+    pub id: MuteFilterId,
+    pub phrase: String,
+    /// Only match the phrase as a whole word, so `cat` does not match `catalog`.
+    pub whole_word: bool,
+    pub scopes: Vec<MuteScope>,
+    pub action: MuteAction,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Named set of users whose posts make up a timeline.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UserList {
+    pub id: ListId,
+    pub owner: UserId,
+    pub name: String,
+    pub description: Option<String>,
+    /// Only the owner sees private lists.
+    pub private: bool,
+    pub member_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserList {
+    pub const MAX_NAME_CHARS: usize = 50;
+    pub const MAX_DESCRIPTION_CHARS: usize = 160;
+}
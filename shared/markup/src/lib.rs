@@ -0,0 +1,523 @@
+//! The small Markdown dialect chat messages are written in: bold, italic,
+//! inline code, code blocks, links, bare URLs, mentions and hashtags.
+//!
+//! Messages are parsed into a [`Document`] which renderers walk, so nothing
+//! in a message ever reaches a page as HTML. Whatever the dialect doesn't
+//! know, including HTML tags, stays plain text.
+
+use url::Url;
+
+mod thread;
+
+pub use thread::split_thread;
+
+/// How deep spans may nest, like italic inside bold inside a link.
+const MAX_DEPTH: usize = 4;
+
+/// Longest name a mention can hold, the longest username.
+pub const MAX_MENTION_CHARS: usize = 20;
+
+pub const MAX_HASHTAG_CHARS: usize = 50;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Document {
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    /// Lines separated by a blank line from the blocks around them.
+    Paragraph(Vec<Inline>),
+    /// Lines fenced by three backticks, with the language after the
+    /// opening fence if there is one.
+    Code {
+        language: Option<String>,
+        code: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inline {
+    Text(String),
+    /// `**bold**`
+    Bold(Vec<Inline>),
+    /// `*italic*` or `_italic_`
+    Italic(Vec<Inline>),
+    /// `` `code` ``
+    Code(String),
+    /// `[text](url)`, or a bare URL with itself as the text. Only http and
+    /// https URLs become links.
+    Link {
+        text: Vec<Inline>,
+        url: Url,
+    },
+    /// `@username`, without the `@`.
+    Mention(String),
+    /// `#tag`, without the `#`.
+    Hashtag(String),
+    LineBreak,
+}
+
+impl Document {
+    /// URLs of all links, in the order they appear.
+    pub fn urls(&self) -> Vec<&Url> {
+        let mut urls = vec![];
+        for block in &self.blocks {
+            if let Block::Paragraph(inlines) = block {
+                collect_urls(inlines, &mut urls);
+            }
+        }
+        urls
+    }
+
+    /// The text readers see, without any markup.
+    pub fn plain_text(&self) -> String {
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|block| match block {
+                Block::Paragraph(inlines) => {
+                    let mut text = String::new();
+                    push_plain_text(inlines, &mut text);
+                    text
+                }
+                Block::Code { code, .. } => code.clone(),
+            })
+            .collect::<Vec<_>>();
+        blocks.join("\n\n")
+    }
+
+    /// Number of characters readers see, which is what message lengths
+    /// are limited by.
+    pub fn visible_chars(&self) -> usize {
+        self.plain_text().chars().count()
+    }
+}
+
+fn collect_urls<'a>(inlines: &'a [Inline], urls: &mut Vec<&'a Url>) {
+    for inline in inlines {
+        match inline {
+            Inline::Link { url, .. } => urls.push(url),
+            Inline::Bold(inner) | Inline::Italic(inner) => collect_urls(inner, urls),
+            _ => {}
+        }
+    }
+}
+
+fn push_plain_text(inlines: &[Inline], text: &mut String) {
+    for inline in inlines {
+        match inline {
+            Inline::Text(part) | Inline::Code(part) => text.push_str(part),
+            Inline::Bold(inner) | Inline::Italic(inner) => push_plain_text(inner, text),
+            Inline::Link { text: inner, .. } => push_plain_text(inner, text),
+            Inline::Mention(name) => {
+                text.push('@');
+                text.push_str(name);
+            }
+            Inline::Hashtag(tag) => {
+                text.push('#');
+                text.push_str(tag);
+            }
+            Inline::LineBreak => text.push('\n'),
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Document {
+    let mut blocks = vec![];
+    let mut paragraph = vec![];
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        if let Some(language) = fence(line) {
+            end_paragraph(&mut paragraph, &mut blocks);
+            let mut code = vec![];
+            // An unclosed block runs to the end of the message
+            for line in lines.by_ref() {
+                if line.trim_end() == "```" {
+                    break;
+                }
+                code.push(line);
+            }
+            blocks.push(Block::Code {
+                language,
+                code: code.join("\n"),
+            });
+        } else if line.trim().is_empty() {
+            end_paragraph(&mut paragraph, &mut blocks);
+        } else {
+            paragraph.push(line);
+        }
+    }
+    end_paragraph(&mut paragraph, &mut blocks);
+    Document { blocks }
+}
+
+/// The language of an opening fence, `None` if there is no language and
+/// no value if the line doesn't open a code block.
+fn fence(line: &str) -> Option<Option<String>> {
+    let info = line.strip_prefix("```")?.trim();
+    if info.is_empty() {
+        return Some(None);
+    }
+    let is_language = info
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '_' | '#' | '.'));
+    is_language.then(|| Some(info.to_string()))
+}
+
+fn end_paragraph(lines: &mut Vec<&str>, blocks: &mut Vec<Block>) {
+    if !lines.is_empty() {
+        blocks.push(Block::Paragraph(inlines(&lines.join("\n"), 0, true)));
+        lines.clear();
+    }
+}
+
+#[derive(Default)]
+struct Inlines {
+    inlines: Vec<Inline>,
+    text: String,
+}
+
+impl Inlines {
+    fn push(&mut self, inline: Inline) {
+        self.end_text();
+        self.inlines.push(inline);
+    }
+
+    fn end_text(&mut self) {
+        if !self.text.is_empty() {
+            self.inlines
+                .push(Inline::Text(std::mem::take(&mut self.text)));
+        }
+    }
+
+    fn finish(mut self) -> Vec<Inline> {
+        self.end_text();
+        self.inlines
+    }
+}
+
+/// Spans in `text`. Links inside the text of links are left out.
+fn inlines(text: &str, depth: usize, links: bool) -> Vec<Inline> {
+    let mut out = Inlines::default();
+    let mut pos = 0;
+    while let Some(c) = text[pos..].chars().next() {
+        let prev = text[..pos].chars().next_back();
+        if let Some((inline, len)) = span(&text[pos..], prev, depth, links) {
+            out.push(inline);
+            pos += len;
+            continue;
+        }
+        match c {
+            '\\' => match text[pos + 1..].chars().next() {
+                // Escaped markup characters are taken as they are
+                Some(escaped) if escaped.is_ascii_punctuation() => {
+                    out.text.push(escaped);
+                    pos += 1 + escaped.len_utf8();
+                    continue;
+                }
+                _ => out.text.push(c),
+            },
+            '\n' => out.push(Inline::LineBreak),
+            _ => out.text.push(c),
+        }
+        pos += c.len_utf8();
+    }
+    out.finish()
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The span `rest` starts with, and its length in bytes. `prev` is the
+/// character in front of it.
+fn span(rest: &str, prev: Option<char>, depth: usize, links: bool) -> Option<(Inline, usize)> {
+    let nests = depth < MAX_DEPTH;
+    let starts_word = !prev.is_some_and(is_word);
+    match rest.chars().next()? {
+        '`' => {
+            let end = rest[1..].find('`')?;
+            let code = &rest[1..1 + end];
+            if code.is_empty() {
+                return None;
+            }
+            Some((Inline::Code(code.to_string()), end + 2))
+        }
+        '*' if nests && rest.starts_with("**") => {
+            let inner = emphasized(&rest[2..], "**")?;
+            Some((
+                Inline::Bold(inlines(inner, depth + 1, links)),
+                inner.len() + 4,
+            ))
+        }
+        '*' if nests => {
+            let inner = emphasized(&rest[1..], "*")?;
+            Some((
+                Inline::Italic(inlines(inner, depth + 1, links)),
+                inner.len() + 2,
+            ))
+        }
+        // Only around whole words, so snake_case names stay as they are
+        '_' if nests && starts_word => {
+            let inner = emphasized(&rest[1..], "_")?;
+            let len = inner.len() + 2;
+            if rest[len..].chars().next().is_some_and(is_word) {
+                return None;
+            }
+            Some((Inline::Italic(inlines(inner, depth + 1, links)), len))
+        }
+        '[' if nests && links => link(rest, depth),
+        'h' if links && starts_word => {
+            let (url, len) = url_at(rest)?;
+            let text = vec![Inline::Text(rest[..len].to_string())];
+            Some((Inline::Link { text, url }, len))
+        }
+        '@' if starts_word => {
+            let name = word(&rest[1..], MAX_MENTION_CHARS)?;
+            Some((Inline::Mention(name.to_string()), name.len() + 1))
+        }
+        '#' if starts_word => {
+            let tag = word(&rest[1..], MAX_HASHTAG_CHARS)?;
+            // Numbers, like #1, aren't tags
+            if tag.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            Some((Inline::Hashtag(tag.to_string()), tag.len() + 1))
+        }
+        _ => None,
+    }
+}
+
+/// The text up to the closing `delimiter`. Emphasis hugs its text, so
+/// `2 * 3 * 4` has none.
+fn emphasized<'a>(text: &'a str, delimiter: &str) -> Option<&'a str> {
+    if text.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let inner = &text[..text.find(delimiter)?];
+    if inner.is_empty() || inner.ends_with(char::is_whitespace) {
+        return None;
+    }
+    Some(inner)
+}
+
+/// `[text](url)` at the start of `rest`.
+fn link(rest: &str, depth: usize) -> Option<(Inline, usize)> {
+    let text_end = rest.find("](")?;
+    let text = &rest[1..text_end];
+    if text.trim().is_empty() || text.contains('\n') {
+        return None;
+    }
+    let url_start = text_end + 2;
+    // The URL may hold parentheses, like Wikipedia links do
+    let mut open = 0;
+    let mut url_end = None;
+    for (at, c) in rest[url_start..].char_indices() {
+        match c {
+            '(' => open += 1,
+            ')' if open == 0 => {
+                url_end = Some(url_start + at);
+                break;
+            }
+            ')' => open -= 1,
+            c if c.is_whitespace() => return None,
+            _ => {}
+        }
+    }
+    let url_end = url_end?;
+    let url = web_url(&rest[url_start..url_end])?;
+    let text = inlines(text, depth + 1, false);
+    Some((Inline::Link { text, url }, url_end + 1))
+}
+
+/// A bare URL at the start of `rest`, and its length in bytes. Punctuation
+/// right after a URL, like a full stop, is not part of it.
+fn url_at(rest: &str) -> Option<(Url, usize)> {
+    if !rest.starts_with("http://") && !rest.starts_with("https://") {
+        return None;
+    }
+    let word = &rest[..rest.find(char::is_whitespace).unwrap_or(rest.len())];
+    let candidate = word.trim_end_matches(|c: char| {
+        matches!(
+            c,
+            '.' | ',' | ';' | ':' | '!' | '?' | '\'' | '"' | ')' | '*' | '_'
+        )
+    });
+    // Keep a closing parenthesis which belongs to the URL
+    let unbalanced = candidate.matches('(').count() > candidate.matches(')').count();
+    let len = if unbalanced && word[candidate.len()..].starts_with(')') {
+        candidate.len() + 1
+    } else {
+        candidate.len()
+    };
+    Some((web_url(&rest[..len])?, len))
+}
+
+fn web_url(text: &str) -> Option<Url> {
+    let url = Url::parse(text).ok()?;
+    let is_web = matches!(url.scheme(), "http" | "https") && url.host_str().is_some();
+    is_web.then_some(url)
+}
+
+/// The word `text` starts with, if it is at most `max_chars` long.
+fn word(text: &str, max_chars: usize) -> Option<&str> {
+    let end = text.find(|c: char| !is_word(c)).unwrap_or(text.len());
+    let word = &text[..end];
+    let chars = word.chars().count();
+    (1..=max_chars).contains(&chars).then_some(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> Inline {
+        Inline::Text(text.to_string())
+    }
+
+    fn paragraph(message: &str) -> Vec<Inline> {
+        match parse(message).blocks.as_slice() {
+            [Block::Paragraph(inlines)] => inlines.clone(),
+            blocks => panic!("expected a single paragraph, got {blocks:?}"),
+        }
+    }
+
+    fn urls(message: &str) -> Vec<String> {
+        parse(message)
+            .urls()
+            .into_iter()
+            .map(|url| url.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn parses_emphasis_and_code() {
+        assert_eq!(
+            paragraph("**bold _and italic_** and `a * b`"),
+            vec![
+                Inline::Bold(vec![
+                    text("bold "),
+                    Inline::Italic(vec![text("and italic")])
+                ]),
+                text(" and "),
+                Inline::Code("a * b".to_string()),
+            ]
+        );
+        assert_eq!(
+            paragraph("_italic_ but not snake_case_name or 2 * 3 * 4"),
+            vec![
+                Inline::Italic(vec![text("italic")]),
+                text(" but not snake_case_name or 2 * 3 * 4"),
+            ]
+        );
+        assert_eq!(paragraph(r"\*not italic\*"), vec![text("*not italic*")]);
+        assert_eq!(paragraph("**unclosed"), vec![text("**unclosed")]);
+    }
+
+    #[test]
+    fn keeps_html_as_text() {
+        assert_eq!(
+            paragraph("<script>alert(1)</script> <b>hi</b>"),
+            vec![text("<script>alert(1)</script> <b>hi</b>")]
+        );
+    }
+
+    #[test]
+    fn parses_links() {
+        assert_eq!(
+            paragraph("[the **docs**](https://docs.rs/url) and https://example.com."),
+            vec![
+                Inline::Link {
+                    text: vec![text("the "), Inline::Bold(vec![text("docs")])],
+                    url: Url::parse("https://docs.rs/url").unwrap(),
+                },
+                text(" and "),
+                Inline::Link {
+                    text: vec![text("https://example.com")],
+                    url: Url::parse("https://example.com").unwrap(),
+                },
+                text("."),
+            ]
+        );
+        assert_eq!(
+            paragraph("[click](javascript:alert(1))"),
+            vec![text("[click](javascript:alert(1))")]
+        );
+        assert_eq!(
+            urls("[Rust](https://en.wikipedia.org/wiki/Rust_(programming_language))"),
+            vec!["https://en.wikipedia.org/wiki/Rust_(programming_language)"]
+        );
+    }
+
+    #[test]
+    fn finds_bare_urls() {
+        assert_eq!(
+            urls("see https://example.com/a?b=c, and http://example.org."),
+            vec!["https://example.com/a?b=c", "http://example.org/"]
+        );
+        assert_eq!(
+            urls("(https://en.wikipedia.org/wiki/Rust_(programming_language))"),
+            vec!["https://en.wikipedia.org/wiki/Rust_(programming_language)"]
+        );
+        assert_eq!(
+            urls("(at https://example.com)"),
+            vec!["https://example.com/"]
+        );
+        assert_eq!(
+            urls("**https://example.com**"),
+            vec!["https://example.com/"]
+        );
+        assert_eq!(
+            urls("no links, ftp://example.com or https:// or `https://example.com`"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            urls("émoji 🦀 https://example.com"),
+            vec!["https://example.com/"]
+        );
+    }
+
+    #[test]
+    fn parses_mentions_and_hashtags() {
+        assert_eq!(
+            paragraph("@ferris likes #rust, not #1 or mail@example.com"),
+            vec![
+                Inline::Mention("ferris".to_string()),
+                text(" likes "),
+                Inline::Hashtag("rust".to_string()),
+                text(", not #1 or mail@example.com"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_blocks() {
+        let document = parse("first\nline\n\n```rust\nfn main() {}\n\n```\nlast");
+        assert_eq!(
+            document.blocks,
+            vec![
+                Block::Paragraph(vec![text("first"), Inline::LineBreak, text("line")]),
+                Block::Code {
+                    language: Some("rust".to_string()),
+                    code: "fn main() {}\n".to_string(),
+                },
+                Block::Paragraph(vec![text("last")]),
+            ]
+        );
+        assert_eq!(
+            parse("```\n**not bold**").blocks,
+            vec![Block::Code {
+                language: None,
+                code: "**not bold**".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn counts_visible_chars() {
+        let document = parse("**hi** [there](https://example.com) @you");
+        assert_eq!(document.plain_text(), "hi there @you");
+        assert_eq!(document.visible_chars(), 13);
+    }
+}
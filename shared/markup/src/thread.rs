@@ -0,0 +1,120 @@
+//! Splitting texts too long for a single post into a thread of posts.
+
+use crate::parse;
+
+/// Splits `text` into parts of at most `max_chars` visible characters,
+/// numbered like `2/3` at their end and broken between words where
+/// possible. Text which fits stays a single part without a number.
+///
+/// Markup spanning a break, like bold text, is shown as written.
+pub fn split_thread(text: &str, max_chars: usize) -> Vec<String> {
+    let text = text.trim();
+    if visible_chars(text) <= max_chars {
+        return vec![text.to_string()];
+    }
+    let mut digits = 1;
+    loop {
+        // Room for the number, like " 12/12"
+        let parts = pack(text, max_chars.saturating_sub(2 * digits + 2).max(1));
+        let total = parts.len();
+        if total.to_string().len() <= digits {
+            return parts
+                .into_iter()
+                .enumerate()
+                .map(|(index, part)| format!("{part} {}/{total}", index + 1))
+                .collect();
+        }
+        digits += 1;
+    }
+}
+
+fn visible_chars(text: &str) -> usize {
+    parse(text).visible_chars()
+}
+
+/// Fills parts word by word.
+fn pack(text: &str, max_chars: usize) -> Vec<String> {
+    let mut parts = vec![];
+    let mut part = String::new();
+    for word in text.split_inclusive(char::is_whitespace) {
+        let extended = format!("{part}{word}");
+        if visible_chars(extended.trim_end()) <= max_chars {
+            part = extended;
+            continue;
+        }
+        end_part(&mut part, &mut parts);
+        // Words longer than a part are broken up anywhere. Markup never
+        // shows more characters than it is written with, so this fits.
+        let mut rest = word;
+        while visible_chars(rest.trim_end()) > max_chars {
+            let end = rest
+                .char_indices()
+                .nth(max_chars)
+                .map_or(rest.len(), |(at, _)| at);
+            parts.push(rest[..end].to_string());
+            rest = &rest[end..];
+        }
+        part.push_str(rest);
+    }
+    end_part(&mut part, &mut parts);
+    parts
+}
+
+fn end_part(part: &mut String, parts: &mut Vec<String>) {
+    let trimmed = part.trim();
+    if !trimmed.is_empty() {
+        parts.push(trimmed.to_string());
+    }
+    part.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_short_texts_whole() {
+        assert_eq!(
+            split_thread("  **short** text\n", 10),
+            vec!["**short** text"]
+        );
+    }
+
+    #[test]
+    fn splits_between_words() {
+        let text = "one two three four five six seven eight nine ten eleven twelve";
+        let parts = split_thread(text, 20);
+        assert_eq!(
+            parts,
+            vec![
+                "one two three 1/5",
+                "four five six 2/5",
+                "seven eight nine 3/5",
+                "ten eleven 4/5",
+                "twelve 5/5",
+            ]
+        );
+        assert!(parts.iter().all(|part| visible_chars(part) <= 20));
+    }
+
+    #[test]
+    fn counts_visible_chars_only() {
+        let text = "[docs](https://docs.rs/url/latest/url/struct.Url.html) are **good** reading";
+        let parts = split_thread(text, 20);
+        assert_eq!(
+            parts,
+            vec![
+                "[docs](https://docs.rs/url/latest/url/struct.Url.html) are **good** 1/2",
+                "reading 2/2",
+            ]
+        );
+    }
+
+    #[test]
+    fn breaks_up_long_words() {
+        let parts = split_thread(&"a".repeat(25), 10);
+        assert_eq!(parts.len(), 5);
+        assert!(parts.iter().all(|part| visible_chars(part) <= 10));
+        assert_eq!(parts[0], "aaaaaa 1/5");
+    }
+}